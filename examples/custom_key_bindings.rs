@@ -86,7 +86,7 @@ impl ConditionalEventHandler for TabEventHandler {
 
 fn main() -> Result<()> {
     let mut rl = Editor::<MyHelper>::new()?;
-    rl.set_helper(Some(MyHelper(HistoryHinter {})));
+    rl.set_helper(Some(MyHelper(HistoryHinter::new())));
 
     let ceh = Box::new(CompleteHintHandler);
     rl.bind_sequence(KeyEvent::ctrl('E'), EventHandler::Conditional(ceh.clone()));
@@ -102,7 +102,6 @@ fn main() -> Result<()> {
 
     loop {
         let line = rl.readline("> ")?;
-        rl.add_history_entry(line.as_str());
         println!("Line: {}", line);
     }
 }
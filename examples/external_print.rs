@@ -23,7 +23,6 @@ fn main() -> Result<()> {
 
     loop {
         let line = rl.readline("> ")?;
-        rl.add_history_entry(line.as_str());
         println!("Line: {}", line);
     }
 }
@@ -0,0 +1,38 @@
+use std::borrow::Cow::{self, Owned};
+
+use yatima_rustyline::highlight::Highlighter;
+use yatima_rustyline::hint::HistoryHinter;
+use yatima_rustyline::{Editor, Result};
+use yatima_rustyline_derive::{Completer, Helper, Hinter, Validator};
+
+#[derive(Completer, Helper, Hinter, Validator)]
+struct MyHelper {
+    #[rustyline(Hinter)]
+    hinter: HistoryHinter,
+}
+
+impl Highlighter for MyHelper {
+    // dimmed
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Owned(format!("\x1b[2m{}\x1b[m", hint))
+    }
+}
+
+/// Hints from history: type the beginning of a previous entry then press
+/// right arrow or end to accept the hint.
+fn main() -> Result<()> {
+    env_logger::init();
+    let h = MyHelper {
+        hinter: HistoryHinter {
+            case_insensitive: true,
+        },
+    };
+    let mut rl = Editor::new()?;
+    rl.set_helper(Some(h));
+    rl.add_history_entry("SELECT * FROM users");
+    rl.add_history_entry("select count(*) from users");
+    loop {
+        let line = rl.readline("> ")?;
+        println!("Line: {}", line);
+    }
+}
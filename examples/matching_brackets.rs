@@ -0,0 +1,38 @@
+use yatima_rustyline::highlight::MatchingBracketHighlighter;
+use yatima_rustyline::style::{Color, Style};
+use yatima_rustyline::validate::MatchingBracketValidator;
+use yatima_rustyline::{Editor, Result};
+use yatima_rustyline_derive::{Completer, Helper, Highlighter, Hinter, Validator};
+
+#[derive(Completer, Helper, Highlighter, Hinter, Validator)]
+struct MyHelper {
+    #[rustyline(Highlighter)]
+    highlighter: MatchingBracketHighlighter,
+    #[rustyline(Validator)]
+    validator: MatchingBracketValidator,
+}
+
+/// Brackets are highlighted when the cursor is on (or just after) one of them,
+/// and the input is not accepted until they are balanced.
+fn main() -> Result<()> {
+    let h = MyHelper {
+        // opening bracket in green, closing bracket in yellow
+        highlighter: MatchingBracketHighlighter::new_with_color(
+            Style {
+                bold: true,
+                ..Style::from(Color::Basic(2))
+            },
+            Style {
+                bold: true,
+                ..Style::from(Color::Basic(3))
+            },
+        ),
+        validator: MatchingBracketValidator::new(),
+    };
+    let mut rl = Editor::new()?;
+    rl.set_helper(Some(h));
+
+    let input = rl.readline("> ")?;
+    println!("Input: {}", input);
+    Ok(())
+}
@@ -1,9 +1,9 @@
-use yatima_rustyline::{Editor, Result};
+use yatima_rustyline::{DefaultEditor, Result};
 
 /// Minimal REPL
 fn main() -> Result<()> {
     env_logger::init();
-    let mut rl = Editor::<()>::new()?;
+    let mut rl = DefaultEditor::new()?;
     loop {
         let line = rl.readline("> ")?; // read
         println!("Line: {}", line); // eval / print
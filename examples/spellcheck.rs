@@ -0,0 +1,39 @@
+use std::borrow::Cow::{self, Owned};
+use std::path::Path;
+
+use yatima_rustyline::highlight::Highlighter;
+use yatima_rustyline::hint::SpellcheckHinter;
+use yatima_rustyline::{Editor, Result};
+use yatima_rustyline_derive::{Completer, Helper, Hinter, Validator};
+
+#[derive(Completer, Helper, Hinter, Validator)]
+struct MyHelper {
+    #[rustyline(Hinter)]
+    hinter: SpellcheckHinter,
+}
+
+impl Highlighter for MyHelper {
+    // dimmed
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Owned(format!("\x1b[2m{}\x1b[m", hint))
+    }
+}
+
+/// Spelling suggestions: a mistyped word is followed by the closest
+/// dictionary word.
+fn main() -> Result<()> {
+    env_logger::init();
+    let words = Path::new("/usr/share/dict/words");
+    let hinter = if words.exists() {
+        SpellcheckHinter::from_file(words)?
+    } else {
+        let dictionary = ["hello", "world", "rust", "readline", "spelling"];
+        SpellcheckHinter::new(dictionary.iter().map(|w| (*w).to_owned()).collect())
+    };
+    let mut rl = Editor::new()?;
+    rl.set_helper(Some(MyHelper { hinter }));
+    loop {
+        let line = rl.readline("> ")?;
+        println!("Line: {}", line);
+    }
+}
@@ -0,0 +1,24 @@
+import init, { WebEditor } from "./pkg/app.js";
+
+await init();
+
+// `WebEditor` writes `\n`: xterm.js must translate it to `\r\n`
+const term = new Terminal({ convertEol: true, cols: 80 });
+term.open(document.getElementById("terminal"));
+term.focus();
+
+const editor = new WebEditor((data) => term.write(data));
+term.onData((data) => editor.push(data));
+
+for (;;) {
+  try {
+    const line = await editor.readline(">> ");
+    term.write(`Line: ${line}\n`);
+  } catch (err) {
+    // "Interrupted" on Ctrl-C, "Eof" on Ctrl-D
+    term.write(`${err}\n`);
+    if (err === "Eof") {
+      break;
+    }
+  }
+}
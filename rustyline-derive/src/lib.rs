@@ -1,28 +1,310 @@
+//! Derive macros for the `yatima-rustyline` helper traits.
+//!
+//! `Completer`, `Highlighter`, `Hinter` and `Validator` delegate to the field
+//! marked with the name of the trait (`#[rustyline(Completer)]`, ...) or use
+//! the default implementation of the trait when no field is marked.
+//!
+//! A field also marked `#[rustyline(default)]` is not read: a default value
+//! of its type is used instead, so the type must implement `Default`.
+//!
+//! ```
+//! use yatima_rustyline::validate::MatchingBracketValidator;
+//! use yatima_rustyline_derive::{Completer, Helper, Highlighter, Hinter, Validator};
+//!
+//! #[derive(Completer, Helper, Highlighter, Hinter, Validator)]
+//! struct MyHelper {
+//!     #[rustyline(Validator)]
+//!     #[rustyline(default)]
+//!     validator: MatchingBracketValidator,
+//! }
+//! ```
+//!
+//! The value returned by `Validator::validate_while_typing` can be forced with
+//! `#[rustyline(validate_while_typing = false)]` (or `true`) on the marked
+//! field, for example to validate an expensive input only on Enter:
+//!
+//! ```
+//! use yatima_rustyline::validate::{MatchingBracketValidator, Validator};
+//! use yatima_rustyline_derive::Validator;
+//!
+//! #[derive(Validator)]
+//! struct MyHelper {
+//!     #[rustyline(Validator)]
+//!     #[rustyline(validate_while_typing = false)]
+//!     validator: MatchingBracketValidator,
+//! }
+//! let helper = MyHelper {
+//!     validator: MatchingBracketValidator::new(),
+//! };
+//! assert!(!helper.validate_while_typing());
+//! ```
+//!
+//! ```compile_fail
+//! use yatima_rustyline::validate::MatchingBracketValidator;
+//! use yatima_rustyline_derive::Validator;
+//!
+//! #[derive(Validator)]
+//! struct MyHelper {
+//!     #[rustyline(Validator)]
+//!     #[rustyline(validate_on_enter = true)] // unknown attribute
+//!     validator: MatchingBracketValidator,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use yatima_rustyline::validate::Validator;
+//! use yatima_rustyline_derive::Validator;
+//!
+//! struct NoDefault;
+//! impl Validator for NoDefault {}
+//!
+//! #[derive(Validator)]
+//! struct MyHelper {
+//!     #[rustyline(Validator)]
+//!     #[rustyline(default)]
+//!     validator: NoDefault, // the trait `Default` is not implemented
+//! }
+//! ```
+//!
+//! Fields of a tuple struct are delegated to by index, so all of them must be
+//! marked:
+//!
+//! ```
+//! use yatima_rustyline::completion::FilenameCompleter;
+//! use yatima_rustyline::hint::HistoryHinter;
+//! use yatima_rustyline::validate::{MatchingBracketValidator, Validator};
+//! use yatima_rustyline_derive::{Completer, Helper, Highlighter, Hinter, Validator};
+//!
+//! #[derive(Completer, Helper, Highlighter, Hinter, Validator)]
+//! struct MyHelper(
+//!     #[rustyline(Completer)] FilenameCompleter,
+//!     #[rustyline(Hinter)] HistoryHinter,
+//!     #[rustyline(Validator)]
+//!     #[rustyline(validate_while_typing = false)]
+//!     MatchingBracketValidator,
+//! );
+//! let helper = MyHelper(
+//!     FilenameCompleter::new(),
+//!     HistoryHinter::new(),
+//!     MatchingBracketValidator::new(),
+//! );
+//! assert!(!helper.validate_while_typing());
+//! ```
+//!
+//! ```compile_fail
+//! use yatima_rustyline::validate::MatchingBracketValidator;
+//! use yatima_rustyline_derive::Validator;
+//!
+//! #[derive(Validator)]
+//! struct MyHelper(
+//!     #[rustyline(Validator)] MatchingBracketValidator,
+//!     String, // ambiguous delegation: not marked
+//! );
+//! ```
+//!
+//! For an enum, `Validator` delegates to the marked variant (with a single
+//! field) or to the marked field of a variant; the other variants are always
+//! valid:
+//!
+//! ```
+//! use std::io::Cursor;
+//! use yatima_rustyline::validate::MatchingBracketValidator;
+//! use yatima_rustyline::{Config, Editor, Helper};
+//! use yatima_rustyline_derive::{Completer, Helper, Highlighter, Hinter, Validator};
+//!
+//! #[derive(Completer, Helper, Highlighter, Hinter, Validator)]
+//! enum ByVariant {
+//!     Lenient,
+//!     #[rustyline(Validator)]
+//!     Brackets(MatchingBracketValidator),
+//! }
+//!
+//! #[derive(Completer, Helper, Highlighter, Hinter, Validator)]
+//! enum ByField {
+//!     Lenient,
+//!     Brackets {
+//!         name: String,
+//!         #[rustyline(Validator)]
+//!         validator: MatchingBracketValidator,
+//!     },
+//! }
+//!
+//! fn readline<H: Helper>(helper: H) -> yatima_rustyline::Result<String> {
+//!     // the first Enter is rejected while the bracket is not closed
+//!     let input = Cursor::new("(a\rb)\r");
+//!     let mut rl = Editor::with_io(Config::default(), input, Vec::new())?;
+//!     rl.set_helper(Some(helper));
+//!     rl.readline("> ")
+//! }
+//!
+//! let brackets = ByVariant::Brackets(MatchingBracketValidator::new());
+//! assert_eq!("(a\nb)", readline(brackets)?);
+//! assert_eq!("(a", readline(ByVariant::Lenient)?);
+//! let brackets = ByField::Brackets {
+//!     name: "brackets".to_owned(),
+//!     validator: MatchingBracketValidator::new(),
+//! };
+//! assert_eq!("(a\nb)", readline(brackets)?);
+//! assert_eq!("(a", readline(ByField::Lenient)?);
+//! # Ok::<(), yatima_rustyline::error::ReadlineError>(())
+//! ```
+//!
+//! ```compile_fail
+//! use yatima_rustyline_derive::Validator;
+//!
+//! #[derive(Validator)]
+//! enum MyHelper {
+//!     #[rustyline(Validator)]
+//!     Unit, // nothing to delegate to
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use yatima_rustyline::validate::MatchingBracketValidator;
+//! use yatima_rustyline_derive::Validator;
+//!
+//! #[derive(Validator)]
+//! enum MyHelper {
+//!     #[rustyline(Validator)]
+//!     Brackets(MatchingBracketValidator),
+//!     #[rustyline(Validator)]
+//!     Other(MatchingBracketValidator), // only one variant is allowed
+//! }
+//! ```
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Field, Index, Path};
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Field, Fields, Generics,
+    Index, Lit, Meta, Path, Result, Variant,
+};
 
-fn get_field_by_attr<'a>(data: &'a Data, ident: &str) -> Option<(usize, &'a Field)> {
+fn has_attr(attrs: &[Attribute], ident: &str) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path.is_ident("rustyline")
+            && attr
+                .parse_args::<Path>()
+                .map_or(false, |arg| arg.is_ident(ident))
+    })
+}
+
+/// Value of `#[rustyline(validate_while_typing = true/false)]` if present
+fn validate_while_typing_attr(attrs: &[Attribute]) -> Result<Option<bool>> {
+    let mut value = None;
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("rustyline")) {
+        if let Meta::NameValue(meta) = attr.parse_args::<Meta>()? {
+            if !meta.path.is_ident("validate_while_typing") {
+                return Err(Error::new_spanned(
+                    meta.path,
+                    "unknown rustyline attribute, expected `validate_while_typing`",
+                ));
+            }
+            if let Lit::Bool(lit) = meta.lit {
+                value = Some(lit.value);
+            } else {
+                return Err(Error::new_spanned(meta.lit, "expected `true` or `false`"));
+            }
+        }
+    }
+    Ok(value)
+}
+
+fn get_field_by_attr<'a>(data: &'a Data, ident: &str) -> Result<Option<(usize, &'a Field)>> {
     if let Data::Struct(struct_data) = &data {
-        let mut fields = struct_data.fields.iter().enumerate().filter(|(_, field)| {
-            field.attrs.iter().any(|attr| {
-                attr.path.is_ident("rustyline")
-                    && attr
-                        .parse_args::<Path>()
-                        .map_or(false, |arg| arg.is_ident(ident))
-            })
-        });
+        let mut fields = struct_data
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| has_attr(&field.attrs, ident));
 
         let field = fields.next();
 
-        if fields.next().is_some() {
-            panic!("Only one {:} field is allowed.", ident);
+        if let Some((_, other)) = fields.next() {
+            return Err(Error::new_spanned(
+                other,
+                format!("Only one {:} field is allowed.", ident),
+            ));
+        }
+
+        if let (Fields::Unnamed(_), Some((index, field))) = (&struct_data.fields, field) {
+            let unannotated = struct_data
+                .fields
+                .iter()
+                .any(|f| !f.attrs.iter().any(|attr| attr.path.is_ident("rustyline")));
+            if unannotated {
+                return Err(Error::new_spanned(
+                    field,
+                    format!(
+                        "ambiguous delegation: {} is delegated to field {} of a tuple struct \
+                         with unannotated fields, use named fields instead",
+                        ident, index
+                    ),
+                ));
+            }
         }
 
-        field
+        Ok(field)
     } else {
-        None
+        Ok(None)
+    }
+}
+
+/// Find the variant of an enum delegating to `ident` and its marked field.
+///
+/// The attribute can be put either on the variant (with a single field) or on
+/// one of its fields.
+fn get_variant_field_by_attr<'a>(
+    data: &'a Data,
+    ident: &str,
+) -> Result<Option<(&'a Variant, usize, &'a Field)>> {
+    if let Data::Enum(enum_data) = &data {
+        let mut found = None;
+        for variant in &enum_data.variants {
+            let field = if has_attr(&variant.attrs, ident) {
+                if variant.fields.is_empty() {
+                    return Err(Error::new_spanned(
+                        variant,
+                        format!("{} cannot be delegated to a unit variant", ident),
+                    ));
+                } else if variant.fields.len() > 1 {
+                    return Err(Error::new_spanned(
+                        variant,
+                        format!(
+                            "ambiguous delegation: variant {} has several fields, \
+                             put #[rustyline({})] on one of them",
+                            variant.ident, ident
+                        ),
+                    ));
+                }
+                variant.fields.iter().next().map(|field| (0, field))
+            } else {
+                let mut fields = variant
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, field)| has_attr(&field.attrs, ident));
+                let field = fields.next();
+                if let Some((_, other)) = fields.next() {
+                    return Err(Error::new_spanned(
+                        other,
+                        format!("Only one {:} field is allowed.", ident),
+                    ));
+                }
+                field
+            };
+            if let Some((index, field)) = field {
+                if found.is_some() {
+                    return Err(Error::new_spanned(
+                        variant,
+                        format!("Only one {:} variant is allowed.", ident),
+                    ));
+                }
+                found = Some((variant, index, field));
+            }
+        }
+        Ok(found)
+    } else {
+        Ok(None)
     }
 }
 
@@ -35,14 +317,37 @@ fn field_name_or_index_token(index: usize, field: &Field) -> TokenStream2 {
     }
 }
 
+/// Expression delegated to: `self.field`, or a default value of the field type
+/// when the field is marked `#[rustyline(default)]`.
+///
+/// In the latter case, a `Default` bound on the field type is added to
+/// `generics`.
+fn delegate_token(index: usize, field: &Field, generics: &mut Generics) -> TokenStream2 {
+    let field_type = &field.ty;
+    if has_attr(&field.attrs, "default") {
+        generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(#field_type: ::std::default::Default));
+        quote!((<#field_type as ::std::default::Default>::default()))
+    } else {
+        let field_name_or_index = field_name_or_index_token(index, field);
+        quote!(self.#field_name_or_index)
+    }
+}
+
 #[proc_macro_derive(Completer, attributes(rustyline))]
 pub fn completer_macro_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
-    let generics = input.generics;
+    let mut generics = input.generics;
+    let field = match get_field_by_attr(&input.data, "Completer") {
+        Ok(field) => field,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let delegate = field.map(|(index, field)| delegate_token(index, field, &mut generics));
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
-    let expanded = if let Some((index, field)) = get_field_by_attr(&input.data, "Completer") {
-        let field_name_or_index = field_name_or_index_token(index, field);
+    let expanded = if let (Some((_, field)), Some(delegate)) = (field, delegate) {
         let field_type = &field.ty;
 
         quote! {
@@ -56,11 +361,11 @@ pub fn completer_macro_derive(input: TokenStream) -> TokenStream {
                     pos: usize,
                     ctx: &::yatima_rustyline::Context<'_>,
                 ) -> ::yatima_rustyline::Result<(usize, ::std::vec::Vec<Self::Candidate>)> {
-                    ::yatima_rustyline::completion::Completer::complete(&self.#field_name_or_index, line, pos, ctx)
+                    ::yatima_rustyline::completion::Completer::complete(&#delegate, line, pos, ctx)
                 }
 
                 fn update(&self, line: &mut ::yatima_rustyline::line_buffer::LineBuffer, start: usize, elected: &str) {
-                    ::yatima_rustyline::completion::Completer::update(&self.#field_name_or_index, line, start, elected)
+                    ::yatima_rustyline::completion::Completer::update(&#delegate, line, start, elected)
                 }
             }
         }
@@ -94,16 +399,47 @@ pub fn helper_macro_derive(input: TokenStream) -> TokenStream {
 pub fn highlighter_macro_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
-    let generics = input.generics;
+    let mut generics = input.generics;
+    let field = match get_field_by_attr(&input.data, "Highlighter") {
+        Ok(field) => field,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let delegate = field.map(|(index, field)| delegate_token(index, field, &mut generics));
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
-    let expanded = if let Some((index, field)) = get_field_by_attr(&input.data, "Highlighter") {
-        let field_name_or_index = field_name_or_index_token(index, field);
+    let expanded = if let (Some((_, field)), Some(delegate)) = (field, delegate) {
+        let (highlight_prompt, highlight_continuation_prompt) = if has_attr(&field.attrs, "default")
+        {
+            // the default value does not outlive the call
+            (
+                quote! {
+                    ::std::borrow::Cow::Owned(
+                        ::yatima_rustyline::highlight::Highlighter::highlight_prompt(&#delegate, prompt, default)
+                            .into_owned(),
+                    )
+                },
+                quote! {
+                    ::std::borrow::Cow::Owned(
+                        ::yatima_rustyline::highlight::Highlighter::highlight_continuation_prompt(&#delegate, prompt)
+                            .into_owned(),
+                    )
+                },
+            )
+        } else {
+            (
+                quote! {
+                    ::yatima_rustyline::highlight::Highlighter::highlight_prompt(&#delegate, prompt, default)
+                },
+                quote! {
+                    ::yatima_rustyline::highlight::Highlighter::highlight_continuation_prompt(&#delegate, prompt)
+                },
+            )
+        };
 
         quote! {
             #[automatically_derived]
             impl #impl_generics ::yatima_rustyline::highlight::Highlighter for #name #ty_generics #where_clause {
                 fn highlight<'l>(&self, line: &'l str, pos: usize) -> ::std::borrow::Cow<'l, str> {
-                    ::yatima_rustyline::highlight::Highlighter::highlight(&self.#field_name_or_index, line, pos)
+                    ::yatima_rustyline::highlight::Highlighter::highlight(&#delegate, line, pos)
                 }
 
                 fn highlight_prompt<'b, 's: 'b, 'p: 'b>(
@@ -111,11 +447,18 @@ pub fn highlighter_macro_derive(input: TokenStream) -> TokenStream {
                     prompt: &'p str,
                     default: bool,
                 ) -> ::std::borrow::Cow<'b, str> {
-                    ::yatima_rustyline::highlight::Highlighter::highlight_prompt(&self.#field_name_or_index, prompt, default)
+                    #highlight_prompt
+                }
+
+                fn highlight_continuation_prompt<'b, 's: 'b, 'p: 'b>(
+                    &'s self,
+                    prompt: &'p str,
+                ) -> ::std::borrow::Cow<'b, str> {
+                    #highlight_continuation_prompt
                 }
 
                 fn highlight_hint<'h>(&self, hint: &'h str) -> ::std::borrow::Cow<'h, str> {
-                    ::yatima_rustyline::highlight::Highlighter::highlight_hint(&self.#field_name_or_index, hint)
+                    ::yatima_rustyline::highlight::Highlighter::highlight_hint(&#delegate, hint)
                 }
 
                 fn highlight_candidate<'c>(
@@ -123,11 +466,11 @@ pub fn highlighter_macro_derive(input: TokenStream) -> TokenStream {
                     candidate: &'c str,
                     completion: ::yatima_rustyline::config::CompletionType,
                 ) -> ::std::borrow::Cow<'c, str> {
-                    ::yatima_rustyline::highlight::Highlighter::highlight_candidate(&self.#field_name_or_index, candidate, completion)
+                    ::yatima_rustyline::highlight::Highlighter::highlight_candidate(&#delegate, candidate, completion)
                 }
 
                 fn highlight_char(&self, line: &str, pos: usize) -> bool {
-                    ::yatima_rustyline::highlight::Highlighter::highlight_char(&self.#field_name_or_index, line, pos)
+                    ::yatima_rustyline::highlight::Highlighter::highlight_char(&#delegate, line, pos)
                 }
             }
         }
@@ -145,10 +488,14 @@ pub fn highlighter_macro_derive(input: TokenStream) -> TokenStream {
 pub fn hinter_macro_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
-    let generics = input.generics;
+    let mut generics = input.generics;
+    let field = match get_field_by_attr(&input.data, "Hinter") {
+        Ok(field) => field,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let delegate = field.map(|(index, field)| delegate_token(index, field, &mut generics));
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
-    let expanded = if let Some((index, field)) = get_field_by_attr(&input.data, "Hinter") {
-        let field_name_or_index = field_name_or_index_token(index, field);
+    let expanded = if let (Some((_, field)), Some(delegate)) = (field, delegate) {
         let field_type = &field.ty;
 
         quote! {
@@ -157,7 +504,7 @@ pub fn hinter_macro_derive(input: TokenStream) -> TokenStream {
                 type Hint = <#field_type as ::yatima_rustyline::hint::Hinter>::Hint;
 
                 fn hint(&self, line: &str, pos: usize, ctx: &::yatima_rustyline::Context<'_>) -> ::std::option::Option<Self::Hint> {
-                    ::yatima_rustyline::hint::Hinter::hint(&self.#field_name_or_index, line, pos, ctx)
+                    ::yatima_rustyline::hint::Hinter::hint(&#delegate, line, pos, ctx)
                 }
             }
         }
@@ -176,10 +523,78 @@ pub fn hinter_macro_derive(input: TokenStream) -> TokenStream {
 pub fn validator_macro_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
-    let generics = input.generics;
+    let mut generics = input.generics;
+    if let Data::Enum(_) = input.data {
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        let variant = match get_variant_field_by_attr(&input.data, "Validator") {
+            Ok(variant) => variant,
+            Err(err) => return err.to_compile_error().into(),
+        };
+        let expanded = if let Some((variant, index, field)) = variant {
+            let variant_name = &variant.ident;
+            let field_name_or_index = field_name_or_index_token(index, field);
+            let typing = validate_while_typing_attr(&variant.attrs).and_then(|value| match value {
+                Some(value) => Ok(Some(value)),
+                None => validate_while_typing_attr(&field.attrs),
+            });
+            let typing = match typing {
+                Ok(Some(value)) => quote!(Self::#variant_name { .. } => #value,),
+                Ok(None) => quote! {
+                    Self::#variant_name { #field_name_or_index: validator, .. } => {
+                        ::yatima_rustyline::validate::Validator::validate_while_typing(validator)
+                    }
+                },
+                Err(err) => return err.to_compile_error().into(),
+            };
+
+            quote! {
+                #[automatically_derived]
+                impl #impl_generics ::yatima_rustyline::validate::Validator for #name #ty_generics #where_clause {
+                    fn validate(
+                        &self,
+                        ctx: &mut ::yatima_rustyline::validate::ValidationContext,
+                    ) -> ::yatima_rustyline::Result<::yatima_rustyline::validate::ValidationResult> {
+                        #[allow(unreachable_patterns)]
+                        match self {
+                            Self::#variant_name { #field_name_or_index: validator, .. } => {
+                                ::yatima_rustyline::validate::Validator::validate(validator, ctx)
+                            }
+                            _ => ::std::result::Result::Ok(::yatima_rustyline::validate::ValidationResult::Valid(None)),
+                        }
+                    }
+
+                    fn validate_while_typing(&self) -> bool {
+                        #[allow(unreachable_patterns)]
+                        match self {
+                            #typing
+                            _ => false,
+                        }
+                    }
+                }
+            }
+        } else {
+            quote! {
+                #[automatically_derived]
+                impl #impl_generics ::yatima_rustyline::validate::Validator for #name #ty_generics #where_clause {
+                }
+            }
+        };
+        return TokenStream::from(expanded);
+    }
+    let field = match get_field_by_attr(&input.data, "Validator") {
+        Ok(field) => field,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    let delegate = field.map(|(index, field)| delegate_token(index, field, &mut generics));
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
-    let expanded = if let Some((index, field)) = get_field_by_attr(&input.data, "Validator") {
-        let field_name_or_index = field_name_or_index_token(index, field);
+    let expanded = if let (Some((_, field)), Some(delegate)) = (field, delegate) {
+        let typing = match validate_while_typing_attr(&field.attrs) {
+            Ok(Some(value)) => quote!(#value),
+            Ok(None) => quote! {
+                ::yatima_rustyline::validate::Validator::validate_while_typing(&#delegate)
+            },
+            Err(err) => return err.to_compile_error().into(),
+        };
 
         quote! {
             #[automatically_derived]
@@ -188,11 +603,11 @@ pub fn validator_macro_derive(input: TokenStream) -> TokenStream {
                     &self,
                     ctx: &mut ::yatima_rustyline::validate::ValidationContext,
                 ) -> ::yatima_rustyline::Result<::yatima_rustyline::validate::ValidationResult> {
-                    ::yatima_rustyline::validate::Validator::validate(&self.#field_name_or_index, ctx)
+                    ::yatima_rustyline::validate::Validator::validate(&#delegate, ctx)
                 }
 
                 fn validate_while_typing(&self) -> bool {
-                    ::yatima_rustyline::validate::Validator::validate_while_typing(&self.#field_name_or_index)
+                    #typing
                 }
             }
         }
@@ -0,0 +1,192 @@
+//! Cancellable readline for tokio applications
+use std::io::{self, Write};
+use std::time::Duration;
+
+use log::debug;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader, Stdin};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::ReadlineError;
+use crate::keymap::Refresher;
+use crate::tty::{self, RawReader, Renderer, Term};
+use crate::validate::Validator;
+use crate::{accept_direct, Edition, Editor, Guard, Helper, History, Result};
+
+/// Kept between `Editor::readline_async` calls so that the input read ahead
+/// by a call is seen by the next one
+pub(crate) type AsyncStdin = BufReader<Stdin>;
+
+impl<H: Helper> Editor<H> {
+    /// Same as [`Editor::readline`] but stdin is awaited instead of
+    /// blocking the thread, and the call is aborted with
+    /// [`ReadlineError::Interrupted`] as soon as `cancel` is cancelled.
+    ///
+    /// The line is edited on the current task: the returned future is not
+    /// `Send` (await it from `main` or inside a `LocalSet`). Commands
+    /// needing more keys (completion, incremental search, quoted insert)
+    /// still read them synchronously, and the messages of an
+    /// [`ExternalPrinter`](crate::ExternalPrinter) are displayed with the
+    /// next key. An editor created by [`Editor::with_io`] reads its input
+    /// synchronously.
+    ///
+    /// tokio reads stdin on a blocking thread: after a cancellation, that
+    /// read goes on until the next key (the runtime shutdown waits for it,
+    /// see `tokio::io::stdin`).
+    ///
+    /// ```no_run
+    /// use yatima_rustyline::{CancellationToken, DefaultEditor};
+    ///
+    /// # async fn run() -> yatima_rustyline::Result<()> {
+    /// let mut rl = DefaultEditor::new()?;
+    /// let cancel = CancellationToken::new();
+    /// // e.g. cancelled on shutdown by another task
+    /// let shutdown = cancel.clone();
+    /// let line = rl.readline_async(">> ", &cancel).await?;
+    /// println!("Line: {}", line);
+    /// # drop(shutdown);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(all(unix, feature = "async"))))]
+    pub async fn readline_async(
+        &mut self,
+        prompt: &str,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        let mut stdin = self
+            .async_stdin
+            .take()
+            .unwrap_or_else(|| BufReader::new(tokio::io::stdin()));
+        let user_input = self.readline_from(prompt, &mut stdin, cancel).await;
+        self.async_stdin = Some(stdin);
+        user_input
+    }
+
+    /// `readline_async` reading `input` instead of stdin
+    pub(crate) async fn readline_from<R: AsyncBufRead + Unpin>(
+        &mut self,
+        prompt: &str,
+        input: &mut R,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        self.partial = None;
+        let user_input = self.readline_input_async(prompt, input, cancel).await;
+        self.accept_input(user_input)
+    }
+
+    async fn readline_input_async<R: AsyncBufRead + Unpin>(
+        &mut self,
+        prompt: &str,
+        input: &mut R,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        if self.io.is_some() {
+            return self.readline_input(prompt, None, None);
+        } else if self.term.is_unsupported() {
+            debug!(target: "rustyline", "unsupported terminal");
+            // Write prompt and flush it to stdout
+            let mut stdout = io::stdout();
+            stdout.write_all(prompt.as_bytes())?;
+            stdout.flush()?;
+
+            return readline_direct_async(input, io::stderr(), &self.helper, cancel).await;
+        } else if !self.term.is_input_tty() {
+            debug!(target: "rustyline", "stdin is not a tty");
+            return readline_direct_async(input, io::stderr(), &self.helper, cancel).await;
+        }
+        let (original_mode, term_key_map) = self.term.enable_raw_mode()?;
+        let guard = Guard(&original_mode);
+        let user_input = self
+            .readline_edit_async(prompt, &original_mode, term_key_map, input, cancel)
+            .await;
+        if self.vi_cursor_shape() {
+            // restore default cursor shape
+            let _ = self.term.create_writer().set_cursor_shape(None);
+        }
+        drop(guard); // disable_raw_mode(original_mode)?;
+        self.term.writeln()?;
+        user_input
+    }
+
+    /// Edit the line, awaiting `input` whenever the terminal has no pending
+    /// key: the bytes read are decoded by the terminal reader.
+    async fn readline_edit_async<R: AsyncBufRead + Unpin>(
+        &mut self,
+        prompt: &str,
+        original_mode: &tty::Mode,
+        term_key_map: tty::KeyMap,
+        input: &mut R,
+        cancel: &CancellationToken,
+    ) -> Result<String> {
+        let mut out = self.term.create_writer();
+        let mut rdr = self.term.create_reader(&self.config, term_key_map);
+        rdr.set_sequences(&self.sequences);
+        let no_history = History::new();
+        let mut edition = Edition::new(
+            self,
+            &mut out,
+            &mut rdr,
+            prompt,
+            None,
+            Some(original_mode),
+            &no_history,
+        )?;
+        loop {
+            let ready = match rdr.poll_input(Duration::ZERO) {
+                Ok(ready) => ready,
+                Err(ReadlineError::WindowResized) => {
+                    edition.s.out.update_size();
+                    edition.s.refresh_line()?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if !ready {
+                let n = tokio::select! {
+                    bytes = input.fill_buf() => {
+                        let bytes = bytes?;
+                        rdr.feed(bytes);
+                        bytes.len()
+                    }
+                    _ = cancel.cancelled() => {
+                        edition.s.move_cursor_to_end()?;
+                        return Err(ReadlineError::Interrupted);
+                    }
+                };
+                if n == 0 {
+                    return Err(ReadlineError::Eof);
+                }
+                input.consume(n);
+                // SIGWINCH only interrupts a blocking read of the terminal
+                let columns = edition.s.out.get_columns();
+                edition.s.out.update_size();
+                if edition.s.out.get_columns() != columns {
+                    edition.s.refresh_line()?;
+                }
+            }
+            if edition.step(&mut rdr, &mut None)? {
+                return edition.finish();
+            }
+        }
+    }
+}
+
+/// `readline_direct` awaiting the lines of `reader`
+async fn readline_direct_async(
+    reader: &mut (impl AsyncBufRead + Unpin),
+    mut writer: impl Write,
+    validator: &Option<impl Validator>,
+    cancel: &CancellationToken,
+) -> Result<String> {
+    let mut input = String::new();
+
+    loop {
+        let n = tokio::select! {
+            n = reader.read_line(&mut input) => n?,
+            _ = cancel.cancelled() => return Err(ReadlineError::Interrupted),
+        };
+        if let Some(line) = accept_direct(&mut input, n, &mut writer, validator)? {
+            return Ok(line);
+        }
+    }
+}
@@ -9,6 +9,7 @@ use crate::keymap::{Anchor, At, Cmd, Movement, Word};
 use crate::keymap::{InputState, Refresher};
 use crate::kill_ring::{KillRing, Mode};
 use crate::line_buffer::WordAction;
+use crate::tty::Renderer;
 use crate::{Helper, Result};
 
 pub enum Status {
@@ -16,9 +17,9 @@ pub enum Status {
     Submit,
 }
 
-pub fn execute<H: Helper>(
+pub fn execute<H: Helper, W: Renderer>(
     cmd: Cmd,
-    s: &mut State<'_, '_, H>,
+    s: &mut State<'_, '_, H, W>,
     input_state: &InputState,
     kill_ring: &Arc<Mutex<KillRing>>,
     config: &Config,
@@ -26,7 +27,11 @@ pub fn execute<H: Helper>(
     use Status::{Proceed, Submit};
 
     match cmd {
-        Cmd::EndOfFile | Cmd::AcceptLine | Cmd::AcceptOrInsertLine { .. } | Cmd::Newline => {
+        Cmd::EndOfFile
+        | Cmd::AcceptLine
+        | Cmd::AcceptOrInsertLine { .. }
+        | Cmd::Newline
+        | Cmd::OperateAndGetNext => {
             if s.has_hint() || !s.is_default_prompt() {
                 // Force a refresh without hints to leave the previous
                 // line as the user typed it after a newline.
@@ -35,13 +40,27 @@ pub fn execute<H: Helper>(
         }
         _ => {}
     };
+    if !matches!(
+        cmd,
+        Cmd::SelfInsert(1, _) | Cmd::Kill(Movement::BackwardChar(1))
+    ) {
+        // the closing brackets may have been moved or deleted
+        s.auto_closed.clear();
+    }
     match cmd {
         Cmd::CompleteHint => {
             complete_hint_line(s)?;
         }
+        Cmd::SelfInsert(1, c) if config.auto_close_brackets() => {
+            s.edit_insert_bracket(c)?;
+        }
         Cmd::SelfInsert(n, c) => {
             s.edit_insert(c, n)?;
         }
+        Cmd::Complete if s.helper.is_none() => {
+            // nothing to complete: insert a literal tab
+            s.edit_insert('\t', 1)?;
+        }
         Cmd::Insert(n, text) => {
             s.edit_yank(input_state, &text, Anchor::Before, n)?;
         }
@@ -114,8 +133,13 @@ pub fn execute<H: Helper>(
         Cmd::Yank(n, anchor) => {
             // retrieve (yank) last item killed
             let mut kill_ring = kill_ring.lock().unwrap();
-            if let Some(text) = kill_ring.yank() {
+            if let Some(text) = kill_ring.yank(n) {
+                let len = s.line.len();
                 s.edit_yank(input_state, text, anchor, n)?;
+                if s.line.len() == len {
+                    // nothing yanked (line is full): no yank-pop
+                    kill_ring.reset();
+                }
             }
         }
         Cmd::ViYankTo(ref mvt) => {
@@ -127,12 +151,12 @@ pub fn execute<H: Helper>(
         Cmd::Newline => {
             s.edit_insert('\n', 1)?;
         }
-        Cmd::AcceptLine | Cmd::AcceptOrInsertLine { .. } => {
+        Cmd::AcceptLine | Cmd::AcceptOrInsertLine { .. } | Cmd::OperateAndGetNext => {
             let validation_result = s.validate()?;
             let valid = validation_result.is_valid();
             let end = s.line.is_end_of_input();
             match (cmd, valid, end) {
-                (Cmd::AcceptLine, ..)
+                (Cmd::AcceptLine | Cmd::OperateAndGetNext, ..)
                 | (Cmd::AcceptOrInsertLine { .. }, true, true)
                 | (
                     Cmd::AcceptOrInsertLine {
@@ -147,6 +171,9 @@ pub fn execute<H: Helper>(
                 | (Cmd::AcceptOrInsertLine { .. }, true, false) => {
                     if valid || !validation_result.has_message() {
                         s.edit_insert('\n', 1)?;
+                    } else {
+                        // rejected by the validator
+                        s.out.beep()?;
                     }
                 }
                 _ => unreachable!(),
@@ -168,6 +195,9 @@ pub fn execute<H: Helper>(
             // capitalize word after point
             s.edit_word(WordAction::Capitalize)?;
         }
+        Cmd::Kill(Movement::BackwardChar(1)) if config.auto_close_brackets() => {
+            s.edit_backspace_bracket()?;
+        }
         Cmd::Kill(ref mvt) => {
             s.edit_kill(mvt)?;
         }
@@ -212,6 +242,22 @@ pub fn execute<H: Helper>(
         Cmd::Undo(n) => {
             if s.changes.borrow_mut().undo(&mut s.line, n) {
                 s.refresh_line()?;
+            } else {
+                s.out.beep()?;
+            }
+        }
+        Cmd::Redo(n) => {
+            let mut redone = false;
+            for _ in 0..n {
+                if !s.changes.borrow_mut().redo(&mut s.line) {
+                    break;
+                }
+                redone = true;
+            }
+            if redone {
+                s.refresh_line()?;
+            } else {
+                s.out.beep()?;
             }
         }
         Cmd::Dedent(mvt) => {
@@ -220,6 +266,10 @@ pub fn execute<H: Helper>(
         Cmd::Indent(mvt) => {
             s.edit_indent(&mvt, config.indent_size(), false)?;
         }
+        Cmd::Abort => {
+            // nothing to abort
+            s.out.beep()?;
+        }
         Cmd::Interrupt => {
             // Move to end, in case cursor was in the middle of the
             // line, so that next thing application prints goes after
@@ -3,7 +3,8 @@ use std::borrow::Cow::{self, Borrowed, Owned};
 use std::fs;
 use std::path::{self, Path};
 
-use crate::line_buffer::LineBuffer;
+use crate::line_buffer::{self, LineBuffer};
+use crate::validate;
 use crate::{Context, Result};
 use memchr::memchr;
 
@@ -13,6 +14,21 @@ pub trait Candidate {
     fn display(&self) -> &str;
     /// Text to insert in line.
     fn replacement(&self) -> &str;
+    /// Short explanation displayed next to the candidate when listing
+    /// alternatives (like `commit  Record changes to the repository`).
+    ///
+    /// When at least one candidate has a description, alternatives are listed
+    /// one per row, with descriptions aligned in a second column.
+    fn description(&self) -> Option<&str> {
+        None
+    }
+    /// Relevance of the candidate.
+    ///
+    /// When at least one candidate has a score, alternatives are sorted by
+    /// score, highest first, before being cycled through or listed.
+    fn score(&self) -> Option<f64> {
+        None
+    }
 }
 
 impl Candidate for String {
@@ -64,6 +80,55 @@ impl Candidate for Pair {
     }
 }
 
+/// Completion candidate with a relevance score
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompletionWithScore {
+    /// Text to display when listing alternatives.
+    pub display: String,
+    /// Text to insert in line.
+    pub replacement: String,
+    /// Relevance of the candidate, the highest comes first.
+    pub score: f64,
+}
+
+impl CompletionWithScore {
+    /// Constructor
+    pub fn new<D: Into<String>, R: Into<String>>(display: D, replacement: R, score: f64) -> Self {
+        Self {
+            display: display.into(),
+            replacement: replacement.into(),
+            score,
+        }
+    }
+}
+
+impl Candidate for CompletionWithScore {
+    fn display(&self) -> &str {
+        self.display.as_str()
+    }
+
+    fn replacement(&self) -> &str {
+        self.replacement.as_str()
+    }
+
+    fn score(&self) -> Option<f64> {
+        Some(self.score)
+    }
+}
+
+/// Sort `candidates` by score, highest first.
+///
+/// The order of candidates with the same score (or without score) is kept.
+pub(crate) fn sort_by_score<C: Candidate>(candidates: &mut [C]) {
+    if candidates.iter().any(|c| c.score().is_some()) {
+        // candidates without score come last
+        candidates.sort_by(|a, b| match (a.score(), b.score()) {
+            (Some(a), Some(b)) => b.total_cmp(&a),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        });
+    }
+}
+
 // TODO: let the implementers customize how the candidate(s) are displayed
 // https://github.com/kkawakam/rustyline/issues/302
 
@@ -79,6 +144,9 @@ pub trait Completer {
     /// partial word to be completed.
     ///
     /// ("ls /usr/loc", 11) => Ok((3, vec!["/usr/local/"]))
+    ///
+    /// `ctx` gives access to the history, the terminal width and the cursor
+    /// column.
     fn complete(
         &self, // FIXME should be `&mut self`
         line: &str,
@@ -89,9 +157,12 @@ pub trait Completer {
         Ok((0, Vec::with_capacity(0)))
     }
     /// Updates the edited `line` with the `elected` candidate.
+    ///
+    /// By default, only the word being completed (from `start` to the cursor)
+    /// is replaced, see [`line_buffer::replace_word`].
     fn update(&self, line: &mut LineBuffer, start: usize, elected: &str) {
         let end = line.pos();
-        line.replace(start..end, elected);
+        line_buffer::replace_word(line, start, end, elected);
     }
 }
 
@@ -144,6 +215,18 @@ box_completer! { Box Rc Arc }
 pub struct FilenameCompleter {
     break_chars: &'static [u8],
     double_quotes_special_chars: &'static [u8],
+    case_insensitive: bool,
+    matching: FilenameMatch,
+}
+
+/// How directory entries are matched against the partial file name.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FilenameMatch {
+    /// Entries starting with the partial file name
+    #[default]
+    Prefix,
+    /// Entries containing the partial file name
+    Substring,
 }
 
 const DOUBLE_QUOTES_ESCAPE_CHAR: Option<char> = Some('\\');
@@ -192,13 +275,38 @@ impl FilenameCompleter {
         Self {
             break_chars: &DEFAULT_BREAK_CHARS,
             double_quotes_special_chars: &DOUBLE_QUOTES_SPECIAL_CHARS,
+            case_insensitive: cfg!(any(windows, target_os = "macos")),
+            matching: FilenameMatch::default(),
         }
     }
 
+    /// Match file names ignoring case.
+    ///
+    /// By default, matching is case insensitive only on Windows and macOS.
+    pub fn set_case_insensitive(&mut self, yes: bool) {
+        self.case_insensitive = yes;
+    }
+
+    /// Set how file names are matched against the partial path.
+    ///
+    /// By default, file names must start with the partial path.
+    pub fn set_matching(&mut self, matching: FilenameMatch) {
+        self.matching = matching;
+    }
+
     /// Takes the currently edited `line` with the cursor `pos`ition and
     /// returns the start position and the completion candidates for the
     /// partial path to be completed.
     pub fn complete_path(&self, line: &str, pos: usize) -> Result<(usize, Vec<Pair>)> {
+        self.complete_path_ignoring_case(line, pos, self.case_insensitive)
+    }
+
+    fn complete_path_ignoring_case(
+        &self,
+        line: &str,
+        pos: usize,
+        case_insensitive: bool,
+    ) -> Result<(usize, Vec<Pair>)> {
         let (start, path, esc_char, break_chars, quote) =
             if let Some((idx, quote)) = find_unclosed_quote(&line[..pos]) {
                 let start = idx + 1;
@@ -224,7 +332,14 @@ impl FilenameCompleter {
                 let path = unescape(path, ESCAPE_CHAR);
                 (start, path, ESCAPE_CHAR, &self.break_chars, Quote::None)
             };
-        let mut matches = filename_complete(&path, esc_char, break_chars, quote);
+        let mut matches = filename_complete(
+            &path,
+            esc_char,
+            break_chars,
+            quote,
+            case_insensitive,
+            self.matching,
+        );
         #[allow(clippy::unnecessary_sort_by)]
         matches.sort_by(|a, b| a.display().cmp(b.display()));
         Ok((start, matches))
@@ -240,8 +355,235 @@ impl Default for FilenameCompleter {
 impl Completer for FilenameCompleter {
     type Candidate = Pair;
 
-    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Result<(usize, Vec<Pair>)> {
-        self.complete_path(line, pos)
+    /// Case is ignored as configured by
+    /// [`Context::completion_ignore_case`], if set.
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Result<(usize, Vec<Pair>)> {
+        let case_insensitive = ctx
+            .completion_ignore_case()
+            .unwrap_or(self.case_insensitive);
+        self.complete_path_ignoring_case(line, pos, case_insensitive)
+    }
+}
+
+/// Completes the word before the cursor (delimited by whitespace) against a
+/// fixed list of words (keywords, commands, ...).
+#[derive(Clone, Debug, Default)]
+pub struct WordCompleter {
+    words: Vec<String>,
+}
+
+const WHITESPACE: [u8; 3] = [b' ', b'\t', b'\n'];
+
+impl WordCompleter {
+    /// Constructor
+    #[must_use]
+    pub fn new(words: Vec<String>) -> Self {
+        Self { words }
+    }
+}
+
+impl Completer for WordCompleter {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Result<(usize, Vec<String>)> {
+        let (start, word) = extract_word(line, pos, None, &WHITESPACE);
+        let candidates = self
+            .words
+            .iter()
+            .filter(|w| w.starts_with(word))
+            .cloned()
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+/// Wraps another completer and closes the brackets left open before the
+/// cursor: with `f(x` and `x_value` suggested by the inner completer,
+/// `x_value)` is suggested.
+///
+/// Brackets are matched like [`MatchingBracketValidator`] does (nothing is
+/// appended when they are mismatched) except that brackets inside
+/// double-quoted string literals are ignored.
+///
+/// [`MatchingBracketValidator`]: crate::validate::MatchingBracketValidator
+#[derive(Clone, Debug, Default)]
+pub struct PairCompleter<C> {
+    completer: C,
+}
+
+impl<C> PairCompleter<C> {
+    /// Constructor
+    #[must_use]
+    pub fn new(completer: C) -> Self {
+        Self { completer }
+    }
+}
+
+impl<C: Completer> Completer for PairCompleter<C> {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Result<(usize, Vec<Pair>)> {
+        let (start, candidates) = self.completer.complete(line, pos, ctx)?;
+        let mut stack = validate::unclosed_brackets(&line[..pos], true).unwrap_or_default();
+        // brackets already closed after the cursor
+        for c in validate::unpaired_closing_brackets(&line[pos..], true) {
+            match (stack.last(), c) {
+                (Some('('), ')') | (Some('['), ']') | (Some('{'), '}') => {
+                    stack.pop();
+                }
+                _ => break,
+            }
+        }
+        let closing: String = stack
+            .iter()
+            .rev()
+            .map(|c| match c {
+                '(' => ')',
+                '[' => ']',
+                _ => '}',
+            })
+            .collect();
+        let candidates = candidates
+            .iter()
+            .map(|c| Pair {
+                display: c.display().to_owned(),
+                replacement: format!("{}{}", c.replacement(), closing),
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+/// Node of a command tree completed by [`CommandCompleter`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommandNode {
+    /// Command name
+    pub name: String,
+    /// Short explanation displayed next to the name
+    pub description: Option<String>,
+    /// Option flags (like `--verbose` or `-v`)
+    pub flags: Vec<String>,
+    /// Subcommands
+    pub children: Vec<CommandNode>,
+}
+
+impl CommandNode {
+    /// Constructor
+    #[must_use]
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    fn child(&self, name: &str) -> Option<&CommandNode> {
+        self.children.iter().find(|c| c.name == name)
+    }
+}
+
+/// Subcommand or flag suggested by [`CommandCompleter`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandCandidate {
+    name: String,
+    description: Option<String>,
+}
+
+impl Candidate for CommandCandidate {
+    fn display(&self) -> &str {
+        &self.name
+    }
+
+    fn replacement(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+/// Completes subcommands and flags from a command tree.
+///
+/// The words (delimited by whitespace) before the cursor select the deepest
+/// matching subcommand, then its subcommands and flags are suggested for the
+/// word being typed.
+#[derive(Clone, Debug, Default)]
+pub struct CommandCompleter {
+    root: CommandNode,
+}
+
+impl CommandCompleter {
+    /// Constructor. `root` name is not completed: the line starts with one
+    /// of its subcommands.
+    #[must_use]
+    pub fn new(root: CommandNode) -> Self {
+        Self { root }
+    }
+
+    /// Build the command tree from a `clap::Command`.
+    ///
+    /// Hidden arguments and subcommands are left out.
+    #[cfg(feature = "clap")]
+    #[must_use]
+    pub fn from_clap(cmd: &clap::Command) -> Self {
+        Self::new(clap_node(cmd))
+    }
+}
+
+#[cfg(feature = "clap")]
+fn clap_node(cmd: &clap::Command) -> CommandNode {
+    let mut flags = Vec::new();
+    for arg in cmd.get_arguments().filter(|arg| !arg.is_hide_set()) {
+        if let Some(long) = arg.get_long() {
+            flags.push(format!("--{}", long));
+        }
+        if let Some(short) = arg.get_short() {
+            flags.push(format!("-{}", short));
+        }
+    }
+    CommandNode {
+        name: cmd.get_name().to_owned(),
+        description: cmd.get_about().map(ToString::to_string),
+        flags,
+        children: cmd
+            .get_subcommands()
+            .filter(|sub| !sub.is_hide_set())
+            .map(clap_node)
+            .collect(),
+    }
+}
+
+impl Completer for CommandCompleter {
+    type Candidate = CommandCandidate;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> Result<(usize, Vec<CommandCandidate>)> {
+        let (start, word) = extract_word(line, pos, None, &WHITESPACE);
+        let mut node = &self.root;
+        for token in line[..start].split_whitespace() {
+            // flags and arguments do not change the current command
+            if let Some(child) = node.child(token) {
+                node = child;
+            }
+        }
+        let commands = node.children.iter().map(|c| CommandCandidate {
+            name: c.name.clone(),
+            description: c.description.clone(),
+        });
+        let flags = node.flags.iter().map(|f| CommandCandidate {
+            name: f.clone(),
+            description: None,
+        });
+        let candidates = commands
+            .chain(flags)
+            .filter(|c| c.name.starts_with(word))
+            .collect();
+        Ok((start, candidates))
     }
 }
 
@@ -321,6 +663,8 @@ fn filename_complete(
     esc_char: Option<char>,
     break_chars: &[u8],
     quote: Quote,
+    case_insensitive: bool,
+    matching: FilenameMatch,
 ) -> Vec<Pair> {
     #[cfg(feature = "with-dirs")]
     use dirs_next::home_dir;
@@ -370,11 +714,15 @@ fn filename_complete(
 
     // if any of the below IO operations have errors, just ignore them
     if let Ok(read_dir) = dir.read_dir() {
-        let file_name = normalize(file_name);
+        let file_name = normalize(file_name, case_insensitive);
         for entry in read_dir.flatten() {
             if let Some(s) = entry.file_name().to_str() {
-                let ns = normalize(s);
-                if ns.starts_with(file_name.as_ref()) {
+                let ns = normalize(s, case_insensitive);
+                let found = match matching {
+                    FilenameMatch::Prefix => ns.starts_with(file_name.as_ref()),
+                    FilenameMatch::Substring => ns.contains(file_name.as_ref()),
+                };
+                if found {
                     if let Ok(metadata) = fs::metadata(entry.path()) {
                         let mut path = String::from(dir_name) + s;
                         if metadata.is_dir() {
@@ -392,15 +740,12 @@ fn filename_complete(
     entries
 }
 
-#[cfg(any(windows, target_os = "macos"))]
-fn normalize(s: &str) -> Cow<str> {
-    // case insensitive
-    Owned(s.to_lowercase())
-}
-
-#[cfg(not(any(windows, target_os = "macos")))]
-fn normalize(s: &str) -> Cow<str> {
-    Cow::Borrowed(s)
+fn normalize(s: &str, case_insensitive: bool) -> Cow<str> {
+    if case_insensitive {
+        Owned(s.to_lowercase())
+    } else {
+        Borrowed(s)
+    }
 }
 
 /// Given a `line` and a cursor `pos`ition,
@@ -632,9 +977,279 @@ mod tests {
         )
     }
 
-    #[cfg(windows)]
+    #[test]
+    pub fn word_completer() {
+        use super::{Completer, WordCompleter};
+        use crate::history::History;
+        use crate::Context;
+
+        let completer = WordCompleter::new(vec![
+            "help".to_owned(),
+            "hello".to_owned(),
+            "quit".to_owned(),
+        ]);
+        let history = History::new();
+        let ctx = Context::new(&history);
+        // end of line
+        assert_eq!(
+            (5, vec!["help".to_owned(), "hello".to_owned()]),
+            completer.complete("quit he", 7, &ctx).unwrap()
+        );
+        // cursor in the middle of a word: only the part before the cursor is
+        // matched
+        assert_eq!(
+            (0, vec!["quit".to_owned()]),
+            completer.complete("qu help", 2, &ctx).unwrap()
+        );
+        assert_eq!(
+            (3, vec!["hello".to_owned()]),
+            completer.complete("qu hellx", 7, &ctx).unwrap()
+        );
+        assert_eq!((0, vec![]), completer.complete("x", 1, &ctx).unwrap());
+    }
+
+    fn git() -> super::CommandNode {
+        use super::CommandNode;
+
+        let mut add = CommandNode::new("add");
+        add.flags = vec!["--fetch".to_owned(), "-f".to_owned()];
+        let mut remote = CommandNode::new("remote");
+        remote.description = Some("Manage remotes".to_owned());
+        remote.flags = vec!["--verbose".to_owned()];
+        remote.children = vec![add, CommandNode::new("remove")];
+        let mut root = CommandNode::new("git");
+        root.flags = vec!["--version".to_owned()];
+        root.children = vec![remote, CommandNode::new("rebase")];
+        root
+    }
+
+    #[test]
+    pub fn command_completer() {
+        use super::{Candidate, CommandCompleter, Completer};
+        use crate::history::History;
+        use crate::Context;
+
+        let completer = CommandCompleter::new(git());
+        let history = History::new();
+        let ctx = Context::new(&history);
+        let complete = |line: &str| -> (usize, Vec<String>) {
+            let (start, candidates) = completer.complete(line, line.len(), &ctx).unwrap();
+            (
+                start,
+                candidates
+                    .iter()
+                    .map(|c| c.replacement().to_owned())
+                    .collect(),
+            )
+        };
+        // depth 0
+        assert_eq!(
+            (
+                0,
+                vec![
+                    "remote".to_owned(),
+                    "rebase".to_owned(),
+                    "--version".to_owned()
+                ]
+            ),
+            complete("")
+        );
+        assert_eq!((0, vec!["remote".to_owned()]), complete("rem"));
+        assert_eq!((0, vec!["--version".to_owned()]), complete("--"));
+        // depth 1
+        assert_eq!(
+            (
+                7,
+                vec![
+                    "add".to_owned(),
+                    "remove".to_owned(),
+                    "--verbose".to_owned()
+                ]
+            ),
+            complete("remote ")
+        );
+        assert_eq!((7, vec!["remove".to_owned()]), complete("remote rem"));
+        // depth 2, after a flag of the parent command
+        assert_eq!(
+            (21, vec!["--fetch".to_owned(), "-f".to_owned()]),
+            complete("remote --verbose add ")
+        );
+        assert_eq!((11, vec!["--fetch".to_owned()]), complete("remote add --"));
+        // leaf
+        assert_eq!((21, vec![]), complete("remote remove origin "));
+
+        let (_, candidates) = completer.complete("r", 1, &ctx).unwrap();
+        assert_eq!(Some("Manage remotes"), candidates[0].description());
+        assert_eq!(None, candidates[1].description());
+    }
+
+    #[test]
+    #[cfg(feature = "clap")]
+    pub fn command_completer_from_clap() {
+        use super::CommandCompleter;
+        use clap::{Arg, ArgAction, Command};
+
+        let flag = |name: &'static str| Arg::new(name).long(name).action(ArgAction::SetTrue);
+        let app = Command::new("git")
+            .arg(flag("version"))
+            .subcommand(
+                Command::new("remote")
+                    .about("Manage remotes")
+                    .arg(flag("verbose"))
+                    .subcommand(Command::new("add").arg(flag("fetch").short('f')))
+                    .subcommand(Command::new("remove"))
+                    .subcommand(Command::new("prune").hide(true)),
+            )
+            .subcommand(Command::new("rebase"));
+        assert_eq!(git(), CommandCompleter::from_clap(&app).root);
+    }
+
+    #[test]
+    pub fn pair_completer() {
+        use super::{Completer, PairCompleter};
+        use crate::history::History;
+        use crate::{Context, Result};
+
+        struct Value;
+        impl Completer for Value {
+            type Candidate = &'static str;
+
+            fn complete(
+                &self,
+                _: &str,
+                pos: usize,
+                _: &Context<'_>,
+            ) -> Result<(usize, Vec<&'static str>)> {
+                Ok((pos - 1, vec!["x_value"]))
+            }
+        }
+
+        let completer = PairCompleter::new(Value);
+        let history = History::new();
+        let ctx = Context::new(&history);
+        let replacements = |line: &str| -> Vec<String> {
+            let (_, candidates) = completer.complete(line, line.len(), &ctx).unwrap();
+            candidates.into_iter().map(|c| c.replacement).collect()
+        };
+        assert_eq!(vec!["x_value"], replacements("x"));
+        assert_eq!(vec!["x_value)"], replacements("f(x"));
+        // nested
+        assert_eq!(vec!["x_value)]})"], replacements("f({a: [g(x"));
+        assert_eq!(vec!["x_value)"], replacements("f(g(y), x"));
+        // brackets in string literals are ignored
+        assert_eq!(vec!["x_value)"], replacements("f(\"(\\\"[\", x"));
+        assert_eq!(vec!["x_value"], replacements("\")\" x"));
+        // mismatched
+        assert_eq!(vec!["x_value"], replacements("f(]x"));
+        // brackets opened after the cursor are ignored
+        let (_, candidates) = completer.complete("f(x; g(", 3, &ctx).unwrap();
+        assert_eq!("x_value)", candidates[0].replacement);
+        // brackets already closed after the cursor
+        let (_, candidates) = completer.complete("f(x)", 3, &ctx).unwrap();
+        assert_eq!("x_value", candidates[0].replacement);
+        let (_, candidates) = completer.complete("f([x])", 4, &ctx).unwrap();
+        assert_eq!("x_value", candidates[0].replacement);
+        let (_, candidates) = completer.complete("f([x, g(y)]", 4, &ctx).unwrap();
+        assert_eq!("x_value)", candidates[0].replacement);
+    }
+
     #[test]
     pub fn normalize() {
-        assert_eq!(super::normalize("Windows"), "windows")
+        assert_eq!(super::normalize("Windows", true), "windows");
+        assert_eq!(super::normalize("Windows", false), "Windows")
+    }
+
+    #[test]
+    pub fn filename_matching() {
+        use super::{Candidate, FilenameCompleter, FilenameMatch};
+        use std::path::MAIN_SEPARATOR as SEP;
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in &["Readme.md", "readme.txt", "SRC", "my_read.rs"] {
+            std::fs::File::create(dir.path().join(name)).unwrap();
+        }
+        let complete = |completer: &FilenameCompleter, partial: &str| {
+            let path = format!("{}{}{}", dir.path().display(), SEP, partial);
+            let (_, candidates) = completer.complete_path(&path, path.len()).unwrap();
+            candidates
+                .iter()
+                .map(|c| c.display().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let mut completer = FilenameCompleter::new();
+        completer.set_case_insensitive(false);
+        assert_eq!(vec!["readme.txt"], complete(&completer, "read"));
+        assert_eq!(vec!["SRC"], complete(&completer, "S"));
+
+        completer.set_case_insensitive(true);
+        assert_eq!(
+            vec!["Readme.md", "readme.txt"],
+            complete(&completer, "read")
+        );
+        assert_eq!(vec!["SRC"], complete(&completer, "s"));
+
+        completer.set_matching(FilenameMatch::Substring);
+        assert_eq!(
+            vec!["Readme.md", "my_read.rs", "readme.txt"],
+            complete(&completer, "READ")
+        );
+
+        completer.set_case_insensitive(false);
+        assert_eq!(
+            vec!["my_read.rs", "readme.txt"],
+            complete(&completer, "read")
+        );
+    }
+
+    #[test]
+    pub fn filename_completion_ignore_case() {
+        use super::{Candidate, Completer, FilenameCompleter};
+        use crate::history::History;
+        use crate::Context;
+        use std::path::MAIN_SEPARATOR as SEP;
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in &["Readme.md", "readme.txt"] {
+            std::fs::File::create(dir.path().join(name)).unwrap();
+        }
+        let path = format!("{}{}read", dir.path().display(), SEP);
+        let history = History::new();
+        let mut ctx = Context::new(&history);
+        let mut completer = FilenameCompleter::new();
+        completer.set_case_insensitive(false);
+        let complete = |completer: &FilenameCompleter, ctx: &Context<'_>| {
+            let (_, candidates) = completer.complete(&path, path.len(), ctx).unwrap();
+            candidates
+                .iter()
+                .map(|c| c.display().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec!["readme.txt"], complete(&completer, &ctx));
+        // editor setting overrides the completer one
+        ctx.completion_ignore_case = Some(true);
+        assert_eq!(vec!["Readme.md", "readme.txt"], complete(&completer, &ctx));
+        completer.set_case_insensitive(true);
+        ctx.completion_ignore_case = Some(false);
+        assert_eq!(vec!["readme.txt"], complete(&completer, &ctx));
+    }
+
+    #[test]
+    pub fn sort_by_score() {
+        use super::{Candidate, CompletionWithScore};
+        let mut candidates = vec![
+            CompletionWithScore::new("a", "a", 0.5),
+            CompletionWithScore::new("b", "b", 2.0),
+            CompletionWithScore::new("c", "c", 0.5),
+            CompletionWithScore::new("d", "d", 1.0),
+        ];
+        super::sort_by_score(&mut candidates);
+        let order: Vec<&str> = candidates.iter().map(Candidate::display).collect();
+        assert_eq!(vec!["b", "d", "a", "c"], order);
+
+        let mut candidates = vec!["b".to_owned(), "a".to_owned()];
+        super::sort_by_score(&mut candidates);
+        assert_eq!(vec!["b", "a"], candidates);
     }
 }
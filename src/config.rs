@@ -1,6 +1,8 @@
 //! Customize line editor
 use std::default::Default;
 
+use crate::line_buffer::MAX_LINE;
+
 /// User preferences
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Config {
@@ -8,10 +10,19 @@ pub struct Config {
     max_history_size: usize, // history_max_entries
     history_duplicates: HistoryDuplicates,
     history_ignore_space: bool,
+    /// Save the time each entry was added in the history file.
+    #[cfg(feature = "with-timestamps")]
+    history_timestamps: bool,
     completion_type: CompletionType,
     /// When listing completion alternatives, only display
     /// one screen of possibilities at a time.
     completion_prompt_limit: usize,
+    /// What to do when there is no completion
+    completion_display_mode: CompletionDisplayMode,
+    /// Whether completion ignores case (`None`: up to the completer)
+    completion_ignore_case: Option<bool>,
+    /// Case sensitivity of the incremental history search
+    search_case: SearchCase,
     /// Duration (milliseconds) Rustyline will wait for a character when
     /// reading an ambiguous key sequence.
     keyseq_timeout: i32,
@@ -34,10 +45,37 @@ pub struct Config {
     check_cursor_position: bool,
     /// Bracketed paste on unix platform
     enable_bracketed_paste: bool,
+    /// Prompt displayed at the beginning of each line after the first one
+    continuation_prompt: &'static str,
+    /// Maximum number of bytes in the edited line.
+    max_line_length: usize,
+    /// Let Ctrl-C raise `SIGINT` instead of reading it as a key
+    ctrlc_as_signal: bool,
+    /// Read invalid UTF-8 input as U+FFFD instead of failing
+    replace_invalid_utf8: bool,
+    /// Insert closing brackets along with the opening ones
+    auto_close_brackets: bool,
+    /// Change the cursor shape with the vi input mode
+    vi_cursor_shape: bool,
 }
 
 impl Config {
     /// Returns a `Config` builder.
+    ///
+    /// ```
+    /// use yatima_rustyline::config::{BellStyle, Config, EditMode};
+    /// use yatima_rustyline::DefaultEditor;
+    ///
+    /// let config = Config::builder()
+    ///     .max_history_size(1000)
+    ///     .edit_mode(EditMode::Vi)
+    ///     .auto_add_history(true)
+    ///     .bell_style(BellStyle::None)
+    ///     .build();
+    /// let mut rl = DefaultEditor::with_config(config)?;
+    /// rl.add_history_entry("first");
+    /// # Ok::<(), yatima_rustyline::error::ReadlineError>(())
+    /// ```
     #[must_use]
     pub fn builder() -> Builder {
         Builder::new()
@@ -83,6 +121,21 @@ impl Config {
         self.history_ignore_space = yes;
     }
 
+    /// Tell if the time each entry was added is saved in the history file
+    /// (as a `#<unix_timestamp>` line before the entry).
+    ///
+    /// By default, it is not.
+    #[cfg(feature = "with-timestamps")]
+    #[must_use]
+    pub fn history_timestamps(&self) -> bool {
+        self.history_timestamps
+    }
+
+    #[cfg(feature = "with-timestamps")]
+    pub(crate) fn set_history_timestamps(&mut self, yes: bool) {
+        self.history_timestamps = yes;
+    }
+
     /// Completion behaviour.
     ///
     /// By default, `CompletionType::Circular`.
@@ -99,6 +152,34 @@ impl Config {
         self.completion_prompt_limit
     }
 
+    /// What to do when the completer returns no candidate.
+    ///
+    /// By default, `CompletionDisplayMode::Bell`.
+    #[must_use]
+    pub fn completion_display_mode(&self) -> CompletionDisplayMode {
+        self.completion_display_mode
+    }
+
+    /// Whether completion ignores case (like readline
+    /// `completion-ignore-case`), see [`Context::completion_ignore_case`].
+    ///
+    /// By default (`None`), it is up to the completer.
+    ///
+    /// [`Context::completion_ignore_case`]: crate::Context::completion_ignore_case
+    #[must_use]
+    pub fn completion_ignore_case(&self) -> Option<bool> {
+        self.completion_ignore_case
+    }
+
+    /// Case sensitivity of the incremental history search.
+    ///
+    /// By default, `SearchCase::Smart` (`SearchCase::Insensitive` with the
+    /// `case_insensitive_history_search` feature).
+    #[must_use]
+    pub fn search_case(&self) -> SearchCase {
+        self.search_case
+    }
+
     /// Duration (milliseconds) Rustyline will wait for a character when
     /// reading an ambiguous key sequence (used for `EditMode::Vi` mode on unix
     /// platform).
@@ -129,6 +210,10 @@ impl Config {
         self.bell_style
     }
 
+    pub(crate) fn set_bell_style(&mut self, bell_style: BellStyle) {
+        self.bell_style = bell_style;
+    }
+
     /// Tell if colors should be enabled.
     ///
     /// By default, they are except if stdout is not a TTY.
@@ -192,6 +277,66 @@ impl Config {
     pub fn enable_bracketed_paste(&self) -> bool {
         self.enable_bracketed_paste
     }
+
+    /// Prompt displayed at the beginning of each line of a multiline input
+    /// after the first one.
+    ///
+    /// By default, two spaces.
+    #[must_use]
+    pub fn continuation_prompt(&self) -> &'static str {
+        self.continuation_prompt
+    }
+
+    pub(crate) fn set_continuation_prompt(&mut self, prompt: &'static str) {
+        self.continuation_prompt = prompt;
+    }
+
+    /// Maximum number of bytes in the edited line.
+    ///
+    /// By default, 4096.
+    #[must_use]
+    pub fn max_line_length(&self) -> usize {
+        self.max_line_length
+    }
+
+    pub(crate) fn set_max_line_length(&mut self, max_len: usize) {
+        self.max_line_length = max_len;
+    }
+
+    /// Whether Ctrl-C raises `SIGINT` (unix only).
+    ///
+    /// By default, it's disabled: Ctrl-C is read as a key and
+    /// `readline` returns `ReadlineError::Interrupted`.
+    #[must_use]
+    pub fn ctrlc_as_signal(&self) -> bool {
+        self.ctrlc_as_signal
+    }
+
+    /// Whether invalid UTF-8 input is read as `U+FFFD` (unix only).
+    ///
+    /// By default, it's disabled: `readline` fails with an
+    /// `InvalidData` I/O error.
+    #[must_use]
+    pub fn replace_invalid_utf8(&self) -> bool {
+        self.replace_invalid_utf8
+    }
+
+    /// Whether typing `(`, `[` or `{` also inserts the closing bracket.
+    ///
+    /// By default, it's disabled.
+    #[must_use]
+    pub fn auto_close_brackets(&self) -> bool {
+        self.auto_close_brackets
+    }
+
+    /// Whether the cursor shape reflects the vi input mode (a bar in insert
+    /// mode, a block in command mode).
+    ///
+    /// By default, it's disabled.
+    #[must_use]
+    pub fn vi_cursor_shape(&self) -> bool {
+        self.vi_cursor_shape
+    }
 }
 
 impl Default for Config {
@@ -200,8 +345,13 @@ impl Default for Config {
             max_history_size: 100,
             history_duplicates: HistoryDuplicates::IgnoreConsecutive,
             history_ignore_space: false,
+            #[cfg(feature = "with-timestamps")]
+            history_timestamps: false,
             completion_type: CompletionType::Circular, // TODO Validate
             completion_prompt_limit: 100,
+            completion_display_mode: CompletionDisplayMode::default(),
+            completion_ignore_case: None,
+            search_case: SearchCase::default(),
             keyseq_timeout: -1,
             edit_mode: EditMode::Emacs,
             auto_add_history: false,
@@ -212,22 +362,29 @@ impl Default for Config {
             indent_size: 2,
             check_cursor_position: false,
             enable_bracketed_paste: true,
+            continuation_prompt: "  ",
+            max_line_length: MAX_LINE,
+            ctrlc_as_signal: false,
+            replace_invalid_utf8: false,
+            auto_close_brackets: false,
+            vi_cursor_shape: false,
         }
     }
 }
 
 /// Beep or flash or nothing
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BellStyle {
     /// Beep
     Audible,
     /// Silent
     None,
-    /// Flash screen (not supported)
+    /// Flash screen (not supported on Windows)
     Visible,
 }
 
-/// `Audible` by default on unix (overridden by current Terminal settings).
+/// `Audible` by default on unix (only when the output is a terminal).
 /// `None` on windows.
 impl Default for BellStyle {
     #[cfg(any(windows, target_arch = "wasm32"))]
@@ -269,8 +426,59 @@ pub enum CompletionType {
     Fuzzy,
 }
 
+/// What to do when the completer returns no candidate
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompletionDisplayMode {
+    /// Ring the bell (according to `BellStyle`)
+    #[default]
+    Bell,
+    /// Display "No completions" below the line until the next key
+    Print,
+    /// Do nothing
+    Silent,
+}
+
+/// Case sensitivity of the incremental history search
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SearchCase {
+    /// Case-insensitive unless the query contains an uppercase letter
+    Smart,
+    /// Always case-sensitive
+    Sensitive,
+    /// Always case-insensitive
+    Insensitive,
+}
+
+impl SearchCase {
+    /// Should `query` be matched ignoring case ?
+    pub(crate) fn is_insensitive(self, query: &str) -> bool {
+        match self {
+            SearchCase::Smart => !query.chars().any(char::is_uppercase),
+            SearchCase::Sensitive => false,
+            SearchCase::Insensitive => true,
+        }
+    }
+}
+
+/// `Insensitive` with the `case_insensitive_history_search` feature.
+/// `Smart` otherwise.
+impl Default for SearchCase {
+    #[cfg(feature = "case_insensitive_history_search")]
+    fn default() -> Self {
+        SearchCase::Insensitive
+    }
+
+    #[cfg(not(feature = "case_insensitive_history_search"))]
+    fn default() -> Self {
+        SearchCase::Smart
+    }
+}
+
 /// Style of editing / Standard keymaps
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum EditMode {
     /// Emacs keymap
@@ -291,6 +499,18 @@ pub enum ColorMode {
     Disabled,
 }
 
+impl ColorMode {
+    /// Resolve this mode given whether the output stream is a terminal
+    /// supporting colors.
+    pub(crate) fn colors_enabled(self, is_tty: bool) -> bool {
+        match self {
+            ColorMode::Enabled => is_tty,
+            ColorMode::Forced => true,
+            ColorMode::Disabled => false,
+        }
+    }
+}
+
 /// Should the editor use stdio
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[non_exhaustive]
@@ -353,6 +573,17 @@ impl Builder {
         self
     }
 
+    /// Tell if the time each entry was added is saved in the history file
+    /// (as a `#<unix_timestamp>` line before the entry).
+    ///
+    /// By default, it is not.
+    #[cfg(feature = "with-timestamps")]
+    #[must_use]
+    pub fn history_timestamps(mut self, yes: bool) -> Self {
+        self.set_history_timestamps(yes);
+        self
+    }
+
     /// Set `completion_type`.
     #[must_use]
     pub fn completion_type(mut self, completion_type: CompletionType) -> Self {
@@ -368,11 +599,35 @@ impl Builder {
         self
     }
 
+    /// Set `completion_display_mode`.
+    #[must_use]
+    pub fn completion_display_mode(mut self, mode: CompletionDisplayMode) -> Self {
+        self.set_completion_display_mode(mode);
+        self
+    }
+
+    /// Whether completion ignores case, overriding the completer setting
+    /// (like [`FilenameCompleter::set_case_insensitive`]).
+    ///
+    /// [`FilenameCompleter::set_case_insensitive`]: crate::completion::FilenameCompleter::set_case_insensitive
+    #[must_use]
+    pub fn completion_ignore_case(mut self, yes: bool) -> Self {
+        self.set_completion_ignore_case(yes);
+        self
+    }
+
+    /// Set `search_case`.
+    #[must_use]
+    pub fn search_case(mut self, search_case: SearchCase) -> Self {
+        self.set_search_case(search_case);
+        self
+    }
+
     /// Timeout for ambiguous key sequences in milliseconds.
-    /// Currently, it is used only to distinguish a single ESC from an ESC
-    /// sequence.
-    /// After seeing an ESC key, wait at most `keyseq_timeout_ms` for another
-    /// byte.
+    /// It is used to distinguish a single ESC from an ESC sequence, and more
+    /// generally a key sequence sent by the terminal from its prefix.
+    /// After seeing a prefix (like an ESC key), wait at most
+    /// `keyseq_timeout_ms` for another byte (forever if negative).
     #[must_use]
     pub fn keyseq_timeout(mut self, keyseq_timeout_ms: i32) -> Self {
         self.set_keyseq_timeout(keyseq_timeout_ms);
@@ -388,7 +643,9 @@ impl Builder {
 
     /// Tell if lines are automatically added to the history.
     ///
-    /// By default, they are not.
+    /// When disabled, the caller is responsible for calling
+    /// `Editor::add_history_entry` with the lines it wants to keep.
+    /// By default, they are not added.
     #[must_use]
     pub fn auto_add_history(mut self, yes: bool) -> Self {
         self.set_auto_add_history(yes);
@@ -456,6 +713,67 @@ impl Builder {
         self
     }
 
+    /// Prompt displayed at the beginning of each line of a multiline input
+    /// after the first one.
+    ///
+    /// By default, two spaces.
+    #[must_use]
+    pub fn continuation_prompt(mut self, prompt: &'static str) -> Self {
+        self.set_continuation_prompt(prompt);
+        self
+    }
+
+    /// Set the maximum number of bytes in the edited line.
+    ///
+    /// Insertions past this limit are rejected with a bell.
+    /// By default, 4096.
+    #[must_use]
+    pub fn max_line_length(mut self, max_len: usize) -> Self {
+        self.set_max_line_length(max_len);
+        self
+    }
+
+    /// Let Ctrl-C raise `SIGINT` (unix only).
+    ///
+    /// When enabled, `ISIG` is left set in raw mode so the terminal driver
+    /// delivers `SIGINT` to the process instead of a key to `readline`.
+    /// Must be set before the `Editor` is created.
+    /// By default, it's disabled.
+    #[must_use]
+    pub fn ctrlc_as_signal(mut self, yes: bool) -> Self {
+        self.p.ctrlc_as_signal = yes;
+        self
+    }
+
+    /// Read invalid UTF-8 input as `U+FFFD REPLACEMENT CHARACTER` (unix
+    /// only) instead of failing with an `InvalidData` I/O error.
+    /// By default, it's disabled.
+    #[must_use]
+    pub fn replace_invalid_utf8(mut self, yes: bool) -> Self {
+        self.p.replace_invalid_utf8 = yes;
+        self
+    }
+
+    /// Insert the closing bracket when `(`, `[` or `{` is typed, leaving the
+    /// cursor between them. Typing the closing bracket then moves over it,
+    /// and backspace deletes the empty pair.
+    /// By default, it's disabled.
+    #[must_use]
+    pub fn auto_close_brackets(mut self, yes: bool) -> Self {
+        self.set_auto_close_brackets(yes);
+        self
+    }
+
+    /// Change the cursor shape with the vi input mode (a bar in insert mode,
+    /// a block in command mode), using the DECSCUSR escape sequence not
+    /// supported by all terminals.
+    /// By default, it's disabled.
+    #[must_use]
+    pub fn vi_cursor_shape(mut self, yes: bool) -> Self {
+        self.set_vi_cursor_shape(yes);
+        self
+    }
+
     /// Builds a `Config` with the settings specified so far.
     #[must_use]
     pub fn build(self) -> Config {
@@ -494,6 +812,16 @@ pub trait Configurer {
     fn set_history_ignore_space(&mut self, yes: bool) {
         self.config_mut().set_history_ignore_space(yes);
     }
+
+    /// Tell if the time each entry was added is saved in the history file
+    /// (as a `#<unix_timestamp>` line before the entry).
+    ///
+    /// By default, it is not.
+    #[cfg(feature = "with-timestamps")]
+    fn set_history_timestamps(&mut self, yes: bool) {
+        self.config_mut().set_history_timestamps(yes);
+    }
+
     /// Set `completion_type`.
     fn set_completion_type(&mut self, completion_type: CompletionType) {
         self.config_mut().completion_type = completion_type;
@@ -505,6 +833,24 @@ pub trait Configurer {
         self.config_mut().completion_prompt_limit = completion_prompt_limit;
     }
 
+    /// Set `completion_display_mode`.
+    fn set_completion_display_mode(&mut self, mode: CompletionDisplayMode) {
+        self.config_mut().completion_display_mode = mode;
+    }
+
+    /// Whether completion ignores case, overriding the completer setting
+    /// (like [`FilenameCompleter::set_case_insensitive`]).
+    ///
+    /// [`FilenameCompleter::set_case_insensitive`]: crate::completion::FilenameCompleter::set_case_insensitive
+    fn set_completion_ignore_case(&mut self, yes: bool) {
+        self.config_mut().completion_ignore_case = Some(yes);
+    }
+
+    /// Set `search_case`.
+    fn set_search_case(&mut self, search_case: SearchCase) {
+        self.config_mut().search_case = search_case;
+    }
+
     /// Timeout for ambiguous key sequences in milliseconds.
     fn set_keyseq_timeout(&mut self, keyseq_timeout_ms: i32) {
         self.config_mut().keyseq_timeout = keyseq_timeout_ms;
@@ -521,14 +867,16 @@ pub trait Configurer {
 
     /// Tell if lines are automatically added to the history.
     ///
-    /// By default, they are not.
+    /// When disabled, the caller is responsible for calling
+    /// `Editor::add_history_entry` with the lines it wants to keep.
+    /// By default, they are not added.
     fn set_auto_add_history(&mut self, yes: bool) {
         self.config_mut().auto_add_history = yes;
     }
 
     /// Set bell style: beep, flash or nothing.
     fn set_bell_style(&mut self, bell_style: BellStyle) {
-        self.config_mut().bell_style = bell_style;
+        self.config_mut().set_bell_style(bell_style);
     }
 
     /// Forces colorization on or off.
@@ -571,4 +919,84 @@ pub trait Configurer {
     fn enable_bracketed_paste(&mut self, enabled: bool) {
         self.config_mut().enable_bracketed_paste = enabled;
     }
+
+    /// Prompt displayed at the beginning of each line of a multiline input
+    /// after the first one.
+    ///
+    /// By default, two spaces.
+    fn set_continuation_prompt(&mut self, prompt: &'static str) {
+        self.config_mut().set_continuation_prompt(prompt);
+    }
+
+    /// Set the maximum number of bytes in the edited line.
+    ///
+    /// Insertions past this limit are rejected with a bell.
+    /// By default, 4096.
+    fn set_max_line_length(&mut self, max_len: usize) {
+        self.config_mut().set_max_line_length(max_len);
+    }
+
+    /// Insert the closing bracket when `(`, `[` or `{` is typed, leaving the
+    /// cursor between them. Typing the closing bracket then moves over it,
+    /// and backspace deletes the empty pair.
+    /// By default, it's disabled.
+    fn set_auto_close_brackets(&mut self, yes: bool) {
+        self.config_mut().auto_close_brackets = yes;
+    }
+
+    /// Change the cursor shape with the vi input mode (a bar in insert mode,
+    /// a block in command mode), using the DECSCUSR escape sequence not
+    /// supported by all terminals.
+    /// By default, it's disabled.
+    fn set_vi_cursor_shape(&mut self, yes: bool) {
+        self.config_mut().vi_cursor_shape = yes;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BellStyle, ColorMode, CompletionType, Config, Configurer, EditMode};
+
+    #[test]
+    fn builder() {
+        let config = Config::builder()
+            .max_history_size(10)
+            .completion_type(CompletionType::List)
+            .edit_mode(EditMode::Vi)
+            .auto_add_history(true)
+            .tab_stop(4)
+            .bell_style(BellStyle::None)
+            .build();
+        assert_eq!(10, config.max_history_size());
+        assert_eq!(CompletionType::List, config.completion_type());
+        assert_eq!(EditMode::Vi, config.edit_mode());
+        assert!(config.auto_add_history());
+        assert_eq!(4, config.tab_stop());
+        assert_eq!(BellStyle::None, config.bell_style());
+
+        // unchanged settings keep their default value
+        let default = Config::default();
+        assert_eq!(
+            default.history_ignore_space(),
+            config.history_ignore_space()
+        );
+        assert_eq!(default.indent_size(), config.indent_size());
+        assert_eq!("  ", config.continuation_prompt());
+        assert!(!config.vi_cursor_shape());
+
+        let mut builder = Config::builder();
+        builder.set_max_history_size(10);
+        builder.set_edit_mode(EditMode::Vi);
+        assert_eq!(config.edit_mode(), builder.build().edit_mode());
+    }
+
+    #[test]
+    fn colors_enabled() {
+        assert!(ColorMode::Enabled.colors_enabled(true));
+        assert!(!ColorMode::Enabled.colors_enabled(false));
+        assert!(ColorMode::Forced.colors_enabled(true));
+        assert!(ColorMode::Forced.colors_enabled(false));
+        assert!(!ColorMode::Disabled.colors_enabled(true));
+        assert!(!ColorMode::Disabled.colors_enabled(false));
+    }
 }
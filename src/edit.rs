@@ -1,18 +1,22 @@
 //! Command processor
 
 use log::debug;
+use std::borrow::Cow::{self, Borrowed, Owned};
 use std::cell::RefCell;
+use std::cmp;
 use std::fmt;
 use std::rc::Rc;
-use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthChar;
 
 use super::{Context, Helper, Result};
 use crate::error::ReadlineError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::external_editor;
+use crate::grapheme::UnicodeSegmentation;
 use crate::highlight::Highlighter;
-use crate::hint::Hint;
+use crate::hint::{Hint, Hinter, HistoryHinter};
 use crate::history::SearchDirection;
-use crate::keymap::{Anchor, At, CharSearch, Cmd, Movement, RepeatCount, Word};
+use crate::keymap::{Anchor, At, CharSearch, Cmd, InputMode, Movement, RepeatCount, Word};
 use crate::keymap::{InputState, Invoke, Refresher};
 use crate::layout::{Layout, Position};
 use crate::line_buffer::{LineBuffer, WordAction, MAX_LINE};
@@ -22,8 +26,8 @@ use crate::validate::{ValidationContext, ValidationResult};
 
 /// Represent the state during line editing.
 /// Implement rendering.
-pub struct State<'out, 'prompt, H: Helper> {
-    pub out: &'out mut <Terminal as Term>::Writer,
+pub struct State<'out, 'prompt, H: Helper, W: Renderer = <Terminal as Term>::Writer> {
+    pub out: &'out mut W,
     prompt: &'prompt str,  // Prompt to display (rl_prompt)
     prompt_size: Position, // Prompt Unicode/visible width and height
     pub line: LineBuffer,  // Edited line buffer
@@ -35,6 +39,121 @@ pub struct State<'out, 'prompt, H: Helper> {
     pub ctx: Context<'out>,          // Give access to history for `hinter`
     pub hint: Option<Box<dyn Hint>>, // last hint displayed
     highlight_char: bool,            // `true` if a char has been highlighted
+    pub history_hint: bool,          // fallback to history entries as hints
+    pub search_match: Option<(usize, usize)>, // incremental search match in `line`
+    pub mask: Option<char>,          // displayed in place of each char of `line`
+    pub auto_closed: Vec<usize>,     // closing brackets inserted in `line`, innermost last
+}
+
+/// Closing bracket of `ch` (see `Config::auto_close_brackets`)
+fn closing_bracket(ch: char) -> Option<char> {
+    match ch {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        _ => None,
+    }
+}
+
+fn is_closing_bracket(ch: char) -> bool {
+    matches!(ch, ')' | ']' | '}')
+}
+
+/// `line` with each char but newlines replaced by `mask`
+fn masked_line(line: &LineBuffer, mask: char) -> LineBuffer {
+    let mut masked = String::with_capacity(line.len());
+    let mut pos = 0;
+    for (i, c) in line.char_indices() {
+        if i == line.pos() {
+            pos = masked.len();
+        }
+        masked.push(if c == '\n' { c } else { mask });
+    }
+    if line.pos() == line.len() {
+        pos = masked.len();
+    }
+    let mut buf = LineBuffer::with_capacity(masked.len()).can_growth(true);
+    buf.update(&masked, pos);
+    buf
+}
+
+/// Validation error or message displayed in place of a hint
+struct MessageHint(String);
+
+impl Hint for MessageHint {
+    fn display(&self) -> &str {
+        &self.0
+    }
+
+    fn completion(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// End of a matching history entry displayed (dimmed) after the line
+struct HistoryHint {
+    display: String,
+    completion: String,
+}
+
+impl Hint for HistoryHint {
+    fn display(&self) -> &str {
+        &self.display
+    }
+
+    fn completion(&self) -> Option<&str> {
+        Some(&self.completion)
+    }
+}
+
+/// Displays in reverse video the part of the line matching the incremental
+/// search query.
+struct SearchMatch<'h> {
+    highlighter: Option<&'h dyn Highlighter>,
+    range: (usize, usize),
+}
+
+impl Highlighter for SearchMatch<'_> {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        let (start, end) = self.range;
+        if start >= end || end > line.len() {
+            return Borrowed(line);
+        }
+        Owned(format!(
+            "{}\x1b[7m{}\x1b[27m{}",
+            &line[..start],
+            &line[start..end],
+            &line[end..]
+        ))
+    }
+
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(
+        &'s self,
+        prompt: &'p str,
+        default: bool,
+    ) -> Cow<'b, str> {
+        match self.highlighter {
+            Some(highlighter) => highlighter.highlight_prompt(prompt, default),
+            None => Borrowed(prompt),
+        }
+    }
+
+    fn highlight_continuation_prompt<'b, 's: 'b, 'p: 'b>(
+        &'s self,
+        prompt: &'p str,
+    ) -> Cow<'b, str> {
+        match self.highlighter {
+            Some(highlighter) => highlighter.highlight_continuation_prompt(prompt),
+            None => Borrowed(prompt),
+        }
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        match self.highlighter {
+            Some(highlighter) => highlighter.highlight_hint(hint),
+            None => Borrowed(hint),
+        }
+    }
 }
 
 enum Info<'m> {
@@ -43,13 +162,13 @@ enum Info<'m> {
     Msg(Option<&'m str>),
 }
 
-impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
+impl<'out, 'prompt, H: Helper, W: Renderer> State<'out, 'prompt, H, W> {
     pub fn new(
-        out: &'out mut <Terminal as Term>::Writer,
+        out: &'out mut W,
         prompt: &'prompt str,
         helper: Option<&'out H>,
         ctx: Context<'out>,
-    ) -> State<'out, 'prompt, H> {
+    ) -> State<'out, 'prompt, H, W> {
         let prompt_size = out.calculate_position(prompt, Position::default());
         State {
             out,
@@ -64,6 +183,10 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
             ctx,
             hint: None,
             highlight_char: false,
+            history_hint: false,
+            search_match: None,
+            mask: None,
+            auto_closed: Vec::new(),
         }
     }
 
@@ -78,7 +201,7 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
     pub fn next_cmd(
         &mut self,
         input_state: &mut InputState,
-        rdr: &mut <Terminal as Term>::Reader,
+        rdr: &mut W::Reader,
         single_esc_abort: bool,
         ignore_external_print: bool,
     ) -> Result<Cmd> {
@@ -116,11 +239,11 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
         // calculate the desired position of the cursor
         let cursor = self
             .out
-            .calculate_position(&self.line[..self.line.pos()], self.prompt_size);
+            .calculate_line_position(&self.line[..self.line.pos()], self.prompt_size);
         if self.layout.cursor == cursor {
             return Ok(());
         }
-        if self.highlight_char() {
+        if self.highlight_char() || self.mask.is_some() {
             let prompt_size = self.prompt_size;
             self.refresh(self.prompt, prompt_size, true, Info::NoHint)?;
         } else {
@@ -142,7 +265,15 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
         Ok(())
     }
 
-    pub fn move_cursor_at_leftmost(&mut self, rdr: &mut <Terminal as Term>::Reader) -> Result<()> {
+    /// Display `msg` on the row below the edited line, in place of the hint:
+    /// it is erased by the next refresh.
+    pub fn show_message(&mut self, msg: &str) -> Result<()> {
+        let prompt_size = self.prompt_size;
+        self.hint = Some(Box::new(MessageHint(format!("\n{}", msg))));
+        self.refresh(self.prompt, prompt_size, true, Info::Hint)
+    }
+
+    pub fn move_cursor_at_leftmost(&mut self, rdr: &mut W::Reader) -> Result<()> {
         self.out.move_cursor_at_leftmost(rdr)
     }
 
@@ -158,32 +289,54 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
             Info::Hint => self.hint.as_ref().map(|h| h.display()),
             Info::Msg(msg) => msg,
         };
-        let highlighter = if self.out.colors_enabled() {
-            self.helper.map(|h| h as &dyn Highlighter)
+        let masked;
+        let line = match self.mask {
+            Some(mask) => {
+                masked = masked_line(&self.line, mask);
+                &masked
+            }
+            None => &self.line,
+        };
+        let search_match;
+        let highlighter = if self.mask.is_some() {
+            None
+        } else if self.out.colors_enabled() {
+            let highlighter = self.helper.map(|h| h as &dyn Highlighter);
+            if let Some(range) = self.search_match {
+                search_match = SearchMatch { highlighter, range };
+                Some(&search_match as &dyn Highlighter)
+            } else {
+                highlighter
+            }
         } else {
             None
         };
 
         let new_layout = self
             .out
-            .compute_layout(prompt_size, default_prompt, &self.line, info);
+            .compute_layout(prompt_size, default_prompt, line, info);
 
         debug!(target: "rustyline", "old layout: {:?}", self.layout);
         debug!(target: "rustyline", "new layout: {:?}", new_layout);
-        self.out.refresh_line(
-            prompt,
-            &self.line,
-            info,
-            &self.layout,
-            &new_layout,
-            highlighter,
-        )?;
+        self.out
+            .refresh_line(prompt, line, info, &self.layout, &new_layout, highlighter)?;
         self.layout = new_layout;
 
         Ok(())
     }
 
+    /// Update terminal width and cursor column given to completers and
+    /// hinters.
+    pub fn update_context(&mut self) {
+        let cursor = self
+            .out
+            .calculate_position(&self.line[..self.line.pos()], self.prompt_size);
+        self.ctx.term_cols = self.out.get_columns();
+        self.ctx.cursor_col = cursor.col;
+    }
+
     pub fn hint(&mut self) {
+        self.update_context();
         if let Some(hinter) = self.helper {
             let hint = hinter.hint(self.line.as_str(), self.line.pos(), &self.ctx);
             self.hint = match hint {
@@ -193,6 +346,40 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
         } else {
             self.hint = None;
         }
+        if self.hint.is_none() && self.history_hint {
+            self.hint = self.history_hint();
+        }
+        if let Some(msg) = self.validate_while_typing() {
+            self.hint = Some(Box::new(MessageHint(msg)));
+        }
+    }
+
+    fn history_hint(&self) -> Option<Box<dyn Hint>> {
+        let completion =
+            HistoryHinter::new().hint(self.line.as_str(), self.line.pos(), &self.ctx)?;
+        let display = if self.out.colors_enabled() {
+            format!("\x1b[2m{}\x1b[0m", completion)
+        } else {
+            completion.clone()
+        };
+        Some(Box::new(HistoryHint {
+            display,
+            completion,
+        }))
+    }
+
+    /// Error message of an invalid input when the validator asks to be
+    /// called while typing
+    fn validate_while_typing(&mut self) -> Option<String> {
+        let validator = self.helper.filter(|v| v.validate_while_typing())?;
+        match validator.validate(&mut ValidationContext::new(self)) {
+            Ok(ValidationResult::Invalid(msg)) => msg,
+            Ok(_) => None,
+            Err(err) => {
+                debug!(target: "rustyline", "validation failed: {}", err);
+                None
+            }
+        }
     }
 
     fn highlight_char(&mut self) -> bool {
@@ -245,13 +432,13 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
     }
 }
 
-impl<'out, 'prompt, H: Helper> Invoke for State<'out, 'prompt, H> {
+impl<'out, 'prompt, H: Helper, W: Renderer> Invoke for State<'out, 'prompt, H, W> {
     fn input(&self) -> &str {
         self.line.as_str()
     }
 }
 
-impl<'out, 'prompt, H: Helper> Refresher for State<'out, 'prompt, H> {
+impl<'out, 'prompt, H: Helper, W: Renderer> Refresher for State<'out, 'prompt, H, W> {
     fn refresh_line(&mut self) -> Result<()> {
         let prompt_size = self.prompt_size;
         self.hint();
@@ -317,7 +504,7 @@ impl<'out, 'prompt, H: Helper> Refresher for State<'out, 'prompt, H> {
     }
 }
 
-impl<'out, 'prompt, H: Helper> fmt::Debug for State<'out, 'prompt, H> {
+impl<'out, 'prompt, H: Helper, W: Renderer> fmt::Debug for State<'out, 'prompt, H, W> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("State")
             .field("prompt", &self.prompt)
@@ -330,7 +517,7 @@ impl<'out, 'prompt, H: Helper> fmt::Debug for State<'out, 'prompt, H> {
     }
 }
 
-impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
+impl<'out, 'prompt, H: Helper, W: Renderer> State<'out, 'prompt, H, W> {
     pub fn clear_screen(&mut self) -> Result<()> {
         self.out.clear_screen()?;
         self.layout.cursor = Position::default();
@@ -345,6 +532,7 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
                 let prompt_size = self.prompt_size;
                 let no_previous_hint = self.hint.is_none();
                 self.hint();
+                let ch = self.mask.unwrap_or(ch);
                 let width = ch.width().unwrap_or(0);
                 if n == 1
                     && width != 0 // Ctrl-V + \t or \n ...
@@ -366,7 +554,76 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
                 self.refresh_line()
             }
         } else {
-            Ok(())
+            // line is full
+            self.out.beep()
+        }
+    }
+
+    /// Insert `ch`, closing brackets (see `Config::auto_close_brackets`):
+    /// an opening bracket is inserted with its closing one and typing this
+    /// closing bracket moves over it.
+    pub fn edit_insert_bracket(&mut self, ch: char) -> Result<()> {
+        let pos = self.line.pos();
+        if is_closing_bracket(ch)
+            && self.auto_closed.last() == Some(&pos)
+            && self.line.as_str()[pos..].starts_with(ch)
+        {
+            self.auto_closed.pop();
+            self.line.move_forward(1);
+            return self.move_cursor();
+        }
+        let len = self.line.len();
+        if let Some(close) = closing_bracket(ch) {
+            if self
+                .line
+                .must_truncate(len + ch.len_utf8() + close.len_utf8())
+            {
+                // line is full
+                return self.out.beep();
+            }
+            self.changes.borrow_mut().begin();
+            self.line.insert(ch, 1);
+            self.line.insert(close, 1);
+            self.line.move_backward(1);
+            self.changes.borrow_mut().end();
+            self.shift_auto_closed(len);
+            self.auto_closed.push(self.line.pos());
+            return self.refresh_line();
+        }
+        self.edit_insert(ch, 1)?;
+        self.shift_auto_closed(len);
+        Ok(())
+    }
+
+    /// Delete the empty pair of brackets around the cursor or the character
+    /// before the cursor (see `Config::auto_close_brackets`).
+    pub fn edit_backspace_bracket(&mut self) -> Result<()> {
+        let pos = self.line.pos();
+        let len = self.line.len();
+        let open = self.line.as_str()[..pos].chars().next_back();
+        let close = self.line.as_str()[pos..].chars().next();
+        if open.is_some() && open.and_then(closing_bracket) == close {
+            if self.auto_closed.last() == Some(&pos) {
+                self.auto_closed.pop();
+            }
+            self.changes.borrow_mut().begin();
+            self.line.delete(1);
+            self.line.backspace(1);
+            self.changes.borrow_mut().end();
+            self.shift_auto_closed(len);
+            return self.refresh_line();
+        }
+        self.edit_kill(&Movement::BackwardChar(1))?;
+        self.shift_auto_closed(len);
+        Ok(())
+    }
+
+    /// Follow the closing brackets inserted after the cursor when the line
+    /// length changes from `len` by an edit at the cursor.
+    fn shift_auto_closed(&mut self, len: usize) {
+        let new_len = self.line.len();
+        for pos in &mut self.auto_closed {
+            *pos = *pos + new_len - len;
         }
     }
 
@@ -415,12 +672,16 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
             self.line.move_forward(1);
         }
         if self.line.yank(text, n).is_some() {
-            if !input_state.is_emacs_mode() {
+            if input_state.input_mode == InputMode::Command {
+                // vi command mode: cursor on the last yanked char
                 self.line.move_backward(1);
             }
             self.refresh_line()
-        } else {
+        } else if text.is_empty() {
             Ok(())
+        } else {
+            // line is full
+            self.out.beep()
         }
     }
 
@@ -493,6 +754,9 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
     pub fn edit_kill(&mut self, mvt: &Movement) -> Result<()> {
         if self.line.kill(mvt) {
             self.refresh_line()
+        } else if let Movement::BackwardChar(_) | Movement::ForwardChar(_) = mvt {
+            // nothing to delete
+            self.out.beep()
         } else {
             Ok(())
         }
@@ -502,6 +766,10 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
         if text.is_empty() {
             return Ok(());
         }
+        if self.line.must_truncate(self.line.len() + text.len()) {
+            // line is full
+            return self.out.beep();
+        }
         let cursor = self.line.pos();
         self.line.insert_str(cursor, text);
         self.refresh_line()
@@ -537,7 +805,7 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
 
     /// Moves the cursor to the same column in the line above
     pub fn edit_move_line_up(&mut self, n: RepeatCount) -> Result<bool> {
-        if self.line.move_to_line_up(n) {
+        if self.move_to_wrapped_row(n, true) || self.line.move_to_line_up(n) {
             self.move_cursor()?;
             Ok(true)
         } else {
@@ -547,7 +815,7 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
 
     /// Moves the cursor to the same column in the line above
     pub fn edit_move_line_down(&mut self, n: RepeatCount) -> Result<bool> {
-        if self.line.move_to_line_down(n) {
+        if self.move_to_wrapped_row(n, false) || self.line.move_to_line_down(n) {
             self.move_cursor()?;
             Ok(true)
         } else {
@@ -555,6 +823,59 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
         }
     }
 
+    /// Moves the cursor `n` terminal rows up or down when the current line
+    /// is wrapped, staying in the same line.
+    ///
+    /// Fails if the cursor is already on the first (or last) row of the
+    /// current line.
+    fn move_to_wrapped_row(&mut self, n: RepeatCount, up: bool) -> bool {
+        let line = self.line.as_str();
+        let pos = self.line.pos();
+        let start = line[..pos].rfind('\n').map_or(0, |i| i + 1);
+        let end = line[pos..].find('\n').map_or(line.len(), |i| pos + i);
+        let start_pos = self
+            .out
+            .calculate_line_position(&line[..start], self.prompt_size);
+        let cursor = self.out.calculate_position(&line[start..pos], start_pos);
+        let row = if up {
+            if cursor.row == start_pos.row {
+                return false;
+            }
+            cmp::max(cursor.row.saturating_sub(n), start_pos.row)
+        } else {
+            let end_pos = self.out.calculate_position(&line[start..end], start_pos);
+            if cursor.row == end_pos.row {
+                return false;
+            }
+            cmp::min(cursor.row + n, end_pos.row)
+        };
+        // last grapheme boundary of `row` which is not after the cursor
+        // column (or the first one when the row starts after it)
+        let mut target = None;
+        let mut p = start_pos;
+        let mut graphemes = line[start..end].grapheme_indices(true);
+        let mut idx = start;
+        while p.row <= row {
+            if p.row == row && (p.col <= cursor.col || target.is_none()) {
+                target = Some(idx);
+            }
+            match graphemes.next() {
+                Some((i, g)) => {
+                    p = self.out.calculate_position(g, p);
+                    idx = start + i + g.len();
+                }
+                None => break,
+            }
+        }
+        match target {
+            Some(target) => {
+                self.line.set_pos(target);
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn edit_move_to(&mut self, cs: CharSearch, n: RepeatCount) -> Result<()> {
         if self.line.move_to(cs, n) {
             self.move_cursor()
@@ -590,17 +911,17 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
     pub fn edit_history_next(&mut self, prev: bool) -> Result<()> {
         let history = self.ctx.history;
         if history.is_empty() {
-            return Ok(());
+            return self.out.beep();
         }
         if self.ctx.history_index == history.len() {
             if prev {
                 // Save the current edited line before overwriting it
                 self.backup();
             } else {
-                return Ok(());
+                return self.out.beep();
             }
         } else if self.ctx.history_index == 0 && prev {
-            return Ok(());
+            return self.out.beep();
         }
         if prev {
             self.ctx.history_index -= 1;
@@ -630,21 +951,28 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
         {
             return self.out.beep();
         }
-        if dir == SearchDirection::Reverse {
-            self.ctx.history_index -= 1;
+        let start = if dir == SearchDirection::Reverse {
+            self.ctx.history_index - 1
         } else {
-            self.ctx.history_index += 1;
-        }
-        if let Some(sr) = history.starts_with(
-            &self.line.as_str()[..self.line.pos()],
-            self.ctx.history_index,
-            dir,
-        ) {
+            self.ctx.history_index + 1
+        };
+        if let Some(sr) = history.starts_with(&self.line.as_str()[..self.line.pos()], start, dir) {
+            if self.ctx.history_index == history.len() {
+                // Save the current edited line before overwriting it
+                self.backup();
+            }
             self.ctx.history_index = sr.idx;
             self.changes.borrow_mut().begin();
             self.line.update(sr.entry, sr.pos);
             self.changes.borrow_mut().end();
             self.refresh_line()
+        } else if dir == SearchDirection::Forward {
+            // Restore current edited line
+            self.ctx.history_index = history.len();
+            self.changes.borrow_mut().begin();
+            self.restore();
+            self.changes.borrow_mut().end();
+            self.refresh_line()
         } else {
             self.out.beep()
         }
@@ -680,6 +1008,25 @@ impl<'out, 'prompt, H: Helper> State<'out, 'prompt, H> {
         self.refresh_line()
     }
 
+    /// Replace the current line with its content edited by `editor`.
+    ///
+    /// The line is left untouched if the editor fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn edit_externally(&mut self, editor: &str) -> bool {
+        match external_editor::edit(&self.line, editor) {
+            Ok(text) => {
+                self.changes.borrow_mut().begin();
+                self.line.update(&text, text.len());
+                self.changes.borrow_mut().end();
+                true
+            }
+            Err(err) => {
+                debug!(target: "rustyline", "external editor: {}", err);
+                false
+            }
+        }
+    }
+
     /// Change the indentation of the lines covered by movement
     pub fn edit_indent(&mut self, mvt: &Movement, amount: usize, dedent: bool) -> Result<()> {
         if self.line.indent(mvt, amount, dedent) {
@@ -711,6 +1058,10 @@ pub fn init_state<'out, H: Helper>(
         ctx: Context::new(history),
         hint: Some(Box::new("hint".to_owned())),
         highlight_char: false,
+        history_hint: false,
+        search_match: None,
+        mask: None,
+        auto_closed: Vec::new(),
     }
 }
 
@@ -718,6 +1069,8 @@ pub fn init_state<'out, H: Helper>(
 mod test {
     use super::init_state;
     use crate::history::History;
+    use crate::keymap::Movement;
+    use crate::keymap::Refresher;
     use crate::tty::Sink;
 
     #[test]
@@ -735,6 +1088,8 @@ mod test {
             s.edit_history_next(false).unwrap();
             assert_eq!(line, s.line.as_str());
         }
+        // no next entry
+        assert_eq!("\x07\x07", s.out.written);
 
         s.edit_history_next(true).unwrap();
         assert_eq!(line, s.saved_line_for_history.as_str());
@@ -747,6 +1102,8 @@ mod test {
             assert_eq!(0, s.ctx.history_index);
             assert_eq!("line0", s.line.as_str());
         }
+        // no previous entry
+        assert_eq!("\x07\x07\x07", s.out.written);
 
         s.edit_history_next(false).unwrap();
         assert_eq!(line, s.saved_line_for_history.as_str());
@@ -758,4 +1115,384 @@ mod test {
         assert_eq!(2, s.ctx.history_index);
         assert_eq!(line, s.line.as_str());
     }
+
+    #[test]
+    fn bell_style() {
+        use crate::config::BellStyle;
+
+        let history = History::new();
+        for (bell_style, expected) in [
+            (BellStyle::Audible, "\x07"),
+            (BellStyle::Visible, "\x1b[?5h\x1b[?5l"),
+            (BellStyle::None, ""),
+        ] {
+            let mut out = Sink {
+                bell_style,
+                ..Sink::default()
+            };
+            let mut s = init_state(&mut out, "", 0, None::<&()>, &history);
+            // backspace at column 0
+            s.edit_kill(&Movement::BackwardChar(1)).unwrap();
+            assert_eq!(expected, s.out.written);
+        }
+    }
+
+    #[test]
+    fn max_line_length() {
+        use crate::line_buffer::LineBuffer;
+
+        let mut out = Sink::default();
+        let history = History::new();
+        let mut s = init_state(&mut out, "", 0, None::<&()>, &history);
+        s.line = LineBuffer::with_capacity(8).can_growth(true);
+        s.line.set_max_len(4);
+        for c in "abcdef".chars() {
+            s.edit_insert(c, 1).unwrap();
+        }
+        assert_eq!("abcd", s.line.as_str());
+        assert_eq!(2, s.out.written.matches('\x07').count());
+        s.out.written.clear();
+
+        s.line.move_home();
+        s.edit_insert_text("xy").unwrap();
+        s.edit_insert('z', 2).unwrap();
+        assert_eq!("abcd", s.line.as_str());
+        assert_eq!("\x07\x07", s.out.written);
+    }
+
+    #[test]
+    fn highlight_char() {
+        use std::borrow::Cow::{self, Owned};
+
+        struct BracketHelper;
+        impl crate::highlight::Highlighter for BracketHelper {
+            fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+                Owned(format!("{}|{}", &line[..pos], &line[pos..]))
+            }
+
+            fn highlight_char(&self, line: &str, pos: usize) -> bool {
+                line[pos..].starts_with('(')
+            }
+        }
+        crate::test::impl_helper!(BracketHelper: Completer, Hinter, Validator);
+
+        let mut out = Sink {
+            colors_enabled: true,
+            ..Sink::default()
+        };
+        let history = History::new();
+        let helper = Some(BracketHelper);
+        let mut s = init_state(&mut out, "a(b", 3, helper.as_ref(), &history);
+        s.refresh_line().unwrap();
+        s.out.rendered.clear();
+        // no need to highlight: the cursor is just moved
+        s.edit_move_backward(1).unwrap();
+        assert_eq!("", s.out.rendered);
+        // cursor on a bracket
+        s.edit_move_backward(1).unwrap();
+        assert_eq!("a|(b", s.out.rendered);
+        // previously highlighted
+        s.edit_move_backward(1).unwrap();
+        assert_eq!("|a(b", s.out.rendered);
+        s.out.rendered.clear();
+        s.edit_move_forward(2).unwrap();
+        assert_eq!("", s.out.rendered);
+    }
+
+    #[test]
+    fn color_mode() {
+        use std::borrow::Cow::{self, Owned};
+
+        use crate::config::{Behavior, BellStyle, ColorMode};
+        use crate::tty::{DummyTerminal, Term};
+
+        struct Colored;
+        impl crate::highlight::Highlighter for Colored {
+            fn highlight<'l>(&self, line: &'l str, _: usize) -> Cow<'l, str> {
+                Owned(format!("\x1b[1m{}\x1b[0m", line))
+            }
+        }
+        crate::test::impl_helper!(Colored: Completer, Hinter, Validator);
+
+        let history = History::new();
+        let helper = Some(Colored);
+        for (color_mode, escaped) in [
+            (ColorMode::Forced, true),
+            (ColorMode::Enabled, false), // output is not a tty
+            (ColorMode::Disabled, false),
+        ] {
+            let term = DummyTerminal::new(
+                color_mode,
+                Behavior::default(),
+                8,
+                BellStyle::None,
+                true,
+                false,
+            )
+            .unwrap();
+            let mut out = term.create_writer();
+            let mut s = init_state(&mut out, "line", 4, helper.as_ref(), &history);
+            s.refresh_line().unwrap();
+            assert_eq!(
+                escaped,
+                s.out.rendered.contains("\x1b["),
+                "{:?}",
+                color_mode
+            );
+            assert!(s.out.rendered.contains("line"));
+        }
+    }
+
+    #[test]
+    fn external_print() {
+        let mut out = Sink::default();
+        let history = History::new();
+        let helper: Option<()> = None;
+        let mut s = init_state(&mut out, "line", 2, helper.as_ref(), &history);
+        s.external_print("msg".to_owned()).unwrap();
+        s.external_print("msg\n".to_owned()).unwrap();
+        assert_eq!("line", s.line.as_str());
+        assert_eq!(2, s.line.pos());
+        assert_eq!("msg\nmsg\n", out.written);
+    }
+
+    #[test]
+    fn validate_while_typing() {
+        use crate::validate::Validator;
+        use crate::validate::{MatchingBracketValidator, ValidationContext, ValidationResult};
+
+        struct WhileTyping(MatchingBracketValidator);
+        impl Validator for WhileTyping {
+            fn validate(&self, ctx: &mut ValidationContext) -> crate::Result<ValidationResult> {
+                self.0.validate(ctx)
+            }
+
+            fn validate_while_typing(&self) -> bool {
+                true
+            }
+        }
+        crate::test::impl_helper!(WhileTyping: Completer, Hinter, Highlighter);
+
+        let mut out = Sink::default();
+        let history = History::new();
+        let helper = Some(WhileTyping(MatchingBracketValidator::new()));
+        let mut s = init_state(&mut out, "(a]", 3, helper.as_ref(), &history);
+        s.hint();
+        let hint = s.hint.as_ref().unwrap();
+        assert_eq!(
+            "Mismatched brackets: '(' is not properly closed",
+            hint.display()
+        );
+        assert_eq!(None, hint.completion());
+
+        // incomplete input
+        s.edit_kill(&crate::keymap::Movement::BackwardChar(1))
+            .unwrap();
+        assert!(s.hint.is_none());
+    }
+
+    #[test]
+    fn dynamic_prompt() {
+        use std::borrow::Cow::{self, Borrowed, Owned};
+
+        struct Counter(usize);
+        impl crate::highlight::Highlighter for Counter {
+            fn highlight_prompt<'b, 's: 'b, 'p: 'b>(
+                &'s self,
+                prompt: &'p str,
+                default: bool,
+            ) -> Cow<'b, str> {
+                if default {
+                    Owned(format!("[{}] {}", self.0, prompt))
+                } else {
+                    Borrowed(prompt)
+                }
+            }
+        }
+        crate::test::impl_helper!(Counter: Completer, Hinter, Validator);
+
+        let mut out = Sink {
+            colors_enabled: true,
+            ..Sink::default()
+        };
+        let history = History::new();
+        let mut helper = Some(Counter(1));
+        let mut s = init_state(&mut out, "line", 4, helper.as_ref(), &history);
+        s.prompt = "> ";
+        s.refresh_line().unwrap();
+        assert_eq!("[1] > line", s.out.rendered);
+        s.refresh_prompt_and_line("(search)").unwrap();
+        assert_eq!("(search)line", s.out.rendered);
+
+        helper.as_mut().unwrap().0 = 2;
+        let mut s = init_state(&mut out, "", 0, helper.as_ref(), &history);
+        s.prompt = "> ";
+        s.refresh_line().unwrap();
+        assert_eq!("[2] > ", s.out.rendered);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn edit_externally() {
+        let mut out = Sink::default();
+        let history = History::new();
+        let mut s = init_state(&mut out, "hello", 5, None::<&()>, &history);
+        assert!(s.edit_externally("echo world >>"));
+        assert_eq!("hello\nworld", s.line.as_str());
+        assert_eq!(11, s.line.pos());
+
+        assert!(!s.edit_externally("false"));
+        assert_eq!("hello\nworld", s.line.as_str());
+
+        // the temporary file is only readable by its owner
+        assert!(s.edit_externally(r#"f() { ls -l "$1" | cut -c1-10 > "$1"; }; f"#));
+        assert_eq!("-rw-------", s.line.as_str());
+    }
+
+    #[test]
+    fn continuation_prompt() {
+        let mut out = Sink {
+            continuation_prompt: ".. ".to_owned(),
+            ..Sink::default()
+        };
+        let history = History::new();
+        let mut s = init_state(&mut out, "a\nb\nc", 3, None::<&()>, &history);
+        s.prompt = "> ";
+        s.hint = None;
+        s.refresh_line().unwrap();
+        assert_eq!("> a\n.. b\n.. c", s.out.rendered);
+    }
+
+    #[test]
+    fn continuation_prompt_after_incomplete() {
+        use crate::validate::{
+            MatchingBracketValidator, ValidationContext, ValidationResult, Validator,
+        };
+
+        struct Brackets(MatchingBracketValidator);
+        impl Validator for Brackets {
+            fn validate(&self, ctx: &mut ValidationContext) -> crate::Result<ValidationResult> {
+                self.0.validate(ctx)
+            }
+        }
+        crate::test::impl_helper!(Brackets: Completer, Hinter, Highlighter);
+
+        let mut out = Sink {
+            continuation_prompt: "... ".to_owned(),
+            ..Sink::default()
+        };
+        let history = History::new();
+        let helper = Some(Brackets(MatchingBracketValidator::new()));
+        let mut s = init_state(&mut out, "(a", 2, helper.as_ref(), &history);
+        s.prompt = "> ";
+        s.hint = None;
+        // what `AcceptOrInsertLine` does with an incomplete input
+        assert!(matches!(
+            s.validate().unwrap(),
+            ValidationResult::Incomplete
+        ));
+        s.edit_insert('\n', 1).unwrap();
+        s.edit_insert('b', 1).unwrap();
+        s.edit_insert('\n', 1).unwrap();
+        s.edit_insert(')', 1).unwrap();
+        s.refresh_line().unwrap();
+        assert_eq!("> (a\n... b\n... )", s.out.rendered);
+        assert!(s.validate().unwrap().is_valid());
+    }
+
+    #[test]
+    fn highlight_continuation_prompt() {
+        use std::borrow::Cow::{self, Owned};
+
+        struct Dimmed;
+        impl crate::highlight::Highlighter for Dimmed {
+            fn highlight_prompt<'b, 's: 'b, 'p: 'b>(
+                &'s self,
+                prompt: &'p str,
+                _default: bool,
+            ) -> Cow<'b, str> {
+                Owned(format!("\x1b[1m{}\x1b[m", prompt))
+            }
+
+            fn highlight_continuation_prompt<'b, 's: 'b, 'p: 'b>(
+                &'s self,
+                prompt: &'p str,
+            ) -> Cow<'b, str> {
+                Owned(format!("\x1b[2m{}\x1b[m", prompt))
+            }
+        }
+        crate::test::impl_helper!(Dimmed: Completer, Hinter, Validator);
+
+        let mut out = Sink {
+            colors_enabled: true,
+            continuation_prompt: ".. ".to_owned(),
+            ..Sink::default()
+        };
+        let history = History::new();
+        let helper = Some(Dimmed);
+        let mut s = init_state(&mut out, "a\nb", 3, helper.as_ref(), &history);
+        s.prompt = "> ";
+        s.hint = None;
+        s.refresh_line().unwrap();
+        assert_eq!("\x1b[1m> \x1b[ma\n\x1b[2m.. \x1b[mb", s.out.rendered);
+    }
+
+    #[test]
+    fn hint_context() {
+        use crate::layout::Position;
+
+        struct Cols;
+        impl crate::hint::Hinter for Cols {
+            type Hint = String;
+
+            fn hint(&self, _: &str, _: usize, ctx: &crate::Context<'_>) -> Option<String> {
+                Some(format!("{}/{}", ctx.cursor_col(), ctx.term_cols()))
+            }
+        }
+        crate::test::impl_helper!(Cols: Completer, Highlighter, Validator);
+
+        let mut out = Sink::default();
+        let history = History::new();
+        let helper = Some(Cols);
+        let mut s = init_state(&mut out, "hello", 3, helper.as_ref(), &history);
+        s.prompt = "> ";
+        s.prompt_size = Position { col: 2, row: 0 };
+        s.hint();
+        assert_eq!(Some("5/80"), s.hint.as_ref().map(|h| h.display()));
+    }
+
+    #[test]
+    fn search_match() {
+        let mut out = Sink {
+            colors_enabled: true,
+            ..Sink::default()
+        };
+        let history = History::new();
+        let helper = Some(());
+        let mut s = init_state(&mut out, "Cargo build", 0, helper.as_ref(), &history);
+        s.hint = None;
+        s.search_match = Some((6, 8));
+        s.refresh_line().unwrap();
+        assert_eq!("Cargo \x1b[7mbu\x1b[27mild", s.out.rendered);
+    }
+
+    #[test]
+    fn mask() {
+        let mut out = Sink::default();
+        let history = History::new();
+        let mut s = init_state::<()>(&mut out, "", 0, None, &history);
+        s.hint = None;
+        s.mask = Some('*');
+        for c in "pa\u{e9}".chars() {
+            s.edit_insert(c, 1).unwrap();
+        }
+        // inserted at the end of the line, without a full refresh
+        assert_eq!("***", s.out.written);
+        s.edit_move_backward(1).unwrap();
+        s.edit_insert('s', 1).unwrap();
+        assert_eq!("****", s.out.rendered);
+        assert_eq!("pas\u{e9}", s.line.as_str());
+        assert_eq!(3, s.line.pos());
+        assert_eq!(3, s.layout.cursor.col);
+    }
 }
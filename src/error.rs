@@ -29,6 +29,12 @@ pub enum ReadlineError {
     /// Something went wrong calling a Windows API
     #[cfg(windows)]
     SystemError(clipboard_win::SystemError),
+    /// Invalid regular expression
+    #[cfg(feature = "regex-search")]
+    InvalidPattern(regex::Error),
+    /// No line was entered before the deadline
+    /// (see [`Editor::readline_with_timeout`](crate::Editor::readline_with_timeout))
+    Timeout,
 }
 
 impl fmt::Display for ReadlineError {
@@ -44,6 +50,9 @@ impl fmt::Display for ReadlineError {
             ReadlineError::Decode(ref err) => err.fmt(f),
             #[cfg(windows)]
             ReadlineError::SystemError(ref err) => err.fmt(f),
+            #[cfg(feature = "regex-search")]
+            ReadlineError::InvalidPattern(ref err) => err.fmt(f),
+            ReadlineError::Timeout => write!(f, "Timeout"),
         }
     }
 }
@@ -61,6 +70,9 @@ impl Error for ReadlineError {
             ReadlineError::Decode(ref err) => Some(err),
             #[cfg(windows)]
             ReadlineError::SystemError(_) => None,
+            #[cfg(feature = "regex-search")]
+            ReadlineError::InvalidPattern(ref err) => Some(err),
+            ReadlineError::Timeout => None,
         }
     }
 }
@@ -97,6 +109,13 @@ impl From<io::ErrorKind> for ReadlineError {
     }
 }
 
+#[cfg(feature = "regex-search")]
+impl From<regex::Error> for ReadlineError {
+    fn from(err: regex::Error) -> Self {
+        ReadlineError::InvalidPattern(err)
+    }
+}
+
 #[cfg(unix)]
 impl From<nix::Error> for ReadlineError {
     fn from(err: nix::Error) -> Self {
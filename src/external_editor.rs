@@ -0,0 +1,87 @@
+//! Edit the current line in an external editor
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, ExitStatus};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(unix)]
+const DEFAULT_EDITOR: &str = "vi";
+#[cfg(not(unix))]
+const DEFAULT_EDITOR: &str = "notepad";
+
+/// Editor command: `$VISUAL` or `$EDITOR` or `vi`
+pub(crate) fn command() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_owned())
+}
+
+/// Write `text` to a temporary file, open it with `editor` and return the
+/// edited content (without its trailing newline).
+pub(crate) fn edit(text: &str, editor: &str) -> io::Result<String> {
+    let path = temp_path();
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    // the line may be sensitive: other users must not read it
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(&path)?;
+    let result = file
+        .write_all(text.as_bytes())
+        .and_then(|_| file.write_all(b"\n"))
+        .and_then(|_| {
+            drop(file);
+            spawn(editor, &path)
+        })
+        .and_then(|status| {
+            if status.success() {
+                fs::read_to_string(&path)
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    format!("{} exited with {}", editor, status),
+                ))
+            }
+        });
+    let _ = fs::remove_file(&path);
+    let mut text = result?;
+    if text.ends_with('\n') {
+        text.pop();
+        if text.ends_with('\r') {
+            text.pop();
+        }
+    }
+    Ok(text)
+}
+
+fn temp_path() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    env::temp_dir().join(format!("rustyline-{}-{}.txt", process::id(), nanos))
+}
+
+#[cfg(unix)]
+fn spawn(editor: &str, path: &Path) -> io::Result<ExitStatus> {
+    // like bash, `editor` is interpreted by the shell so that it can have
+    // arguments (`code --wait`)
+    Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path)
+        .status()
+}
+
+#[cfg(not(unix))]
+fn spawn(editor: &str, path: &Path) -> io::Result<ExitStatus> {
+    let mut args = editor.split_whitespace();
+    let program = args.next().unwrap_or(DEFAULT_EDITOR);
+    Command::new(program).args(args).arg(path).status()
+}
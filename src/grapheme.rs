@@ -0,0 +1,108 @@
+//! Units of cursor movement and display: extended grapheme clusters with the
+//! `grapheme` feature, `char`s otherwise.
+//!
+//! Without the feature, `e` + combining accent or emoji ZWJ sequences are
+//! split, but `unicode-segmentation` is not needed.
+
+#[cfg(feature = "grapheme")]
+pub(crate) use unicode_segmentation::UnicodeSegmentation;
+
+#[cfg(not(feature = "grapheme"))]
+pub(crate) use self::chars::UnicodeSegmentation;
+
+#[cfg(not(feature = "grapheme"))]
+mod chars {
+    use core::str::CharIndices;
+
+    /// Same API as `unicode_segmentation::UnicodeSegmentation`, one `char`
+    /// per grapheme.
+    pub(crate) trait UnicodeSegmentation {
+        fn graphemes(&self, is_extended: bool) -> Graphemes<'_>;
+        fn grapheme_indices(&self, is_extended: bool) -> GraphemeIndices<'_>;
+    }
+
+    impl UnicodeSegmentation for str {
+        fn graphemes(&self, is_extended: bool) -> Graphemes<'_> {
+            Graphemes(self.grapheme_indices(is_extended))
+        }
+
+        fn grapheme_indices(&self, _: bool) -> GraphemeIndices<'_> {
+            GraphemeIndices {
+                s: self,
+                chars: self.char_indices(),
+            }
+        }
+    }
+
+    pub(crate) struct GraphemeIndices<'a> {
+        s: &'a str,
+        chars: CharIndices<'a>,
+    }
+
+    impl<'a> GraphemeIndices<'a> {
+        fn slice(&self, (i, c): (usize, char)) -> (usize, &'a str) {
+            (i, &self.s[i..i + c.len_utf8()])
+        }
+    }
+
+    impl<'a> Iterator for GraphemeIndices<'a> {
+        type Item = (usize, &'a str);
+
+        fn next(&mut self) -> Option<Self::Item> {
+            let next = self.chars.next()?;
+            Some(self.slice(next))
+        }
+    }
+
+    impl<'a> DoubleEndedIterator for GraphemeIndices<'a> {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            let next = self.chars.next_back()?;
+            Some(self.slice(next))
+        }
+    }
+
+    pub(crate) struct Graphemes<'a>(GraphemeIndices<'a>);
+
+    impl<'a> Iterator for Graphemes<'a> {
+        type Item = &'a str;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.next().map(|(_, g)| g)
+        }
+    }
+
+    impl<'a> DoubleEndedIterator for Graphemes<'a> {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            self.0.next_back().map(|(_, g)| g)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UnicodeSegmentation;
+
+    #[test]
+    fn graphemes() {
+        let s = "e\u{301}\u{1f1eb}\u{1f1f7}x";
+        let graphemes: Vec<(usize, &str)> = s.grapheme_indices(true).collect();
+        if cfg!(feature = "grapheme") {
+            assert_eq!(
+                vec![(0, "e\u{301}"), (3, "\u{1f1eb}\u{1f1f7}"), (11, "x")],
+                graphemes
+            );
+        } else {
+            assert_eq!(
+                vec![
+                    (0, "e"),
+                    (1, "\u{301}"),
+                    (3, "\u{1f1eb}"),
+                    (7, "\u{1f1f7}"),
+                    (11, "x")
+                ],
+                graphemes
+            );
+        }
+        assert_eq!(Some("x"), s.graphemes(true).next_back());
+    }
+}
@@ -1,9 +1,11 @@
 //! Syntax highlighting
 
 use crate::config::CompletionType;
+use crate::style::{Color, Style};
 use memchr::memchr;
 use std::borrow::Cow::{self, Borrowed, Owned};
 use std::cell::Cell;
+use std::collections::HashSet;
 
 /// Syntax highlighter with [ANSI color](https://en.wikipedia.org/wiki/ANSI_escape_code#SGR_(Select_Graphic_Rendition)_parameters).
 /// Rustyline will try to handle escape sequence for ANSI color on windows
@@ -23,6 +25,12 @@ pub trait Highlighter {
     }
     /// Takes the `prompt` and
     /// returns the highlighted version (with ANSI color).
+    ///
+    /// Called on each redraw of the line, so the result may depend on the
+    /// helper state, which can be updated between `readline` calls with
+    /// [`Editor::helper_mut`](crate::Editor::helper_mut).
+    /// `default` is `false` when the prompt given to `readline` has been
+    /// replaced (during incremental search for example).
     fn highlight_prompt<'b, 's: 'b, 'p: 'b>(
         &'s self,
         prompt: &'p str,
@@ -31,6 +39,14 @@ pub trait Highlighter {
         let _ = default;
         Borrowed(prompt)
     }
+    /// Takes the continuation `prompt` displayed after each newline of a
+    /// multiline input and returns the highlighted version (with ANSI color).
+    fn highlight_continuation_prompt<'b, 's: 'b, 'p: 'b>(
+        &'s self,
+        prompt: &'p str,
+    ) -> Cow<'b, str> {
+        Borrowed(prompt)
+    }
     /// Takes the `hint` and
     /// returns the highlighted version (with ANSI color).
     fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
@@ -52,7 +68,9 @@ pub trait Highlighter {
     /// when cursor is moved under a specific char.
     ///
     /// Used to optimize refresh when a character is inserted or the cursor is
-    /// moved.
+    /// moved: the whole line is repainted (with `highlight`) only when this
+    /// returns `true` or returned `true` for the previous position, otherwise
+    /// only the terminal cursor is moved.
     fn highlight_char(&self, line: &str, pos: usize) -> bool {
         let _ = (line, pos);
         false
@@ -74,6 +92,13 @@ impl<'r, H: ?Sized + Highlighter> Highlighter for &'r H {
         (**self).highlight_prompt(prompt, default)
     }
 
+    fn highlight_continuation_prompt<'b, 's: 'b, 'p: 'b>(
+        &'s self,
+        prompt: &'p str,
+    ) -> Cow<'b, str> {
+        (**self).highlight_continuation_prompt(prompt)
+    }
+
     fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
         (**self).highlight_hint(hint)
     }
@@ -91,27 +116,274 @@ impl<'r, H: ?Sized + Highlighter> Highlighter for &'r H {
     }
 }
 
+/// Highlight the parts of the line matching regular expressions.
+///
+/// Patterns are applied from left to right without overlapping: at a given
+/// position, the first pattern (in the list order) that matches wins.
+#[cfg(feature = "regex-highlight")]
+#[cfg_attr(docsrs, doc(cfg(feature = "regex-highlight")))]
+pub struct RegexHighlighter {
+    patterns: Vec<(regex::Regex, Style)>,
+}
+
+#[cfg(feature = "regex-highlight")]
+impl RegexHighlighter {
+    /// Constructor
+    #[must_use]
+    pub fn new(patterns: Vec<(regex::Regex, Style)>) -> Self {
+        Self { patterns }
+    }
+
+    /// Find the leftmost non-empty match starting from `start`
+    fn find_at(&self, line: &str, start: usize) -> Option<(usize, usize, &Style)> {
+        let mut found: Option<(usize, usize, &Style)> = None;
+        for (re, style) in &self.patterns {
+            let mut at = start;
+            while let Some(m) = re.find_at(line, at) {
+                if matches!(found, Some((s, ..)) if s <= m.start()) {
+                    break; // a previous pattern matches before (or at the same position)
+                }
+                if m.start() < m.end() {
+                    found = Some((m.start(), m.end(), style));
+                    break;
+                }
+                // skip empty match
+                match line[m.end()..].chars().next() {
+                    Some(c) => at = m.end() + c.len_utf8(),
+                    None => break,
+                }
+            }
+        }
+        found
+    }
+}
+
+#[cfg(feature = "regex-highlight")]
+impl Highlighter for RegexHighlighter {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        let mut copy = String::new();
+        let mut last = 0;
+        while let Some((start, end, style)) = self.find_at(line, last) {
+            copy.push_str(&line[last..start]);
+            style.paint(&line[start..end], &mut copy);
+            last = end;
+        }
+        if last == 0 {
+            return Borrowed(line);
+        }
+        copy.push_str(&line[last..]);
+        Owned(copy)
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        // each char typed may change a match
+        !self.patterns.is_empty()
+    }
+}
+
+/// Highlight keywords, string literals and comments.
+///
+/// The line is split on whitespaces and punctuation to find keywords.
+pub struct SyntaxHighlighter {
+    keywords: HashSet<String>,
+    string_delimiters: Vec<char>,
+    comment_prefix: Option<String>,
+    keyword_style: Style,
+    string_style: Style,
+    comment_style: Style,
+}
+
+enum SyntaxState {
+    Normal,
+    InString(char, usize), // delimiter and start
+    InComment,
+}
+
+impl SyntaxHighlighter {
+    /// Constructor without any keyword, with `"` and `'` as string delimiters
+    /// and no comment.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            keywords: HashSet::new(),
+            string_delimiters: vec!['"', '\''],
+            comment_prefix: None,
+            keyword_style: Style {
+                bold: true,
+                ..Style::from(Color::Basic(5)) // magenta
+            },
+            string_style: Style::from(Color::Basic(2)), // green
+            comment_style: Style {
+                dim: true,
+                ..Style::default()
+            },
+        }
+    }
+
+    /// Set the keywords
+    #[must_use]
+    pub fn keywords<I, S>(mut self, keywords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.keywords = keywords.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the characters which start and end a string literal
+    #[must_use]
+    pub fn string_delimiters(mut self, delimiters: Vec<char>) -> Self {
+        self.string_delimiters = delimiters;
+        self
+    }
+
+    /// Set the prefix of a comment (up to the end of the line)
+    #[must_use]
+    pub fn comment_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.comment_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Set the style of keywords
+    #[must_use]
+    pub fn keyword_style(mut self, style: Style) -> Self {
+        self.keyword_style = style;
+        self
+    }
+
+    /// Set the style of string literals
+    #[must_use]
+    pub fn string_style(mut self, style: Style) -> Self {
+        self.string_style = style;
+        self
+    }
+
+    /// Set the style of comments
+    #[must_use]
+    pub fn comment_style(mut self, style: Style) -> Self {
+        self.comment_style = style;
+        self
+    }
+
+    fn push_word(&self, word: &str, buf: &mut String) {
+        if self.keywords.contains(word) {
+            self.keyword_style.paint(word, buf);
+        } else {
+            buf.push_str(word);
+        }
+    }
+}
+
+impl Default for SyntaxHighlighter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Highlighter for SyntaxHighlighter {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        let mut copy = String::with_capacity(line.len());
+        let mut state = SyntaxState::Normal;
+        let mut word_start = 0;
+        let mut escaped = false;
+        for (i, c) in line.char_indices() {
+            match state {
+                SyntaxState::Normal => {
+                    let comment = match self.comment_prefix {
+                        Some(ref prefix) => line[i..].starts_with(prefix.as_str()),
+                        None => false,
+                    };
+                    if comment {
+                        self.push_word(&line[word_start..i], &mut copy);
+                        state = SyntaxState::InComment;
+                        self.comment_style.paint(&line[i..], &mut copy);
+                        break;
+                    } else if self.string_delimiters.contains(&c) {
+                        self.push_word(&line[word_start..i], &mut copy);
+                        state = SyntaxState::InString(c, i);
+                    } else if !(c.is_alphanumeric() || c == '_') {
+                        self.push_word(&line[word_start..i], &mut copy);
+                        copy.push(c);
+                        word_start = i + c.len_utf8();
+                    }
+                }
+                SyntaxState::InString(delimiter, start) => {
+                    if escaped {
+                        escaped = false;
+                    } else if c == '\\' {
+                        escaped = true;
+                    } else if c == delimiter {
+                        let end = i + c.len_utf8();
+                        self.string_style.paint(&line[start..end], &mut copy);
+                        state = SyntaxState::Normal;
+                        word_start = end;
+                    }
+                }
+                SyntaxState::InComment => unreachable!(),
+            }
+        }
+        match state {
+            SyntaxState::Normal => self.push_word(&line[word_start..], &mut copy),
+            // unterminated string
+            SyntaxState::InString(_, start) => self.string_style.paint(&line[start..], &mut copy),
+            SyntaxState::InComment => {}
+        }
+        if copy.len() == line.len() {
+            Borrowed(line) // nothing highlighted
+        } else {
+            Owned(copy)
+        }
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        // each char typed may start or end a token
+        true
+    }
+}
+
 const OPENS: &[u8; 3] = b"{[(";
 const CLOSES: &[u8; 3] = b"}])";
 
 // TODO versus https://python-prompt-toolkit.readthedocs.io/en/master/pages/reference.html?highlight=HighlightMatchingBracketProcessor#prompt_toolkit.layout.processors.HighlightMatchingBracketProcessor
 
 /// Highlight matching bracket when typed or cursor moved on.
-#[derive(Default)]
+///
+/// Both brackets of the pair are highlighted, in bold blue by default.
 pub struct MatchingBracketHighlighter {
     bracket: Cell<Option<(u8, usize)>>, // memorize the character to search...
+    open: Style,
+    close: Style,
 }
 
 impl MatchingBracketHighlighter {
     /// Constructor
     #[must_use]
     pub fn new() -> Self {
+        let bold_blue = Style {
+            bold: true,
+            ..Style::from(Color::Basic(4))
+        };
+        Self::new_with_color(bold_blue, bold_blue)
+    }
+
+    /// Constructor with the styles of the opening and the closing brackets
+    #[must_use]
+    pub fn new_with_color(open: Style, close: Style) -> Self {
         Self {
             bracket: Cell::new(None),
+            open,
+            close,
         }
     }
 }
 
+impl Default for MatchingBracketHighlighter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Highlighter for MatchingBracketHighlighter {
     fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
         if line.len() <= 1 {
@@ -119,9 +391,14 @@ impl Highlighter for MatchingBracketHighlighter {
         }
         // highlight matching brace/bracket/parenthesis if it exists
         if let Some((bracket, pos)) = self.bracket.get() {
-            if let Some((matching, idx)) = find_matching_bracket(line, pos, bracket) {
-                let mut copy = line.to_owned();
-                copy.replace_range(idx..=idx, &format!("\x1b[1;34m{}\x1b[0m", matching as char));
+            if let Some((_, idx)) = find_matching_bracket(line, pos, bracket) {
+                let (open, close) = if idx < pos { (idx, pos) } else { (pos, idx) };
+                let mut copy = String::with_capacity(line.len() + 32);
+                copy.push_str(&line[..open]);
+                self.open.paint(&line[open..=open], &mut copy);
+                copy.push_str(&line[open + 1..close]);
+                self.close.paint(&line[close..=close], &mut copy);
+                copy.push_str(&line[close + 1..]);
                 return Owned(copy);
             }
         }
@@ -262,6 +539,108 @@ mod tests {
         assert_eq!(check_bracket("...)", 3), Some((b')', 3)));
         assert_eq!(check_bracket("...)", 4), Some((b')', 3)));
     }
+    #[test]
+    pub fn highlight() {
+        use super::{Color, Highlighter, MatchingBracketHighlighter, Style};
+
+        let highlighter = MatchingBracketHighlighter::new();
+        assert!(highlighter.highlight_char("(a[b])", 2));
+        assert_eq!(
+            "(a\x1b[1;34m[\x1b[0mb\x1b[1;34m]\x1b[0m)",
+            highlighter.highlight("(a[b])", 2)
+        );
+        // cursor after a closing bracket
+        assert!(highlighter.highlight_char("(a[b])", 6));
+        assert_eq!(
+            "\x1b[1;34m(\x1b[0ma[b]\x1b[1;34m)\x1b[0m",
+            highlighter.highlight("(a[b])", 6)
+        );
+        // unmatched
+        assert!(highlighter.highlight_char("(a[b]", 0));
+        assert_eq!("(a[b]", highlighter.highlight("(a[b]", 0));
+        assert!(!highlighter.highlight_char("a b", 2));
+
+        let highlighter = MatchingBracketHighlighter::new_with_color(
+            Style::from(Color::Basic(2)),
+            Style::from(Color::Basic(3)),
+        );
+        highlighter.highlight_char("{}", 0);
+        assert_eq!(
+            "\x1b[32m{\x1b[0m\x1b[33m}\x1b[0m",
+            highlighter.highlight("{}", 0)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "regex-highlight")]
+    pub fn regex_highlighter() {
+        use super::{Color, Highlighter, RegexHighlighter, Style};
+        use regex::Regex;
+
+        fn strip_ansi(s: &str) -> String {
+            Regex::new("\x1b\\[[0-9;]*m")
+                .unwrap()
+                .replace_all(s, "")
+                .into_owned()
+        }
+
+        let highlighter = RegexHighlighter::new(vec![
+            (
+                Regex::new("[0-9]+").unwrap(),
+                Style::from(Color::Ansi256(2)),
+            ),
+            (
+                Regex::new("[a-z]+[0-9]*").unwrap(),
+                Style {
+                    bold: true,
+                    ..Style::default()
+                },
+            ),
+            (
+                Regex::new("x*").unwrap(),
+                Style {
+                    underline: true,
+                    ..Style::default()
+                },
+            ),
+        ]);
+        for line in ["", "let x1 = 42;", "ab12 34", "+-*/", "é 1"] {
+            let highlighted = highlighter.highlight(line, 0);
+            assert_eq!(line, strip_ansi(&highlighted));
+        }
+        // first match wins
+        assert_eq!(
+            "\x1b[1mab12\x1b[0m \x1b[38;5;2m34\x1b[0m",
+            highlighter.highlight("ab12 34", 0)
+        );
+        assert_eq!(
+            "\x1b[38;5;2m1\x1b[0m\x1b[1mab\x1b[0m",
+            highlighter.highlight("1ab", 0)
+        );
+        assert_eq!("+-", highlighter.highlight("+-", 0));
+    }
+
+    #[test]
+    pub fn syntax_highlighter() {
+        use super::{Color, Highlighter, Style, SyntaxHighlighter};
+
+        let highlighter = SyntaxHighlighter::new()
+            .keywords(vec!["let", "if"])
+            .comment_prefix("//")
+            .keyword_style(Style::from(Color::Basic(1)))
+            .string_style(Style::from(Color::Basic(2)))
+            .comment_style(Style::from(Color::Basic(3)));
+        assert_eq!("letter = 1", highlighter.highlight("letter = 1", 0));
+        assert_eq!(
+            "\x1b[31mlet\x1b[0m x=\x1b[32m\"if \\\" // \"\x1b[0m;\x1b[33m// if\x1b[0m",
+            highlighter.highlight("let x=\"if \\\" // \";// if", 0)
+        );
+        assert_eq!(
+            "(\x1b[31mif\x1b[0m) \x1b[32m'é\x1b[0m",
+            highlighter.highlight("(if) 'é", 0)
+        );
+    }
+
     #[test]
     pub fn matching_bracket() {
         use super::matching_bracket;
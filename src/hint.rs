@@ -1,7 +1,11 @@
 //! Hints (suggestions at the right of the prompt as you type).
 
-use crate::history::SearchDirection;
-use crate::Context;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::utils::levenshtein;
+use crate::{Context, Result};
 
 /// A hint returned by Hinter
 pub trait Hint {
@@ -29,6 +33,9 @@ pub trait Hinter {
     /// Takes the currently edited `line` with the cursor `pos`ition and
     /// returns the string that should be displayed or `None`
     /// if no hint is available for the text the user currently typed.
+    ///
+    /// A hint longer than `ctx.term_cols() - ctx.cursor_col()` columns wraps
+    /// onto the next row.
     // TODO Validate: called while editing line but not while moving cursor.
     fn hint(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Option<Self::Hint> {
         let _ = (line, pos, ctx);
@@ -50,7 +57,19 @@ impl<'r, H: ?Sized + Hinter> Hinter for &'r H {
 
 /// Add suggestion based on previous history entries matching current user
 /// input.
-pub struct HistoryHinter {}
+#[derive(Default)]
+pub struct HistoryHinter {
+    /// Match history entries regardless of case
+    pub case_insensitive: bool,
+}
+
+impl HistoryHinter {
+    /// Create a new case-sensitive `HistoryHinter`
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
 
 impl Hinter for HistoryHinter {
     type Hint = String;
@@ -64,22 +83,147 @@ impl Hinter for HistoryHinter {
         } else {
             ctx.history_index()
         };
-        if let Some(sr) = ctx
-            .history
-            .starts_with(line, start, SearchDirection::Reverse)
-        {
-            if sr.entry == line {
-                return None;
-            }
-            return Some(sr.entry[pos..].to_owned());
-        }
+        (0..=start)
+            .rev()
+            .filter_map(|i| ctx.history.get(i))
+            .find_map(|entry| {
+                if self.case_insensitive {
+                    strip_prefix_ignore_case(entry, line)
+                } else {
+                    entry.strip_prefix(line)
+                }
+            })
+            .filter(|suffix| !suffix.is_empty())
+            .map(str::to_owned)
+    }
+}
+
+/// Correction suggested by [`SpellcheckHinter`]
+///
+/// It is only displayed: nothing is inserted when the hint is accepted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpellcheckHint {
+    display: String,
+    word: String,
+}
+
+impl SpellcheckHint {
+    /// Suggested word
+    #[must_use]
+    pub fn word(&self) -> &str {
+        &self.word
+    }
+}
+
+impl Hint for SpellcheckHint {
+    fn display(&self) -> &str {
+        &self.display
+    }
+
+    fn completion(&self) -> Option<&str> {
         None
     }
 }
 
+/// Suggest the closest dictionary word (by Levenshtein distance) when the
+/// word before the cursor is not in the dictionary.
+pub struct SpellcheckHinter {
+    dictionary: HashSet<String>,
+    max_distance: usize,
+}
+
+impl SpellcheckHinter {
+    /// Create a hinter suggesting words of `dictionary` at most 3 edits away
+    /// from the mistyped one
+    #[must_use]
+    pub fn new(dictionary: HashSet<String>) -> Self {
+        Self {
+            dictionary,
+            max_distance: 3,
+        }
+    }
+
+    /// Load the dictionary from a word list file (one word per line)
+    pub fn from_file<P: AsRef<Path> + ?Sized>(path: &P) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let dictionary = content
+            .lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .map(str::to_owned)
+            .collect();
+        Ok(Self::new(dictionary))
+    }
+
+    /// Set the maximum distance between a word and its suggestion.
+    ///
+    /// By default, 3.
+    #[must_use]
+    pub fn max_distance(mut self, max_distance: usize) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Closest dictionary word from `word` or `None` if `word` is known or
+    /// too far from every entry
+    fn correct(&self, word: &str) -> Option<&str> {
+        let lowercase = word.to_lowercase();
+        if self.dictionary.contains(word) || self.dictionary.contains(&lowercase) {
+            return None;
+        }
+        let len = lowercase.chars().count();
+        self.dictionary
+            .iter()
+            .filter(|entry| {
+                let n = entry.chars().count();
+                n.max(len) - n.min(len) <= self.max_distance
+            })
+            .map(|entry| (levenshtein(&lowercase, &entry.to_lowercase()), entry))
+            .filter(|(distance, _)| *distance <= self.max_distance)
+            .min()
+            .map(|(_, entry)| entry.as_str())
+    }
+}
+
+impl Hinter for SpellcheckHinter {
+    type Hint = SpellcheckHint;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<SpellcheckHint> {
+        // only at the end of a word
+        if line[pos..].starts_with(char::is_alphabetic) {
+            return None;
+        }
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphabetic())
+            .last()
+            .map(|(i, _)| i)?;
+        self.correct(&line[start..pos]).map(|word| SpellcheckHint {
+            display: format!(" ({}?)", word),
+            word: word.to_owned(),
+        })
+    }
+}
+
+/// Return what follows `prefix` in `entry` when `entry` starts with `prefix`
+/// ignoring case.
+fn strip_prefix_ignore_case<'e>(entry: &'e str, prefix: &str) -> Option<&'e str> {
+    let mut entry_chars = entry.char_indices();
+    for p in prefix.chars() {
+        let (_, e) = entry_chars.next()?;
+        if !e.to_lowercase().eq(p.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(entry_chars.as_str())
+}
+
 #[cfg(test)]
 mod test {
-    use super::{Hinter, HistoryHinter};
+    use std::collections::HashSet;
+
+    use super::{Hint, Hinter, HistoryHinter, SpellcheckHinter};
     use crate::history::History;
     use crate::Context;
 
@@ -87,8 +231,57 @@ mod test {
     pub fn empty_history() {
         let history = History::new();
         let ctx = Context::new(&history);
-        let hinter = HistoryHinter {};
+        let hinter = HistoryHinter::new();
         let hint = hinter.hint("test", 4, &ctx);
         assert_eq!(None, hint);
     }
+
+    #[test]
+    pub fn most_recent_match() {
+        let mut history = History::new();
+        history.add("test one");
+        history.add("Test two");
+        history.add("test three");
+        let ctx = Context::new(&history);
+        let mut hinter = HistoryHinter::new();
+        assert_eq!(Some(" three".to_owned()), hinter.hint("test", 4, &ctx));
+        assert_eq!(Some("wo".to_owned()), hinter.hint("Test t", 6, &ctx));
+        assert_eq!(None, hinter.hint("TEST T", 6, &ctx));
+        // cursor not at the end of line
+        assert_eq!(None, hinter.hint("test", 2, &ctx));
+
+        hinter.case_insensitive = true;
+        assert_eq!(Some("hree".to_owned()), hinter.hint("TEST T", 6, &ctx));
+        assert_eq!(Some("o".to_owned()), hinter.hint("test tw", 7, &ctx));
+        // same entry
+        assert_eq!(None, hinter.hint("TEST THREE", 10, &ctx));
+    }
+
+    #[test]
+    pub fn spellcheck() {
+        let history = History::new();
+        let ctx = Context::new(&history);
+        let dictionary: HashSet<String> = ["hello", "world", "help"]
+            .iter()
+            .map(|w| (*w).to_owned())
+            .collect();
+        let hinter = SpellcheckHinter::new(dictionary);
+        let hint = hinter.hint("helo wrld", 9, &ctx).unwrap();
+        assert_eq!("world", hint.word());
+        assert_eq!(" (world?)", hint.display());
+        assert_eq!(None, hint.completion());
+        // known word, case insensitive
+        assert_eq!(None, hinter.hint("Hello", 5, &ctx));
+        // not at the end of a word
+        assert_eq!(None, hinter.hint("wrld", 2, &ctx));
+        assert_eq!(None, hinter.hint("wrld ", 5, &ctx));
+        // too far
+        assert_eq!(None, hinter.hint("xylophone", 9, &ctx));
+        let hinter = hinter.max_distance(1);
+        assert_eq!(None, hinter.hint("wrd", 3, &ctx));
+        assert_eq!(
+            Some("help"),
+            hinter.hint("hel", 3, &ctx).as_ref().map(|h| h.word())
+        );
+    }
 }
@@ -8,69 +8,176 @@ use {
         fs::{File, OpenOptions},
         io::SeekFrom,
         path::PathBuf,
-        time::SystemTime,
     },
 };
 
-use std::collections::vec_deque;
+use std::time::SystemTime;
+#[cfg(all(feature = "with-timestamps", not(target_arch = "wasm32")))]
+use std::time::{Duration, UNIX_EPOCH};
+
+use std::borrow::Cow;
 use std::collections::VecDeque;
 use std::iter::DoubleEndedIterator;
-use std::ops::Index;
+use std::ops::{Index, Range};
 use std::path::Path;
 
 use super::Result;
 use crate::config::{Config, HistoryDuplicates};
-
-/// Search direction
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum SearchDirection {
-    /// Search history forward
-    Forward,
-    /// Search history backward
-    Reverse,
+use crate::mem_history::{find, is_ignored, is_stripped_control};
+pub use crate::mem_history::{MemHistory, MemHistoryIter, SearchDirection, SearchResult};
+
+/// Storage of the history entries.
+///
+/// [`History`] strips control characters from new entries and delegates
+/// everything else to its backend. [`FileHistory`] is the default one, other
+/// implementations can store the entries elsewhere (e.g. in a database shared
+/// by several tools), see [`Editor::with_history`](crate::Editor::with_history).
+pub trait HistoryBackend: Send + Sync {
+    /// Add a new entry at the end of the history.
+    ///
+    /// Return `false` when the entry is discarded (e.g. ignored duplicate).
+    fn add(&mut self, line: String) -> bool;
+    /// Return the history entry at position `index`, starting from 0.
+    fn get(&self, index: usize) -> Option<&str>;
+    /// Return the number of entries in the history.
+    fn len(&self) -> usize;
+    /// Return true if the history has no entry.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Search history (start position inclusive [0, len-1]), matching `term`
+    /// ignoring case when `ignore_case` is `true`.
+    ///
+    /// By default, entries are scanned one by one with [`get`](Self::get).
+    fn search(
+        &self,
+        term: &str,
+        start: usize,
+        dir: SearchDirection,
+        ignore_case: bool,
+    ) -> Option<SearchResult<'_>> {
+        let test = |entry: &str| find(entry, term, ignore_case).map(|(start, _)| start);
+        search_match(self, term, start, dir, test)
+    }
+    /// Save the history in the specified file.
+    fn save(&mut self, path: &Path) -> Result<()>;
+    /// Load the history from the specified file.
+    fn load(&mut self, path: &Path) -> Result<()>;
+    /// Append new entries in the specified file.
+    ///
+    /// By default, the whole history is saved.
+    fn append(&mut self, path: &Path) -> Result<()> {
+        self.save(path)
+    }
+    /// Reload the history from the specified file to get the entries
+    /// appended by other instances.
+    ///
+    /// Does nothing by default: a backend reading directly from a shared
+    /// store is always up to date.
+    fn reload(&mut self, path: &Path) -> Result<()> {
+        let _ = path;
+        Ok(())
+    }
+    /// Apply the history settings of `config` (lines starting with a space,
+    /// duplicates and timestamps).
+    ///
+    /// Settings are ignored by default.
+    fn configure(&mut self, config: &Config) {
+        let _ = config;
+    }
+    /// Set the maximum length for the history.
+    ///
+    /// Ignored by default.
+    fn set_max_len(&mut self, len: usize) {
+        let _ = len;
+    }
+    /// Remove the history entry at position `index`, starting from 0.
+    ///
+    /// Not supported by default.
+    fn remove(&mut self, index: usize) -> Option<String> {
+        let _ = index;
+        None
+    }
+    /// Retain only the entries matching the predicate `f`.
+    ///
+    /// By default, entries are removed one by one with
+    /// [`remove`](Self::remove).
+    fn retain(&mut self, f: &mut dyn FnMut(&str) -> bool) {
+        for index in (0..self.len()).rev() {
+            let keep = match self.get(index) {
+                Some(entry) => f(entry),
+                None => true,
+            };
+            if !keep && self.remove(index).is_none() {
+                break; // not supported
+            }
+        }
+    }
+    /// Clear history
+    ///
+    /// By default, entries are removed one by one with
+    /// [`remove`](Self::remove).
+    fn clear(&mut self) {
+        while !self.is_empty() {
+            if self.remove(self.len() - 1).is_none() {
+                break; // not supported
+            }
+        }
+    }
+    /// Add back an entry saved previously, added at `timestamp` if known.
+    ///
+    /// Unlike [`add`](Self::add), the entry is not filtered out (like a
+    /// duplicate or a line starting with a space). By default, it is added like
+    /// a new one.
+    fn restore(&mut self, line: String, timestamp: Option<SystemTime>) -> bool {
+        let _ = timestamp;
+        self.add(line)
+    }
+    /// Return when the entry at position `index` was added, if known.
+    #[cfg(feature = "with-timestamps")]
+    fn timestamp(&self, index: usize) -> Option<SystemTime> {
+        let _ = index;
+        None
+    }
+    /// Return a copy of this backend, if it can be copied.
+    ///
+    /// Otherwise, cloning a [`History`] gives an in-memory [`FileHistory`]
+    /// with the same entries.
+    fn clone_box(&self) -> Option<Box<dyn HistoryBackend>> {
+        None
+    }
 }
 
-/// History search result
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub struct SearchResult<'a> {
-    /// history entry
-    pub entry: &'a str,
-    /// history index
-    pub idx: usize,
-    /// match position in `entry`
-    pub pos: usize,
+fn search_match<'b, B, F>(
+    backend: &'b B,
+    term: &str,
+    start: usize,
+    dir: SearchDirection,
+    test: F,
+) -> Option<SearchResult<'b>>
+where
+    B: HistoryBackend + ?Sized,
+    F: Fn(&str) -> Option<usize>,
+{
+    let get = |idx: usize| backend.get(idx);
+    crate::mem_history::search_match(backend.len(), get, term, start, dir, test)
 }
 
-// HistoryEntry: text + timestamp
-// TODO Make possible to customize how history is stored / loaded.
-// https://github.com/kkawakam/rustyline/issues/442
-// https://github.com/kkawakam/rustyline/issues/127
-// See https://python-prompt-toolkit.readthedocs.io/en/master/pages/reference.html#prompt_toolkit.history.History abstract methods
-
 /// Current state of the history.
-#[derive(Default)]
+///
+/// Entries are borrowed from the backend, which may not store them as
+/// `String`s: indexing, [`get`](History::get), [`last`](History::last) and
+/// [`iter`](History::iter) yield `&str` (they used to yield `&String`, call
+/// `to_owned` where a `String` is needed).
 pub struct History {
-    entries: VecDeque<String>,
-    max_len: usize,
-    pub(crate) ignore_space: bool,
-    pub(crate) ignore_dups: bool,
-    /// Number of entries inputted by user and not saved yet
-    new_entries: usize,
-    /// last path used by either `load` or `save`
-    #[cfg(not(target_arch = "wasm32"))]
-    path_info: Option<PathInfo>,
+    backend: Box<dyn HistoryBackend>,
+    /// Entries containing one of these are not added
+    ignore_patterns: Vec<String>,
+    /// Last settings applied to the backend, kept for the copy made by `clone`
+    config: Option<Config>,
 }
 
-/// Last histo path, modified timestamp and size
-#[cfg(not(target_arch = "wasm32"))]
-struct PathInfo(PathBuf, SystemTime, usize);
-
 impl History {
-    // New multiline-aware history files start with `#V2\n` and have newlines
-    // and backslashes escaped in them.
-    #[cfg(not(target_arch = "wasm32"))]
-    const FILE_VERSION_V2: &'static str = "#V2";
-
     /// Default constructor
     #[must_use]
     pub fn new() -> Self {
@@ -80,72 +187,97 @@ impl History {
     /// Customized constructor with:
     /// - `Config::max_history_size()`,
     /// - `Config::history_ignore_space()`,
-    /// - `Config::history_duplicates()`.
+    /// - `Config::history_duplicates()`,
+    /// - `Config::history_timestamps()`.
     #[must_use]
     pub fn with_config(config: Config) -> Self {
+        let mut history = Self::with_backend(FileHistory::with_config(config));
+        history.config = Some(config);
+        history
+    }
+
+    /// Constructor storing the entries in `backend`.
+    pub fn with_backend<B: HistoryBackend + 'static>(backend: B) -> Self {
         Self {
-            entries: VecDeque::new(),
-            max_len: config.max_history_size(),
-            ignore_space: config.history_ignore_space(),
-            ignore_dups: config.history_duplicates() == HistoryDuplicates::IgnoreConsecutive,
-            new_entries: 0,
-            #[cfg(not(target_arch = "wasm32"))]
-            path_info: None,
+            backend: Box::new(backend),
+            ignore_patterns: Vec::new(),
+            config: None,
         }
     }
 
+    pub(crate) fn configure(&mut self, config: &Config) {
+        self.backend.configure(config);
+        self.config = Some(*config);
+    }
+
+    /// Do not add the entries containing one of `patterns` (e.g. commands
+    /// with a secret). Entries already in the history are kept.
+    pub fn set_ignore_patterns(&mut self, patterns: Vec<String>) {
+        self.ignore_patterns = patterns;
+        self.ignore_patterns.retain(|pattern| !pattern.is_empty());
+    }
+
     /// Return the history entry at position `index`, starting from 0.
     #[must_use]
-    pub fn get(&self, index: usize) -> Option<&String> {
-        self.entries.get(index)
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.backend.get(index)
     }
 
     /// Return the last history entry (i.e. previous command)
     #[must_use]
-    pub fn last(&self) -> Option<&String> {
-        self.entries.back()
+    pub fn last(&self) -> Option<&str> {
+        self.len().checked_sub(1).and_then(|index| self.get(index))
     }
 
     /// Add a new entry in the history.
+    ///
+    /// Control characters (other than newline and tab) are stripped first,
+    /// see [`History::trim_control`]. Entries matching an ignored pattern are
+    /// discarded, see [`History::set_ignore_patterns`].
     pub fn add<S: AsRef<str> + Into<String>>(&mut self, line: S) -> bool {
-        if self.max_len == 0 {
-            return false;
-        }
-        if line.as_ref().is_empty()
-            || (self.ignore_space
-                && line
-                    .as_ref()
-                    .chars()
-                    .next()
-                    .map_or(true, char::is_whitespace))
+        let line: String = if line.as_ref().chars().any(is_stripped_control) {
+            Self::trim_control(line.as_ref()).into_owned()
+        } else {
+            line.into()
+        };
+        if self
+            .ignore_patterns
+            .iter()
+            .any(|pattern| line.contains(pattern.as_str()))
         {
             return false;
         }
-        if self.ignore_dups {
-            if let Some(s) = self.entries.back() {
-                if s == line.as_ref() {
-                    return false;
-                }
-            }
-        }
-        if self.entries.len() == self.max_len {
-            self.entries.pop_front();
+        self.backend.add(line)
+    }
+
+    /// Add back an entry saved by [`Editor::save_state`](crate::Editor::save_state),
+    /// bypassing all filters.
+    pub(crate) fn restore(&mut self, line: String, timestamp: Option<SystemTime>) -> bool {
+        self.backend.restore(line, timestamp)
+    }
+
+    /// Remove control characters (like `ESC`) from `line`, except newline and
+    /// tab, so that an entry cannot mangle the terminal when it is displayed
+    /// again.
+    #[must_use]
+    pub fn trim_control(line: &str) -> Cow<'_, str> {
+        if line.chars().any(is_stripped_control) {
+            Cow::Owned(line.chars().filter(|&c| !is_stripped_control(c)).collect())
+        } else {
+            Cow::Borrowed(line)
         }
-        self.entries.push_back(line.into());
-        self.new_entries = self.new_entries.saturating_add(1).min(self.len());
-        true
     }
 
     /// Return the number of entries in the history.
     #[must_use]
     pub fn len(&self) -> usize {
-        self.entries.len()
+        self.backend.len()
     }
 
     /// Return true if the history has no entry.
     #[must_use]
     pub fn is_empty(&self) -> bool {
-        self.entries.is_empty()
+        self.backend.is_empty()
     }
 
     /// Set the maximum length for the history. This function can be called even
@@ -155,40 +287,255 @@ impl History {
     ///
     /// Like [stifle_history](http://tiswww.case.edu/php/chet/readline/history.html#IDX11).
     pub fn set_max_len(&mut self, len: usize) {
-        self.max_len = len;
-        if self.len() > len {
-            self.entries.drain(..self.len() - len);
-            self.new_entries = self.new_entries.min(len);
-        }
+        self.backend.set_max_len(len);
     }
 
     /// Save the history in the specified file.
-    // TODO history_truncate_file
-    // https://tiswww.case.edu/php/chet/readline/history.html#IDX31
-    #[cfg(not(target_arch = "wasm32"))]
     pub fn save<P: AsRef<Path> + ?Sized>(&mut self, path: &P) -> Result<()> {
-        if self.is_empty() || self.new_entries == 0 {
-            return Ok(());
+        self.backend.save(path.as_ref())
+    }
+
+    /// Append new entries in the specified file.
+    pub fn append<P: AsRef<Path> + ?Sized>(&mut self, path: &P) -> Result<()> {
+        self.backend.append(path.as_ref())
+    }
+
+    /// Reload the history from the specified file to get the entries
+    /// appended by other instances, keeping the entries not saved yet.
+    ///
+    /// # Errors
+    /// Will return `Err` if path does not already exist or could not be read.
+    pub fn reload<P: AsRef<Path> + ?Sized>(&mut self, path: &P) -> Result<()> {
+        self.backend.reload(path.as_ref())
+    }
+
+    /// Load the history from the specified file.
+    ///
+    /// # Errors
+    /// Will return `Err` if path does not already exist or could not be read.
+    pub fn load<P: AsRef<Path> + ?Sized>(&mut self, path: &P) -> Result<()> {
+        self.backend.load(path.as_ref())
+    }
+
+    /// Remove the history entry at position `index`, starting from 0.
+    ///
+    /// Entries already saved are only removed from the file when the history
+    /// is saved again (not appended).
+    pub fn remove(&mut self, index: usize) -> Option<String> {
+        self.backend.remove(index)
+    }
+
+    /// Retain only the entries matching the predicate `f` (e.g. to exclude
+    /// entries containing a secret before saving).
+    pub fn retain<F: FnMut(&str) -> bool>(&mut self, mut f: F) {
+        self.backend.retain(&mut f);
+    }
+
+    /// Clear history
+    pub fn clear(&mut self) {
+        self.backend.clear();
+    }
+
+    /// Search history (start position inclusive [0, len-1]).
+    ///
+    /// Return the absolute index of the nearest history entry that matches
+    /// `term`.
+    ///
+    /// Return None if no entry contains `term` between [start, len -1] for
+    /// forward search
+    /// or between [0, start] for reverse search.
+    #[must_use]
+    pub fn search(&self, term: &str, start: usize, dir: SearchDirection) -> Option<SearchResult> {
+        let ignore_case = cfg!(feature = "case_insensitive_history_search");
+        self.backend.search(term, start, dir, ignore_case)
+    }
+
+    /// Like [`search`](History::search) but matching `term` ignoring case
+    /// when `ignore_case` is `true`.
+    #[must_use]
+    pub fn search_with_case(
+        &self,
+        term: &str,
+        start: usize,
+        dir: SearchDirection,
+        ignore_case: bool,
+    ) -> Option<SearchResult<'_>> {
+        self.backend.search(term, start, dir, ignore_case)
+    }
+
+    /// Anchored search
+    #[must_use]
+    pub fn starts_with(
+        &self,
+        term: &str,
+        start: usize,
+        dir: SearchDirection,
+    ) -> Option<SearchResult> {
+        #[cfg(not(feature = "case_insensitive_history_search"))]
+        {
+            let test = |entry: &str| {
+                if entry.starts_with(term) {
+                    Some(term.len())
+                } else {
+                    None
+                }
+            };
+            search_match(&*self.backend, term, start, dir, test)
+        }
+        #[cfg(feature = "case_insensitive_history_search")]
+        {
+            use regex::{escape, RegexBuilder};
+            if let Ok(re) = RegexBuilder::new(&escape(term))
+                .case_insensitive(true)
+                .build()
+            {
+                let test = |entry: &str| {
+                    re.find(entry)
+                        .and_then(|m| if m.start() == 0 { Some(m) } else { None })
+                        .map(|m| m.end())
+                };
+                search_match(&*self.backend, term, start, dir, test)
+            } else {
+                None
+            }
         }
-        let path = path.as_ref();
-        let old_umask = umask();
-        let f = File::create(path);
-        restore_umask(old_umask);
-        let file = f?;
-        let mut lock = RwLock::new(file);
-        let lock_guard = lock.write()?;
-        self.save_to(&lock_guard, false)?;
-        self.new_entries = 0;
-        self.update_path(path, &lock_guard, self.len())
     }
 
-    /// Save the history in the specified file.
-    /// Not implemented for wasm.
-    // TODO history_truncate_file
-    // https://tiswww.case.edu/php/chet/readline/history.html#IDX31
-    #[cfg(target_arch = "wasm32")]
-    pub fn save<P: AsRef<Path> + ?Sized>(&mut self, _path: &P) -> Result<()> {
-        todo!();
+    /// Return the entries matching the regular expression `pattern`, newest
+    /// first.
+    ///
+    /// # Errors
+    /// Will return `Err` if `pattern` is not a valid regular expression.
+    #[cfg(feature = "regex-search")]
+    pub fn search_regex(&self, pattern: &str) -> Result<impl Iterator<Item = &str> + '_> {
+        let re = regex::Regex::new(pattern)?;
+        Ok(self.iter().rev().filter(move |entry| re.is_match(entry)))
+    }
+
+    /// Return a forward iterator.
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            history: self,
+            range: 0..self.len(),
+        }
+    }
+
+    /// Return a forward iterator over the entries with the time they were
+    /// added, if known (see `Config::history_timestamps`).
+    #[cfg(feature = "with-timestamps")]
+    pub fn iter_with_timestamps(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (&str, Option<SystemTime>)> + '_ {
+        (0..self.len())
+            .filter_map(move |index| Some((self.get(index)?, self.backend.timestamp(index))))
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::with_backend(FileHistory::default())
+    }
+}
+
+impl Clone for History {
+    fn clone(&self) -> Self {
+        if let Some(backend) = self.backend.clone_box() {
+            return Self {
+                backend,
+                ignore_patterns: self.ignore_patterns.clone(),
+                config: self.config,
+            };
+        }
+        let mut copy = FileHistory::with_config(self.config.unwrap_or_default());
+        copy.max_len = copy.max_len.max(self.len());
+        for index in 0..self.len() {
+            if let Some(entry) = self.get(index) {
+                copy.entries.push_back(entry.to_owned());
+                #[cfg(feature = "with-timestamps")]
+                copy.timestamps.push_back(self.backend.timestamp(index));
+            }
+        }
+        let mut history = Self::with_backend(copy);
+        history.ignore_patterns = self.ignore_patterns.clone();
+        history.config = self.config;
+        history
+    }
+}
+
+/// History stored in memory and saved to / loaded from a flat file.
+#[derive(Clone, Default)]
+pub struct FileHistory {
+    entries: VecDeque<String>,
+    /// When each entry was added (same length as `entries`)
+    #[cfg(feature = "with-timestamps")]
+    timestamps: VecDeque<Option<SystemTime>>,
+    max_len: usize,
+    ignore_space: bool,
+    ignore_dups: bool,
+    /// Whether timestamps are recorded and saved
+    #[cfg(feature = "with-timestamps")]
+    with_timestamps: bool,
+    /// Number of entries inputted by user and not saved yet
+    new_entries: usize,
+    /// last path used by either `load` or `save`
+    #[cfg(not(target_arch = "wasm32"))]
+    path_info: Option<PathInfo>,
+}
+
+/// Last histo path, modified timestamp and size
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+struct PathInfo(PathBuf, SystemTime, usize);
+
+impl FileHistory {
+    // New multiline-aware history files start with `#V2\n` and have newlines
+    // and backslashes escaped in them.
+    #[cfg(not(target_arch = "wasm32"))]
+    const FILE_VERSION_V2: &'static str = "#V2";
+
+    /// Default constructor
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    /// Customized constructor, see [`History::with_config`].
+    #[must_use]
+    pub fn with_config(config: Config) -> Self {
+        let mut history = Self {
+            max_len: config.max_history_size(),
+            ..Self::default()
+        };
+        history.configure(&config);
+        history
+    }
+
+    /// Add a new entry with its original `timestamp` (instead of now).
+    #[cfg(feature = "with-timestamps")]
+    fn add_with_timestamp(&mut self, line: String, timestamp: Option<SystemTime>) -> bool {
+        let added = self.add(line);
+        if added {
+            *self.timestamps.back_mut().unwrap() = timestamp;
+        }
+        added
+    }
+
+    /// Empty history with the same settings
+    #[cfg(not(target_arch = "wasm32"))]
+    fn empty_copy(&self) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            #[cfg(feature = "with-timestamps")]
+            timestamps: VecDeque::new(),
+            max_len: self.max_len,
+            ignore_space: self.ignore_space,
+            ignore_dups: self.ignore_dups,
+            #[cfg(feature = "with-timestamps")]
+            with_timestamps: self.with_timestamps,
+            new_entries: 0,
+            path_info: None,
+        }
     }
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -204,8 +551,28 @@ impl History {
             wtr.write_all(b"\n")?;
             0
         };
+        // a leading `#` is only escaped when it could be mistaken for a
+        // timestamp: files without timestamps keep the previous format
+        #[cfg(feature = "with-timestamps")]
+        let escape_hash = self.with_timestamps;
+        #[cfg(not(feature = "with-timestamps"))]
+        let escape_hash = false;
+        #[cfg(feature = "with-timestamps")]
+        let mut timestamps = self.timestamps.iter().skip(first_new_entry);
         for entry in self.entries.iter().skip(first_new_entry) {
+            #[cfg(feature = "with-timestamps")]
+            if let Some(Some(timestamp)) = timestamps.next() {
+                if self.with_timestamps {
+                    let secs = timestamp
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |d| d.as_secs());
+                    writeln!(wtr, "#{}", secs)?; // like zsh/bash extended history
+                }
+            }
             let mut bytes = entry.as_bytes();
+            if escape_hash && bytes.first() == Some(&b'#') {
+                wtr.write_all(b"\\")?;
+            }
             while let Some(i) = memchr::memchr2(b'\\', b'\n', bytes) {
                 let (head, tail) = bytes.split_at(i);
                 wtr.write_all(head)?;
@@ -229,114 +596,31 @@ impl History {
         Ok(())
     }
 
-    /// Append new entries in the specified file.
-    // Like [append_history](http://tiswww.case.edu/php/chet/readline/history.html#IDX30).
-    #[cfg(not(target_arch = "wasm32"))]
-    pub fn append<P: AsRef<Path> + ?Sized>(&mut self, path: &P) -> Result<()> {
-        use std::io::Seek;
-
-        if self.is_empty() || self.new_entries == 0 {
-            return Ok(());
-        }
-        let path = path.as_ref();
-        if !path.exists() || self.new_entries == self.max_len {
-            return self.save(path);
-        }
-        let file = OpenOptions::new().write(true).read(true).open(path)?;
-        let mut lock = RwLock::new(file);
-        let mut lock_guard = lock.write()?;
-        if self.can_just_append(path, &lock_guard)? {
-            lock_guard.seek(SeekFrom::End(0))?;
-            self.save_to(&lock_guard, true)?;
-            let size = self
-                .path_info
-                .as_ref()
-                .unwrap()
-                .2
-                .saturating_add(self.new_entries);
-            self.new_entries = 0;
-            return self.update_path(path, &lock_guard, size);
-        }
-        // we may need to truncate file before appending new entries
-        let mut other = Self {
-            entries: VecDeque::new(),
-            max_len: self.max_len,
-            ignore_space: self.ignore_space,
-            ignore_dups: self.ignore_dups,
-            new_entries: 0,
-            path_info: None,
-        };
-        other.load_from(&lock_guard)?;
-        let first_new_entry = self.entries.len().saturating_sub(self.new_entries);
-        for entry in self.entries.iter().skip(first_new_entry) {
-            other.add(entry);
-        }
-        lock_guard.seek(SeekFrom::Start(0))?;
-        lock_guard.set_len(0)?; // if new size < old size
-        other.save_to(&lock_guard, false)?;
-        self.update_path(path, &lock_guard, other.len())?;
-        self.new_entries = 0;
-        Ok(())
-    }
-
-    /// Append new entries in the specified file.
-    // Like [append_history](http://tiswww.case.edu/php/chet/readline/history.html#IDX30).
-    /// Not implemented for wasm.
-    #[cfg(target_arch = "wasm32")]
-    pub fn append<P: AsRef<Path> + ?Sized>(&mut self, _path: &P) -> Result<()> {
-        todo!();
-    }
-
-    /// Load the history from the specified file.
-    ///
-    /// # Errors
-    /// Will return `Err` if path does not already exist or could not be read.
-    #[cfg(not(target_arch = "wasm32"))]
-    pub fn load<P: AsRef<Path> + ?Sized>(&mut self, path: &P) -> Result<()> {
-        let path = path.as_ref();
-        let file = File::open(path)?;
-        let lock = RwLock::new(file);
-        let lock_guard = lock.read()?;
-        let len = self.len();
-        if self.load_from(&lock_guard)? {
-            self.update_path(path, &lock_guard, self.len() - len)
-        } else {
-            // discard old version on next save
-            self.path_info = None;
-            Ok(())
-        }
-    }
-
-    /// Load the history from the specified file.
-    /// Not implemented for wasm.
-    /// # Errors
-    /// Will return `Err` if path does not already exist or could not be read.
-    #[cfg(target_arch = "wasm32")]
-    pub fn load<P: AsRef<Path> + ?Sized>(&mut self, _path: &P) -> Result<()> {
-        todo!();
-    }
-
     #[cfg(not(target_arch = "wasm32"))]
     fn load_from(&mut self, file: &File) -> Result<bool> {
         use std::io::{BufRead, BufReader};
 
         let rdr = BufReader::new(file);
-        let mut lines = rdr.lines();
         let mut v2 = false;
-        if let Some(first) = lines.next() {
-            let line = first?;
-            if line == Self::FILE_VERSION_V2 {
+        let mut appendable = true;
+        #[cfg(feature = "with-timestamps")]
+        let mut timestamp = None;
+        for (i, line) in rdr.lines().enumerate() {
+            let mut line = line?;
+            if i == 0 && line == Self::FILE_VERSION_V2 {
                 v2 = true;
-            } else {
-                self.add(line);
+                continue;
             }
-        }
-        let mut appendable = v2;
-        for line in lines {
-            let mut line = line?;
             if line.is_empty() {
                 continue;
             }
+            #[cfg(feature = "with-timestamps")]
+            if self.with_timestamps {
+                if let Some(t) = parse_timestamp(&line) {
+                    timestamp = Some(t);
+                    continue;
+                }
+            }
             if v2 {
                 let mut copy = None; // lazily copy line if unescaping is needed
                 let mut str = line.as_str();
@@ -359,6 +643,9 @@ impl History {
                         b'\\' => {
                             s.push('\\'); // unescaped back slash
                         }
+                        b'#' if i == 0 && s.is_empty() => {
+                            s.push('#'); // unescaped leading hash
+                        }
                         _ => {
                             // only line feed and back slash should have been escaped
                             warn!(target: "rustyline", "bad escaped line: {}", line);
@@ -373,197 +660,335 @@ impl History {
                     line = s;
                 }
             }
-            appendable &= self.add(line); // TODO truncate to MAX_LINE
+            if line.chars().any(is_stripped_control) {
+                line = History::trim_control(&line).into_owned();
+            }
+            #[cfg(feature = "with-timestamps")]
+            let added = self.add_with_timestamp(line, timestamp.take());
+            #[cfg(not(feature = "with-timestamps"))]
+            let added = self.add(line);
+            appendable &= added; // TODO truncate to MAX_LINE
+        }
+        self.new_entries = 0; // TODO we may lost new entries if loaded lines < max_len
+        Ok(v2 && appendable)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_path(&mut self, path: &Path, file: &File, size: usize) -> Result<()> {
+        let modified = file.metadata()?.modified()?;
+        if let Some(PathInfo(
+            ref mut previous_path,
+            ref mut previous_modified,
+            ref mut previous_size,
+        )) = self.path_info
+        {
+            if previous_path.as_path() != path {
+                *previous_path = path.to_owned();
+            }
+            *previous_modified = modified;
+            *previous_size = size;
+        } else {
+            self.path_info = Some(PathInfo(path.to_owned(), modified, size));
+        }
+        debug!(target: "rustyline", "PathInfo({:?}, {:?}, {})", path, modified, size);
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn can_just_append(&self, path: &Path, file: &File) -> Result<bool> {
+        if let Some(PathInfo(ref previous_path, ref previous_modified, ref previous_size)) =
+            self.path_info
+        {
+            if previous_path.as_path() != path {
+                debug!(target: "rustyline", "cannot append: {:?} <> {:?}", previous_path, path);
+                return Ok(false);
+            }
+            let modified = file.metadata()?.modified()?;
+            if *previous_modified != modified
+                || self.max_len <= *previous_size
+                || self.max_len < (*previous_size).saturating_add(self.new_entries)
+            {
+                debug!(target: "rustyline", "cannot append: {:?} < {:?} or {} < {} + {}",
+                       previous_modified, modified, self.max_len, previous_size, self.new_entries);
+                Ok(false)
+            } else {
+                Ok(true)
+            }
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+impl HistoryBackend for FileHistory {
+    fn add(&mut self, line: String) -> bool {
+        if self.max_len == 0
+            || is_ignored(
+                &line,
+                self.entries.back(),
+                self.ignore_space,
+                self.ignore_dups,
+            )
+        {
+            return false;
+        }
+        #[cfg(feature = "with-timestamps")]
+        let timestamp = if self.with_timestamps {
+            Some(SystemTime::now())
+        } else {
+            None
+        };
+        #[cfg(not(feature = "with-timestamps"))]
+        let timestamp = None;
+        self.restore(line, timestamp)
+    }
+
+    fn restore(&mut self, line: String, timestamp: Option<SystemTime>) -> bool {
+        if self.max_len == 0 {
+            return false;
+        }
+        if self.entries.len() == self.max_len {
+            self.entries.pop_front();
+            #[cfg(feature = "with-timestamps")]
+            self.timestamps.pop_front();
+        }
+        self.entries.push_back(line);
+        #[cfg(feature = "with-timestamps")]
+        self.timestamps.push_back(timestamp);
+        #[cfg(not(feature = "with-timestamps"))]
+        let _ = timestamp;
+        self.new_entries = self.new_entries.saturating_add(1).min(self.len());
+        true
+    }
+
+    fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    // TODO history_truncate_file
+    // https://tiswww.case.edu/php/chet/readline/history.html#IDX31
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&mut self, path: &Path) -> Result<()> {
+        if self.is_empty() || self.new_entries == 0 {
+            return Ok(());
+        }
+        let old_umask = umask();
+        let f = File::create(path);
+        restore_umask(old_umask);
+        let file = f?;
+        let mut lock = RwLock::new(file);
+        let lock_guard = lock.write()?;
+        self.save_to(&lock_guard, false)?;
+        self.new_entries = 0;
+        self.update_path(path, &lock_guard, self.len())
+    }
+
+    /// Not supported for wasm: there is no file system.
+    #[cfg(target_arch = "wasm32")]
+    fn save(&mut self, _path: &Path) -> Result<()> {
+        Err(unsupported())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load(&mut self, path: &Path) -> Result<()> {
+        let file = File::open(path)?;
+        let lock = RwLock::new(file);
+        let lock_guard = lock.read()?;
+        let len = self.len();
+        if self.load_from(&lock_guard)? {
+            self.update_path(path, &lock_guard, self.len() - len)
+        } else {
+            // discard old version on next save
+            self.path_info = None;
+            Ok(())
+        }
+    }
+
+    /// Not supported for wasm: there is no file system.
+    #[cfg(target_arch = "wasm32")]
+    fn load(&mut self, _path: &Path) -> Result<()> {
+        Err(unsupported())
+    }
+
+    // Like [append_history](http://tiswww.case.edu/php/chet/readline/history.html#IDX30).
+    #[cfg(not(target_arch = "wasm32"))]
+    fn append(&mut self, path: &Path) -> Result<()> {
+        use std::io::Seek;
+
+        if self.is_empty() || self.new_entries == 0 {
+            return Ok(());
+        }
+        if !path.exists() || self.new_entries == self.max_len {
+            return self.save(path);
+        }
+        let file = OpenOptions::new().write(true).read(true).open(path)?;
+        let mut lock = RwLock::new(file);
+        let mut lock_guard = lock.write()?;
+        if self.can_just_append(path, &lock_guard)? {
+            lock_guard.seek(SeekFrom::End(0))?;
+            self.save_to(&lock_guard, true)?;
+            let size = self
+                .path_info
+                .as_ref()
+                .unwrap()
+                .2
+                .saturating_add(self.new_entries);
+            self.new_entries = 0;
+            return self.update_path(path, &lock_guard, size);
         }
-        self.new_entries = 0; // TODO we may lost new entries if loaded lines < max_len
-        Ok(appendable)
-    }
-
-    #[cfg(not(target_arch = "wasm32"))]
-    fn update_path(&mut self, path: &Path, file: &File, size: usize) -> Result<()> {
-        let modified = file.metadata()?.modified()?;
-        if let Some(PathInfo(
-            ref mut previous_path,
-            ref mut previous_modified,
-            ref mut previous_size,
-        )) = self.path_info
-        {
-            if previous_path.as_path() != path {
-                *previous_path = path.to_owned();
-            }
-            *previous_modified = modified;
-            *previous_size = size;
-        } else {
-            self.path_info = Some(PathInfo(path.to_owned(), modified, size));
+        // we may need to truncate file before appending new entries
+        let mut other = self.empty_copy();
+        other.load_from(&lock_guard)?;
+        let first_new_entry = self.entries.len().saturating_sub(self.new_entries);
+        for entry in self.entries.iter().skip(first_new_entry) {
+            other.add(entry.clone());
         }
-        debug!(target: "rustyline", "PathInfo({:?}, {:?}, {})", path, modified, size);
+        lock_guard.seek(SeekFrom::Start(0))?;
+        lock_guard.set_len(0)?; // if new size < old size
+        other.save_to(&lock_guard, false)?;
+        self.update_path(path, &lock_guard, other.len())?;
+        self.new_entries = 0;
         Ok(())
     }
 
+    /// Not supported for wasm: there is no file system.
+    #[cfg(target_arch = "wasm32")]
+    fn append(&mut self, _path: &Path) -> Result<()> {
+        Err(unsupported())
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
-    fn can_just_append(&self, path: &Path, file: &File) -> Result<bool> {
-        if let Some(PathInfo(ref previous_path, ref previous_modified, ref previous_size)) =
-            self.path_info
-        {
-            if previous_path.as_path() != path {
-                debug!(target: "rustyline", "cannot append: {:?} <> {:?}", previous_path, path);
-                return Ok(false);
-            }
-            let modified = file.metadata()?.modified()?;
-            if *previous_modified != modified
-                || self.max_len <= *previous_size
-                || self.max_len < (*previous_size).saturating_add(self.new_entries)
-            {
-                debug!(target: "rustyline", "cannot append: {:?} < {:?} or {} < {} + {}",
-                       previous_modified, modified, self.max_len, previous_size, self.new_entries);
-                Ok(false)
-            } else {
-                Ok(true)
-            }
-        } else {
-            Ok(false)
+    fn reload(&mut self, path: &Path) -> Result<()> {
+        let mut other = self.empty_copy();
+        other.load(path)?;
+        let first_new_entry = self.len() - self.new_entries;
+        #[cfg(feature = "with-timestamps")]
+        let timestamps: Vec<_> = self.timestamps.drain(first_new_entry..).collect();
+        #[cfg(feature = "with-timestamps")]
+        for (entry, timestamp) in self.entries.drain(first_new_entry..).zip(timestamps) {
+            other.add_with_timestamp(entry, timestamp);
+        }
+        #[cfg(not(feature = "with-timestamps"))]
+        for entry in self.entries.drain(first_new_entry..) {
+            other.add(entry);
         }
+        *self = other;
+        Ok(())
     }
 
-    /// Clear history
-    pub fn clear(&mut self) {
-        self.entries.clear();
-        self.new_entries = 0;
+    /// Not supported for wasm: there is no file system.
+    #[cfg(target_arch = "wasm32")]
+    fn reload(&mut self, _path: &Path) -> Result<()> {
+        Err(unsupported())
     }
 
-    /// Search history (start position inclusive [0, len-1]).
-    ///
-    /// Return the absolute index of the nearest history entry that matches
-    /// `term`.
-    ///
-    /// Return None if no entry contains `term` between [start, len -1] for
-    /// forward search
-    /// or between [0, start] for reverse search.
-    #[must_use]
-    pub fn search(&self, term: &str, start: usize, dir: SearchDirection) -> Option<SearchResult> {
-        #[cfg(not(feature = "case_insensitive_history_search"))]
+    fn configure(&mut self, config: &Config) {
+        self.ignore_space = config.history_ignore_space();
+        self.ignore_dups = config.history_duplicates() == HistoryDuplicates::IgnoreConsecutive;
+        #[cfg(feature = "with-timestamps")]
         {
-            let test = |entry: &str| entry.find(term);
-            self.search_match(term, start, dir, test)
-        }
-        #[cfg(feature = "case_insensitive_history_search")]
-        {
-            use regex::{escape, RegexBuilder};
-            if let Ok(re) = RegexBuilder::new(&escape(term))
-                .case_insensitive(true)
-                .build()
-            {
-                let test = |entry: &str| re.find(entry).map(|m| m.start());
-                self.search_match(term, start, dir, test)
-            } else {
-                None
-            }
+            self.with_timestamps = config.history_timestamps();
         }
     }
 
-    /// Anchored search
-    #[must_use]
-    pub fn starts_with(
-        &self,
-        term: &str,
-        start: usize,
-        dir: SearchDirection,
-    ) -> Option<SearchResult> {
-        #[cfg(not(feature = "case_insensitive_history_search"))]
-        {
-            let test = |entry: &str| {
-                if entry.starts_with(term) {
-                    Some(term.len())
-                } else {
-                    None
-                }
-            };
-            self.search_match(term, start, dir, test)
+    fn set_max_len(&mut self, len: usize) {
+        self.max_len = len;
+        if self.len() > len {
+            #[cfg(feature = "with-timestamps")]
+            self.timestamps.drain(..self.len() - len);
+            self.entries.drain(..self.len() - len);
+            self.new_entries = self.new_entries.min(len);
         }
-        #[cfg(feature = "case_insensitive_history_search")]
-        {
-            use regex::{escape, RegexBuilder};
-            if let Ok(re) = RegexBuilder::new(&escape(term))
-                .case_insensitive(true)
-                .build()
-            {
-                let test = |entry: &str| {
-                    re.find(entry)
-                        .and_then(|m| if m.start() == 0 { Some(m) } else { None })
-                        .map(|m| m.end())
-                };
-                self.search_match(term, start, dir, test)
-            } else {
-                None
-            }
+    }
+
+    fn remove(&mut self, index: usize) -> Option<String> {
+        let first_new_entry = self.len() - self.new_entries;
+        let entry = self.entries.remove(index)?;
+        #[cfg(feature = "with-timestamps")]
+        self.timestamps.remove(index);
+        if index >= first_new_entry {
+            self.new_entries -= 1;
         }
+        Some(entry)
     }
 
-    fn search_match<F>(
-        &self,
-        term: &str,
-        start: usize,
-        dir: SearchDirection,
-        test: F,
-    ) -> Option<SearchResult>
-    where
-        F: Fn(&str) -> Option<usize>,
-    {
-        if term.is_empty() || start >= self.len() {
-            return None;
-        }
-        match dir {
-            SearchDirection::Reverse => {
-                for (idx, entry) in self
-                    .entries
-                    .iter()
-                    .rev()
-                    .skip(self.entries.len() - 1 - start)
-                    .enumerate()
-                {
-                    if let Some(cursor) = test(entry) {
-                        return Some(SearchResult {
-                            idx: start - idx,
-                            entry,
-                            pos: cursor,
-                        });
-                    }
-                }
-                None
-            }
-            SearchDirection::Forward => {
-                for (idx, entry) in self.entries.iter().skip(start).enumerate() {
-                    if let Some(cursor) = test(entry) {
-                        return Some(SearchResult {
-                            idx: idx + start,
-                            entry,
-                            pos: cursor,
-                        });
-                    }
-                }
-                None
+    fn retain(&mut self, f: &mut dyn FnMut(&str) -> bool) {
+        let first_new_entry = self.len() - self.new_entries;
+        let mut new_entries = self.new_entries;
+        let mut index = 0;
+        #[cfg(feature = "with-timestamps")]
+        let mut kept = Vec::with_capacity(self.len());
+        self.entries.retain(|entry| {
+            let keep = f(entry);
+            if !keep && index >= first_new_entry {
+                new_entries -= 1;
             }
+            index += 1;
+            #[cfg(feature = "with-timestamps")]
+            kept.push(keep);
+            keep
+        });
+        #[cfg(feature = "with-timestamps")]
+        {
+            let mut kept = kept.into_iter();
+            self.timestamps.retain(|_| kept.next().unwrap_or(true));
         }
+        self.new_entries = new_entries;
     }
 
-    /// Return a forward iterator.
-    #[must_use]
-    pub fn iter(&self) -> Iter<'_> {
-        Iter(self.entries.iter())
+    fn clear(&mut self) {
+        self.entries.clear();
+        #[cfg(feature = "with-timestamps")]
+        self.timestamps.clear();
+        self.new_entries = 0;
+    }
+
+    #[cfg(feature = "with-timestamps")]
+    fn timestamp(&self, index: usize) -> Option<SystemTime> {
+        self.timestamps.get(index).copied().flatten()
     }
+
+    fn clone_box(&self) -> Option<Box<dyn HistoryBackend>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn unsupported() -> crate::error::ReadlineError {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "history files are not supported on wasm",
+    )
+    .into()
+}
+
+/// Parse a `#<unix_timestamp_seconds>` line of an extended history file.
+#[cfg(all(feature = "with-timestamps", not(target_arch = "wasm32")))]
+fn parse_timestamp(line: &str) -> Option<SystemTime> {
+    let secs = line.strip_prefix('#')?;
+    if secs.is_empty() || !secs.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    secs.parse()
+        .ok()
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
 }
 
 impl Index<usize> for History {
-    type Output = String;
+    type Output = str;
 
-    fn index(&self, index: usize) -> &String {
-        &self.entries[index]
+    fn index(&self, index: usize) -> &str {
+        self.get(index).expect("history index out of bounds")
     }
 }
 
 impl<'a> IntoIterator for &'a History {
     type IntoIter = Iter<'a>;
-    type Item = &'a String;
+    type Item = &'a str;
 
     fn into_iter(self) -> Iter<'a> {
         self.iter()
@@ -571,23 +996,28 @@ impl<'a> IntoIterator for &'a History {
 }
 
 /// History iterator.
-pub struct Iter<'a>(vec_deque::Iter<'a, String>);
+pub struct Iter<'a> {
+    history: &'a History,
+    range: Range<usize>,
+}
 
 impl<'a> Iterator for Iter<'a> {
-    type Item = &'a String;
+    type Item = &'a str;
 
-    fn next(&mut self) -> Option<&'a String> {
-        self.0.next()
+    fn next(&mut self) -> Option<&'a str> {
+        self.range.next().and_then(|index| self.history.get(index))
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        self.0.size_hint()
+        self.range.size_hint()
     }
 }
 
 impl<'a> DoubleEndedIterator for Iter<'a> {
-    fn next_back(&mut self) -> Option<&'a String> {
-        self.0.next_back()
+    fn next_back(&mut self) -> Option<&'a str> {
+        self.range
+            .next_back()
+            .and_then(|index| self.history.get(index))
     }
 }
 
@@ -619,7 +1049,9 @@ cfg_if::cfg_if! {
 
 #[cfg(test)]
 mod tests {
-    use super::{History, SearchDirection, SearchResult};
+    use std::borrow::Cow;
+
+    use super::{FileHistory, History, HistoryBackend, SearchDirection, SearchResult};
     use crate::config::Config;
     use crate::Result;
 
@@ -634,14 +1066,17 @@ mod tests {
     #[test]
     fn new() {
         let history = History::new();
-        assert_eq!(0, history.entries.len());
+        assert_eq!(0, history.len());
     }
 
     #[test]
     fn add() {
         let config = Config::builder().history_ignore_space(true).build();
         let mut history = History::with_config(config);
-        assert_eq!(config.max_history_size(), history.max_len);
+        assert_eq!(
+            config.max_history_size(),
+            FileHistory::with_config(config).max_len
+        );
         assert!(history.add("line1"));
         assert!(history.add("line2"));
         assert!(!history.add("line2"));
@@ -649,12 +1084,138 @@ mod tests {
         assert!(!history.add(" line3"));
     }
 
+    #[test]
+    fn add_strips_control() {
+        let mut history = History::new();
+        assert!(history.add("\x1b[31mred\x1b[m"));
+        assert_eq!(Some("[31mred[m"), history.get(0));
+        // already sanitized
+        assert!(!history.add("[31mred[m"));
+        // newline and tab are kept
+        assert!(history.add("a\tb\nc\x07"));
+        assert_eq!(Some("a\tb\nc"), history.get(1));
+        // nothing left
+        assert!(!history.add("\x1b\x00"));
+        assert_eq!(2, history.len());
+
+        assert!(matches!(History::trim_control("abc"), Cow::Borrowed("abc")));
+        assert_eq!("ab", History::trim_control("a\x1bb\r"));
+    }
+
+    #[test]
+    fn add_with_dups() {
+        let config = Config::builder()
+            .history_ignore_dups(false)
+            .history_ignore_space(false)
+            .build();
+        let mut history = History::with_config(config);
+        assert!(history.add("line1"));
+        assert!(history.add("line1"));
+        assert!(history.add(" line2"));
+        assert_eq!(3, history.len());
+    }
+
+    #[test]
+    fn add_evicts_oldest() {
+        let config = Config::builder().max_history_size(2).build();
+        let mut history = History::with_config(config);
+        assert!(history.add("line1"));
+        assert!(history.add("line2"));
+        assert!(history.add("line3"));
+        assert_eq!(2, history.len());
+        assert_eq!(Some("line2"), history.get(0));
+        assert_eq!(Some("line3"), history.last());
+    }
+
+    #[test]
+    fn add_with_all_policies() {
+        let config = Config::builder()
+            .max_history_size(2)
+            .history_ignore_dups(true)
+            .history_ignore_space(true)
+            .build();
+        let mut history = History::with_config(config);
+        assert!(history.add("line1"));
+        assert!(history.add("line2"));
+        // neither a duplicate nor an entry starting with a space evicts anything
+        assert!(!history.add("line2"));
+        assert!(!history.add(" line3"));
+        assert_eq!(Some("line1"), history.get(0));
+        // only consecutive duplicates are ignored
+        assert!(history.add("line1"));
+        assert_eq!(2, history.len());
+        assert_eq!(Some("line2"), history.get(0));
+        assert_eq!(Some("line1"), history.last());
+    }
+
+    #[test]
+    fn iter_index_clear() {
+        let mut history = init();
+        let entries: Vec<&str> = history.iter().collect();
+        assert_eq!(vec!["line1", "line2", "line3"], entries);
+        assert_eq!("line2", &history[1]);
+        assert_eq!(Some("line3"), history.get(2));
+        assert_eq!(None, history.get(3));
+        history.clear();
+        assert!(history.is_empty());
+        assert_eq!(0, history.iter().count());
+    }
+
+    #[test]
+    fn remove_retain() {
+        let mut history = FileHistory::new();
+        for line in &["line1", "line2", "line3"] {
+            assert!(history.add((*line).to_owned()));
+        }
+        assert_eq!(Some("line2".to_owned()), history.remove(1));
+        assert_eq!(None, history.remove(2));
+        assert_eq!(2, history.new_entries);
+
+        assert!(history.add("password secret".to_owned()));
+        assert!(history.add("line4".to_owned()));
+        history.retain(&mut |entry| !entry.contains("secret"));
+        let entries: Vec<&str> = history.entries.iter().map(String::as_str).collect();
+        assert_eq!(vec!["line1", "line3", "line4"], entries);
+        assert_eq!(3, history.new_entries);
+    }
+
+    #[test]
+    fn ignore_patterns() {
+        let mut history = init();
+        history.set_ignore_patterns(vec!["secret".to_owned(), String::new()]);
+        assert!(!history.add("export TOKEN=secret"));
+        assert!(history.add("line4"));
+        assert_eq!(4, history.len());
+        // kept by a copy
+        let mut copy = history.clone();
+        assert!(!copy.add("secret"));
+        history.set_ignore_patterns(Vec::new());
+        assert!(history.add("secret"));
+    }
+
+    #[cfg(feature = "regex-search")]
+    #[test]
+    fn search_regex() -> Result<()> {
+        let mut history = init();
+        history.add("Line4");
+        let entries: Vec<&str> = history.search_regex("^line[13]$")?.collect();
+        assert_eq!(vec!["line3", "line1"], entries);
+        let entries: Vec<&str> = history.search_regex("(?i)^LINE[24]")?.collect();
+        assert_eq!(vec!["Line4", "line2"], entries);
+        assert_eq!(0, history.search_regex("none")?.count());
+        assert!(matches!(
+            history.search_regex("line("),
+            Err(crate::error::ReadlineError::InvalidPattern(_))
+        ));
+        Ok(())
+    }
+
     #[test]
     fn set_max_len() {
         let mut history = init();
         history.set_max_len(1);
-        assert_eq!(1, history.entries.len());
-        assert_eq!(Some(&"line3".to_owned()), history.last());
+        assert_eq!(1, history.len());
+        assert_eq!(Some("line3"), history.last());
     }
 
     #[test]
@@ -678,7 +1239,7 @@ mod tests {
         history.save(tf.path())?;
         let mut history2 = History::new();
         history2.load(tf.path())?;
-        for (a, b) in history.entries.iter().zip(history2.entries.iter()) {
+        for (a, b) in history.iter().zip(history2.iter()) {
             assert_eq!(a, b);
         }
         tf.close()?;
@@ -703,9 +1264,9 @@ mod tests {
         }
         let mut history = History::new();
         history.load(tf.path())?;
-        assert_eq!(history.entries[0], "test\\n \\abc \\123");
-        assert_eq!(history.entries[1], "123\\n\\\\n");
-        assert_eq!(history.entries[2], "abcde");
+        assert_eq!(&history[0], "test\\n \\abc \\123");
+        assert_eq!(&history[1], "123\\n\\\\n");
+        assert_eq!(&history[2], "abcde");
 
         tf.close()?;
         Ok(())
@@ -735,6 +1296,125 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[cfg_attr(miri, ignore)] // unsupported operation: `getcwd` not available when isolation is enabled
+    fn append_new_entries_only() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("history");
+        // file does not exist yet
+        let mut history = init();
+        history.append(&path)?;
+
+        let mut history2 = History::new();
+        history2.load(&path)?;
+        history2.add("line4");
+        history2.add("line5");
+        history2.append(&path)?;
+        // nothing new
+        history2.append(&path)?;
+
+        let content = std::fs::read_to_string(&path)?;
+        assert_eq!("#V2\nline1\nline2\nline3\nline4\nline5\n", content);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // unsupported operation: `getcwd` not available when isolation is enabled
+    fn reload() -> Result<()> {
+        let tf = tempfile::NamedTempFile::new()?;
+
+        // two sessions sharing the same file
+        let mut history1 = History::new();
+        history1.add("line1");
+        history1.append(tf.path())?;
+        let mut history2 = History::new();
+        history2.load(tf.path())?;
+        history2.add("line2");
+        history2.append(tf.path())?;
+
+        history1.add("line3");
+        history1.reload(tf.path())?;
+        let entries: Vec<&str> = history1.iter().collect();
+        assert_eq!(vec!["line1", "line2", "line3"], entries);
+        history1.append(tf.path())?;
+
+        let content = std::fs::read_to_string(tf.path())?;
+        assert_eq!("#V2\nline1\nline2\nline3\n", content);
+        tf.close()?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "with-timestamps")]
+    #[cfg_attr(miri, ignore)] // unsupported operation: `getcwd` not available when isolation is enabled
+    fn timestamps() -> Result<()> {
+        use std::io::Write;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let mut tf = tempfile::NamedTempFile::new()?;
+        // plain and extended entries mixed
+        tf.write_all(b"line1\n#1600000000\nline2\nline3\n")?;
+        let config = Config::builder().history_timestamps(true).build();
+        let mut history = History::with_config(config);
+        history.load(tf.path())?;
+        let epoch = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let entries: Vec<_> = history.iter_with_timestamps().collect();
+        assert_eq!(
+            vec![("line1", None), ("line2", Some(epoch)), ("line3", None)],
+            entries
+        );
+
+        history.remove(0);
+        history.add("line4");
+        assert!(history.iter_with_timestamps().last().unwrap().1.is_some());
+        history.save(tf.path())?;
+        let content = std::fs::read_to_string(tf.path())?;
+        assert!(content.starts_with("#V2\n#1600000000\nline2\nline3\n#"));
+        assert!(content.ends_with("\nline4\n"));
+
+        let mut history = History::with_config(config);
+        history.load(tf.path())?;
+        let entries: Vec<_> = history.iter_with_timestamps().collect();
+        assert_eq!(("line2", Some(epoch)), entries[0]);
+        assert_eq!(None, entries[1].1);
+        assert_eq!("line4", entries[2].0);
+        assert!(entries[2].1.is_some());
+        tf.close()?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // unsupported operation: `getcwd` not available when isolation is enabled
+    fn save_hash_entries() -> Result<()> {
+        let tf = tempfile::NamedTempFile::new()?;
+        let lines = ["echo hi", "#123", "#V2", "ls"];
+        for timestamps in [false, true] {
+            #[cfg(not(feature = "with-timestamps"))]
+            let config = Config::default();
+            #[cfg(feature = "with-timestamps")]
+            let config = Config::builder().history_timestamps(timestamps).build();
+            let mut history = History::with_config(config);
+            for line in &lines {
+                assert!(history.add(*line));
+            }
+            history.save(tf.path())?;
+            let content = std::fs::read_to_string(tf.path())?;
+            // only escaped when timestamps are saved
+            assert_eq!(
+                cfg!(feature = "with-timestamps") && timestamps,
+                content.contains("\\#123\n"),
+                "timestamps: {}",
+                timestamps
+            );
+            let mut history = History::with_config(config);
+            history.load(tf.path())?;
+            let entries: Vec<&str> = history.iter().collect();
+            assert_eq!(lines.to_vec(), entries, "timestamps: {}", timestamps);
+        }
+        tf.close()?;
+        Ok(())
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)] // unsupported operation: `getcwd` not available when isolation is enabled
     fn truncate() -> Result<()> {
@@ -754,7 +1434,7 @@ mod tests {
         let mut history = History::new();
         history.load(tf.path())?;
         assert_eq!(history.len(), 2);
-        assert_eq!(history.entries[1], "l");
+        assert_eq!(&history[1], "l");
 
         tf.close()?;
         Ok(())
@@ -793,6 +1473,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn search_with_case() {
+        let mut history = History::new();
+        history.add("Cargo build");
+        history.add("cargo test");
+        assert_eq!(
+            Some(0),
+            history
+                .search_with_case("cargo b", 1, SearchDirection::Reverse, true)
+                .map(|sr| sr.idx)
+        );
+        assert_eq!(
+            None,
+            history.search_with_case("cargo b", 1, SearchDirection::Reverse, false)
+        );
+        assert_eq!(Some((2, 5)), super::find("a BaR", "bar", true));
+        assert_eq!(None, super::find("a BaR", "bar", false));
+        assert_eq!(Some((2, 5)), super::find("\u{e9}\u{c9}t", "\u{e9}t", true));
+    }
+
     #[test]
     fn reverse_search() {
         let history = init();
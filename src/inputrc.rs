@@ -0,0 +1,465 @@
+//! Subset of GNU readline's `.inputrc` syntax
+//!
+//! Supported lines:
+//! - `"\C-a": beginning-of-line` (quoted key sequence)
+//! - `Meta-b: backward-word` (key name)
+//! - `"\C-xh": "hello"` (macro: text inserted as is)
+//! - `set editing-mode vi` (also `completion-query-items`,
+//!   `completion-ignore-case`, `keyseq-timeout` and `history-size`)
+//! - `$if mode=vi`, `$if term=xterm`, `$else`, `$endif`
+//!
+//! Anything else (`$include`, unknown functions or variables) is ignored with a
+//! warning.
+use log::warn;
+
+use crate::config::EditMode;
+use crate::keymap::{Anchor, At, Cmd, Movement, Word};
+use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
+
+/// A valid `.inputrc` line
+#[derive(Debug, PartialEq)]
+pub(crate) enum Directive {
+    /// Key sequence bound to a command
+    Bind(Vec<E>, Cmd),
+    /// `set editing-mode`
+    EditMode(EditMode),
+    /// `set completion-query-items`
+    CompletionPromptLimit(usize),
+    /// `set completion-ignore-case`
+    CompletionIgnoreCase(bool),
+    /// `set keyseq-timeout`
+    KeyseqTimeout(i32),
+    /// `set history-size`
+    HistorySize(usize),
+}
+
+/// `$if` block being parsed
+struct Conditional {
+    /// whether the enclosing block is being applied
+    outer: bool,
+    /// whether the current branch is being applied
+    active: bool,
+}
+
+/// Parse the content of an `.inputrc` file, evaluating `$if` tests against
+/// the editing `mode` (as changed by the file itself) and the `term` name.
+pub(crate) fn parse(content: &str, mut mode: EditMode, term: &str) -> Vec<Directive> {
+    let mut directives = Vec::new();
+    let mut conditionals: Vec<Conditional> = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let active = conditionals.iter().all(|c| c.active);
+        if let Some(construct) = line.strip_prefix('$') {
+            let (name, arg) = construct
+                .split_once(char::is_whitespace)
+                .unwrap_or((construct, ""));
+            match (name, conditionals.last_mut()) {
+                ("if", _) => conditionals.push(Conditional {
+                    outer: active,
+                    active: active && test(arg.trim(), mode, term),
+                }),
+                ("else", Some(c)) => c.active = c.outer && !c.active,
+                ("endif", Some(_)) => {
+                    conditionals.pop();
+                }
+                _ => {
+                    warn!(target: "rustyline", "inputrc: line {} ignored: {}", i + 1, line);
+                }
+            }
+            continue;
+        }
+        if !active {
+            continue;
+        }
+        match parse_line(line) {
+            Some(directive) => {
+                if let Directive::EditMode(edit_mode) = directive {
+                    mode = edit_mode;
+                }
+                directives.push(directive);
+            }
+            None => warn!(target: "rustyline", "inputrc: line {} ignored: {}", i + 1, line),
+        }
+    }
+    if !conditionals.is_empty() {
+        warn!(target: "rustyline", "inputrc: missing $endif");
+    }
+    directives
+}
+
+/// Evaluate the test of an `$if` construct.
+fn test(condition: &str, mode: EditMode, term: &str) -> bool {
+    match condition.split_once('=') {
+        Some(("mode", value)) => match value.trim() {
+            "emacs" => mode == EditMode::Emacs,
+            "vi" => mode == EditMode::Vi,
+            _ => false,
+        },
+        // either the full terminal name or the part before the first `-`
+        Some(("term", value)) => {
+            let value = value.trim();
+            !value.is_empty() && (value == term || term.split('-').next() == Some(value))
+        }
+        Some(_) => {
+            warn!(target: "rustyline", "inputrc: unsupported test: {}", condition);
+            false
+        }
+        // application name: only for applications setting `rl_readline_name`
+        None => false,
+    }
+}
+
+fn parse_line(line: &str) -> Option<Directive> {
+    if let Some(rest) = line.strip_prefix("set") {
+        if rest.starts_with(char::is_whitespace) {
+            return parse_set(rest.trim_start());
+        }
+    }
+    let (keys, rest) = if let Some(quoted) = line.strip_prefix('"') {
+        let (seq, rest) = split_quoted(quoted)?;
+        (parse_key_seq(seq)?, rest.trim_start().strip_prefix(':')?)
+    } else {
+        let (name, rest) = line.split_once(':')?;
+        (vec![parse_key_name(name.trim())?], rest)
+    };
+    if keys.is_empty() {
+        return None;
+    }
+    let value = rest.trim();
+    let cmd = if let Some(quoted) = value.strip_prefix('"') {
+        let (text, _) = split_quoted(quoted)?;
+        Cmd::Insert(1, unescape(text)?)
+    } else {
+        // trailing comment or garbage is ignored like readline does
+        let name = value.split_whitespace().next()?;
+        let cmd = command(name);
+        if cmd.is_none() {
+            warn!(target: "rustyline", "inputrc: unknown function: {}", name);
+        }
+        cmd?
+    };
+    Some(Directive::Bind(keys, cmd))
+}
+
+fn parse_set(rest: &str) -> Option<Directive> {
+    let mut words = rest.split_whitespace();
+    let name = words.next()?;
+    let value = words.next().unwrap_or("");
+    let directive = match name.to_ascii_lowercase().as_str() {
+        "editing-mode" => match value {
+            "emacs" => Directive::EditMode(EditMode::Emacs),
+            "vi" => Directive::EditMode(EditMode::Vi),
+            _ => return None,
+        },
+        "completion-query-items" => Directive::CompletionPromptLimit(value.parse().ok()?),
+        "keyseq-timeout" => Directive::KeyseqTimeout(value.parse().ok()?),
+        "history-size" => Directive::HistorySize(value.parse().ok()?),
+        "completion-ignore-case" => Directive::CompletionIgnoreCase(boolean(value)),
+        _ => {
+            warn!(target: "rustyline", "inputrc: unsupported variable: {}", name);
+            return None;
+        }
+    };
+    Some(directive)
+}
+
+/// Boolean variable value: like readline, `on` (ignoring case) or `1` is
+/// true, anything else false.
+fn boolean(value: &str) -> bool {
+    value.eq_ignore_ascii_case("on") || value == "1"
+}
+
+/// Split `s` (without its opening quote) at the closing quote.
+fn split_quoted(s: &str) -> Option<(&str, &str)> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some((&s[..i], &s[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Resolve backslash escapes of a macro.
+fn unescape(s: &str) -> Option<String> {
+    let mut text = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            text.push(escape(chars.next()?)?);
+        } else {
+            text.push(c);
+        }
+    }
+    Some(text)
+}
+
+fn escape(c: char) -> Option<char> {
+    Some(match c {
+        'a' => '\x07',
+        'd' => '\x7f',
+        'e' => '\x1b',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '\\' | '"' | '\'' => c,
+        _ => return None,
+    })
+}
+
+/// Parse a quoted key sequence like `\C-x\C-r`, `\M-b` or `\e[A`.
+fn parse_key_seq(s: &str) -> Option<Vec<E>> {
+    let mut keys = Vec::new();
+    let mut mods = M::NONE;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let key = if c == '\\' {
+            match chars.next()? {
+                m @ ('C' | 'M') if chars.peek() == Some(&'-') => {
+                    chars.next();
+                    mods |= if m == 'C' { M::CTRL } else { M::ALT };
+                    continue;
+                }
+                'e' => match chars.peek() {
+                    Some('[') => {
+                        chars.next();
+                        let mut seq = String::new();
+                        for c in chars.by_ref() {
+                            seq.push(c);
+                            if c.is_ascii_alphabetic() || c == '~' {
+                                break;
+                            }
+                        }
+                        E(csi(&seq)?, mods)
+                    }
+                    // ESC prefix is how terminals send Meta
+                    Some(_) => {
+                        mods |= M::ALT;
+                        continue;
+                    }
+                    None => E::new('\x1b', mods),
+                },
+                c => E::new(escape(c)?, mods),
+            }
+        } else {
+            E::new(c, mods)
+        };
+        keys.push(key);
+        mods = M::NONE;
+    }
+    if mods.is_empty() {
+        Some(keys)
+    } else {
+        None // dangling modifier
+    }
+}
+
+/// Key code of a CSI sequence (without `\e[`)
+fn csi(seq: &str) -> Option<K> {
+    Some(match seq {
+        "A" => K::Up,
+        "B" => K::Down,
+        "C" => K::Right,
+        "D" => K::Left,
+        "H" | "1~" | "7~" => K::Home,
+        "F" | "4~" | "8~" => K::End,
+        "2~" => K::Insert,
+        "3~" => K::Delete,
+        "5~" => K::PageUp,
+        "6~" => K::PageDown,
+        _ => return None,
+    })
+}
+
+/// Parse a key name like `Control-u` or `M-DEL`.
+fn parse_key_name(name: &str) -> Option<E> {
+    let mut parts: Vec<&str> = name.split('-').collect();
+    let key = parts.pop()?;
+    let mut mods = M::NONE;
+    for part in parts {
+        mods |= match part.to_ascii_lowercase().as_str() {
+            "c" | "control" | "ctrl" => M::CTRL,
+            "m" | "meta" => M::ALT,
+            _ => return None,
+        };
+    }
+    let c = match key.to_ascii_lowercase().as_str() {
+        "del" | "rubout" => '\x7f',
+        "esc" | "escape" => '\x1b',
+        "lfd" | "newline" => '\n',
+        "ret" | "return" => '\r',
+        "spc" | "space" => ' ',
+        "tab" => '\t',
+        _ => {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return None,
+            }
+        }
+    };
+    Some(E::new(c, mods))
+}
+
+/// Map a readline function name to a command.
+fn command(name: &str) -> Option<Cmd> {
+    Some(match name {
+        "abort" => Cmd::Abort,
+        "accept-line" => Cmd::AcceptLine,
+        "backward-char" => Cmd::Move(Movement::BackwardChar(1)),
+        "backward-delete-char" => Cmd::Kill(Movement::BackwardChar(1)),
+        "backward-kill-line" | "unix-line-discard" => Cmd::Kill(Movement::BeginningOfLine),
+        "backward-kill-word" => Cmd::Kill(Movement::BackwardWord(1, Word::Emacs)),
+        "backward-word" => Cmd::Move(Movement::BackwardWord(1, Word::Emacs)),
+        "beginning-of-history" => Cmd::BeginningOfHistory,
+        "beginning-of-line" => Cmd::Move(Movement::BeginningOfLine),
+        "capitalize-word" => Cmd::CapitalizeWord,
+        "clear-screen" => Cmd::ClearScreen,
+        "complete" => Cmd::Complete,
+        "delete-char" => Cmd::Kill(Movement::ForwardChar(1)),
+        "downcase-word" => Cmd::DowncaseWord,
+        "edit-and-execute-command" => Cmd::ExternalEditor,
+        "end-of-history" => Cmd::EndOfHistory,
+        "end-of-line" => Cmd::Move(Movement::EndOfLine),
+        "forward-char" => Cmd::Move(Movement::ForwardChar(1)),
+        "forward-search-history" => Cmd::ForwardSearchHistory,
+        "forward-word" => Cmd::Move(Movement::ForwardWord(1, At::AfterEnd, Word::Emacs)),
+        "history-search-backward" => Cmd::HistorySearchBackward,
+        "history-search-forward" => Cmd::HistorySearchForward,
+        "kill-line" => Cmd::Kill(Movement::EndOfLine),
+        "kill-whole-line" => Cmd::Kill(Movement::WholeLine),
+        "kill-word" => Cmd::Kill(Movement::ForwardWord(1, At::AfterEnd, Word::Emacs)),
+        "next-history" => Cmd::NextHistory,
+        "operate-and-get-next" => Cmd::OperateAndGetNext,
+        "previous-history" => Cmd::PreviousHistory,
+        "quoted-insert" => Cmd::QuotedInsert,
+        "reverse-search-history" => Cmd::ReverseSearchHistory,
+        "tab-insert" => Cmd::Insert(1, "\t".to_owned()),
+        "transpose-chars" => Cmd::TransposeChars,
+        "transpose-words" => Cmd::TransposeWords(1),
+        "undo" => Cmd::Undo(1),
+        "unix-word-rubout" => Cmd::Kill(Movement::BackwardWord(1, Word::Big)),
+        "upcase-word" => Cmd::UpcaseWord,
+        "yank" => Cmd::Yank(1, Anchor::Before),
+        "yank-pop" => Cmd::YankPop,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::Directive;
+    use crate::config::EditMode;
+    use crate::keymap::{At, Cmd, Movement, Word};
+    use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
+
+    fn parse(content: &str) -> Vec<Directive> {
+        super::parse(content, EditMode::Emacs, "xterm-256color")
+    }
+
+    #[test]
+    fn bindings() {
+        let directives = parse(
+            r#"
+# comment
+"\C-a": beginning-of-line
+"\M-b": backward-word
+Meta-f: forward-word
+"\C-x\C-r": reverse-search-history
+"\e[A": history-search-backward
+"\M-\C-h": "hello\n"
+"#,
+        );
+        assert_eq!(
+            vec![
+                Directive::Bind(vec![E::ctrl('a')], Cmd::Move(Movement::BeginningOfLine)),
+                Directive::Bind(
+                    vec![E::alt('b')],
+                    Cmd::Move(Movement::BackwardWord(1, Word::Emacs))
+                ),
+                Directive::Bind(
+                    vec![E::alt('f')],
+                    Cmd::Move(Movement::ForwardWord(1, At::AfterEnd, Word::Emacs))
+                ),
+                Directive::Bind(vec![E::ctrl('x'), E::ctrl('r')], Cmd::ReverseSearchHistory),
+                Directive::Bind(vec![E(K::Up, M::NONE)], Cmd::HistorySearchBackward),
+                Directive::Bind(
+                    vec![E(K::Char('h'), M::CTRL_ALT)],
+                    Cmd::Insert(1, "hello\n".to_owned())
+                ),
+            ],
+            directives
+        );
+    }
+
+    #[test]
+    fn settings() {
+        let directives =
+            parse("set editing-mode vi\nset keyseq-timeout 100\nset completion-ignore-case On\n");
+        assert_eq!(
+            vec![
+                Directive::EditMode(EditMode::Vi),
+                Directive::KeyseqTimeout(100),
+                Directive::CompletionIgnoreCase(true),
+            ],
+            directives
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let directives = parse(
+            r#"
+$if mode=emacs
+"\C-a": no-such-function
+"\C-": beginning-of-line
+"\C-a" beginning-of-line
+set editing-mode nano
+$endif
+"#,
+        );
+        assert!(directives.is_empty());
+    }
+
+    #[test]
+    fn conditionals() {
+        let directives = parse(
+            r#"
+$if mode=vi
+"\C-a": beginning-of-line
+$else
+"\C-a": end-of-line
+$endif
+$if term=xterm
+"\C-b": backward-char
+$endif
+$if term=linux
+"\C-b": forward-char
+$else
+$if Bash
+"\C-b": undo
+$endif
+$endif
+set editing-mode vi
+$if mode=vi
+"\C-e": end-of-line
+$endif
+"#,
+        );
+        assert_eq!(
+            vec![
+                Directive::Bind(vec![E::ctrl('a')], Cmd::Move(Movement::EndOfLine)),
+                Directive::Bind(vec![E::ctrl('b')], Cmd::Move(Movement::BackwardChar(1))),
+                Directive::EditMode(EditMode::Vi),
+                Directive::Bind(vec![E::ctrl('e')], Cmd::Move(Movement::EndOfLine)),
+            ],
+            directives
+        );
+    }
+}
@@ -1,16 +1,17 @@
 //! Bindings from keys to command for Emacs and Vi modes
+use std::collections::HashMap;
+
 use log::debug;
 
 use super::Result;
 use crate::keys::{KeyCode as K, KeyEvent, KeyEvent as E, Modifiers as M};
-use crate::tty::{self, RawReader, Term, Terminal};
+use crate::movement::repeat_count;
+pub use crate::movement::{Anchor, At, CharSearch, Movement, RepeatCount, Word};
+use crate::tty::{self, RawReader};
 use crate::{Config, EditMode};
 #[cfg(feature = "custom-bindings")]
 use crate::{Event, EventContext, EventHandler};
 
-/// The number of times one command should be repeated.
-pub type RepeatCount = usize;
-
 /// Commands
 #[derive(Debug, Clone, Eq, PartialEq)]
 #[non_exhaustive]
@@ -31,6 +32,8 @@ pub enum Cmd {
     #[cfg(windows)]
     PasteFromClipboard,
     /// complete
+    ///
+    /// Inserts a tab when the editor has no helper
     Complete,
     /// complete-backward
     CompleteBackward,
@@ -42,9 +45,15 @@ pub enum Cmd {
     DowncaseWord,
     /// vi-eof-maybe
     EndOfFile,
+    /// edit-and-execute-command (without executing: the edited text
+    /// replaces the current line)
+    ExternalEditor,
     /// end-of-history
     EndOfHistory,
     /// forward-search-history (incremental search)
+    ///
+    /// Bound to Ctrl-S: flow control is disabled in raw mode so that the
+    /// terminal does not intercept it.
     ForwardSearchHistory,
     /// history-search-backward (common prefix search)
     HistorySearchBackward,
@@ -68,12 +77,19 @@ pub enum Cmd {
     NextHistory,
     /// No action
     Noop,
+    /// operate-and-get-next
+    ///
+    /// Accepts the current line and loads the history entry following it on
+    /// the next `readline` call
+    OperateAndGetNext,
     /// vi-replace
     Overwrite(char),
     /// previous-history
     PreviousHistory,
     /// quoted-insert
     QuotedInsert,
+    /// Redo the changes reverted by `Undo` (not bound by default)
+    Redo(RepeatCount),
     /// vi-change-char
     ReplaceChar(RepeatCount, char),
     /// vi-change-to, vi-substitute
@@ -106,7 +122,7 @@ pub enum Cmd {
     /// moves cursor to the line below or switches to next history entry if
     /// the cursor is already on the last line
     LineDownOrNextHistory(RepeatCount),
-    /// Inserts a newline
+    /// Inserts a newline (Alt-Enter), whatever the validator says
     Newline,
     /// Either accepts or inserts a newline
     ///
@@ -207,130 +223,6 @@ impl Cmd {
     }
 }
 
-const fn repeat_count(previous: RepeatCount, new: Option<RepeatCount>) -> RepeatCount {
-    match new {
-        Some(n) => n,
-        None => previous,
-    }
-}
-
-/// Different word definitions
-#[derive(Debug, Clone, Eq, PartialEq, Copy)]
-pub enum Word {
-    /// non-blanks characters
-    Big,
-    /// alphanumeric characters
-    Emacs,
-    /// alphanumeric (and '_') characters
-    Vi,
-}
-
-/// Where to move with respect to word boundary
-#[derive(Debug, Clone, Eq, PartialEq, Copy)]
-pub enum At {
-    /// Start of word.
-    Start,
-    /// Before end of word.
-    BeforeEnd,
-    /// After end of word.
-    AfterEnd,
-}
-
-/// Where to paste (relative to cursor position)
-#[derive(Debug, Clone, Eq, PartialEq, Copy)]
-pub enum Anchor {
-    /// After cursor
-    After,
-    /// Before cursor
-    Before,
-}
-
-/// character search
-#[derive(Debug, Clone, Eq, PartialEq, Copy)]
-pub enum CharSearch {
-    /// Forward search
-    Forward(char),
-    /// Forward search until
-    ForwardBefore(char),
-    /// Backward search
-    Backward(char),
-    /// Backward search until
-    BackwardAfter(char),
-}
-
-impl CharSearch {
-    const fn opposite(self) -> Self {
-        match self {
-            CharSearch::Forward(c) => CharSearch::Backward(c),
-            CharSearch::ForwardBefore(c) => CharSearch::BackwardAfter(c),
-            CharSearch::Backward(c) => CharSearch::Forward(c),
-            CharSearch::BackwardAfter(c) => CharSearch::ForwardBefore(c),
-        }
-    }
-}
-
-/// Where to move
-#[derive(Debug, Clone, Eq, PartialEq)]
-#[non_exhaustive]
-pub enum Movement {
-    /// Whole current line (not really a movement but a range)
-    WholeLine,
-    /// beginning-of-line
-    BeginningOfLine,
-    /// end-of-line
-    EndOfLine,
-    /// backward-word, vi-prev-word
-    BackwardWord(RepeatCount, Word), // Backward until start of word
-    /// forward-word, vi-end-word, vi-next-word
-    ForwardWord(RepeatCount, At, Word), // Forward until start/end of word
-    /// character-search, character-search-backward, vi-char-search
-    ViCharSearch(RepeatCount, CharSearch),
-    /// vi-first-print
-    ViFirstPrint,
-    /// backward-char
-    BackwardChar(RepeatCount),
-    /// forward-char
-    ForwardChar(RepeatCount),
-    /// move to the same column on the previous line
-    LineUp(RepeatCount),
-    /// move to the same column on the next line
-    LineDown(RepeatCount),
-    /// Whole user input (not really a movement but a range)
-    WholeBuffer,
-    /// beginning-of-buffer
-    BeginningOfBuffer,
-    /// end-of-buffer
-    EndOfBuffer,
-}
-
-impl Movement {
-    // Replay this movement with a possible different `RepeatCount`.
-    const fn redo(&self, new: Option<RepeatCount>) -> Self {
-        match *self {
-            Movement::WholeLine => Movement::WholeLine,
-            Movement::BeginningOfLine => Movement::BeginningOfLine,
-            Movement::ViFirstPrint => Movement::ViFirstPrint,
-            Movement::EndOfLine => Movement::EndOfLine,
-            Movement::BackwardWord(previous, word) => {
-                Movement::BackwardWord(repeat_count(previous, new), word)
-            }
-            Movement::ForwardWord(previous, at, word) => {
-                Movement::ForwardWord(repeat_count(previous, new), at, word)
-            }
-            Movement::ViCharSearch(previous, char_search) => {
-                Movement::ViCharSearch(repeat_count(previous, new), char_search)
-            }
-            Movement::BackwardChar(previous) => Movement::BackwardChar(repeat_count(previous, new)),
-            Movement::ForwardChar(previous) => Movement::ForwardChar(repeat_count(previous, new)),
-            Movement::LineUp(previous) => Movement::LineUp(repeat_count(previous, new)),
-            Movement::LineDown(previous) => Movement::LineDown(repeat_count(previous, new)),
-            Movement::WholeBuffer => Movement::WholeBuffer,
-            Movement::BeginningOfBuffer => Movement::BeginningOfBuffer,
-            Movement::EndOfBuffer => Movement::EndOfBuffer,
-        }
-    }
-}
-
 /// Vi input modes
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum InputMode {
@@ -354,6 +246,16 @@ pub struct InputState<'b> {
     last_char_search: Option<CharSearch>, // vi only
 }
 
+/// `InputState` without the bindings, kept by `Editor::process_key` between
+/// calls
+#[derive(Clone)]
+pub(crate) struct InputMemo {
+    input_mode: InputMode,
+    num_args: i16,
+    last_cmd: Cmd,
+    last_char_search: Option<CharSearch>,
+}
+
 /// Provide indirect mutation to user input.
 pub trait Invoke {
     /// currently edited line
@@ -415,9 +317,9 @@ impl<'b> InputState<'b> {
     /// Parse user input into one command
     /// `single_esc_abort` is used in emacs mode on unix platform when a single
     /// esc key is expected to abort current action.
-    pub fn next_cmd(
+    pub fn next_cmd<R: RawReader>(
         &mut self,
-        rdr: &mut <Terminal as Term>::Reader,
+        rdr: &mut R,
         wrt: &mut dyn Refresher,
         single_esc_abort: bool,
         ignore_external_print: bool,
@@ -440,6 +342,17 @@ impl<'b> InputState<'b> {
                 }
             }
         }
+        self.key_cmd(rdr, wrt, key)
+    }
+
+    /// Parse one command starting with `key`, reading the following keys (if
+    /// any) from `rdr`
+    pub(crate) fn key_cmd<R: RawReader>(
+        &mut self,
+        rdr: &mut R,
+        wrt: &mut dyn Refresher,
+        key: KeyEvent,
+    ) -> Result<Cmd> {
         match self.mode {
             EditMode::Emacs => self.emacs(rdr, wrt, key),
             EditMode::Vi if self.input_mode != InputMode::Command => self.vi_insert(rdr, wrt, key),
@@ -447,6 +360,24 @@ impl<'b> InputState<'b> {
         }
     }
 
+    /// Save the state carried from one command to the next
+    pub(crate) fn memo(&self) -> InputMemo {
+        InputMemo {
+            input_mode: self.input_mode,
+            num_args: self.num_args,
+            last_cmd: self.last_cmd.clone(),
+            last_char_search: self.last_char_search,
+        }
+    }
+
+    /// Restore the state saved by [`memo`](InputState::memo)
+    pub(crate) fn resume(&mut self, memo: &InputMemo) {
+        self.input_mode = memo.input_mode;
+        self.num_args = memo.num_args;
+        self.last_cmd = memo.last_cmd.clone();
+        self.last_char_search = memo.last_char_search;
+    }
+
     fn single_esc_abort(&self, single_esc_abort: bool) -> bool {
         match self.mode {
             EditMode::Emacs => single_esc_abort,
@@ -567,7 +498,9 @@ impl<'b> InputState<'b> {
                 }
             }
             // Don't complete hints when the cursor is not at the end of a line
-            E(K::Right, M::NONE) if wrt.has_hint() && wrt.is_cursor_at_end() => Cmd::CompleteHint,
+            E(K::Right | K::End, M::NONE) if wrt.has_hint() && wrt.is_cursor_at_end() => {
+                Cmd::CompleteHint
+            }
             E(K::Char('K'), M::CTRL) => Cmd::Kill(if positive {
                 Movement::EndOfLine
             } else {
@@ -575,6 +508,7 @@ impl<'b> InputState<'b> {
             }),
             E(K::Char('L'), M::CTRL) => Cmd::ClearScreen,
             E(K::Char('N'), M::CTRL) => Cmd::NextHistory,
+            E(K::Char('O'), M::CTRL) => Cmd::OperateAndGetNext,
             E(K::Char('P'), M::CTRL) => Cmd::PreviousHistory,
             E(K::Char('X'), M::CTRL) => {
                 if let Some(cmd) = self.custom_seq_binding(rdr, wrt, &mut evt, n, positive)? {
@@ -586,6 +520,7 @@ impl<'b> InputState<'b> {
                         _ => rdr.next_key(true)?,
                     };
                     match snd_key {
+                        E(K::Char('E'), M::CTRL) => Cmd::ExternalEditor,
                         E(K::Char('G'), M::CTRL) | E::ESC => Cmd::Abort,
                         E(K::Char('U'), M::CTRL) => Cmd::Undo(n),
                         E(K::Backspace, M::NONE) => Cmd::Kill(if positive {
@@ -633,7 +568,7 @@ impl<'b> InputState<'b> {
                 })
             }
             E(K::Char('C' | 'c'), M::ALT) => Cmd::CapitalizeWord,
-            E(K::Char('D' | 'd'), M::ALT) => Cmd::Kill(if positive {
+            E(K::Char('D' | 'd'), M::ALT) | E(K::Delete, M::CTRL) => Cmd::Kill(if positive {
                 Movement::ForwardWord(n, At::AfterEnd, Word::Emacs)
             } else {
                 Movement::BackwardWord(n, Word::Emacs)
@@ -889,8 +824,11 @@ impl<'b> InputState<'b> {
             E(K::Char('H'), M::CTRL) | E::BACKSPACE => Cmd::Kill(Movement::BackwardChar(1)),
             E(K::BackTab, M::NONE) => Cmd::CompleteBackward,
             E(K::Char('I'), M::CTRL) | E(K::Tab, M::NONE) => Cmd::Complete,
+            E(K::Char('G'), M::CTRL) => Cmd::Abort,
             // Don't complete hints when the cursor is not at the end of a line
-            E(K::Right, M::NONE) if wrt.has_hint() && wrt.is_cursor_at_end() => Cmd::CompleteHint,
+            E(K::Right | K::End, M::NONE) if wrt.has_hint() && wrt.is_cursor_at_end() => {
+                Cmd::CompleteHint
+            }
             E(K::Char(k), M::ALT) => {
                 debug!(target: "rustyline", "Vi fast command mode: {}", k);
                 self.input_mode = InputMode::Command;
@@ -1048,8 +986,11 @@ impl<'b> InputState<'b> {
             E(K::Char('J' | 'M'), M::CTRL) | E::ENTER => Cmd::AcceptOrInsertLine {
                 accept_in_the_middle: true,
             },
+            E(K::Enter, M::ALT) => Cmd::Newline,
             E(K::Down, M::NONE) => Cmd::LineDownOrNextHistory(1),
             E(K::Up, M::NONE) => Cmd::LineUpOrPreviousHistory(1),
+            E(K::PageDown, M::NONE) => Cmd::HistorySearchForward,
+            E(K::PageUp, M::NONE) => Cmd::HistorySearchBackward,
             E(K::Char('R'), M::CTRL) => Cmd::ReverseSearchHistory,
             // most terminals override Ctrl+S to suspend execution
             E(K::Char('S'), M::CTRL) => Cmd::ForwardSearchHistory,
@@ -1136,7 +1077,7 @@ impl<'b> InputState<'b> {
         let bindings = self.custom_bindings;
         let handler = bindings.get(evt).or_else(|| bindings.get(&Event::Any));
         if let Some(handler) = handler {
-            match handler {
+            match &**handler {
                 EventHandler::Simple(cmd) => Some(cmd.clone()),
                 EventHandler::Conditional(handler) => {
                     let ctx = EventContext::new(self, wrt);
@@ -1165,7 +1106,7 @@ impl<'b> InputState<'b> {
             }
             let handler = subtrie.get(evt).unwrap();
             if let Some(handler) = handler {
-                let cmd = match handler {
+                let cmd = match &**handler {
                     EventHandler::Simple(cmd) => Some(cmd.clone()),
                     EventHandler::Conditional(handler) => {
                         let ctx = EventContext::new(self, wrt);
@@ -1207,7 +1148,8 @@ impl<'b> InputState<'b> {
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "custom-bindings")] {
-pub type Bindings = radix_trie::Trie<Event, EventHandler>;
+/// Handlers are shared with the clones of the editor
+pub type Bindings = radix_trie::Trie<Event, std::sync::Arc<EventHandler>>;
     } else {
 enum Event {
    KeySeq([KeyEvent; 1]),
@@ -1217,6 +1159,7 @@ impl From<KeyEvent> for Event {
         Event::KeySeq([k])
     }
 }
+#[derive(Clone)]
 pub struct Bindings {}
 impl Bindings {
     pub fn new() -> Bindings {
@@ -1225,3 +1168,183 @@ impl Bindings {
 }
     }
 }
+
+/// Keys sent as raw byte sequences by the terminal (like `\E[1;5C`), fed
+/// one byte at a time by the reader. Preloaded with the sequences sent by
+/// usual terminals (xterm, rxvt, linux console, tmux), which can be
+/// overridden.
+#[derive(Clone, Debug, Default)]
+pub struct SequenceTrie {
+    children: HashMap<u8, SequenceTrie>,
+    key: Option<KeyEvent>,
+}
+
+/// xterm modifier parameters (`\E[1;<m>A`)
+const MODIFIERS: [(u8, M); 7] = [
+    (b'2', M::SHIFT),
+    (b'3', M::ALT),
+    (b'4', M::ALT_SHIFT),
+    (b'5', M::CTRL),
+    (b'6', M::CTRL_SHIFT),
+    (b'7', M::CTRL_ALT),
+    (b'8', M::CTRL_ALT_SHIFT),
+];
+
+impl SequenceTrie {
+    /// Trie with the built-in escape sequences
+    pub(crate) fn new() -> Self {
+        let mut trie = Self::default();
+        trie.insert_builtins();
+        trie
+    }
+
+    // https://invisible-island.net/xterm/xterm-function-keys.html
+    fn insert_builtins(&mut self) {
+        let csi = |seq: &[u8]| [b"\x1b[", seq].concat();
+        // ANSI cursor keys, also with a modifier (xterm)
+        let cursor = [
+            (b'A', K::Up),
+            (b'B', K::Down),
+            (b'C', K::Right),
+            (b'D', K::Left),
+            (b'F', K::End),
+            (b'H', K::Home),
+        ];
+        for &(c, k) in &cursor {
+            self.insert(&csi(&[c]), E(k, M::NONE));
+            self.insert(&[b"\x1bO", &[c][..]].concat(), E(k, M::NONE));
+            for &(m, mods) in &MODIFIERS {
+                self.insert(&csi(&[b'1', b';', m, c]), E(k, mods));
+            }
+        }
+        // Meta + arrow on (some?) Macs when using iTerm defaults
+        for &(c, k) in &cursor[..4] {
+            self.insert(&csi(&[b'1', b';', b'9', c]), E(k, M::ALT));
+        }
+        self.insert(&csi(b"Z"), E(K::BackTab, M::NONE));
+        // rxvt
+        for (i, k) in [K::Up, K::Down, K::Right, K::Left].iter().enumerate() {
+            let c = b'a' + i as u8;
+            self.insert(&csi(&[c]), E(*k, M::SHIFT));
+            self.insert(&[b"\x1bO", &[c][..]].concat(), E(*k, M::CTRL));
+            self.insert(&csi(&[b'5', b'A' + i as u8]), E(*k, M::CTRL));
+        }
+        // Linux console
+        for (i, c) in (b'A'..=b'E').enumerate() {
+            self.insert(&csi(&[b'[', c]), E(K::F(i as u8 + 1), M::NONE));
+        }
+        // SS3
+        self.insert(b"\x1bOM", E::ENTER);
+        for (i, c) in (b'P'..=b'S').enumerate() {
+            self.insert(
+                &[b"\x1bO", &[c][..]].concat(),
+                E(K::F(i as u8 + 1), M::NONE),
+            );
+        }
+        for &(c, n) in &[
+            (b'l', 8),
+            (b't', 5),
+            (b'u', 6),
+            (b'v', 7),
+            (b'w', 9),
+            (b'x', 10),
+        ] {
+            self.insert(&[b"\x1bO", &[c][..]].concat(), E(K::F(n), M::NONE));
+        }
+        // editing keys: `\E[<n>~`, `\E[<n>;<m>~` (xterm), `\E[<n>^` (rxvt)
+        let editing = [
+            (b'2', K::Insert),
+            (b'3', K::Delete),
+            (b'5', K::PageUp),
+            (b'6', K::PageDown),
+        ];
+        for &(n, k) in &editing {
+            self.insert(&csi(&[n, b'~']), E(k, M::NONE));
+            for &(m, mods) in &MODIFIERS {
+                self.insert(&csi(&[n, b';', m, b'~']), E(k, mods));
+            }
+        }
+        // tmux, rxvt
+        for &(n, k) in &[
+            (b'1', K::Home),
+            (b'7', K::Home),
+            (b'4', K::End),
+            (b'8', K::End),
+        ] {
+            self.insert(&csi(&[n, b'~']), E(k, M::NONE));
+        }
+        self.insert(&csi(b"3^"), E(K::Delete, M::CTRL));
+        self.insert(&csi(b"3@"), E(K::Delete, M::CTRL_SHIFT));
+        for &(n, k) in &[
+            (b'5', K::PageUp),
+            (b'6', K::PageDown),
+            (b'7', K::Home),
+            (b'8', K::End),
+        ] {
+            self.insert(&csi(&[n, b'^']), E(k, M::CTRL));
+            self.insert(&csi(&[n, b'$']), E(k, M::SHIFT));
+            self.insert(&csi(&[n, b'@']), E(k, M::CTRL_SHIFT));
+        }
+        // function keys: `\E[<nn>~` (rxvt-unicode for F1 to F4), Ctrl with `;5`
+        let function = [
+            (&b"11"[..], 1),
+            (b"12", 2),
+            (b"13", 3),
+            (b"14", 4),
+            (b"15", 5),
+            (b"17", 6),
+            (b"18", 7),
+            (b"19", 8),
+            (b"20", 9),
+            (b"21", 10),
+            (b"23", 11),
+            (b"24", 12),
+        ];
+        for &(nn, n) in &function {
+            self.insert(&csi(&[nn, b"~"].concat()), E(K::F(n), M::NONE));
+            if n >= 5 {
+                self.insert(&csi(&[nn, b";5~"].concat()), E(K::F(n), M::CTRL));
+            }
+        }
+        for &(c, n) in &[(b'P', 1), (b'Q', 2), (b'S', 4)] {
+            self.insert(&csi(&[b'1', b';', b'5', c]), E(K::F(n), M::CTRL));
+        }
+        // Ctrl + digits (xterm modifyOtherKeys)
+        for &(m, mods) in &MODIFIERS[3..] {
+            for (i, c) in (b'p'..=b'y').enumerate() {
+                let digit = char::from(b'0' + i as u8);
+                self.insert(&csi(&[b'1', b';', m, c]), E(K::Char(digit), mods));
+            }
+        }
+        self.insert(&csi(b"200~"), E(K::BracketedPasteStart, M::NONE));
+        self.insert(&csi(b"201~"), E(K::BracketedPasteEnd, M::NONE));
+    }
+
+    /// Decode `seq` as `key`, returning the previous key if any
+    pub(crate) fn insert(&mut self, seq: &[u8], key: KeyEvent) -> Option<KeyEvent> {
+        let mut node = self;
+        for b in seq {
+            node = node.children.entry(*b).or_default();
+        }
+        node.key.replace(key)
+    }
+
+    /// Node reached by `seq`, if it is a prefix of a bound sequence
+    pub(crate) fn get(&self, seq: &[u8]) -> Option<&SequenceTrie> {
+        let mut node = self;
+        for b in seq {
+            node = node.children.get(b)?;
+        }
+        Some(node)
+    }
+
+    /// Key bound to the sequence ending at this node
+    pub(crate) fn key(&self) -> Option<KeyEvent> {
+        self.key
+    }
+
+    /// Whether no longer sequence starts with the one ending at this node
+    pub(crate) fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+}
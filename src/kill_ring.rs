@@ -1,5 +1,10 @@
 //! Kill Ring management
 use crate::line_buffer::{DeleteListener, Direction};
+use crate::movement::RepeatCount;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(not(feature = "no_std"))]
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Action {
@@ -8,12 +13,16 @@ enum Action {
     Other,
 }
 
+/// Where killed text goes when the previous command was also a kill
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Mode {
+    /// After the text of the current slot (forward kill)
     Append,
+    /// Before the text of the current slot (backward kill)
     Prepend,
 }
 
+/// Killed text, yanked back in the line
 pub struct KillRing {
     slots: Vec<String>,
     // where we are in the kill ring
@@ -70,13 +79,14 @@ impl KillRing {
         }
     }
 
-    /// Yank previously killed text.
+    /// Yank previously killed text (inserted `n` times).
     /// Return `None` when kill-ring is empty.
-    pub fn yank(&mut self) -> Option<&String> {
+    pub fn yank(&mut self, n: RepeatCount) -> Option<&String> {
         if self.slots.is_empty() {
             None
         } else {
-            self.last_action = Action::Yank(self.slots[self.index].len());
+            // remember the span inserted in the line for `yank_pop`
+            self.last_action = Action::Yank(self.slots[self.index].len() * n);
             Some(&self.slots[self.index])
         }
     }
@@ -102,6 +112,20 @@ impl KillRing {
     }
 }
 
+// The capacity of `slots` is the size of the kill-ring: it must be preserved.
+impl Clone for KillRing {
+    fn clone(&self) -> Self {
+        let mut slots = Vec::with_capacity(self.slots.capacity());
+        slots.extend(self.slots.iter().cloned());
+        Self {
+            slots,
+            index: self.index,
+            last_action: self.last_action,
+            killing: self.killing,
+        }
+    }
+}
+
 impl DeleteListener for KillRing {
     fn start_killing(&mut self) {
         self.killing = true;
@@ -123,6 +147,24 @@ impl DeleteListener for KillRing {
     }
 }
 
+/// Kill ring of an `Editor`, shared with its clones
+#[cfg(not(feature = "no_std"))]
+impl DeleteListener for Arc<Mutex<KillRing>> {
+    fn start_killing(&mut self) {
+        self.lock().unwrap().start_killing();
+    }
+
+    fn delete(&mut self, idx: usize, string: &str, dir: Direction) {
+        if let Ok(mut kill_ring) = self.try_lock() {
+            kill_ring.delete(idx, string, dir);
+        }
+    }
+
+    fn stop_killing(&mut self) {
+        self.lock().unwrap().stop_killing();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{Action, KillRing, Mode};
@@ -135,7 +177,7 @@ mod tests {
         assert_eq!(0, kill_ring.index);
         assert_eq!(Action::Kill, kill_ring.last_action);
 
-        assert_eq!(None, kill_ring.yank());
+        assert_eq!(None, kill_ring.yank(1));
         assert_eq!(Action::Kill, kill_ring.last_action);
     }
 
@@ -208,10 +250,12 @@ mod tests {
         kill_ring.reset();
         kill_ring.kill("word2", Mode::Append);
 
-        assert_eq!(Some(&"word2".to_owned()), kill_ring.yank());
+        assert_eq!(Some(&"word2".to_owned()), kill_ring.yank(1));
         assert_eq!(Action::Yank(5), kill_ring.last_action);
-        assert_eq!(Some(&"word2".to_owned()), kill_ring.yank());
+        assert_eq!(Some(&"word2".to_owned()), kill_ring.yank(1));
         assert_eq!(Action::Yank(5), kill_ring.last_action);
+        assert_eq!(Some(&"word2".to_owned()), kill_ring.yank(3));
+        assert_eq!(Action::Yank(15), kill_ring.last_action);
     }
 
     #[test]
@@ -222,9 +266,21 @@ mod tests {
         kill_ring.kill("longword2", Mode::Append);
 
         assert_eq!(None, kill_ring.yank_pop());
-        kill_ring.yank();
+        kill_ring.yank(1);
         assert_eq!(Some((9, &"word1".to_owned())), kill_ring.yank_pop());
         assert_eq!(Some((5, &"longword2".to_owned())), kill_ring.yank_pop());
         assert_eq!(Some((9, &"word1".to_owned())), kill_ring.yank_pop());
     }
+
+    #[test]
+    fn clone() {
+        let mut kill_ring = KillRing::new(2);
+        kill_ring.kill("word1", Mode::Append);
+        let mut clone = kill_ring.clone();
+        assert_eq!(2, clone.slots.capacity());
+        clone.reset();
+        clone.kill("word2", Mode::Append);
+        assert_eq!(1, kill_ring.slots.len());
+        assert_eq!(2, clone.slots.len());
+    }
 }
@@ -8,7 +8,7 @@
 //! Usage
 //!
 //! ```
-//! let mut rl = yatima_rustyline::Editor::<()>::new()?;
+//! let mut rl = yatima_rustyline::DefaultEditor::new()?;
 //! let readline = rl.readline(">> ");
 //! match readline {
 //!     Ok(line) => println!("Line: {:?}", line),
@@ -16,62 +16,149 @@
 //! }
 //! # Ok::<(), yatima_rustyline::error::ReadlineError>(())
 //! ```
+//!
+//! # `no_std`
+//!
+//! With `default-features = false, features = ["no_std"]`, only the
+//! [`line_buffer`], the kill ring and an in-memory [`history`] are compiled:
+//! they just need `alloc`, e.g. for a REPL over the UART of a firmware.
 #![warn(missing_docs)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![cfg_attr(all(feature = "no_std", not(test)), no_std)]
+// some helpers of the alloc-only modules are only used by the `Editor`
+#![cfg_attr(feature = "no_std", allow(dead_code))]
 
-#[cfg(feature = "custom-bindings")]
+extern crate alloc;
+
+#[cfg(all(unix, feature = "async", not(feature = "no_std")))]
+mod async_readline;
+#[cfg(all(feature = "custom-bindings", not(feature = "no_std")))]
 mod binding;
+#[cfg(not(feature = "no_std"))]
 mod command;
+#[cfg(not(feature = "no_std"))]
 pub mod completion;
+#[cfg(not(feature = "no_std"))]
 pub mod config;
+#[cfg(not(feature = "no_std"))]
 mod edit;
+#[cfg(not(feature = "no_std"))]
 pub mod error;
+#[cfg(not(any(target_arch = "wasm32", feature = "no_std")))]
+mod external_editor;
+mod grapheme;
+#[cfg(not(feature = "no_std"))]
 pub mod highlight;
+#[cfg(not(feature = "no_std"))]
 pub mod hint;
+#[cfg(not(feature = "no_std"))]
 pub mod history;
+#[cfg(all(feature = "custom-bindings", not(feature = "no_std")))]
+mod inputrc;
+#[cfg(not(feature = "no_std"))]
 mod keymap;
+#[cfg(not(feature = "no_std"))]
 mod keys;
+#[cfg(not(feature = "no_std"))]
 mod kill_ring;
+#[cfg(feature = "no_std")]
+pub mod kill_ring;
+#[cfg(not(feature = "no_std"))]
 mod layout;
 pub mod line_buffer;
+mod mem_history;
+mod movement;
+#[cfg(not(feature = "no_std"))]
+mod secret;
+#[cfg(not(feature = "no_std"))]
+mod state;
+#[cfg(not(feature = "no_std"))]
+pub mod style;
+#[cfg(not(feature = "no_std"))]
 mod tty;
+#[cfg(not(feature = "no_std"))]
 mod undo;
+#[cfg(not(feature = "no_std"))]
+mod utils;
+#[cfg(not(feature = "no_std"))]
 pub mod validate;
+#[cfg(all(feature = "web", not(feature = "no_std")))]
+mod web;
 
-use std::fmt;
-use std::io::{self, BufRead, Write};
-use std::path::Path;
-use std::result;
-use std::sync::{Arc, Mutex};
-
-use log::debug;
-use unicode_width::UnicodeWidthStr;
-
-use crate::tty::{RawMode, RawReader, Renderer, Term, Terminal};
+/// History API, entries kept in memory only
+#[cfg(feature = "no_std")]
+pub mod history {
+    pub use crate::mem_history::{MemHistory, MemHistoryIter, SearchDirection, SearchResult};
+}
 
-#[cfg(feature = "custom-bindings")]
+#[cfg(not(feature = "no_std"))]
+use {
+    log::debug,
+    std::cmp,
+    std::fmt,
+    std::io::{self, BufRead, Write},
+    std::path::Path,
+    std::result,
+    std::sync::{Arc, Mutex},
+    std::time::{Duration, Instant},
+    unicode_width::UnicodeWidthStr,
+};
+
+#[cfg(not(feature = "no_std"))]
+use crate::tty::{
+    Offscreen, PendingKeys, RawMode, RawReader, Renderer, StreamReader, StreamRenderer, Term,
+    Terminal,
+};
+
+#[cfg(all(feature = "custom-bindings", not(feature = "no_std")))]
 pub use crate::binding::{ConditionalEventHandler, Event, EventContext, EventHandler};
-use crate::completion::{longest_common_prefix, Candidate, Completer};
-pub use crate::config::{Behavior, ColorMode, CompletionType, Config, EditMode, HistoryDuplicates};
-use crate::edit::State;
-use crate::error::ReadlineError;
-use crate::highlight::Highlighter;
-use crate::hint::Hinter;
-use crate::history::{History, SearchDirection};
-pub use crate::keymap::{Anchor, At, CharSearch, Cmd, InputMode, Movement, RepeatCount, Word};
-use crate::keymap::{Bindings, InputState, Refresher};
+#[cfg(not(feature = "no_std"))]
+pub use crate::config::{
+    Behavior, ColorMode, CompletionDisplayMode, CompletionType, Config, EditMode,
+    HistoryDuplicates, SearchCase,
+};
+#[cfg(not(feature = "no_std"))]
+pub use crate::keymap::{Cmd, InputMode};
+#[cfg(not(feature = "no_std"))]
 pub use crate::keys::{KeyCode, KeyEvent, Modifiers};
-use crate::kill_ring::KillRing;
+pub use crate::movement::{Anchor, At, CharSearch, Movement, RepeatCount, Word};
+#[cfg(not(feature = "no_std"))]
+pub use crate::secret::SecretString;
+#[cfg(not(feature = "no_std"))]
+pub use crate::state::EditorState;
+#[cfg(not(feature = "no_std"))]
 pub use crate::tty::ExternalPrinter;
-use crate::validate::Validator;
+#[cfg(all(feature = "web", target_arch = "wasm32", not(feature = "no_std")))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "web", target_arch = "wasm32"))))]
+pub use crate::web::WebEditor;
+#[cfg(all(feature = "web", not(feature = "no_std")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+pub use crate::web::WebInput;
+#[cfg(all(unix, feature = "async", not(feature = "no_std")))]
+#[cfg_attr(docsrs, doc(cfg(all(unix, feature = "async"))))]
+pub use tokio_util::sync::CancellationToken;
+#[cfg(not(feature = "no_std"))]
+use {
+    crate::completion::{longest_common_prefix, Candidate, Completer},
+    crate::edit::State,
+    crate::error::ReadlineError,
+    crate::highlight::Highlighter,
+    crate::hint::Hinter,
+    crate::history::{History, HistoryBackend, SearchDirection},
+    crate::keymap::{Bindings, InputMemo, InputState, Refresher, SequenceTrie},
+    crate::kill_ring::KillRing,
+    crate::validate::Validator,
+};
 
 /// The error type for I/O and Linux Syscalls (Errno)
+#[cfg(not(feature = "no_std"))]
 pub type Result<T> = result::Result<T, error::ReadlineError>;
 
 /// Completes the line/word
-fn complete_line<H: Helper>(
-    rdr: &mut <Terminal as Term>::Reader,
-    s: &mut State<'_, '_, H>,
+#[cfg(not(feature = "no_std"))]
+fn complete_line<H: Helper, W: Renderer>(
+    rdr: &mut W::Reader,
+    s: &mut State<'_, '_, H, W>,
     input_state: &mut InputState,
     config: &Config,
 ) -> Result<Option<Cmd>> {
@@ -81,11 +168,17 @@ fn complete_line<H: Helper>(
     };
 
     let completer = s.helper.unwrap();
+    s.update_context();
     // get a list of completions
-    let (start, candidates) = completer.complete(&s.line, s.line.pos(), &s.ctx)?;
+    let (start, mut candidates) = completer.complete(&s.line, s.line.pos(), &s.ctx)?;
+    completion::sort_by_score(&mut candidates);
     // if no completions, we are done
     if candidates.is_empty() {
-        s.out.beep()?;
+        match config.completion_display_mode() {
+            CompletionDisplayMode::Bell => s.out.beep()?,
+            CompletionDisplayMode::Print => s.show_message("No completions")?,
+            CompletionDisplayMode::Silent => {}
+        }
         Ok(None)
     } else if CompletionType::Circular == config.completion_type() {
         let mark = s.changes.borrow_mut().begin();
@@ -177,9 +270,14 @@ fn complete_line<H: Helper>(
                 && cmd != Cmd::SelfInsert(1, 'n')
                 && cmd != Cmd::SelfInsert(1, 'N')
                 && cmd != Cmd::Kill(Movement::BackwardChar(1))
+                && cmd != Cmd::Interrupt
+                && cmd != Cmd::Abort
             {
                 cmd = s.next_cmd(input_state, rdr, false, true)?;
             }
+            if cmd == Cmd::Interrupt {
+                return Ok(Some(cmd));
+            }
             matches!(cmd, Cmd::SelfInsert(1, 'y' | 'Y'))
         } else {
             true
@@ -254,7 +352,8 @@ fn complete_line<H: Helper>(
 }
 
 /// Completes the current hint
-fn complete_hint_line<H: Helper>(s: &mut State<'_, '_, H>) -> Result<()> {
+#[cfg(not(feature = "no_std"))]
+fn complete_hint_line<H: Helper, W: Renderer>(s: &mut State<'_, '_, H, W>) -> Result<()> {
     let hint = match s.hint.as_ref() {
         Some(hint) => hint,
         None => return Ok(()),
@@ -270,31 +369,18 @@ fn complete_hint_line<H: Helper>(s: &mut State<'_, '_, H>) -> Result<()> {
     s.refresh_line()
 }
 
-fn page_completions<C: Candidate, H: Helper>(
-    rdr: &mut <Terminal as Term>::Reader,
-    s: &mut State<'_, '_, H>,
+#[cfg(not(feature = "no_std"))]
+fn page_completions<C: Candidate, H: Helper, W: Renderer>(
+    rdr: &mut W::Reader,
+    s: &mut State<'_, '_, H, W>,
     input_state: &mut InputState,
     candidates: &[C],
 ) -> Result<Option<Cmd>> {
-    use std::cmp;
-
-    let min_col_pad = 2;
-    let cols = s.out.get_columns();
-    let max_width = cmp::min(
-        cols,
-        candidates
-            .iter()
-            .map(|s| s.display().width())
-            .max()
-            .unwrap()
-            + min_col_pad,
-    );
-    let num_cols = cols / max_width;
-
+    let columns = Columns::new(candidates, s.out.get_columns());
     let mut pause_row = s.out.get_rows() - 1;
-    let num_rows = (candidates.len() + num_cols - 1) / num_cols;
     let mut ab = String::new();
-    for row in 0..num_rows {
+    let mut next = None;
+    for row in 0..columns.num_rows {
         if row == pause_row {
             s.out.write_and_flush("\n--More--")?;
             let mut cmd = Cmd::Noop;
@@ -309,6 +395,8 @@ fn page_completions<C: Candidate, H: Helper>(
                 && cmd != Cmd::AcceptLine
                 && cmd != Cmd::Newline
                 && !matches!(cmd, Cmd::AcceptOrInsertLine { .. })
+                && cmd != Cmd::Interrupt
+                && cmd != Cmd::Abort
             {
                 cmd = s.next_cmd(input_state, rdr, false, true)?;
             }
@@ -319,43 +407,115 @@ fn page_completions<C: Candidate, H: Helper>(
                 Cmd::AcceptLine | Cmd::Newline | Cmd::AcceptOrInsertLine { .. } => {
                     pause_row += 1;
                 }
+                Cmd::Interrupt => {
+                    next = Some(cmd);
+                    break;
+                }
                 _ => break,
             }
         }
         s.out.write_and_flush("\n")?;
         ab.clear();
-        for col in 0..num_cols {
-            let i = (col * num_rows) + row;
+        columns.push_row(&mut ab, candidates, row, s.highlighter());
+        s.out.write_and_flush(ab.as_str())?;
+    }
+    s.out.write_and_flush("\n")?;
+    s.layout.end.row = 0; // dirty way to make clear_old_rows do nothing
+    s.layout.cursor.row = 0;
+    s.refresh_line()?;
+    Ok(next)
+}
+
+/// Candidates laid out in columns sized to the terminal width (like `ls`),
+/// sorted top to bottom then left to right.
+///
+/// Candidates with a description are laid out one per row instead, the
+/// descriptions being aligned in a second column.
+#[cfg(not(feature = "no_std"))]
+struct Columns {
+    /// Column width, including padding
+    width: usize,
+    num_cols: usize,
+    num_rows: usize,
+    /// Whether descriptions are displayed
+    described: bool,
+}
+
+#[cfg(not(feature = "no_std"))]
+impl Columns {
+    fn new<C: Candidate>(candidates: &[C], cols: usize) -> Self {
+        let min_col_pad = 2;
+        let width = cmp::min(
+            cols,
+            candidates
+                .iter()
+                .map(|s| s.display().width())
+                .max()
+                .unwrap_or(0)
+                + min_col_pad,
+        );
+        let described = candidates.iter().any(|c| c.description().is_some());
+        let num_cols = if described {
+            1
+        } else {
+            cmp::max(cols / width, 1)
+        };
+        let num_rows = (candidates.len() + num_cols - 1) / num_cols;
+        Self {
+            width,
+            num_cols,
+            num_rows,
+            described,
+        }
+    }
+
+    /// Append the candidates displayed on `row` to `ab`.
+    fn push_row<C: Candidate>(
+        &self,
+        ab: &mut String,
+        candidates: &[C],
+        row: usize,
+        highlighter: Option<&dyn Highlighter>,
+    ) {
+        for col in 0..self.num_cols {
+            let i = (col * self.num_rows) + row;
             if i < candidates.len() {
                 let candidate = &candidates[i].display();
                 let width = candidate.width();
-                if let Some(highlighter) = s.highlighter() {
+                if let Some(highlighter) = highlighter {
                     ab.push_str(&highlighter.highlight_candidate(candidate, CompletionType::List));
                 } else {
                     ab.push_str(candidate);
                 }
-                if ((col + 1) * num_rows) + row < candidates.len() {
-                    for _ in width..max_width {
+                if self.described {
+                    if let Some(description) = candidates[i].description() {
+                        for _ in width..self.width {
+                            ab.push(' ');
+                        }
+                        ab.push_str(description);
+                    }
+                } else if ((col + 1) * self.num_rows) + row < candidates.len() {
+                    // no padding after the last candidate of the row
+                    for _ in width..self.width {
                         ab.push(' ');
                     }
                 }
             }
         }
-        s.out.write_and_flush(ab.as_str())?;
     }
-    s.out.write_and_flush("\n")?;
-    s.layout.end.row = 0; // dirty way to make clear_old_rows do nothing
-    s.layout.cursor.row = 0;
-    s.refresh_line()?;
-    Ok(None)
 }
 
-/// Incremental search
-fn reverse_incremental_search<H: Helper>(
-    rdr: &mut <Terminal as Term>::Reader,
-    s: &mut State<'_, '_, H>,
+/// Incremental search, from the newest entry toward the oldest one
+/// (`Reverse`) or from the entry being browsed toward the newest one
+/// (`Forward`)
+#[cfg(not(feature = "no_std"))]
+fn incremental_search<H: Helper, W: Renderer>(
+    rdr: &mut W::Reader,
+    s: &mut State<'_, '_, H, W>,
     input_state: &mut InputState,
     history: &History,
+    config: &Config,
+    mut direction: SearchDirection,
 ) -> Result<Option<Cmd>> {
     if history.is_empty() {
         return Ok(None);
@@ -366,17 +526,23 @@ fn reverse_incremental_search<H: Helper>(
     let backup_pos = s.line.pos();
 
     let mut search_buf = String::new();
-    let mut history_idx = history.len() - 1;
-    let mut direction = SearchDirection::Reverse;
+    let mut history_idx = match direction {
+        SearchDirection::Reverse => history.len() - 1,
+        SearchDirection::Forward => cmp::min(s.ctx.history_index, history.len() - 1),
+    };
     let mut success = true;
 
     let mut cmd;
-    // Display the reverse-i-search prompt and process chars
+    // Display the (reverse-)i-search prompt and process chars
     loop {
+        let name = match direction {
+            SearchDirection::Reverse => "reverse-i-search",
+            SearchDirection::Forward => "i-search",
+        };
         let prompt = if success {
-            format!("(reverse-i-search)`{}': ", search_buf)
+            format!("({})`{}': ", name, search_buf)
         } else {
-            format!("(failed reverse-i-search)`{}': ", search_buf)
+            format!("(failed {})`{}': ", name, search_buf)
         };
         s.refresh_prompt_and_line(&prompt)?;
 
@@ -409,33 +575,43 @@ fn reverse_incremental_search<H: Helper>(
                 }
                 Cmd::Abort => {
                     // Restore current edited line (before search)
+                    s.search_match = None;
                     s.line.update(&backup, backup_pos);
                     s.refresh_line()?;
                     s.changes.borrow_mut().truncate(mark);
                     return Ok(None);
                 }
                 Cmd::Move(_) => {
+                    s.search_match = None;
                     s.refresh_line()?; // restore prompt
                     break;
                 }
                 _ => break,
             }
         }
-        success = match history.search(&search_buf, history_idx, direction) {
+        let ignore_case = config.search_case().is_insensitive(&search_buf);
+        success = match history.search_with_case(&search_buf, history_idx, direction, ignore_case) {
             Some(sr) => {
                 history_idx = sr.idx;
+                s.search_match = mem_history::find(sr.entry, &search_buf, ignore_case);
                 s.line.update(sr.entry, sr.pos);
                 true
             }
-            _ => false,
+            _ => {
+                s.out.beep()?;
+                false
+            }
         };
     }
+    s.search_match = None;
     s.changes.borrow_mut().end();
     Ok(Some(cmd))
 }
 
+#[cfg(not(feature = "no_std"))]
 struct Guard<'m>(&'m tty::Mode);
 
+#[cfg(not(feature = "no_std"))]
 #[allow(unused_must_use)]
 impl Drop for Guard<'_> {
     fn drop(&mut self) {
@@ -445,6 +621,7 @@ impl Drop for Guard<'_> {
 }
 
 // Helper to handle backspace characters in a direct input
+#[cfg(not(feature = "no_std"))]
 fn apply_backspace_direct(input: &str) -> String {
     // Setup the output buffer
     // No '\b' in the input in the common case, so set the capacity to the input
@@ -455,9 +632,9 @@ fn apply_backspace_direct(input: &str) -> String {
     // As many graphemes as input bytes in the common case
     let mut grapheme_sizes: Vec<u8> = Vec::with_capacity(input.len());
 
-    for g in unicode_segmentation::UnicodeSegmentation::graphemes(input, true) {
-        if g == "\u{0008}" {
-            // backspace char
+    for g in grapheme::UnicodeSegmentation::graphemes(input, true) {
+        if g == "\u{0008}" || g == "\u{007f}" {
+            // backspace or delete char
             if let Some(n) = grapheme_sizes.pop() {
                 // Remove the last grapheme
                 out.truncate(out.len() - n as usize);
@@ -471,6 +648,7 @@ fn apply_backspace_direct(input: &str) -> String {
     out
 }
 
+#[cfg(not(feature = "no_std"))]
 fn readline_direct(
     mut reader: impl BufRead,
     mut writer: impl Write,
@@ -479,77 +657,140 @@ fn readline_direct(
     let mut input = String::new();
 
     loop {
-        if reader.read_line(&mut input)? == 0 {
-            return Err(error::ReadlineError::Eof);
+        let n = reader.read_line(&mut input)?;
+        if let Some(line) = accept_direct(&mut input, n, &mut writer, validator)? {
+            return Ok(line);
         }
-        // Remove trailing newline
-        let trailing_n = input.ends_with('\n');
-        let trailing_r;
+    }
+}
 
-        if trailing_n {
+/// Handle the `n` bytes of a line just appended to `input` (0 at EOF).
+/// Return the line when it is complete, `None` when more input is needed.
+#[cfg(not(feature = "no_std"))]
+fn accept_direct(
+    input: &mut String,
+    n: usize,
+    writer: &mut impl Write,
+    validator: &Option<impl Validator>,
+) -> Result<Option<String>> {
+    if n == 0 {
+        if input.is_empty() {
+            return Err(error::ReadlineError::Eof);
+        }
+        // EOF after an incomplete input: accept what has been read so far
+        if input.ends_with('\n') {
             input.pop();
-            trailing_r = input.ends_with('\r');
-            if trailing_r {
+            if input.ends_with('\r') {
                 input.pop();
             }
-        } else {
-            trailing_r = false;
         }
+        return Ok(Some(std::mem::take(input)));
+    }
+    // Remove trailing newline
+    let trailing_n = input.ends_with('\n');
+    let trailing_r;
+
+    if trailing_n {
+        input.pop();
+        trailing_r = input.ends_with('\r');
+        if trailing_r {
+            input.pop();
+        }
+    } else {
+        trailing_r = false;
+    }
 
-        input = apply_backspace_direct(&input);
+    *input = apply_backspace_direct(input);
 
-        match validator.as_ref() {
-            None => return Ok(input),
-            Some(v) => {
-                let mut ctx = input.as_str();
-                let mut ctx = validate::ValidationContext::new(&mut ctx);
+    match validator.as_ref() {
+        None => return Ok(Some(std::mem::take(input))),
+        Some(v) => {
+            let mut ctx = input.as_str();
+            let mut ctx = validate::ValidationContext::new(&mut ctx);
 
-                match v.validate(&mut ctx)? {
-                    validate::ValidationResult::Valid(msg) => {
-                        if let Some(msg) = msg {
-                            writer.write_all(msg.as_bytes())?;
-                        }
-                        return Ok(input);
-                    }
-                    validate::ValidationResult::Invalid(Some(msg)) => {
+            match v.validate(&mut ctx)? {
+                validate::ValidationResult::Valid(msg) => {
+                    if let Some(msg) = msg {
                         writer.write_all(msg.as_bytes())?;
                     }
-                    validate::ValidationResult::Incomplete => {
-                        // Add newline and keep on taking input
-                        if trailing_r {
-                            input.push('\r');
-                        }
-                        if trailing_n {
-                            input.push('\n');
-                        }
+                    return Ok(Some(std::mem::take(input)));
+                }
+                validate::ValidationResult::Invalid(Some(msg)) => {
+                    writer.write_all(msg.as_bytes())?;
+                }
+                validate::ValidationResult::Incomplete => {
+                    // Add newline and keep on taking input
+                    if trailing_r {
+                        input.push('\r');
+                    }
+                    if trailing_n {
+                        input.push('\n');
                     }
-                    _ => {}
                 }
+                _ => {}
             }
         }
     }
+    Ok(None)
 }
 
 /// Syntax specific helper.
 ///
 /// TODO Tokenizer/parser used for both completion, suggestion, highlighting.
 /// (parse current line once)
+#[cfg(not(feature = "no_std"))]
 pub trait Helper
 where
     Self: Completer + Hinter + Highlighter + Validator,
 {
 }
 
+#[cfg(not(feature = "no_std"))]
 impl Helper for () {}
 
+#[cfg(not(feature = "no_std"))]
 impl<'h, H: ?Sized + Helper> Helper for &'h H {}
 
+/// Event notified by [`Editor::readline_with_events`] while the line is
+/// edited.
+#[cfg(not(feature = "no_std"))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReadlineEvent {
+    /// A char has been inserted
+    Char(char),
+    /// Ctrl-C: the line is aborted
+    CtrlC,
+    /// Ctrl-D on an empty line: end of input
+    CtrlD,
+    /// Another history entry is displayed (previous / next entry or
+    /// incremental search)
+    HistoryNav,
+    /// Completion has been requested
+    Complete,
+    /// The line has been accepted
+    Finish(String),
+}
+
+/// Call `listener` (if any) with `event`
+#[cfg(not(feature = "no_std"))]
+fn notify(listener: &mut Option<&mut dyn FnMut(ReadlineEvent)>, event: ReadlineEvent) {
+    if let Some(listener) = listener {
+        listener(event);
+    }
+}
+
 /// Completion/suggestion context
+#[cfg(not(feature = "no_std"))]
 pub struct Context<'h> {
     history: &'h History,
     history_index: usize,
+    term_cols: usize,
+    cursor_col: usize,
+    completion_ignore_case: Option<bool>,
 }
 
+#[cfg(not(feature = "no_std"))]
 impl<'h> Context<'h> {
     /// Constructor. Visible for testing.
     #[must_use]
@@ -557,6 +798,9 @@ impl<'h> Context<'h> {
         Context {
             history,
             history_index: history.len(),
+            term_cols: 80,
+            cursor_col: 0,
+            completion_ignore_case: None,
         }
     }
 
@@ -571,9 +815,364 @@ impl<'h> Context<'h> {
     pub fn history_index(&self) -> usize {
         self.history_index
     }
+
+    /// Width of the terminal, as a number of columns
+    #[must_use]
+    pub fn term_cols(&self) -> usize {
+        self.term_cols
+    }
+
+    /// Display column of the cursor (prompt included) on its row.
+    ///
+    /// `term_cols() - cursor_col()` columns are available after the cursor
+    /// before the line wraps.
+    #[must_use]
+    pub fn cursor_col(&self) -> usize {
+        self.cursor_col
+    }
+
+    /// Whether completion should ignore case, when set by the editor
+    /// configuration (see [`Config::completion_ignore_case`]).
+    ///
+    /// `None` leaves the choice to the completer.
+    #[must_use]
+    pub fn completion_ignore_case(&self) -> Option<bool> {
+        self.completion_ignore_case
+    }
+}
+
+/// Line being edited by `Editor::edit_line`, one command at a time
+#[cfg(not(feature = "no_std"))]
+struct Edition<'out, 'prompt, H: Helper, W: Renderer> {
+    s: State<'out, 'prompt, H, W>,
+    input_state: InputState<'out>,
+    term: &'out mut Terminal,
+    config: &'out Config,
+    history: &'out History,
+    /// Reset by most commands, even when `kill_ring` is a private one
+    shared_kill_ring: &'out Arc<Mutex<KillRing>>,
+    kill_ring: Arc<Mutex<KillRing>>,
+    next_history: &'out mut Option<usize>,
+    deadline: Option<Instant>,
+    partial: &'out mut Option<(String, usize)>,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    original_mode: Option<&'out tty::Mode>,
+    vi_cursor_shape: bool,
+    input_mode: InputMode,
+}
+
+#[cfg(not(feature = "no_std"))]
+impl<'out, 'prompt, H: Helper, W: Renderer> Edition<'out, 'prompt, H, W> {
+    /// Display `prompt` followed by the initial line.
+    ///
+    /// `no_history` replaces the history of `editor` for sensitive input.
+    fn new(
+        editor: &'out mut Editor<H>,
+        out: &'out mut W,
+        rdr: &mut W::Reader,
+        prompt: &'prompt str,
+        initial: Option<(&str, &str)>,
+        original_mode: Option<&'out tty::Mode>,
+        no_history: &'out History,
+    ) -> Result<Self> {
+        out.set_continuation_prompt(editor.config.continuation_prompt());
+
+        editor.reset_kill_ring(); // TODO recreate a new kill ring vs Arc<Mutex<KillRing>>
+        let vi_cursor_shape = editor.vi_cursor_shape();
+
+        let Editor {
+            term,
+            history,
+            helper,
+            kill_ring: shared_kill_ring,
+            config,
+            custom_bindings,
+            deadline,
+            partial,
+            history_hint,
+            sensitive,
+            mask,
+            next_history,
+            ..
+        } = editor;
+        // sensitive input is neither killed into the shared kill ring nor
+        // mixed with the history entries
+        let (history, kill_ring, helper): (&History, _, _) = if *sensitive {
+            let kill_ring = Arc::new(Mutex::new(KillRing::new(1)));
+            (no_history, kill_ring, None)
+        } else {
+            (history, shared_kill_ring.clone(), helper.as_ref())
+        };
+        let mut ctx = Context::new(history);
+        ctx.completion_ignore_case = config.completion_ignore_case();
+        let mut s = State::new(out, prompt, helper, ctx);
+        s.line.set_max_len(config.max_line_length());
+        s.history_hint = *history_hint;
+        s.mask = *mask;
+
+        let input_state = InputState::new(config, custom_bindings);
+
+        // initial text is not an undoable change
+        let next = if *sensitive {
+            None
+        } else {
+            next_history.take()
+        };
+        if let Some((left, right)) = initial {
+            s.line
+                .update((left.to_owned() + right).as_ref(), left.len());
+        } else if let Some(index) = next {
+            if let Some(entry) = history.get(index) {
+                s.ctx.history_index = index;
+                s.line.update(entry, entry.len());
+            }
+        }
+
+        s.line.set_delete_listener(Box::new(kill_ring.clone()));
+        s.line.set_change_listener(s.changes.clone());
+
+        if original_mode.is_some() && term.is_output_tty() && config.check_cursor_position() {
+            if let Err(e) = s.move_cursor_at_leftmost(rdr) {
+                if let ReadlineError::WindowResized = e {
+                    s.out.update_size();
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+        s.refresh_line()?;
+
+        let input_mode = input_state.input_mode;
+        if vi_cursor_shape {
+            s.out.set_cursor_shape(Some(input_mode))?;
+        }
+        Ok(Self {
+            s,
+            input_state,
+            term,
+            config,
+            history,
+            shared_kill_ring,
+            kill_ring,
+            next_history,
+            deadline: *deadline,
+            partial,
+            original_mode,
+            vi_cursor_shape,
+            input_mode,
+        })
+    }
+
+    /// Wait for the next key until the deadline of
+    /// `Editor::readline_with_timeout` (if any).
+    /// Return `false` on timeout: the line is erased and kept for the next
+    /// call.
+    fn wait_input(&mut self, rdr: &mut W::Reader) -> Result<bool> {
+        let deadline = match self.deadline {
+            Some(deadline) => deadline,
+            None => return Ok(true),
+        };
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match rdr.poll_input(timeout) {
+                Ok(true) => return Ok(true),
+                Ok(false) => {
+                    self.s.out.clear_rows(&self.s.layout)?;
+                    *self.partial = Some((self.s.line.as_str().to_owned(), self.s.line.pos()));
+                    return Ok(false);
+                }
+                Err(ReadlineError::WindowResized) => {
+                    self.s.out.update_size();
+                    self.s.refresh_line()?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Read and execute the next command.
+    /// Return `true` when the line is accepted.
+    fn step(
+        &mut self,
+        rdr: &mut W::Reader,
+        listener: &mut Option<&mut dyn FnMut(ReadlineEvent)>,
+    ) -> Result<bool> {
+        let s = &mut self.s;
+        let input_state = &mut self.input_state;
+        let mut cmd = s.next_cmd(input_state, rdr, false, false)?;
+
+        if self.vi_cursor_shape && self.input_mode != input_state.input_mode {
+            self.input_mode = input_state.input_mode;
+            s.out.set_cursor_shape(Some(self.input_mode))?;
+        }
+
+        if cmd.should_reset_kill_ring() {
+            self.shared_kill_ring.lock().unwrap().reset();
+        }
+
+        // First trigger commands that need extra input
+
+        if cmd == Cmd::Complete && s.helper.is_some() {
+            let next = complete_line(rdr, s, input_state, self.config)?;
+            notify(listener, ReadlineEvent::Complete);
+            if let Some(next) = next {
+                cmd = next;
+            } else {
+                return Ok(false);
+            }
+        }
+
+        if cmd == Cmd::ReverseSearchHistory || cmd == Cmd::ForwardSearchHistory {
+            // Search history backward or forward
+            let direction = if cmd == Cmd::ReverseSearchHistory {
+                SearchDirection::Reverse
+            } else {
+                SearchDirection::Forward
+            };
+            let next =
+                incremental_search(rdr, s, input_state, self.history, self.config, direction)?;
+            notify(listener, ReadlineEvent::HistoryNav);
+            if let Some(next) = next {
+                cmd = next;
+            } else {
+                return Ok(false);
+            }
+        }
+
+        #[cfg(unix)]
+        if cmd == Cmd::Suspend {
+            if let Some(original_mode) = self.original_mode {
+                original_mode.disable_raw_mode()?;
+                tty::suspend()?;
+                let _ = self.term.enable_raw_mode()?; // TODO original_mode may have changed
+                s.out.update_size(); // window may have been resized
+                s.refresh_line()?;
+            }
+            return Ok(false);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if cmd == Cmd::ExternalEditor {
+            // the editor can only be run on the terminal
+            let edited = if let Some(original_mode) = self.original_mode {
+                original_mode.disable_raw_mode()?;
+                let edited = s.edit_externally(&external_editor::command());
+                let _ = self.term.enable_raw_mode()?;
+                s.out.update_size(); // window may have been resized
+                edited
+            } else {
+                false
+            };
+            if !edited {
+                s.out.beep()?;
+            }
+            s.refresh_line()?;
+            return Ok(false);
+        }
+
+        #[cfg(unix)]
+        if cmd == Cmd::QuotedInsert {
+            // Quoted insert
+            let c = rdr.next_char()?;
+            s.edit_insert(c, 1)?;
+            return Ok(false);
+        }
+
+        #[cfg(windows)]
+        if cmd == Cmd::PasteFromClipboard {
+            let clipboard = rdr.read_pasted_text()?;
+            s.edit_yank(input_state, &clipboard[..], Anchor::Before, 1)?;
+        }
+
+        // Tiny test quirk
+        #[cfg(test)]
+        if matches!(
+            cmd,
+            Cmd::AcceptLine
+                | Cmd::Newline
+                | Cmd::AcceptOrInsertLine { .. }
+                | Cmd::OperateAndGetNext
+        ) {
+            self.term.cursor = s.layout.cursor.col;
+        }
+
+        let operate = cmd == Cmd::OperateAndGetNext;
+        let inserted = if let Cmd::SelfInsert(_, c) = cmd {
+            Some(c)
+        } else {
+            None
+        };
+        let history_index = s.ctx.history_index;
+        // Execute things can be done solely on a state object
+        match command::execute(cmd, s, input_state, &self.kill_ring, self.config) {
+            Ok(command::Status::Proceed) => {
+                if let Some(c) = inserted {
+                    notify(listener, ReadlineEvent::Char(c));
+                } else if s.ctx.history_index != history_index {
+                    notify(listener, ReadlineEvent::HistoryNav);
+                }
+                Ok(false)
+            }
+            Ok(command::Status::Submit) => {
+                if operate && s.ctx.history_index < self.history.len() {
+                    *self.next_history = Some(s.ctx.history_index + 1);
+                }
+                Ok(true)
+            }
+            Err(ReadlineError::Interrupted) => {
+                notify(listener, ReadlineEvent::CtrlC);
+                Err(ReadlineError::Interrupted)
+            }
+            Err(ReadlineError::Eof) => {
+                notify(listener, ReadlineEvent::CtrlD);
+                Err(ReadlineError::Eof)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Move to end, in case cursor was in the middle of the line, so that
+    /// next thing application prints goes after the input
+    fn finish(mut self) -> Result<String> {
+        self.s.edit_move_buffer_end()?;
+        Ok(self.s.line.into_string())
+    }
+
+    /// Save what a command may change before reading all its keys
+    #[cfg(feature = "web")]
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            line: self.s.line.as_str().to_owned(),
+            pos: self.s.line.pos(),
+            history_index: self.s.ctx.history_index,
+            input: self.input_state.memo(),
+            changes: self.s.changes.borrow().clone(),
+        }
+    }
+
+    /// Undo a command interrupted by the lack of keys, so that it can be
+    /// replayed from its first key
+    #[cfg(feature = "web")]
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        self.s.line.update(&checkpoint.line, checkpoint.pos);
+        self.s.ctx.history_index = checkpoint.history_index;
+        self.input_state.resume(&checkpoint.input);
+        *self.s.changes.borrow_mut() = checkpoint.changes;
+    }
+}
+
+/// See `Edition::checkpoint`
+#[cfg(feature = "web")]
+struct Checkpoint {
+    line: String,
+    pos: usize,
+    history_index: usize,
+    input: InputMemo,
+    changes: undo::Changeset,
 }
 
 /// Line editor
+#[cfg(not(feature = "no_std"))]
 #[must_use]
 pub struct Editor<H: Helper> {
     term: Terminal,
@@ -582,8 +1181,76 @@ pub struct Editor<H: Helper> {
     kill_ring: Arc<Mutex<KillRing>>,
     config: Config,
     custom_bindings: Bindings,
+    /// Keys bound to raw byte sequences
+    sequences: SequenceTrie,
+    io: Option<Arc<Mutex<DirectIo>>>,
+    /// Deadline of the current `readline_with_timeout` call
+    deadline: Option<Instant>,
+    /// Line (and cursor position) being edited when the last
+    /// `readline_with_timeout` call timed out
+    partial: Option<(String, usize)>,
+    /// Whether history entries are suggested while typing
+    /// (see `readline_with_history_search`)
+    history_hint: bool,
+    /// Neither hints, completion nor history for the current call
+    /// (see `readline_sensitive`)
+    sensitive: bool,
+    /// Char displayed in place of the typed ones
+    mask: Option<char>,
+    /// Line being edited with `process_key`
+    driver: KeyDriver,
+    /// History index of the line loaded by the next `readline` call
+    /// (see `Cmd::OperateAndGetNext`)
+    next_history: Option<usize>,
+    /// Applied to the accepted line (see `set_line_transform`)
+    line_transform: Option<Arc<LineTransform>>,
+    /// Stdin read by `readline_async`
+    #[cfg(all(unix, feature = "async"))]
+    async_stdin: Option<async_readline::AsyncStdin>,
+}
+
+/// State kept between `Editor::process_key` calls
+#[cfg(not(feature = "no_std"))]
+#[derive(Clone, Default)]
+struct KeyDriver {
+    line: String,
+    pos: usize,
+    /// Keys of an incomplete sequence (like a numeric argument waiting for
+    /// its command)
+    pending: Vec<KeyEvent>,
+    input: Option<InputMemo>,
 }
 
+/// Input/output used instead of the terminal (see [`Editor::with_io`]) and
+/// whether the last key read was a CR (so that the LF of a CRLF pair is
+/// skipped by the next `readline`)
+#[cfg(not(feature = "no_std"))]
+type DirectIo = (Box<dyn BufRead + Send>, Box<dyn Write + Send>, bool);
+
+/// See [`Editor::set_line_transform`]
+#[cfg(not(feature = "no_std"))]
+type LineTransform = dyn Fn(&str) -> String + Send + Sync;
+
+/// Default editor with no helper
+///
+/// Useful for simple REPLs which need neither completion, hints,
+/// highlighting nor validation.
+///
+/// ```
+/// use yatima_rustyline::DefaultEditor;
+///
+/// let mut rl = DefaultEditor::new()?;
+/// // stdin is not a tty here so the line is read directly from it
+/// match rl.readline("> ") {
+///     Ok(line) => println!("Line: {}", line),
+///     Err(err) => println!("Error: {:?}", err),
+/// }
+/// # Ok::<(), yatima_rustyline::error::ReadlineError>(())
+/// ```
+#[cfg(not(feature = "no_std"))]
+pub type DefaultEditor = Editor<()>;
+
+#[cfg(not(feature = "no_std"))]
 #[allow(clippy::new_without_default)]
 impl<H: Helper> Editor<H> {
     /// Create an editor with the default configuration
@@ -599,6 +1266,7 @@ impl<H: Helper> Editor<H> {
             config.tab_stop(),
             config.bell_style(),
             config.enable_bracketed_paste(),
+            config.ctrlc_as_signal(),
         )?;
         Ok(Self {
             term,
@@ -607,9 +1275,65 @@ impl<H: Helper> Editor<H> {
             kill_ring: Arc::new(Mutex::new(KillRing::new(60))),
             config,
             custom_bindings: Bindings::new(),
+            sequences: SequenceTrie::new(),
+            io: None,
+            deadline: None,
+            partial: None,
+            history_hint: false,
+            sensitive: false,
+            mask: None,
+            driver: KeyDriver::default(),
+            next_history: None,
+            line_transform: None,
+            #[cfg(all(unix, feature = "async"))]
+            async_stdin: None,
         })
     }
 
+    /// Create an editor storing its history in `backend` (instead of a
+    /// [`FileHistory`](history::FileHistory)).
+    ///
+    /// The history settings of `config` are applied to `backend`.
+    pub fn with_history<B: HistoryBackend + 'static>(config: Config, backend: B) -> Result<Self> {
+        let mut editor = Self::with_config(config)?;
+        editor.history = History::with_backend(backend);
+        editor.history.configure(&editor.config);
+        editor.history.set_max_len(editor.config.max_history_size());
+        Ok(editor)
+    }
+
+    /// Create an editor reading keys from `input` and rendering to `output`
+    /// instead of using the terminal (stdin/stdout).
+    ///
+    /// Useful to drive the editor from a socket, a PTY or from tests: `input`
+    /// is decoded like the bytes sent by an ANSI terminal and `output`
+    /// receives ANSI escape sequences, so line editing, history, completion
+    /// and highlighting work as with the terminal. A width of 80 columns is
+    /// assumed and [`Editor::readline_with_timeout`] cannot time out.
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use yatima_rustyline::{Config, DefaultEditor};
+    ///
+    /// let input = Cursor::new("hello\n");
+    /// let mut rl = DefaultEditor::with_io(Config::default(), input, Vec::new())?;
+    /// assert_eq!("hello", rl.readline("> ")?);
+    /// # Ok::<(), yatima_rustyline::error::ReadlineError>(())
+    /// ```
+    pub fn with_io<R, W>(config: Config, input: R, output: W) -> Result<Self>
+    where
+        R: BufRead + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let mut editor = Self::with_config(config)?;
+        editor.io = Some(Arc::new(Mutex::new((
+            Box::new(input),
+            Box::new(output),
+            false,
+        ))));
+        Ok(editor)
+    }
+
     /// This method will read a line from STDIN and will display a `prompt`.
     ///
     /// It uses terminal-style interaction if `stdin` is connected to a
@@ -617,7 +1341,7 @@ impl<H: Helper> Editor<H> {
     /// Otherwise (e.g., if `stdin` is a pipe or the terminal is not supported),
     /// it uses file-style interaction.
     pub fn readline(&mut self, prompt: &str) -> Result<String> {
-        self.readline_with(prompt, None)
+        self.readline_with(prompt, None, None)
     }
 
     /// This function behaves in the exact same manner as `readline`, except
@@ -628,11 +1352,309 @@ impl<H: Helper> Editor<H> {
     /// the cursor and the string on the right is what will appear to the
     /// right of the cursor.
     pub fn readline_with_initial(&mut self, prompt: &str, initial: (&str, &str)) -> Result<String> {
-        self.readline_with(prompt, Some(initial))
+        self.readline_with(prompt, Some(initial), None)
+    }
+
+    /// Like [`Editor::readline`] but calls `callback` after each event while
+    /// the line is edited (e.g. to update a live preview).
+    ///
+    /// `callback` is called on the current thread, once the line has been
+    /// redrawn: it must not block. The last event is
+    /// [`ReadlineEvent::Finish`], with the line returned. Only `Finish` is
+    /// notified when the input is not a terminal.
+    pub fn readline_with_events<F: FnMut(ReadlineEvent)>(
+        &mut self,
+        prompt: &str,
+        mut callback: F,
+    ) -> Result<String> {
+        let line = self.readline_with(prompt, None, Some(&mut callback))?;
+        callback(ReadlineEvent::Finish(line.clone()));
+        Ok(line)
     }
 
-    fn readline_with(&mut self, prompt: &str, initial: Option<(&str, &str)>) -> Result<String> {
-        if self.term.is_unsupported() {
+    /// Like [`Editor::readline`] but, while typing, the most recent history
+    /// entry starting with the line is suggested (dimmed) after the cursor.
+    ///
+    /// Right arrow or End accepts the suggestion, any other key updates it.
+    /// Nothing is suggested when no history entry matches. The helper's
+    /// [`Hinter`] takes precedence when it returns a hint.
+    pub fn readline_with_history_search(&mut self, prompt: &str) -> Result<String> {
+        self.history_hint = true;
+        let result = self.readline_with(prompt, None, None);
+        self.history_hint = false;
+        result
+    }
+
+    /// Like [`Editor::readline`] but for sensitive input: the helper is not
+    /// used while editing (no hint, completion, highlighting or validation),
+    /// the history can neither be browsed nor searched, killed text is not
+    /// kept after the call and the line is not added to the history.
+    ///
+    /// When `mask` is given, it is displayed in place of each typed char.
+    /// The line is still fully editable and returned as typed. See also
+    /// [`Editor::readline_masked`] for passwords.
+    pub fn readline_sensitive(&mut self, prompt: &str, mask: Option<char>) -> Result<String> {
+        self.sensitive = true;
+        self.mask = mask;
+        let result = self.readline_with(prompt, None, None);
+        self.sensitive = false;
+        self.mask = None;
+        result
+    }
+
+    /// Like [`Editor::readline`] but can be cancelled from another thread:
+    /// sending to the sender of `abort_rx` makes this call return
+    /// [`ReadlineError::Interrupted`], with the terminal restored.
+    ///
+    /// The abort is only noticed while waiting for a key in the main edit
+    /// loop of a supported terminal (not during an incremental search or a
+    /// completion prompt).
+    #[cfg(unix)]
+    pub fn readline_with_abort(
+        &mut self,
+        prompt: &str,
+        abort_rx: std::sync::mpsc::Receiver<()>,
+    ) -> Result<String> {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::mpsc::RecvTimeoutError;
+
+        use nix::unistd::{close, pipe, write};
+
+        // `select(2)` cannot wait on a channel: forward the abort to a pipe
+        let (rd, wr) = pipe()?;
+        let done = Arc::new(AtomicBool::new(false));
+        let watcher = {
+            let done = done.clone();
+            std::thread::spawn(move || loop {
+                match abort_rx.recv_timeout(Duration::from_millis(50)) {
+                    Err(RecvTimeoutError::Timeout) if !done.load(Ordering::SeqCst) => {}
+                    Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+                    Ok(()) => {
+                        let _ = write(wr, b"a");
+                        break;
+                    }
+                }
+            })
+        };
+        self.term.abort_pipe = Some(rd);
+        let result = self.readline(prompt);
+        self.term.abort_pipe = None;
+        done.store(true, Ordering::SeqCst);
+        let _ = watcher.join();
+        let _ = close(rd);
+        let _ = close(wr);
+        result
+    }
+
+    /// Like [`Editor::readline`] but gives up with
+    /// [`ReadlineError::Timeout`] if no line has been entered after `timeout`.
+    ///
+    /// The partially typed line is erased from the screen and restored by the
+    /// next call to `readline_with_timeout`, so that the application can do
+    /// some other work in between. Any other `readline` call discards it.
+    /// The timeout is only checked between key presses, and only when the
+    /// input is a supported terminal.
+    pub fn readline_with_timeout(&mut self, prompt: &str, timeout: Duration) -> Result<String> {
+        let partial = self.partial.take();
+        let initial = partial.as_ref().map(|(line, pos)| line.split_at(*pos));
+        self.deadline = Some(Instant::now() + timeout);
+        let result = self.readline_with(prompt, initial, None);
+        self.deadline = None;
+        result
+    }
+
+    /// Read a password (or any other secret) without echoing it.
+    ///
+    /// The line is neither displayed nor added to the history.
+    /// Ctrl-C aborts with [`ReadlineError::Interrupted`].
+    pub fn readline_no_echo(&mut self, prompt: &str) -> Result<SecretString> {
+        self.readline_secret(prompt, None)
+    }
+
+    /// Read a password (or any other secret) displaying `mask` (e.g. `'*'`)
+    /// for each character typed.
+    ///
+    /// Neither the highlighter nor the hinter is called and the line is not
+    /// added to the history.
+    /// `mask` is expected to be a single-column character.
+    pub fn readline_masked(&mut self, prompt: &str, mask: char) -> Result<SecretString> {
+        self.readline_secret(prompt, Some(mask))
+    }
+
+    /// Read a single key press in raw mode, without any line editing.
+    ///
+    /// Escape sequences (arrow keys, function keys, ...) are decoded into
+    /// one `KeyEvent`. Nothing is displayed and the terminal mode is restored
+    /// before returning. Fails if the input is not a terminal.
+    pub fn read_key(&mut self) -> Result<KeyEvent> {
+        let (original_mode, term_key_map) = self.term.enable_raw_mode()?;
+        let guard = Guard(&original_mode);
+        let mut rdr = self.term.create_reader(&self.config, term_key_map);
+        rdr.set_sequences(&self.sequences);
+        let key = loop {
+            match rdr.next_key(false) {
+                Err(ReadlineError::WindowResized) => continue,
+                key => break key,
+            }
+        };
+        drop(guard); // disable_raw_mode(original_mode)?;
+        key
+    }
+
+    /// Apply one key press to the line edited without a terminal, according
+    /// to the keymap and the custom bindings.
+    ///
+    /// Returns `Some(line)` when the line is accepted (like `readline` does,
+    /// including the history update), `None` otherwise: the line is then
+    /// available with [`current_buffer`](Editor::current_buffer).
+    /// Keys of a multi-key sequence (like `M-2 C-d` or vi `dw`) are applied
+    /// once the sequence is complete.
+    /// Commands needing the terminal (completion, incremental search,
+    /// external editor, quoted insert, suspend) are ignored.
+    pub fn process_key(&mut self, key: KeyEvent) -> Result<Option<String>> {
+        self.driver.pending.push(key);
+        let mut out = Offscreen::default();
+        out.set_continuation_prompt(self.config.continuation_prompt());
+        let mut ctx = Context::new(&self.history);
+        ctx.completion_ignore_case = self.config.completion_ignore_case();
+        let mut s = State::new(&mut out, "", self.helper.as_ref(), ctx);
+        s.line.set_max_len(self.config.max_line_length());
+        s.line.update(&self.driver.line, self.driver.pos);
+        s.line.set_delete_listener(Box::new(self.kill_ring.clone()));
+        s.line.set_change_listener(s.changes.clone());
+        s.refresh_line()?;
+
+        let mut input_state = InputState::new(&self.config, &self.custom_bindings);
+        if let Some(ref memo) = self.driver.input {
+            input_state.resume(memo);
+        }
+        let mut rdr = PendingKeys::from(self.driver.pending.clone());
+        let first = rdr.next_key(false)?;
+        let cmd = match input_state.key_cmd(&mut rdr, &mut s, first) {
+            // wait for the next keys of the sequence
+            Err(ReadlineError::Eof) => return Ok(None),
+            cmd => cmd?,
+        };
+        self.driver.pending.clear();
+        if cmd.should_reset_kill_ring() {
+            self.reset_kill_ring();
+        }
+        let status = command::execute(cmd, &mut s, &input_state, &self.kill_ring, &self.config);
+        let pos = s.line.pos();
+        let line = s.line.into_string();
+        match status {
+            Ok(command::Status::Proceed) => {
+                self.driver.pos = pos;
+                self.driver.line = line;
+                self.driver.input = Some(input_state.memo());
+                Ok(None)
+            }
+            Ok(command::Status::Submit) => {
+                self.driver = KeyDriver::default();
+                let line = self.transform_line(line);
+                if self.config.auto_add_history() {
+                    self.add_history_entry(line.as_str());
+                }
+                Ok(Some(line))
+            }
+            Err(err) => {
+                self.driver = KeyDriver::default();
+                Err(err)
+            }
+        }
+    }
+
+    /// Line edited with [`process_key`](Editor::process_key)
+    #[must_use]
+    pub fn current_buffer(&self) -> &str {
+        &self.driver.line
+    }
+
+    fn readline_secret(&mut self, prompt: &str, mask: Option<char>) -> Result<SecretString> {
+        if self.io.is_none() && !self.term.is_unsupported() && self.term.is_input_tty() {
+            let (original_mode, term_key_map) = self.term.enable_raw_mode()?;
+            let guard = Guard(&original_mode);
+            let mut out = self.term.create_writer();
+            out.write_and_flush(prompt)?;
+            let mut rdr = self.term.create_reader(&self.config, term_key_map);
+            let secret = secret::read_secret(&mut rdr, &mut out, mask);
+            drop(guard); // disable_raw_mode(original_mode)?;
+            self.term.writeln()?;
+            secret
+        } else {
+            if let Some(ref io) = self.io {
+                let mut io = io.lock().unwrap();
+                let (reader, writer, _) = &mut *io;
+                writer.write_all(prompt.as_bytes())?;
+                writer.flush()?;
+                secret::read_secret_line(reader, line_buffer::MAX_LINE)
+            } else {
+                debug!(target: "rustyline", "stdin is not a tty");
+                let mut stdout = io::stdout();
+                stdout.write_all(prompt.as_bytes())?;
+                stdout.flush()?;
+                secret::read_secret_line(&mut io::stdin().lock(), line_buffer::MAX_LINE)
+            }
+        }
+    }
+
+    fn readline_with(
+        &mut self,
+        prompt: &str,
+        initial: Option<(&str, &str)>,
+        listener: Option<&mut dyn FnMut(ReadlineEvent)>,
+    ) -> Result<String> {
+        // a line interrupted by a timeout is only restored by the next
+        // `readline_with_timeout`
+        self.partial = None;
+        let user_input = self.readline_input(prompt, initial, listener);
+        self.accept_input(user_input)
+    }
+
+    /// Transform the line read and add it to the history (see
+    /// [`Config::auto_add_history`])
+    fn accept_input(&mut self, user_input: Result<String>) -> Result<String> {
+        let user_input = user_input.map(|line| self.transform_line(line));
+        if self.config.auto_add_history() && !self.sensitive {
+            if let Ok(ref line) = user_input {
+                let len = self.history.len();
+                if self.add_history_entry(line.as_str()) && self.history.len() == len {
+                    // the oldest entry has been dropped
+                    self.next_history = self.next_history.and_then(|i| i.checked_sub(1));
+                }
+            }
+        }
+        user_input
+    }
+
+    fn transform_line(&self, line: String) -> String {
+        match self.line_transform {
+            Some(ref transform) => transform(&line),
+            None => line,
+        }
+    }
+
+    fn readline_input(
+        &mut self,
+        prompt: &str,
+        initial: Option<(&str, &str)>,
+        listener: Option<&mut dyn FnMut(ReadlineEvent)>,
+    ) -> Result<String> {
+        #[cfg(all(target_arch = "wasm32", feature = "web-sys"))]
+        if self.io.is_none() {
+            return tty::prompt(prompt);
+        }
+        if let Some(io) = self.io.clone() {
+            let mut io = io.lock().unwrap();
+            let (reader, writer, after_cr) = &mut *io;
+            let mut out = StreamRenderer::new(writer, &self.config);
+            let mut rdr = StreamReader::new(reader, after_cr);
+            let user_input = self.edit_line(&mut out, &mut rdr, prompt, initial, None, listener);
+            if !matches!(user_input, Err(ReadlineError::Timeout)) {
+                out.write_and_flush("\n")?;
+            }
+            user_input
+        } else if self.term.is_unsupported() {
             debug!(target: "rustyline", "unsupported terminal");
             // Write prompt and flush it to stdout
             let mut stdout = io::stdout();
@@ -643,14 +1665,16 @@ impl<H: Helper> Editor<H> {
         } else if self.term.is_input_tty() {
             let (original_mode, term_key_map) = self.term.enable_raw_mode()?;
             let guard = Guard(&original_mode);
-            let user_input = self.readline_edit(prompt, initial, &original_mode, term_key_map);
-            if self.config.auto_add_history() {
-                if let Ok(ref line) = user_input {
-                    self.add_history_entry(line.as_str());
-                }
+            let user_input =
+                self.readline_edit(prompt, initial, &original_mode, term_key_map, listener);
+            if self.vi_cursor_shape() {
+                // restore default cursor shape
+                let _ = self.term.create_writer().set_cursor_shape(None);
             }
             drop(guard); // disable_raw_mode(original_mode)?;
-            self.term.writeln()?;
+            if !matches!(user_input, Err(ReadlineError::Timeout)) {
+                self.term.writeln()?;
+            }
             user_input
         } else {
             debug!(target: "rustyline", "stdin is not a tty");
@@ -668,112 +1692,53 @@ impl<H: Helper> Editor<H> {
         initial: Option<(&str, &str)>,
         original_mode: &tty::Mode,
         term_key_map: tty::KeyMap,
+        listener: Option<&mut dyn FnMut(ReadlineEvent)>,
     ) -> Result<String> {
         let mut stdout = self.term.create_writer();
-
-        self.reset_kill_ring(); // TODO recreate a new kill ring vs Arc<Mutex<KillRing>>
-        let ctx = Context::new(&self.history);
-        let mut s = State::new(&mut stdout, prompt, self.helper.as_ref(), ctx);
-
-        let mut input_state = InputState::new(&self.config, &self.custom_bindings);
-
-        s.line.set_delete_listener(self.kill_ring.clone());
-        s.line.set_change_listener(s.changes.clone());
-
-        if let Some((left, right)) = initial {
-            s.line
-                .update((left.to_owned() + right).as_ref(), left.len());
-        }
-
         let mut rdr = self.term.create_reader(&self.config, term_key_map);
-        if self.term.is_output_tty() && self.config.check_cursor_position() {
-            if let Err(e) = s.move_cursor_at_leftmost(&mut rdr) {
-                if let ReadlineError::WindowResized = e {
-                    s.out.update_size();
-                } else {
-                    return Err(e);
-                }
-            }
-        }
-        s.refresh_line()?;
+        rdr.set_sequences(&self.sequences);
+        self.edit_line(
+            &mut stdout,
+            &mut rdr,
+            prompt,
+            initial,
+            Some(original_mode),
+            listener,
+        )
+    }
 
+    /// Edit the line with `out` and `rdr`.
+    ///
+    /// `original_mode` is `None` when they are not the terminal (see
+    /// [`Editor::with_io`]).
+    fn edit_line<W: Renderer>(
+        &mut self,
+        out: &mut W,
+        rdr: &mut W::Reader,
+        prompt: &str,
+        initial: Option<(&str, &str)>,
+        original_mode: Option<&tty::Mode>,
+        mut listener: Option<&mut dyn FnMut(ReadlineEvent)>,
+    ) -> Result<String> {
+        let no_history = History::new();
+        let mut edition =
+            Edition::new(self, out, rdr, prompt, initial, original_mode, &no_history)?;
         loop {
-            let mut cmd = s.next_cmd(&mut input_state, &mut rdr, false, false)?;
-
-            if cmd.should_reset_kill_ring() {
-                self.reset_kill_ring();
-            }
-
-            // First trigger commands that need extra input
-
-            if cmd == Cmd::Complete && s.helper.is_some() {
-                let next = complete_line(&mut rdr, &mut s, &mut input_state, &self.config)?;
-                if let Some(next) = next {
-                    cmd = next;
-                } else {
-                    continue;
-                }
-            }
-
-            if cmd == Cmd::ReverseSearchHistory {
-                // Search history backward
-                let next =
-                    reverse_incremental_search(&mut rdr, &mut s, &mut input_state, &self.history)?;
-                if let Some(next) = next {
-                    cmd = next;
-                } else {
-                    continue;
-                }
-            }
-
-            #[cfg(unix)]
-            if cmd == Cmd::Suspend {
-                original_mode.disable_raw_mode()?;
-                tty::suspend()?;
-                let _ = self.term.enable_raw_mode()?; // TODO original_mode may have changed
-                s.out.update_size(); // window may have been resized
-                s.refresh_line()?;
-                continue;
-            }
-
-            #[cfg(unix)]
-            if cmd == Cmd::QuotedInsert {
-                // Quoted insert
-                let c = rdr.next_char()?;
-                s.edit_insert(c, 1)?;
-                continue;
+            if !edition.wait_input(rdr)? {
+                return Err(ReadlineError::Timeout);
             }
-
-            #[cfg(windows)]
-            if cmd == Cmd::PasteFromClipboard {
-                let clipboard = rdr.read_pasted_text()?;
-                s.edit_yank(&input_state, &clipboard[..], Anchor::Before, 1)?;
-            }
-
-            // Tiny test quirk
-            #[cfg(test)]
-            if matches!(
-                cmd,
-                Cmd::AcceptLine | Cmd::Newline | Cmd::AcceptOrInsertLine { .. }
-            ) {
-                self.term.cursor = s.layout.cursor.col;
-            }
-
-            // Execute things can be done solely on a state object
-            match command::execute(cmd, &mut s, &input_state, &self.kill_ring, &self.config)? {
-                command::Status::Proceed => continue,
-                command::Status::Submit => break,
+            if edition.step(rdr, &mut listener)? {
+                return edition.finish();
             }
         }
+    }
 
-        // Move to end, in case cursor was in the middle of the line, so that
-        // next thing application prints goes after the input
-        s.edit_move_buffer_end()?;
-
-        if cfg!(windows) {
-            let _ = original_mode; // silent warning
-        }
-        Ok(s.line.into_string())
+    /// Whether the cursor shape should reflect vi input mode (see
+    /// [`Config::vi_cursor_shape`])
+    fn vi_cursor_shape(&self) -> bool {
+        self.config.vi_cursor_shape()
+            && self.config.edit_mode() == EditMode::Vi
+            && self.term.is_output_tty()
     }
 
     /// Load the history from the specified file.
@@ -791,16 +1756,46 @@ impl<H: Helper> Editor<H> {
         self.history.append(path)
     }
 
+    /// Return the history entries matching the regular expression `pattern`,
+    /// newest first.
+    #[cfg(feature = "regex-search")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "regex-search")))]
+    pub fn search_history(&self, pattern: &str) -> Result<impl Iterator<Item = &str> + '_> {
+        self.history.search_regex(pattern)
+    }
+
+    /// Reload the history from the specified file to get the entries
+    /// appended by other instances (see `append_history`).
+    pub fn read_history<P: AsRef<Path> + ?Sized>(&mut self, path: &P) -> Result<()> {
+        self.history.reload(path)
+    }
+
     /// Add a new entry in the history.
     pub fn add_history_entry<S: AsRef<str> + Into<String>>(&mut self, line: S) -> bool {
         self.history.add(line)
     }
 
+    /// Do not add to the history the lines containing one of `patterns`
+    /// (e.g. commands with a secret).
+    pub fn set_history_ignore(&mut self, patterns: Vec<String>) {
+        self.history.set_ignore_patterns(patterns);
+    }
+
     /// Clear history.
     pub fn clear_history(&mut self) {
         self.history.clear();
     }
 
+    /// Return the history entries, oldest first, with the time they were added
+    /// if known.
+    #[cfg(feature = "with-timestamps")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "with-timestamps")))]
+    pub fn history_with_timestamps(
+        &self,
+    ) -> impl Iterator<Item = (&str, Option<std::time::SystemTime>)> + '_ {
+        self.history.iter_with_timestamps()
+    }
+
     /// Return a mutable reference to the history object.
     pub fn history_mut(&mut self) -> &mut History {
         &mut self.history
@@ -817,6 +1812,19 @@ impl<H: Helper> Editor<H> {
         self.helper = helper;
     }
 
+    /// Set a function applied to the accepted line before it is returned and
+    /// added to the history, like trimming trailing whitespace.
+    ///
+    /// ```
+    /// use yatima_rustyline::DefaultEditor;
+    /// let mut rl = DefaultEditor::new()?;
+    /// rl.set_line_transform(Some(Box::new(|line: &str| line.trim_end().to_owned())));
+    /// # Ok::<(), yatima_rustyline::error::ReadlineError>(())
+    /// ```
+    pub fn set_line_transform(&mut self, transform: Option<Box<LineTransform>>) {
+        self.line_transform = transform.map(Arc::from);
+    }
+
     /// Return a mutable reference to the helper.
     pub fn helper_mut(&mut self) -> Option<&mut H> {
         self.helper.as_mut()
@@ -836,7 +1844,8 @@ impl<H: Helper> Editor<H> {
         handler: R,
     ) -> Option<EventHandler> {
         self.custom_bindings
-            .insert(Event::normalize(key_seq.into()), handler.into())
+            .insert(Event::normalize(key_seq.into()), Arc::new(handler.into()))
+            .and_then(|handler| Arc::try_unwrap(handler).ok())
     }
 
     /// Remove a binding for the given sequence.
@@ -845,6 +1854,86 @@ impl<H: Helper> Editor<H> {
     pub fn unbind_sequence<E: Into<Event>>(&mut self, key_seq: E) -> Option<EventHandler> {
         self.custom_bindings
             .remove(&Event::normalize(key_seq.into()))
+            .and_then(|handler| Arc::try_unwrap(handler).ok())
+    }
+
+    /// Bind a single key to a command.
+    ///
+    /// The binding takes precedence over the default one of the current edit
+    /// mode.
+    /// ```
+    /// use yatima_rustyline::{Cmd, DefaultEditor, KeyEvent, Movement};
+    /// let mut rl = DefaultEditor::new()?;
+    /// rl.bind_key(KeyEvent::ctrl('B'), Cmd::Move(Movement::BeginningOfLine));
+    /// # Ok::<(), yatima_rustyline::error::ReadlineError>(())
+    /// ```
+    #[cfg(feature = "custom-bindings")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "custom-bindings")))]
+    pub fn bind_key(&mut self, key: KeyEvent, cmd: Cmd) -> Option<EventHandler> {
+        self.bind_sequence(key, cmd)
+    }
+
+    /// Remove a binding for the given key, restoring the default one.
+    #[cfg(feature = "custom-bindings")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "custom-bindings")))]
+    pub fn unbind_key(&mut self, key: KeyEvent) -> Option<EventHandler> {
+        self.unbind_sequence(key)
+    }
+
+    /// Decode a raw byte sequence sent by the terminal as `key`.
+    ///
+    /// This is useful for special keys the built-in escape sequences do not
+    /// cover, which it can also override. The key is then handled like any
+    /// other one: it can be bound with [`bind_sequence`](Self::bind_sequence)
+    /// and preceded by a numeric argument. The longest matching sequence
+    /// wins, its bytes may be received separately within the
+    /// [`keyseq_timeout`](Config::keyseq_timeout); input that does not match
+    /// is decoded as usual. Only the Unix terminal sees raw bytes.
+    /// Returns the key previously decoded from `seq`, if any.
+    /// ```
+    /// use yatima_rustyline::{Cmd, DefaultEditor, KeyCode, KeyEvent, Modifiers};
+    /// let mut rl = DefaultEditor::new()?;
+    /// // Shift-F1 in xterm
+    /// let key = KeyEvent(KeyCode::F(1), Modifiers::SHIFT);
+    /// assert_eq!(None, rl.bind_raw_sequence(b"\x1b[1;2P", key));
+    /// rl.bind_sequence(key, Cmd::ClearScreen);
+    /// # Ok::<(), yatima_rustyline::error::ReadlineError>(())
+    /// ```
+    #[cfg(feature = "custom-bindings")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "custom-bindings")))]
+    pub fn bind_raw_sequence(&mut self, seq: &[u8], key: KeyEvent) -> Option<KeyEvent> {
+        self.sequences.insert(seq, key)
+    }
+
+    /// Load key bindings and settings from an `.inputrc` file.
+    ///
+    /// Only a subset of GNU readline syntax is supported: key bindings
+    /// (`"\C-a": beginning-of-line`, `Meta-b: backward-word`, macros) and the
+    /// `editing-mode`, `completion-query-items`, `completion-ignore-case`,
+    /// `keyseq-timeout` and `history-size` variables. `$if` constructs are
+    /// evaluated against the current editing mode and the `TERM` environment
+    /// variable. Other lines are ignored with a warning.
+    #[cfg(feature = "custom-bindings")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "custom-bindings")))]
+    pub fn load_inputrc<P: AsRef<Path> + ?Sized>(&mut self, path: &P) -> Result<()> {
+        use crate::config::Configurer;
+        use crate::inputrc::Directive;
+
+        let content = std::fs::read_to_string(path)?;
+        let term = std::env::var("TERM").unwrap_or_default();
+        for directive in inputrc::parse(&content, self.config.edit_mode(), &term) {
+            match directive {
+                Directive::Bind(keys, cmd) => {
+                    self.bind_sequence(Event::KeySeq(keys), cmd);
+                }
+                Directive::EditMode(edit_mode) => self.set_edit_mode(edit_mode),
+                Directive::CompletionPromptLimit(limit) => self.set_completion_prompt_limit(limit),
+                Directive::CompletionIgnoreCase(yes) => self.set_completion_ignore_case(yes),
+                Directive::KeyseqTimeout(timeout) => self.set_keyseq_timeout(timeout),
+                Directive::HistorySize(size) => self.set_max_history_size(size),
+            }
+        }
+        Ok(())
     }
 
     /// Returns an iterator over edited lines
@@ -886,12 +1975,75 @@ impl<H: Helper> Editor<H> {
         }
     }
 
+    /// Returns `true` if the terminal has no line edition support: when the
+    /// editor was created, `$TERM` named a terminal like `dumb` or `emacs`.
+    ///
+    /// On such terminals, `readline` does not switch to raw mode: the line is
+    /// read as typed, with only backspace handled.
+    #[must_use]
+    pub fn is_dumb_terminal(&self) -> bool {
+        self.term.is_unsupported()
+    }
+
+    /// Returns `true` if the output is colorized, according to the
+    /// configured [`ColorMode`] and whether the output stream is a tty.
+    #[must_use]
+    pub fn color_enabled(&self) -> bool {
+        self.term.colors_enabled()
+    }
+
+    /// Snapshot of the history and of the settings worth keeping across
+    /// invocations.
+    #[must_use]
+    pub fn save_state(&self) -> EditorState {
+        EditorState {
+            history: self.history.iter().map(str::to_owned).collect(),
+            max_history_size: self.config.max_history_size(),
+            edit_mode: self.config.edit_mode(),
+            bell_style: self.config.bell_style(),
+            #[cfg(feature = "with-timestamps")]
+            timestamps: self
+                .history
+                .iter_with_timestamps()
+                .map(|(_, timestamp)| timestamp)
+                .collect(),
+        }
+    }
+
+    /// Replace the history and the settings by the ones saved by
+    /// [`Editor::save_state`].
+    ///
+    /// History entries are restored as is, even if they would be filtered out
+    /// by the current settings (like [`history_ignore_dups`]).
+    ///
+    /// The line being edited (if any) is left untouched.
+    ///
+    /// [`history_ignore_dups`]: config::Builder::history_ignore_dups
+    pub fn restore_state(&mut self, state: EditorState) {
+        use crate::config::Configurer;
+
+        self.set_max_history_size(state.max_history_size);
+        self.set_edit_mode(state.edit_mode);
+        self.set_bell_style(state.bell_style);
+        self.history.clear();
+        #[cfg(feature = "with-timestamps")]
+        let mut timestamps = state.timestamps.into_iter();
+        for entry in state.history {
+            #[cfg(feature = "with-timestamps")]
+            let timestamp = timestamps.next().flatten();
+            #[cfg(not(feature = "with-timestamps"))]
+            let timestamp = None;
+            self.history.restore(entry, timestamp);
+        }
+    }
+
     /// Create an external printer
     pub fn create_external_printer(&mut self) -> Result<<Terminal as Term>::ExternalPrinter> {
         self.term.create_external_printer()
     }
 }
 
+#[cfg(not(feature = "no_std"))]
 impl<H: Helper> config::Configurer for Editor<H> {
     fn config_mut(&mut self) -> &mut Config {
         &mut self.config
@@ -904,20 +2056,65 @@ impl<H: Helper> config::Configurer for Editor<H> {
 
     fn set_history_ignore_dups(&mut self, yes: bool) {
         self.config_mut().set_history_ignore_dups(yes);
-        self.history.ignore_dups = yes;
+        self.history.configure(&self.config);
     }
 
     fn set_history_ignore_space(&mut self, yes: bool) {
         self.config_mut().set_history_ignore_space(yes);
-        self.history.ignore_space = yes;
+        self.history.configure(&self.config);
+    }
+
+    #[cfg(feature = "with-timestamps")]
+    fn set_history_timestamps(&mut self, yes: bool) {
+        self.config_mut().set_history_timestamps(yes);
+        self.history.configure(&self.config);
     }
 
     fn set_color_mode(&mut self, color_mode: ColorMode) {
         self.config_mut().set_color_mode(color_mode);
         self.term.color_mode = color_mode;
     }
+
+    fn set_bell_style(&mut self, bell_style: config::BellStyle) {
+        self.config_mut().set_bell_style(bell_style);
+        self.term.bell_style = bell_style;
+    }
+}
+
+/// Fork an editor.
+///
+/// History, kill ring, configuration, helper and key bindings are copied so
+/// that changing the clone does not affect the original. Custom event handlers
+/// and the input/output given to [`Editor::with_io`] are shared.
+/// The clone starts with no raw mode active and without the external printer
+/// of the original.
+#[cfg(not(feature = "no_std"))]
+impl<H: Helper + Clone> Clone for Editor<H> {
+    fn clone(&self) -> Self {
+        Self {
+            term: self.term.clone(),
+            history: self.history.clone(),
+            helper: self.helper.clone(),
+            kill_ring: Arc::new(Mutex::new(self.kill_ring.lock().unwrap().clone())),
+            config: self.config,
+            custom_bindings: self.custom_bindings.clone(),
+            sequences: self.sequences.clone(),
+            io: self.io.clone(),
+            deadline: None,
+            partial: self.partial.clone(),
+            history_hint: self.history_hint,
+            sensitive: self.sensitive,
+            mask: self.mask,
+            driver: self.driver.clone(),
+            next_history: self.next_history,
+            line_transform: self.line_transform.clone(),
+            #[cfg(all(unix, feature = "async"))]
+            async_stdin: None,
+        }
+    }
 }
 
+#[cfg(not(feature = "no_std"))]
 impl<H: Helper> fmt::Debug for Editor<H> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Editor")
@@ -927,11 +2124,13 @@ impl<H: Helper> fmt::Debug for Editor<H> {
     }
 }
 
+#[cfg(not(feature = "no_std"))]
 struct Iter<'a, H: Helper> {
     editor: &'a mut Editor<H>,
     prompt: &'a str,
 }
 
+#[cfg(not(feature = "no_std"))]
 impl<'a, H: Helper> Iterator for Iter<'a, H> {
     type Item = Result<String>;
 
@@ -945,10 +2144,10 @@ impl<'a, H: Helper> Iterator for Iter<'a, H> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(feature = "no_std")))]
 #[macro_use]
 extern crate assert_matches;
-#[cfg(test)]
+#[cfg(all(test, not(feature = "no_std")))]
 mod test;
 
 #[cfg(doctest)]
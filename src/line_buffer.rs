@@ -1,14 +1,15 @@
 //! Line buffer with current cursor position
-use crate::keymap::{At, CharSearch, Movement, RepeatCount, Word};
-use std::cell::RefCell;
-use std::cmp::min;
-use std::fmt;
-use std::iter;
-use std::ops::{Deref, Index, Range};
-use std::rc::Rc;
-use std::string::Drain;
-use std::sync::{Arc, Mutex};
-use unicode_segmentation::UnicodeSegmentation;
+use crate::grapheme::UnicodeSegmentation;
+use crate::movement::{At, CharSearch, Movement, RepeatCount, Word};
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::{Drain, String, ToString};
+use core::cell::RefCell;
+use core::cmp::min;
+use core::fmt;
+use core::iter;
+use core::ops::{Deref, Index, Range};
 
 /// Default maximum buffer size for the line read
 pub(crate) const MAX_LINE: usize = 4096;
@@ -61,7 +62,8 @@ pub struct LineBuffer {
     buf: String,      // Edited line buffer (rl_line_buffer)
     pos: usize,       // Current cursor position (byte position) (rl_point)
     can_growth: bool, // Whether to allow dynamic growth
-    dl: Option<Arc<Mutex<dyn DeleteListener>>>,
+    max_len: usize,   // Maximum length (in bytes) when growth is allowed
+    dl: Option<Box<dyn DeleteListener>>,
     cl: Option<Rc<RefCell<dyn ChangeListener>>>,
 }
 
@@ -82,6 +84,7 @@ impl LineBuffer {
             buf: String::with_capacity(capacity),
             pos: 0,
             can_growth: false,
+            max_len: usize::MAX,
             dl: None,
             cl: None,
         }
@@ -93,8 +96,24 @@ impl LineBuffer {
         self
     }
 
-    fn must_truncate(&self, new_len: usize) -> bool {
-        !self.can_growth && new_len > self.buf.capacity()
+    /// Set the maximum length (in bytes) of the line when dynamic growth is
+    /// allowed.
+    pub(crate) fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len;
+    }
+
+    fn max_len(&self) -> usize {
+        if self.can_growth {
+            self.max_len
+        } else {
+            self.buf.capacity()
+        }
+    }
+
+    /// Tell if the line would exceed its maximum length with `new_len`
+    /// bytes.
+    pub(crate) fn must_truncate(&self, new_len: usize) -> bool {
+        new_len > self.max_len()
     }
 
     #[cfg(test)]
@@ -110,7 +129,7 @@ impl LineBuffer {
         lb
     }
 
-    pub(crate) fn set_delete_listener(&mut self, dl: Arc<Mutex<dyn DeleteListener>>) {
+    pub(crate) fn set_delete_listener(&mut self, dl: Box<dyn DeleteListener>) {
         self.dl = Some(dl);
     }
 
@@ -159,8 +178,11 @@ impl LineBuffer {
         assert!(pos <= buf.len());
         let end = self.len();
         self.drain(0..end, Direction::default());
-        let max = self.buf.capacity();
         if self.must_truncate(buf.len()) {
+            let mut max = self.max_len();
+            while !buf.is_char_boundary(max) {
+                max -= 1;
+            }
             self.insert_str(0, &buf[..max]);
             if pos > max {
                 self.pos = max;
@@ -882,11 +904,8 @@ impl LineBuffer {
     }
 
     fn drain(&mut self, range: Range<usize>, dir: Direction) -> Drain<'_> {
-        for dl in &self.dl {
-            let lock = dl.try_lock();
-            if let Ok(mut dl) = lock {
-                dl.delete(range.start, &self.buf[range.start..range.end], dir);
-            }
+        if let Some(dl) = self.dl.as_mut() {
+            dl.delete(range.start, &self.buf[range.start..range.end], dir);
         }
         for cl in &self.cl {
             if let Ok(mut cl) = cl.try_borrow_mut() {
@@ -1003,10 +1022,14 @@ impl LineBuffer {
 
     /// Kill range specified by `mvt`.
     pub fn kill(&mut self, mvt: &Movement) -> bool {
-        let notify = !matches!(*mvt, Movement::ForwardChar(_) | Movement::BackwardChar(_));
+        // like readline, deleting chars only saves them in the kill ring when a
+        // numeric argument is given
+        let notify = match *mvt {
+            Movement::ForwardChar(n) | Movement::BackwardChar(n) => n > 1,
+            _ => true,
+        };
         if notify {
-            if let Some(dl) = self.dl.as_ref() {
-                let mut dl = dl.lock().unwrap();
+            if let Some(dl) = self.dl.as_mut() {
                 dl.start_killing();
             }
         }
@@ -1073,8 +1096,7 @@ impl LineBuffer {
             }
         };
         if notify {
-            if let Some(dl) = self.dl.as_ref() {
-                let mut dl = dl.lock().unwrap();
+            if let Some(dl) = self.dl.as_mut() {
                 dl.stop_killing();
             }
         }
@@ -1148,6 +1170,16 @@ impl Deref for LineBuffer {
     }
 }
 
+/// Replace the word in `start..end` with the `elected` completion candidate
+/// and move the cursor after it.
+///
+/// Text after `end` (typically what is on the right of the cursor) is left
+/// untouched.
+pub fn replace_word(line: &mut LineBuffer, start: usize, end: usize, elected: &str) {
+    let end = end.clamp(start, line.len());
+    line.replace(start..end, elected);
+}
+
 fn is_start_of_word(word_def: Word, previous: &str, grapheme: &str) -> bool {
     (!is_word_char(word_def, previous) && is_word_char(word_def, grapheme))
         || (word_def == Word::Vi && !is_other_char(previous) && is_other_char(grapheme))
@@ -1173,8 +1205,10 @@ fn is_other_char(grapheme: &str) -> bool {
 
 #[cfg(test)]
 mod test {
-    use super::{ChangeListener, DeleteListener, Direction, LineBuffer, WordAction, MAX_LINE};
-    use crate::keymap::{At, CharSearch, Word};
+    use super::{
+        replace_word, ChangeListener, DeleteListener, Direction, LineBuffer, WordAction, MAX_LINE,
+    };
+    use crate::movement::{At, CharSearch, Word};
     use std::cell::RefCell;
     use std::rc::Rc;
 
@@ -1211,6 +1245,21 @@ mod test {
         fn replace(&mut self, _: usize, _: &str, _: &str) {}
     }
 
+    #[test]
+    fn replace_word_keeps_suffix() {
+        let mut s = LineBuffer::init("ls /usr/loc bin", 11, None);
+        replace_word(&mut s, 3, 11, "/usr/local/");
+        assert_eq!("ls /usr/local/ bin", s.as_str());
+        assert_eq!(14, s.pos());
+
+        // end past the line is clamped
+        let mut s = LineBuffer::init("cd sr", 5, None);
+        replace_word(&mut s, 3, 42, "src/");
+        assert_eq!("cd src/", s.as_str());
+        assert_eq!(7, s.pos());
+    }
+
+    #[cfg(feature = "grapheme")]
     #[test]
     fn next_pos() {
         let s = LineBuffer::init("ö̲g̈", 0, None);
@@ -1223,6 +1272,7 @@ mod test {
         assert_eq!(Some(7), pos);
     }
 
+    #[cfg(feature = "grapheme")]
     #[test]
     fn prev_pos() {
         let s = LineBuffer::init("ö̲g̈", 4, None);
@@ -1348,6 +1398,7 @@ mod test {
         assert!(!ok);
     }
 
+    #[cfg(feature = "grapheme")]
     #[test]
     fn move_grapheme() {
         let mut s = LineBuffer::init("ag̈", 4, None);
@@ -1361,6 +1412,50 @@ mod test {
         assert_eq!(4, s.pos);
     }
 
+    #[cfg(feature = "grapheme")]
+    #[test]
+    fn move_flag_and_combining_accent() {
+        // flag (two regional indicators), e + combining acute accent, ZWJ sequence
+        let text = "\u{1f1eb}\u{1f1f7}e\u{301}\u{1f469}\u{200d}\u{1f52c}";
+        let mut s = LineBuffer::init(text, 0, None);
+        assert!(s.move_forward(1));
+        assert_eq!(8, s.pos);
+        assert!(s.move_forward(1));
+        assert_eq!(11, s.pos);
+        assert!(s.move_forward(1));
+        assert_eq!(text.len(), s.pos);
+        assert!(!s.move_forward(1));
+
+        assert!(s.move_backward(2));
+        assert_eq!(8, s.pos);
+        assert!(s.move_backward(1));
+        assert_eq!(0, s.pos);
+    }
+
+    #[cfg(feature = "grapheme")]
+    #[test]
+    fn backspace_grapheme() {
+        let mut s = LineBuffer::init("a\u{1f1eb}\u{1f1f7}e\u{301}", 12, None);
+        assert!(s.backspace(1));
+        assert_eq!("a\u{1f1eb}\u{1f1f7}", s.buf);
+        assert!(s.backspace(1));
+        assert_eq!("a", s.buf);
+        assert_eq!(1, s.pos);
+    }
+
+    #[cfg(not(feature = "grapheme"))]
+    #[test]
+    fn move_char() {
+        // without the `grapheme` feature, the combining accent is a step
+        let mut s = LineBuffer::init("ae\u{301}", 4, None);
+        assert!(s.move_backward(1));
+        assert_eq!(2, s.pos);
+        assert!(s.backspace(1));
+        assert_eq!("a\u{301}", s.buf);
+        assert!(s.move_forward(1));
+        assert_eq!(3, s.pos);
+    }
+
     #[test]
     fn delete() {
         let cl = Listener::new();
@@ -0,0 +1,315 @@
+//! History entries kept in memory, without `std`
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use core::ops::Range;
+
+/// Search direction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchDirection {
+    /// Search history forward
+    Forward,
+    /// Search history backward
+    Reverse,
+}
+
+/// History search result
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SearchResult<'a> {
+    /// history entry
+    pub entry: &'a str,
+    /// history index
+    pub idx: usize,
+    /// match position in `entry`
+    pub pos: usize,
+}
+
+/// Byte range of the first occurrence of `term` in `entry`.
+pub(crate) fn find(entry: &str, term: &str, ignore_case: bool) -> Option<(usize, usize)> {
+    if !ignore_case {
+        return entry.find(term).map(|start| (start, start + term.len()));
+    }
+    'outer: for (start, _) in entry.char_indices() {
+        let mut end = start;
+        let mut chars = entry[start..].chars();
+        for t in term.chars() {
+            match chars.next() {
+                Some(c) if c == t || c.to_lowercase().eq(t.to_lowercase()) => {
+                    end += c.len_utf8();
+                }
+                _ => continue 'outer,
+            }
+        }
+        return Some((start, end));
+    }
+    None
+}
+
+/// First entry (returned by `get`) matching `test`, from `start` (inclusive)
+/// to the end of the history or back to its first entry.
+pub(crate) fn search_match<'b, G, F>(
+    len: usize,
+    get: G,
+    term: &str,
+    start: usize,
+    dir: SearchDirection,
+    test: F,
+) -> Option<SearchResult<'b>>
+where
+    G: Fn(usize) -> Option<&'b str>,
+    F: Fn(&str) -> Option<usize>,
+{
+    if term.is_empty() || start >= len {
+        return None;
+    }
+    let matches = |idx: usize| {
+        let entry = get(idx)?;
+        test(entry).map(|pos| SearchResult { entry, idx, pos })
+    };
+    match dir {
+        SearchDirection::Reverse => (0..=start).rev().find_map(matches),
+        SearchDirection::Forward => (start..len).find_map(matches),
+    }
+}
+
+/// Whether `line` must not be added after the `last` entry.
+pub(crate) fn is_ignored(
+    line: &str,
+    last: Option<&String>,
+    ignore_space: bool,
+    ignore_dups: bool,
+) -> bool {
+    line.is_empty()
+        || (ignore_space && line.starts_with(char::is_whitespace))
+        || (ignore_dups && last.map(String::as_str) == Some(line))
+}
+
+pub(crate) fn is_stripped_control(c: char) -> bool {
+    c.is_control() && c != '\n' && c != '\t'
+}
+
+/// History entries kept in memory only.
+///
+/// Same filters as the default history of an `Editor` (control characters,
+/// lines starting with a space, consecutive duplicates, maximum length) but
+/// it only needs `alloc`: see the `no_std` feature.
+#[derive(Clone, Debug)]
+pub struct MemHistory {
+    entries: VecDeque<String>,
+    max_len: usize,
+    ignore_space: bool,
+    ignore_dups: bool,
+}
+
+impl MemHistory {
+    /// Empty history keeping at most `max_len` entries
+    #[must_use]
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            max_len,
+            ignore_space: false,
+            ignore_dups: true,
+        }
+    }
+
+    /// Don't add lines starting with a space when `yes` (they are added by
+    /// default).
+    pub fn set_ignore_space(&mut self, yes: bool) {
+        self.ignore_space = yes;
+    }
+
+    /// Don't add a line equal to the last entry when `yes` (the default).
+    pub fn set_ignore_dups(&mut self, yes: bool) {
+        self.ignore_dups = yes;
+    }
+
+    /// Set the maximum length for the history. Oldest entries are removed
+    /// when it shrinks.
+    pub fn set_max_len(&mut self, len: usize) {
+        self.max_len = len;
+        if self.len() > len {
+            self.entries.drain(..self.len() - len);
+        }
+    }
+
+    /// Add a new entry in the history, without its control characters
+    /// (other than newline and tab).
+    ///
+    /// Return `false` when the entry is discarded.
+    pub fn add<S: AsRef<str> + Into<String>>(&mut self, line: S) -> bool {
+        let line: String = if line.as_ref().chars().any(is_stripped_control) {
+            line.as_ref()
+                .chars()
+                .filter(|&c| !is_stripped_control(c))
+                .collect()
+        } else {
+            line.into()
+        };
+        if self.max_len == 0
+            || is_ignored(
+                &line,
+                self.entries.back(),
+                self.ignore_space,
+                self.ignore_dups,
+            )
+        {
+            return false;
+        }
+        if self.entries.len() == self.max_len {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(line);
+        true
+    }
+
+    /// Return the history entry at position `index`, starting from 0.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Return the number of entries in the history.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return true if the history has no entry.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove the history entry at position `index`, starting from 0.
+    pub fn remove(&mut self, index: usize) -> Option<String> {
+        self.entries.remove(index)
+    }
+
+    /// Clear history
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Search history (start position inclusive [0, len-1]), matching `term`
+    /// ignoring case when `ignore_case` is `true`.
+    #[must_use]
+    pub fn search(
+        &self,
+        term: &str,
+        start: usize,
+        dir: SearchDirection,
+        ignore_case: bool,
+    ) -> Option<SearchResult<'_>> {
+        let test = |entry: &str| find(entry, term, ignore_case).map(|(start, _)| start);
+        search_match(self.len(), |idx| self.get(idx), term, start, dir, test)
+    }
+
+    /// Return a forward iterator.
+    #[must_use]
+    pub fn iter(&self) -> MemHistoryIter<'_> {
+        MemHistoryIter {
+            history: self,
+            range: 0..self.len(),
+        }
+    }
+}
+
+impl Default for MemHistory {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl<'a> IntoIterator for &'a MemHistory {
+    type IntoIter = MemHistoryIter<'a>;
+    type Item = &'a str;
+
+    fn into_iter(self) -> MemHistoryIter<'a> {
+        self.iter()
+    }
+}
+
+/// [`MemHistory`] iterator.
+pub struct MemHistoryIter<'a> {
+    history: &'a MemHistory,
+    range: Range<usize>,
+}
+
+impl<'a> Iterator for MemHistoryIter<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.range.next().and_then(|index| self.history.get(index))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for MemHistoryIter<'a> {
+    fn next_back(&mut self) -> Option<&'a str> {
+        self.range
+            .next_back()
+            .and_then(|index| self.history.get(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemHistory, SearchDirection, SearchResult};
+
+    #[test]
+    fn add() {
+        let mut history = MemHistory::new(2);
+        assert!(history.add("line1"));
+        assert!(!history.add("line1"));
+        assert!(!history.add(""));
+        assert!(history.add("\x1b[31mline2"));
+        assert!(history.add(" line3"));
+        assert_eq!(
+            vec!["[31mline2", " line3"],
+            history.iter().collect::<Vec<_>>()
+        );
+
+        history.set_ignore_space(true);
+        assert!(!history.add(" line4"));
+        history.set_ignore_space(false);
+        history.set_ignore_dups(false);
+        assert!(history.add(" line3"));
+        history.set_max_len(1);
+        assert_eq!(Some(" line3"), history.iter().next_back());
+        assert_eq!(1, history.len());
+    }
+
+    #[test]
+    fn search() {
+        let mut history = MemHistory::default();
+        history.add("line1");
+        history.add("Line2");
+        history.add("line3");
+        assert_eq!(
+            Some(SearchResult {
+                idx: 1,
+                entry: "Line2",
+                pos: 0
+            }),
+            history.search("line", 1, SearchDirection::Forward, true)
+        );
+        assert_eq!(
+            Some(SearchResult {
+                idx: 0,
+                entry: "line1",
+                pos: 0
+            }),
+            history.search("line", 1, SearchDirection::Reverse, false)
+        );
+        assert_eq!(
+            None,
+            history.search("line", 3, SearchDirection::Reverse, false)
+        );
+        assert_eq!(Some("line1".to_owned()), history.remove(0));
+        history.clear();
+        assert!(history.is_empty());
+    }
+}
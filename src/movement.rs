@@ -0,0 +1,129 @@
+//! Movements and ranges of the line buffer, shared by the key bindings and
+//! [`LineBuffer`](crate::line_buffer::LineBuffer)
+
+/// The number of times one command should be repeated.
+pub type RepeatCount = usize;
+
+pub(crate) const fn repeat_count(previous: RepeatCount, new: Option<RepeatCount>) -> RepeatCount {
+    match new {
+        Some(n) => n,
+        None => previous,
+    }
+}
+
+/// Different word definitions
+#[derive(Debug, Clone, Eq, PartialEq, Copy)]
+pub enum Word {
+    /// non-blanks characters
+    Big,
+    /// alphanumeric characters
+    Emacs,
+    /// alphanumeric (and '_') characters
+    Vi,
+}
+
+/// Where to move with respect to word boundary
+#[derive(Debug, Clone, Eq, PartialEq, Copy)]
+pub enum At {
+    /// Start of word.
+    Start,
+    /// Before end of word.
+    BeforeEnd,
+    /// After end of word.
+    AfterEnd,
+}
+
+/// Where to paste (relative to cursor position)
+#[derive(Debug, Clone, Eq, PartialEq, Copy)]
+pub enum Anchor {
+    /// After cursor
+    After,
+    /// Before cursor
+    Before,
+}
+
+/// character search
+#[derive(Debug, Clone, Eq, PartialEq, Copy)]
+pub enum CharSearch {
+    /// Forward search
+    Forward(char),
+    /// Forward search until
+    ForwardBefore(char),
+    /// Backward search
+    Backward(char),
+    /// Backward search until
+    BackwardAfter(char),
+}
+
+impl CharSearch {
+    pub(crate) const fn opposite(self) -> Self {
+        match self {
+            CharSearch::Forward(c) => CharSearch::Backward(c),
+            CharSearch::ForwardBefore(c) => CharSearch::BackwardAfter(c),
+            CharSearch::Backward(c) => CharSearch::Forward(c),
+            CharSearch::BackwardAfter(c) => CharSearch::ForwardBefore(c),
+        }
+    }
+}
+
+/// Where to move
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Movement {
+    /// Whole current line (not really a movement but a range)
+    WholeLine,
+    /// beginning-of-line
+    BeginningOfLine,
+    /// end-of-line
+    EndOfLine,
+    /// backward-word, vi-prev-word
+    BackwardWord(RepeatCount, Word), // Backward until start of word
+    /// forward-word, vi-end-word, vi-next-word
+    ForwardWord(RepeatCount, At, Word), // Forward until start/end of word
+    /// character-search, character-search-backward, vi-char-search
+    ViCharSearch(RepeatCount, CharSearch),
+    /// vi-first-print
+    ViFirstPrint,
+    /// backward-char
+    BackwardChar(RepeatCount),
+    /// forward-char
+    ForwardChar(RepeatCount),
+    /// move to the same column on the previous line
+    LineUp(RepeatCount),
+    /// move to the same column on the next line
+    LineDown(RepeatCount),
+    /// Whole user input (not really a movement but a range)
+    WholeBuffer,
+    /// beginning-of-buffer
+    BeginningOfBuffer,
+    /// end-of-buffer
+    EndOfBuffer,
+}
+
+impl Movement {
+    // Replay this movement with a possible different `RepeatCount`.
+    pub(crate) const fn redo(&self, new: Option<RepeatCount>) -> Self {
+        match *self {
+            Movement::WholeLine => Movement::WholeLine,
+            Movement::BeginningOfLine => Movement::BeginningOfLine,
+            Movement::ViFirstPrint => Movement::ViFirstPrint,
+            Movement::EndOfLine => Movement::EndOfLine,
+            Movement::BackwardWord(previous, word) => {
+                Movement::BackwardWord(repeat_count(previous, new), word)
+            }
+            Movement::ForwardWord(previous, at, word) => {
+                Movement::ForwardWord(repeat_count(previous, new), at, word)
+            }
+            Movement::ViCharSearch(previous, char_search) => {
+                Movement::ViCharSearch(repeat_count(previous, new), char_search)
+            }
+            Movement::BackwardChar(previous) => Movement::BackwardChar(repeat_count(previous, new)),
+            Movement::ForwardChar(previous) => Movement::ForwardChar(repeat_count(previous, new)),
+            Movement::LineUp(previous) => Movement::LineUp(repeat_count(previous, new)),
+            Movement::LineDown(previous) => Movement::LineDown(repeat_count(previous, new)),
+            Movement::WholeBuffer => Movement::WholeBuffer,
+            Movement::BeginningOfBuffer => Movement::BeginningOfBuffer,
+            Movement::EndOfBuffer => Movement::EndOfBuffer,
+        }
+    }
+}
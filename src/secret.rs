@@ -0,0 +1,315 @@
+//! Secret (password) input
+use std::fmt;
+use std::io::{self, BufRead};
+use std::ops::Deref;
+use std::ptr;
+use std::str;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+use crate::error::ReadlineError;
+use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
+use crate::tty::{RawReader, Renderer};
+use crate::{Cmd, Result};
+
+/// String whose heap allocation is zeroed when dropped.
+///
+/// Returned by [`Editor::readline_no_echo`](crate::Editor::readline_no_echo)
+/// and [`Editor::readline_masked`](crate::Editor::readline_masked).
+pub struct SecretString(String);
+
+impl SecretString {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self(String::with_capacity(capacity))
+    }
+
+    /// Extracts a string slice containing the secret.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Append `ch` without leaving a copy of the secret in a freed
+    /// allocation.
+    pub(crate) fn push(&mut self, ch: char) {
+        self.reserve(ch.len_utf8());
+        self.0.push(ch);
+    }
+
+    /// Append `s` without leaving a copy of the secret in a freed
+    /// allocation.
+    pub(crate) fn push_str(&mut self, s: &str) {
+        self.reserve(s.len());
+        self.0.push_str(s);
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<char> {
+        // removed bytes stay in the spare capacity which is zeroed on drop
+        self.0.pop()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub(crate) fn truncate(&mut self, new_len: usize) {
+        self.0.truncate(new_len);
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        let len = self.0.len();
+        if len + additional <= self.0.capacity() {
+            return;
+        }
+        let capacity = std::cmp::max(2 * self.0.capacity(), len + additional);
+        let mut grown = String::with_capacity(capacity);
+        grown.push_str(&self.0);
+        // the old allocation is zeroed when `old` is dropped
+        let _old = Self(std::mem::replace(&mut self.0, grown));
+    }
+}
+
+impl Deref for SecretString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(***)")
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        // Safety: only NUL bytes are written, which is valid UTF-8
+        let bytes = unsafe { self.0.as_mut_vec() };
+        for b in bytes.iter_mut() {
+            unsafe { ptr::write_volatile(b, 0) };
+        }
+        for b in bytes.spare_capacity_mut() {
+            unsafe { ptr::write_volatile(b.as_mut_ptr(), 0) };
+        }
+        bytes.clear();
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// Read a line without echoing nor storing anything but the secret itself.
+///
+/// When `mask` is given, it is displayed once for each character typed.
+pub(crate) fn read_secret<R: RawReader, W: Renderer>(
+    rdr: &mut R,
+    out: &mut W,
+    mask: Option<char>,
+) -> Result<SecretString> {
+    let mut secret = SecretString::with_capacity(64);
+    loop {
+        let key = rdr.next_key(false)?;
+        if let Some(Cmd::Interrupt) = rdr.find_binding(&key) {
+            return Err(ReadlineError::Interrupted);
+        }
+        match key {
+            E(K::Char(c), M::NONE | M::SHIFT) => {
+                secret.push(c);
+                echo_mask(out, mask, 1)?;
+            }
+            E::ENTER | E(K::Char('J' | 'M'), M::CTRL) => break,
+            E::BACKSPACE | E(K::Char('H'), M::CTRL) if !secret.is_empty() => {
+                secret.pop();
+                erase_mask(out, mask, 1)?;
+            }
+            E(K::Char('U'), M::CTRL) => {
+                erase_mask(out, mask, secret.chars().count())?;
+                secret.clear();
+            }
+            E(K::Char('C'), M::CTRL) => return Err(ReadlineError::Interrupted),
+            E(K::Char('D'), M::CTRL) if secret.is_empty() => return Err(ReadlineError::Eof),
+            E(K::BracketedPasteStart, M::NONE) => {
+                let paste = SecretString(rdr.read_pasted_text()?);
+                secret.push_str(&paste);
+                echo_mask(out, mask, paste.chars().count())?;
+            }
+            _ => {}
+        }
+    }
+    Ok(secret)
+}
+
+/// Read a line from `reader` (not a terminal), without its line terminator.
+///
+/// The line is copied from the reader buffer in chunks so that the secret is
+/// never left in a freed allocation.
+pub(crate) fn read_secret_line<R: BufRead + ?Sized>(
+    reader: &mut R,
+    capacity: usize,
+) -> Result<SecretString> {
+    let mut secret = SecretString::with_capacity(capacity);
+    // bytes of a character split between two chunks
+    let mut pending = [0u8; 4];
+    let read = read_line_chunks(reader, &mut secret, &mut pending);
+    for b in pending.iter_mut() {
+        unsafe { ptr::write_volatile(b, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+    if !read? {
+        return Err(ReadlineError::Eof);
+    }
+    let len = secret.trim_end_matches('\r').len();
+    secret.truncate(len);
+    Ok(secret)
+}
+
+/// Return `false` when there is nothing to read.
+fn read_line_chunks<R: BufRead + ?Sized>(
+    reader: &mut R,
+    secret: &mut SecretString,
+    pending: &mut [u8; 4],
+) -> Result<bool> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        )
+    };
+    let mut pending_len = 0;
+    let mut read = false;
+    loop {
+        let buf = match reader.fill_buf() {
+            Ok(buf) => buf,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if buf.is_empty() {
+            break;
+        }
+        read = true;
+        let (mut chunk, eol) = match memchr::memchr(b'\n', buf) {
+            Some(i) => (&buf[..i], true),
+            None => (buf, false),
+        };
+        let used = chunk.len() + usize::from(eol);
+        // complete the character split by the previous chunk
+        while pending_len > 0 && !chunk.is_empty() {
+            pending[pending_len] = chunk[0];
+            pending_len += 1;
+            chunk = &chunk[1..];
+            match str::from_utf8(&pending[..pending_len]) {
+                Ok(s) => {
+                    secret.push_str(s);
+                    pending_len = 0;
+                }
+                Err(e) if e.error_len().is_none() && pending_len < pending.len() => {}
+                Err(_) => return Err(invalid().into()),
+            }
+        }
+        match str::from_utf8(chunk) {
+            Ok(s) => secret.push_str(s),
+            Err(e) if e.error_len().is_none() => {
+                let (valid, tail) = chunk.split_at(e.valid_up_to());
+                secret.push_str(str::from_utf8(valid).unwrap());
+                pending[..tail.len()].copy_from_slice(tail);
+                pending_len = tail.len();
+            }
+            Err(_) => return Err(invalid().into()),
+        }
+        reader.consume(used);
+        if eol {
+            break;
+        }
+    }
+    if pending_len > 0 {
+        return Err(invalid().into());
+    }
+    Ok(read)
+}
+
+fn echo_mask<W: Renderer>(out: &mut W, mask: Option<char>, n: usize) -> Result<()> {
+    match mask {
+        Some(mask) if n > 0 => out.write_and_flush(&mask.to_string().repeat(n)),
+        _ => Ok(()),
+    }
+}
+
+fn erase_mask<W: Renderer>(out: &mut W, mask: Option<char>, n: usize) -> Result<()> {
+    match mask {
+        Some(_) if n > 0 => out.write_and_flush(&"\x08 \x08".repeat(n)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_secret, read_secret_line, SecretString};
+    use crate::error::ReadlineError;
+    use crate::keys::KeyEvent as E;
+    use crate::tty::Sink;
+
+    #[test]
+    fn grow() {
+        let mut secret = SecretString::with_capacity(1);
+        secret.push('a');
+        secret.push_str("bcd");
+        secret.push('é');
+        assert_eq!("abcdé", secret.as_str());
+        assert_eq!("SecretString(***)", format!("{:?}", secret));
+    }
+
+    #[test]
+    fn read_line() {
+        use std::io::BufReader;
+
+        let input = "pass\u{e9}\r\nnext\n".as_bytes();
+        // chunks smaller than a character
+        let mut reader = BufReader::with_capacity(1, input);
+        let secret = read_secret_line(&mut reader, 2).unwrap();
+        assert_eq!("pass\u{e9}", secret.as_str());
+        assert_eq!("next", read_secret_line(&mut reader, 2).unwrap().as_str());
+        assert_matches!(read_secret_line(&mut reader, 2), Err(ReadlineError::Eof));
+
+        let mut reader = BufReader::with_capacity(1, &b"a\xe9\n"[..]);
+        assert_matches!(read_secret_line(&mut reader, 2), Err(ReadlineError::Io(_)));
+    }
+
+    #[test]
+    fn read() {
+        let keys = vec![
+            E::from('a'),
+            E::from('x'),
+            E::BACKSPACE,
+            E::from('b'),
+            E::ENTER,
+        ];
+        let secret = read_secret(&mut keys.into_iter(), &mut Sink::default(), None).unwrap();
+        assert_eq!("ab", secret.as_str());
+
+        let keys = vec![E::from('a'), E::ctrl('U'), E::from('b'), E::ENTER];
+        let secret = read_secret(&mut keys.into_iter(), &mut Sink::default(), None).unwrap();
+        assert_eq!("b", secret.as_str());
+
+        let keys = vec![E::from('a'), E::ctrl('C')];
+        let err = read_secret(&mut keys.into_iter(), &mut Sink::default(), None);
+        assert_matches!(err, Err(ReadlineError::Interrupted));
+
+        let keys = vec![E::ctrl('D')];
+        let err = read_secret(&mut keys.into_iter(), &mut Sink::default(), None);
+        assert_matches!(err, Err(ReadlineError::Eof));
+    }
+
+    #[test]
+    fn mask() {
+        let keys = vec![
+            E::from('a'),
+            E::from('b'),
+            E::BACKSPACE,
+            E::from('c'),
+            E::ENTER,
+        ];
+        let mut out = Sink::default();
+        let secret = read_secret(&mut keys.into_iter(), &mut out, Some('*')).unwrap();
+        assert_eq!("ac", secret.as_str());
+        assert_eq!("**\x08 \x08*", out.written);
+    }
+}
@@ -0,0 +1,30 @@
+//! Editor state kept across invocations
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "with-timestamps")]
+use std::time::SystemTime;
+
+use crate::config::{BellStyle, EditMode};
+
+/// History and settings of an [`Editor`](crate::Editor), see
+/// [`Editor::save_state`](crate::Editor::save_state).
+///
+/// With the `serde` feature, it can be persisted in any format supported by
+/// `serde` instead of a history file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EditorState {
+    /// History entries, oldest first
+    pub history: Vec<String>,
+    /// Maximum number of history entries
+    pub max_history_size: usize,
+    /// Emacs or Vi mode
+    pub edit_mode: EditMode,
+    /// Beep or flash or nothing
+    pub bell_style: BellStyle,
+    /// When each history entry was added, if known
+    #[cfg(feature = "with-timestamps")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub timestamps: Vec<Option<SystemTime>>,
+}
@@ -0,0 +1,209 @@
+//! Text styles rendered as ANSI escape sequences, used by the highlighters of
+//! the [`highlight`](crate::highlight) module
+use std::env;
+
+/// Foreground or background color
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    /// One of the 16 standard colors (0-7 normal, 8-15 bright)
+    ///
+    /// Only the 4 lowest bits are used: `Basic(17)` is the same as
+    /// `Basic(1)`.
+    Basic(u8),
+    /// One of the 256 colors of the xterm palette
+    Ansi256(u8),
+    /// True color, approximated with the 256 colors palette when the
+    /// terminal does not support it
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    fn push_sgr(self, params: &mut Vec<String>, background: bool, truecolor: bool) {
+        let base = if background { 40 } else { 30 };
+        match self {
+            Color::Basic(n) if n & 0x08 == 0 => {
+                params.push((base + u16::from(n & 0x07)).to_string());
+            }
+            Color::Basic(n) => params.push((base + 60 + u16::from(n & 0x07)).to_string()),
+            Color::Ansi256(n) => params.push(format!("{};5;{}", base + 8, n)),
+            Color::Rgb(r, g, b) if truecolor => {
+                params.push(format!("{};2;{};{};{}", base + 8, r, g, b));
+            }
+            Color::Rgb(r, g, b) => {
+                params.push(format!("{};5;{}", base + 8, rgb_to_ansi256(r, g, b)));
+            }
+        }
+    }
+}
+
+/// Closest color in the 6x6x6 cube of the 256 colors palette
+fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    fn level(v: u8) -> u8 {
+        if v < 48 {
+            0
+        } else if v < 115 {
+            1
+        } else {
+            (v - 35) / 40
+        }
+    }
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+/// Check if the terminal supports 24-bit colors (`$COLORTERM` contains
+/// `truecolor` or `24bit`).
+#[must_use]
+pub fn is_truecolor_supported() -> bool {
+    match env::var("COLORTERM") {
+        Ok(v) => v.contains("truecolor") || v.contains("24bit"),
+        Err(_) => false,
+    }
+}
+
+/// Colors and attributes of a piece of text
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    /// Foreground color
+    pub fg: Option<Color>,
+    /// Background color
+    pub bg: Option<Color>,
+    /// Bold
+    pub bold: bool,
+    /// Dimmed
+    pub dim: bool,
+    /// Italic
+    pub italic: bool,
+    /// Underline
+    pub underline: bool,
+    /// Strikethrough
+    pub strikethrough: bool,
+}
+
+impl Style {
+    /// Escape sequence put before the styled text.
+    ///
+    /// RGB colors are emitted as is only when
+    /// [`is_truecolor_supported`] returns `true`.
+    /// An empty string is returned for the default style.
+    #[must_use]
+    pub fn ansi_prefix(&self) -> String {
+        self.prefix(is_truecolor_supported())
+    }
+
+    /// Escape sequence put after the styled text
+    #[must_use]
+    pub fn ansi_suffix() -> &'static str {
+        "\x1b[0m"
+    }
+
+    fn prefix(&self, truecolor: bool) -> String {
+        let mut params = Vec::new();
+        if self.bold {
+            params.push("1".to_owned());
+        }
+        if self.dim {
+            params.push("2".to_owned());
+        }
+        if self.italic {
+            params.push("3".to_owned());
+        }
+        if self.underline {
+            params.push("4".to_owned());
+        }
+        if self.strikethrough {
+            params.push("9".to_owned());
+        }
+        if let Some(fg) = self.fg {
+            fg.push_sgr(&mut params, false, truecolor);
+        }
+        if let Some(bg) = self.bg {
+            bg.push_sgr(&mut params, true, truecolor);
+        }
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("\x1b[{}m", params.join(";"))
+        }
+    }
+}
+
+impl Style {
+    /// Append `text` with this style to `buf`.
+    pub(crate) fn paint(&self, text: &str, buf: &mut String) {
+        let prefix = self.ansi_prefix();
+        if prefix.is_empty() {
+            buf.push_str(text);
+        } else {
+            buf.push_str(&prefix);
+            buf.push_str(text);
+            buf.push_str(Self::ansi_suffix());
+        }
+    }
+}
+
+impl From<Color> for Style {
+    /// Style with only a foreground color
+    fn from(color: Color) -> Self {
+        Self {
+            fg: Some(color),
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Color, Style};
+
+    #[test]
+    fn ansi_prefix() {
+        assert_eq!("", Style::default().prefix(true));
+        let style = Style {
+            fg: Some(Color::Basic(1)),
+            bold: true,
+            ..Style::default()
+        };
+        assert_eq!("\x1b[1;31m", style.prefix(false));
+        let style = Style {
+            fg: Some(Color::Basic(9)),
+            bg: Some(Color::Basic(4)),
+            italic: true,
+            underline: true,
+            strikethrough: true,
+            ..Style::default()
+        };
+        assert_eq!("\x1b[3;4;9;91;44m", style.prefix(false));
+        let style = Style {
+            fg: Some(Color::Ansi256(208)),
+            bg: Some(Color::Basic(12)),
+            ..Style::default()
+        };
+        assert_eq!("\x1b[38;5;208;104m", style.prefix(false));
+        assert_eq!("\x1b[0m", Style::ansi_suffix());
+        // only the 4 lowest bits of a basic color are used
+        assert_eq!(
+            Style::from(Color::Basic(1)).prefix(false),
+            Style::from(Color::Basic(17)).prefix(false)
+        );
+        assert_eq!(
+            "\x1b[2;95m",
+            Style {
+                dim: true,
+                ..Style::from(Color::Basic(29))
+            }
+            .prefix(false)
+        );
+    }
+
+    #[test]
+    fn rgb() {
+        let style = Style {
+            fg: Some(Color::Rgb(255, 128, 0)),
+            bg: Some(Color::Rgb(0, 0, 0)),
+            ..Style::default()
+        };
+        assert_eq!("\x1b[38;2;255;128;0;48;2;0;0;0m", style.prefix(true));
+        // fallback to the 256 colors palette
+        assert_eq!("\x1b[38;5;208;48;5;16m", style.prefix(false));
+    }
+}
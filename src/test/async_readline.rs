@@ -0,0 +1,72 @@
+//! `Editor::readline_async` tests
+use std::future::Future;
+
+use tokio::io::{duplex, BufReader};
+
+use super::init_editor;
+use crate::config::EditMode;
+use crate::error::ReadlineError;
+use crate::keys::KeyEvent as E;
+use crate::CancellationToken;
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn pending_keys() {
+    let mut editor = init_editor(EditMode::Emacs, &[E::from('a'), E::ENTER]);
+    // nothing to read: the keys already received are enough
+    let (rd, _wr) = duplex(64);
+    let mut input = BufReader::new(rd);
+    let cancel = CancellationToken::new();
+    let line = block_on(editor.readline_from("", &mut input, &cancel));
+    assert_eq!("a", line.unwrap());
+}
+
+#[test]
+fn cancel_while_waiting() {
+    let mut editor = init_editor(EditMode::Emacs, &[E::from('a')]);
+    let (rd, _wr) = duplex(64);
+    let mut input = BufReader::new(rd);
+    let cancel = CancellationToken::new();
+    let (line, _) = block_on(async {
+        tokio::join!(editor.readline_from("", &mut input, &cancel), async {
+            cancel.cancel()
+        })
+    });
+    assert_matches!(line, Err(ReadlineError::Interrupted));
+}
+
+#[test]
+fn end_of_input() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    let mut input = BufReader::new(&b""[..]);
+    let cancel = CancellationToken::new();
+    let line = block_on(editor.readline_from("", &mut input, &cancel));
+    assert_matches!(line, Err(ReadlineError::Eof));
+}
+
+#[test]
+fn not_a_terminal() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    editor.term.unsupported = true;
+    // lines read ahead are kept by `input` for the next call
+    let mut input = BufReader::new(&b"line1\nline2"[..]);
+    let cancel = CancellationToken::new();
+    let line = block_on(editor.readline_from("", &mut input, &cancel));
+    assert_eq!("line1", line.unwrap());
+    let line = block_on(editor.readline_from("", &mut input, &cancel));
+    assert_eq!("line2", line.unwrap());
+    let line = block_on(editor.readline_from("", &mut input, &cancel));
+    assert_matches!(line, Err(ReadlineError::Eof));
+
+    let (rd, _wr) = duplex(64);
+    let mut input = BufReader::new(rd);
+    cancel.cancel();
+    let line = block_on(editor.readline_from("", &mut input, &cancel));
+    assert_matches!(line, Err(ReadlineError::Interrupted));
+}
@@ -306,9 +306,100 @@ fn ctrl_w() {
     }
 }
 
+#[test]
+fn newline() {
+    for mode in &[EditMode::Emacs, EditMode::Vi] {
+        assert_line(
+            *mode,
+            &[E::from('a'), E(K::Enter, M::ALT), E::from('b'), E::ENTER],
+            "a\nb",
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "custom-bindings")]
+fn newline_binding() {
+    use crate::Cmd;
+    let mut editor = init_editor(
+        EditMode::Emacs,
+        &[E::from('a'), E::ctrl('J'), E::from('b'), E::ENTER],
+    );
+    editor.bind_key(E::ctrl('J'), Cmd::Newline);
+    assert_eq!("a\nb", editor.readline(">>").unwrap());
+}
+
+#[test]
+fn auto_close_brackets() {
+    use crate::config::Configurer;
+    for mode in &[EditMode::Emacs, EditMode::Vi] {
+        let read = |keys: &[E]| {
+            let mut editor = init_editor(*mode, keys);
+            editor.set_auto_close_brackets(true);
+            let line = editor.readline_with_initial("", ("", "")).unwrap();
+            (line, editor.term.cursor)
+        };
+        // insert pair
+        assert_eq!(
+            ("f()".to_owned(), 2),
+            read(&[E::from('f'), E::from('('), E::ENTER])
+        );
+        // skip over close
+        assert_eq!(
+            ("[a]b".to_owned(), 4),
+            read(&[
+                E::from('['),
+                E::from('a'),
+                E::from(']'),
+                E::from('b'),
+                E::ENTER
+            ])
+        );
+        // backspace deletes pair
+        assert_eq!(
+            ("x".to_owned(), 1),
+            read(&[
+                E::from('x'),
+                E::from('{'),
+                E::from('a'),
+                E::BACKSPACE,
+                E::BACKSPACE,
+                E::ENTER
+            ])
+        );
+        // nested pairs
+        assert_eq!(
+            ("(())".to_owned(), 4),
+            read(&[
+                E::from('('),
+                E::from('('),
+                E::from(')'),
+                E::from(')'),
+                E::ENTER
+            ])
+        );
+        // only the inserted closing bracket is moved over
+        let mut editor = init_editor(*mode, &[E::from(')'), E::ENTER]);
+        editor.set_auto_close_brackets(true);
+        let line = editor.readline_with_initial("", ("f(", ")")).unwrap();
+        assert_eq!(("f())", 3), (line.as_str(), editor.term.cursor));
+        // not after the cursor has moved away
+        assert_eq!(
+            ("())".to_owned(), 2),
+            read(&[
+                E::from('('),
+                E(K::Left, M::NONE),
+                E(K::Right, M::NONE),
+                E::from(')'),
+                E::ENTER
+            ])
+        );
+    }
+}
+
 #[test]
 fn ctrl_y() {
-    for mode in &[EditMode::Emacs /* FIXME, EditMode::Vi */] {
+    for mode in &[EditMode::Emacs, EditMode::Vi] {
         assert_cursor(
             *mode,
             ("Hello, ", "world"),
@@ -327,6 +418,13 @@ fn ctrl__() {
             &[E::ctrl('W'), E::ctrl('_'), E::ENTER],
             ("Hello, ", "world"),
         );
+        // initial text cannot be undone
+        assert_cursor(
+            *mode,
+            ("Hello, ", "world"),
+            &[E::ctrl('_'), E::ENTER],
+            ("Hello, ", "world"),
+        );
         if *mode == EditMode::Vi {
             // vi command mode
             assert_cursor(
@@ -338,3 +436,83 @@ fn ctrl__() {
         }
     }
 }
+
+#[test]
+fn undo_kill() {
+    for mode in &[EditMode::Emacs, EditMode::Vi] {
+        let mut keys: Vec<E> = "hello world".chars().map(E::from).collect();
+        // kill word, undo the kill, undo the insertion of " world"
+        keys.extend(&[E::ctrl('W'), E::ctrl('_'), E::ctrl('_'), E::ENTER]);
+        assert_line(*mode, &keys, "hello");
+    }
+}
+
+#[test]
+fn bracketed_paste() {
+    for mode in &[EditMode::Emacs, EditMode::Vi] {
+        assert_cursor(
+            *mode,
+            ("Hello, ", ""),
+            &[
+                E(K::BracketedPasteStart, M::NONE),
+                E::from('w'),
+                E::ENTER,
+                E(K::Char('D'), M::NONE),
+                E::ctrl('C'),
+                E(K::BracketedPasteEnd, M::NONE),
+                E::ENTER,
+            ],
+            ("Hello, w\nD", ""),
+        );
+    }
+}
+
+#[test]
+fn no_echo() {
+    let mut editor = init_editor(EditMode::Emacs, &[E::from('a'), E::from('b'), E::ENTER]);
+    let secret = editor.readline_no_echo(">>").unwrap();
+    assert_eq!("ab", secret.as_str());
+    assert!(editor.history().is_empty());
+
+    let mut editor = init_editor(EditMode::Emacs, &[E::from('a'), E::ctrl('C')]);
+    let err = editor.readline_no_echo(">>");
+    assert_matches!(err, Err(ReadlineError::Interrupted));
+}
+
+#[test]
+fn masked() {
+    let mut editor = init_editor(
+        EditMode::Emacs,
+        &[E::from('a'), E::from('b'), E::BACKSPACE, E::ENTER],
+    );
+    let secret = editor.readline_masked(">>", '*').unwrap();
+    assert_eq!("a", secret.as_str());
+    assert!(editor.history().is_empty());
+}
+
+#[test]
+fn up_down_in_wrapped_line() {
+    // 90 columns, wrapped on two rows by the 80 columns test terminal
+    let line = "0123456789".repeat(9);
+    for mode in &[EditMode::Emacs, EditMode::Vi] {
+        assert_line_with_initial(
+            *mode,
+            (&line, ""),
+            &[E(K::Up, M::NONE), E::from('x'), E::ENTER],
+            &format!("{}x{}", &line[..10], &line[10..]),
+        );
+        assert_line_with_initial(
+            *mode,
+            (&line[..5], &line[5..]),
+            &[E(K::Down, M::NONE), E::from('x'), E::ENTER],
+            &format!("{}x{}", &line[..85], &line[85..]),
+        );
+        // history is only browsed from the first / last row
+        let mut editor = init_editor(*mode, &[E(K::Up, M::NONE), E(K::Up, M::NONE), E::ENTER]);
+        editor.add_history_entry("entry");
+        assert_eq!(
+            "entry",
+            editor.readline_with_initial("", (&line, "")).unwrap()
+        );
+    }
+}
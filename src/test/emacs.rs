@@ -370,6 +370,38 @@ fn meta_d() {
     );
 }
 
+#[test]
+fn ctrl_delete() {
+    assert_cursor(
+        EditMode::Emacs,
+        ("Hello", ", world!"),
+        &[E(K::Delete, M::CTRL), E::ENTER],
+        ("Hello", "!"),
+    );
+    assert_cursor(
+        EditMode::Emacs,
+        ("Hello", ", world!"),
+        &[E::alt('2'), E(K::Delete, M::CTRL), E::ENTER],
+        ("Hello", ""),
+    );
+}
+
+#[test]
+fn ctrl_left_right() {
+    assert_cursor(
+        EditMode::Emacs,
+        ("Hello, world!", ""),
+        &[E(K::Left, M::CTRL), E::ENTER],
+        ("Hello, ", "world!"),
+    );
+    assert_cursor(
+        EditMode::Emacs,
+        ("", "Hello, world!"),
+        &[E(K::Right, M::CTRL), E(K::Right, M::CTRL), E::ENTER],
+        ("Hello, world", "!"),
+    );
+}
+
 #[test]
 fn meta_t() {
     assert_cursor(
@@ -403,6 +435,69 @@ fn meta_y() {
     );
 }
 
+#[test]
+fn meta_y_cycle() {
+    // three kills, a yank then yank-pops going back in the ring
+    let yank_pop = |pops: usize, expected: &str| {
+        let mut keys = vec![
+            E::ctrl('W'),
+            E(K::Left, M::NONE),
+            E::ctrl('W'),
+            E(K::Left, M::NONE),
+            E::ctrl('W'),
+            E::ctrl('E'),
+            E::ctrl('Y'),
+        ];
+        for _ in 0..pops {
+            keys.push(E::alt('Y'));
+        }
+        keys.push(E::ENTER);
+        assert_cursor(
+            EditMode::Emacs,
+            ("one two three", ""),
+            &keys,
+            (expected, ""),
+        );
+    };
+    yank_pop(0, "  one");
+    yank_pop(1, "  two");
+    yank_pop(2, "  three");
+    // the ring wraps around
+    yank_pop(3, "  one");
+    // with a count, the whole yanked text is replaced
+    assert_cursor(
+        EditMode::Emacs,
+        ("a b", ""),
+        &[
+            E::ctrl('W'),
+            E(K::Left, M::NONE),
+            E::ctrl('E'),
+            E::ctrl('W'),
+            E::alt('2'),
+            E::ctrl('Y'),
+            E::alt('Y'),
+            E::ENTER,
+        ],
+        ("b", ""),
+    );
+    // yank-pop only applies right after a yank
+    assert_cursor(
+        EditMode::Emacs,
+        ("a b", ""),
+        &[
+            E::ctrl('W'),
+            E(K::Left, M::NONE),
+            E::ctrl('E'),
+            E::ctrl('W'),
+            E::ctrl('Y'),
+            E::from('x'),
+            E::alt('Y'),
+            E::ENTER,
+        ],
+        ("a x", ""),
+    );
+}
+
 #[test]
 fn meta_backspace() {
     assert_cursor(
@@ -421,4 +516,60 @@ fn meta_digit() {
         &[E::alt('3'), E::from('h'), E::ENTER],
         ("hhh", ""),
     );
+    assert_cursor(
+        EditMode::Emacs,
+        ("", "abcdef"),
+        &[E::alt('3'), E::ctrl('F'), E::ENTER],
+        ("abc", "def"),
+    );
+    // digits following the first one extend the argument
+    assert_cursor(
+        EditMode::Emacs,
+        ("", "abcdefghijklmn"),
+        &[E::alt('1'), E::from('2'), E::ctrl('F'), E::ENTER],
+        ("abcdefghijkl", "mn"),
+    );
+    // the argument is cleared after the next command
+    assert_cursor(
+        EditMode::Emacs,
+        ("", "abcdef"),
+        &[E::alt('2'), E::ctrl('F'), E::ctrl('F'), E::ENTER],
+        ("abc", "def"),
+    );
+}
+
+#[test]
+fn meta_digit_kill() {
+    // deleted chars go to the kill ring
+    assert_cursor(
+        EditMode::Emacs,
+        ("", "abcdef"),
+        &[
+            E::alt('3'),
+            E::ctrl('D'),
+            E::ctrl('E'),
+            E::ctrl('Y'),
+            E::ENTER,
+        ],
+        ("defabc", ""),
+    );
+    // negative argument
+    assert_cursor(
+        EditMode::Emacs,
+        ("abcdef", ""),
+        &[E::alt('-'), E::alt('3'), E::ctrl('D'), E::ENTER],
+        ("abc", ""),
+    );
+    assert_cursor(
+        EditMode::Emacs,
+        ("ab", "cd"),
+        &[E::alt('-'), E::ctrl('K'), E::ENTER],
+        ("", "cd"),
+    );
+    assert_cursor(
+        EditMode::Emacs,
+        ("ab", "cd"),
+        &[E::alt('-'), E::ctrl('U'), E::ENTER],
+        ("ab", ""),
+    );
 }
@@ -1,7 +1,12 @@
 //! History related commands tests
-use super::assert_history;
-use crate::config::EditMode;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use super::{assert_history, init_editor};
+use crate::config::{Config, Configurer, EditMode};
+use crate::history::{HistoryBackend, SearchDirection, SearchResult};
 use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
+use crate::{Editor, Result};
 
 #[test]
 fn down_key() {
@@ -146,13 +151,13 @@ fn ctrl_r() {
             ("car", "go"),
         );
         assert_history(
-            EditMode::Emacs,
+            *mode,
             &["rustc", "cargo"],
             &[
                 E::from('a'),
                 E::ctrl('R'),
                 E::from('r'),
-                E::ctrl('G'), // abort (FIXME: doesn't work with vi mode)
+                E::ctrl('G'), // abort
                 E::ENTER,
             ],
             "",
@@ -161,6 +166,71 @@ fn ctrl_r() {
     }
 }
 
+#[test]
+#[cfg(not(feature = "case_insensitive_history_search"))]
+fn ctrl_r_smart_case() {
+    for mode in &[EditMode::Emacs, EditMode::Vi] {
+        // lowercase query: case-insensitive
+        assert_history(
+            *mode,
+            &["Cargo build", "cargo test"],
+            &[
+                E::ctrl('R'),
+                E::from('b'),
+                E::from('u'),
+                E(K::Right, M::NONE), // just to assert cursor pos
+                E::ENTER,
+            ],
+            "",
+            ("Cargo b", "uild"),
+        );
+        assert_history(
+            *mode,
+            &["Cargo build", "cargo test"],
+            &[
+                E::ctrl('R'),
+                E::from('c'),
+                E(K::Right, M::NONE), // just to assert cursor pos
+                E::ENTER,
+            ],
+            "",
+            ("c", "argo test"),
+        );
+        // mixed-case query: case-sensitive
+        assert_history(
+            *mode,
+            &["Cargo build", "cargo test"],
+            &[
+                E::ctrl('R'),
+                E::from('C'),
+                E(K::Right, M::NONE), // just to assert cursor pos
+                E::ENTER,
+            ],
+            "",
+            ("C", "argo build"),
+        );
+    }
+}
+
+#[test]
+fn ctrl_r_search_case() {
+    use super::init_editor;
+    use crate::config::{Configurer, SearchCase};
+
+    let keys = [E::ctrl('R'), E::from('c'), E::from('a'), E::ENTER];
+    let mut editor = init_editor(EditMode::Emacs, &keys);
+    editor.set_search_case(SearchCase::Sensitive);
+    editor.history.add("cargo test");
+    editor.history.add("Cat");
+    assert_eq!("cargo test", editor.readline("").unwrap());
+
+    let mut editor = init_editor(EditMode::Emacs, &keys);
+    editor.set_search_case(SearchCase::Insensitive);
+    editor.history.add("cargo test");
+    editor.history.add("Cat");
+    assert_eq!("Cat", editor.readline("").unwrap());
+}
+
 #[test]
 fn ctrl_r_with_long_prompt() {
     for mode in &[EditMode::Emacs, EditMode::Vi] {
@@ -194,6 +264,63 @@ fn ctrl_s() {
     }
 }
 
+#[test]
+fn forward_incremental_search() {
+    for mode in &[EditMode::Emacs, EditMode::Vi] {
+        // from the browsed entry toward the newest one
+        assert_history(
+            *mode,
+            &["rustc", "cargo", "rustup"],
+            &[
+                E(K::Up, M::NONE),
+                E(K::Up, M::NONE),
+                E(K::Up, M::NONE),
+                E::ctrl('S'),
+                E::from('r'),
+                E::from('u'),
+                E::ctrl('S'),
+                E(K::Right, M::NONE), // just to assert cursor pos
+                E::ENTER,
+            ],
+            "",
+            ("r", "ustup"),
+        );
+        // Ctrl-R reverses the direction
+        assert_history(
+            *mode,
+            &["rustc", "cargo", "rustup"],
+            &[
+                E(K::Up, M::NONE),
+                E(K::Up, M::NONE),
+                E(K::Up, M::NONE),
+                E::ctrl('S'),
+                E::from('r'),
+                E::from('u'),
+                E::ctrl('S'),
+                E::ctrl('R'),
+                E(K::Right, M::NONE),
+                E::ENTER,
+            ],
+            "",
+            ("r", "ustc"),
+        );
+        // when not browsing, only the newest entry is searched
+        assert_history(
+            *mode,
+            &["rustc", "cargo"],
+            &[
+                E::ctrl('S'),
+                E::from('r'),
+                E::ctrl('S'),
+                E(K::Right, M::NONE),
+                E::ENTER,
+            ],
+            "",
+            ("car", "go"),
+        );
+    }
+}
+
 #[test]
 fn meta_lt() {
     assert_history(
@@ -241,3 +368,207 @@ fn meta_gt() {
         ("a", ""),
     );
 }
+
+#[test]
+fn page_up() {
+    for mode in &[EditMode::Emacs, EditMode::Vi] {
+        assert_history(*mode, &[], &[E(K::PageUp, M::NONE), E::ENTER], "", ("", ""));
+        assert_history(
+            *mode,
+            &["line1", "other", "line2"],
+            &[
+                E::from('l'),
+                E(K::PageUp, M::NONE),
+                E(K::PageUp, M::NONE),
+                E::ENTER,
+            ],
+            "",
+            ("l", "ine1"),
+        );
+        assert_history(
+            *mode,
+            &["line1", "other"],
+            &[
+                E::from('x'),
+                E(K::PageUp, M::NONE), // no match
+                E::ENTER,
+            ],
+            "",
+            ("x", ""),
+        );
+    }
+}
+
+#[test]
+fn page_down() {
+    for mode in &[EditMode::Emacs, EditMode::Vi] {
+        assert_history(
+            *mode,
+            &["line1", "other", "line2"],
+            &[
+                E::from('l'),
+                E(K::PageUp, M::NONE),
+                E(K::PageUp, M::NONE),
+                E(K::PageDown, M::NONE),
+                E::ENTER,
+            ],
+            "",
+            ("l", "ine2"),
+        );
+        assert_history(
+            *mode,
+            &["line1", "other"],
+            &[
+                E::from('l'),
+                E(K::PageUp, M::NONE),
+                E(K::PageDown, M::NONE), // restore original line
+                E::ENTER,
+            ],
+            "",
+            ("l", ""),
+        );
+    }
+}
+
+#[test]
+fn clear_history() {
+    let mut editor = super::init_editor(EditMode::Emacs, &[]);
+    editor.add_history_entry("line1");
+    editor.add_history_entry("line2");
+    assert_eq!(2, editor.history().len());
+    editor.clear_history();
+    assert!(editor.history().is_empty());
+}
+
+#[test]
+fn history_ignore() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    editor.set_auto_add_history(true);
+    editor.set_history_ignore(vec!["pass".to_owned()]);
+    editor.term.keys = "su pass\n".chars().map(E::from).collect();
+    assert_eq!("su pass", editor.readline("> ").unwrap());
+    editor.term.keys = "ls\n".chars().map(E::from).collect();
+    assert_eq!("ls", editor.readline("> ").unwrap());
+    assert_eq!(1, editor.history().len());
+    assert_eq!(Some("ls"), editor.history().last());
+}
+
+#[test]
+fn operate_and_get_next() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    for line in &["cd src", "make", "make install"] {
+        editor.add_history_entry(*line);
+    }
+    editor.term.keys = vec![
+        E(K::Up, M::NONE),
+        E(K::Up, M::NONE),
+        E(K::Up, M::NONE),
+        E::ctrl('O'),
+    ];
+    assert_eq!("cd src", editor.readline("> ").unwrap());
+    // the entry following the accepted one is loaded
+    editor.term.keys = vec![E::ctrl('O')];
+    assert_eq!("make", editor.readline("> ").unwrap());
+    editor.term.keys = vec![E::ENTER];
+    assert_eq!("make install", editor.readline("> ").unwrap());
+    // back to an empty line
+    editor.term.keys = vec![E::ctrl('O')];
+    assert_eq!("", editor.readline("> ").unwrap());
+    editor.term.keys = vec![E::ENTER];
+    assert_eq!("", editor.readline("> ").unwrap());
+}
+
+/// In-memory backend recording the calls it receives
+struct MockBackend {
+    entries: Vec<String>,
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl HistoryBackend for MockBackend {
+    fn add(&mut self, line: String) -> bool {
+        self.calls.lock().unwrap().push(format!("add {}", line));
+        self.entries.push(line);
+        true
+    }
+
+    fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn search(
+        &self,
+        term: &str,
+        start: usize,
+        dir: SearchDirection,
+        _ignore_case: bool,
+    ) -> Option<SearchResult<'_>> {
+        self.calls.lock().unwrap().push(format!("search {}", term));
+        let idx = match dir {
+            SearchDirection::Reverse => self.entries[..=start]
+                .iter()
+                .rposition(|entry| entry.contains(term))?,
+            SearchDirection::Forward => {
+                start
+                    + self.entries[start..]
+                        .iter()
+                        .position(|entry| entry.contains(term))?
+            }
+        };
+        let entry = self.entries[idx].as_str();
+        Some(SearchResult {
+            entry,
+            idx,
+            pos: entry.find(term)?,
+        })
+    }
+
+    fn save(&mut self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    fn load(&mut self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn custom_backend() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let backend = MockBackend {
+        entries: Vec::new(),
+        calls: calls.clone(),
+    };
+    let config = Config::builder().auto_add_history(true).build();
+    let mut editor = Editor::<()>::with_history(config, backend).unwrap();
+    editor.add_history_entry("rustc");
+    editor.add_history_entry("cargo");
+    editor.term.keys = vec![E::ctrl('R'), E::from('u'), E::ENTER];
+    assert_eq!("rustc", editor.readline("> ").unwrap());
+    assert_eq!(
+        vec!["add rustc", "add cargo", "search u", "add rustc"],
+        *calls.lock().unwrap()
+    );
+    assert_eq!(3, editor.history().len());
+    assert_eq!(Some("rustc"), editor.history().last());
+}
+
+#[test]
+fn clone_custom_backend() {
+    let backend = MockBackend {
+        entries: Vec::new(),
+        calls: Arc::new(Mutex::new(Vec::new())),
+    };
+    let config = Config::builder().history_ignore_space(true).build();
+    let mut editor = Editor::<()>::with_history(config, backend).unwrap();
+    editor.add_history_entry("cargo");
+    // the copy is a `FileHistory` with the same settings
+    let mut copy = editor.history().clone();
+    assert_eq!(Some("cargo"), copy.last());
+    assert!(!copy.add(" secret"));
+    assert!(!copy.add("cargo"));
+    assert!(copy.add("rustc"));
+}
@@ -1,21 +1,53 @@
+use std::io;
 use std::vec::IntoIter;
 
 use crate::completion::Completer;
-use crate::config::{Config, EditMode};
+use crate::config::{
+    BellStyle, ColorMode, CompletionDisplayMode, CompletionType, Config, Configurer, EditMode,
+};
 use crate::edit::init_state;
-use crate::highlight::Highlighter;
+use crate::error::ReadlineError;
 use crate::hint::Hinter;
-use crate::keymap::{Bindings, Cmd, InputState};
+use crate::keymap::{Bindings, Cmd, InputState, Refresher};
 use crate::keys::{KeyCode as K, KeyEvent, KeyEvent as E, Modifiers as M};
 use crate::tty::Sink;
-use crate::validate::Validator;
-use crate::{apply_backspace_direct, readline_direct, Context, Editor, Helper, Result};
+use crate::{apply_backspace_direct, readline_direct, Context, Editor, Result};
 
+#[cfg(all(unix, feature = "async"))]
+mod async_readline;
 mod common;
 mod emacs;
 mod history;
 mod vi_cmd;
 mod vi_insert;
+#[cfg(feature = "web")]
+mod web;
+
+/// Implement [`Helper`] for a test type, with the default behaviour for the
+/// listed traits (the other ones being implemented by the test itself).
+macro_rules! impl_helper {
+    (@default $name:ty, Completer) => {
+        impl $crate::completion::Completer for $name {
+            type Candidate = String;
+        }
+    };
+    (@default $name:ty, Hinter) => {
+        impl $crate::hint::Hinter for $name {
+            type Hint = String;
+        }
+    };
+    (@default $name:ty, Highlighter) => {
+        impl $crate::highlight::Highlighter for $name {}
+    };
+    (@default $name:ty, Validator) => {
+        impl $crate::validate::Validator for $name {}
+    };
+    ($name:ty: $($default:ident),*) => {
+        $($crate::test::impl_helper!(@default $name, $default);)*
+        impl $crate::Helper for $name {}
+    };
+}
+pub(crate) use impl_helper;
 
 fn init_editor(mode: EditMode, keys: &[KeyEvent]) -> Editor<()> {
     let config = Config::builder().edit_mode(mode).build();
@@ -37,17 +69,7 @@ impl Completer for SimpleCompleter {
         Ok((0, vec![line.to_owned() + "t"]))
     }
 }
-impl Hinter for SimpleCompleter {
-    type Hint = String;
-
-    fn hint(&self, _line: &str, _pos: usize, _ctx: &Context<'_>) -> Option<Self::Hint> {
-        None
-    }
-}
-
-impl Helper for SimpleCompleter {}
-impl Highlighter for SimpleCompleter {}
-impl Validator for SimpleCompleter {}
+impl_helper!(SimpleCompleter: Hinter, Highlighter, Validator);
 
 #[test]
 fn complete_line() {
@@ -71,6 +93,497 @@ fn complete_line() {
     assert_eq!(4, s.line.pos());
 }
 
+#[test]
+fn complete_by_score() {
+    use crate::completion::CompletionWithScore;
+
+    struct Scored;
+    impl Completer for Scored {
+        type Candidate = CompletionWithScore;
+
+        fn complete(
+            &self,
+            _line: &str,
+            _pos: usize,
+            _ctx: &Context<'_>,
+        ) -> Result<(usize, Vec<CompletionWithScore>)> {
+            Ok((
+                0,
+                vec![
+                    CompletionWithScore::new("low", "low", 0.5),
+                    CompletionWithScore::new("mid", "mid", 1.0),
+                    CompletionWithScore::new("high", "high", 2.0),
+                ],
+            ))
+        }
+    }
+    impl_helper!(Scored: Hinter, Highlighter, Validator);
+
+    let config = Config::builder()
+        .completion_type(CompletionType::Circular)
+        .build();
+    let mut editor = Editor::<Scored>::with_config(config).unwrap();
+    editor.set_helper(Some(Scored));
+    let tab = E(K::Tab, M::NONE);
+    // cycled through by decreasing score
+    for (tabs, expected) in [(1, "high"), (2, "mid"), (3, "low")] {
+        editor.term.keys = vec![tab; tabs];
+        editor.term.keys.push(E::ENTER);
+        assert_eq!(expected, editor.readline(">>").unwrap());
+    }
+}
+
+#[test]
+fn no_completion() {
+    let history = crate::history::History::new();
+    let helper = Some(());
+    let bindings = Bindings::new();
+    for (mode, expected) in [
+        (CompletionDisplayMode::Bell, "\x07"),
+        (CompletionDisplayMode::Print, ""),
+        (CompletionDisplayMode::Silent, ""),
+    ] {
+        let mut out = Sink {
+            bell_style: BellStyle::Audible,
+            ..Sink::default()
+        };
+        let mut s = init_state(&mut out, "rus", 3, helper.as_ref(), &history);
+        let config = Config::builder().completion_display_mode(mode).build();
+        let mut input_state = InputState::new(&config, &bindings);
+        let mut rdr: IntoIter<KeyEvent> = Vec::new().into_iter();
+        let cmd = super::complete_line(&mut rdr, &mut s, &mut input_state, &config).unwrap();
+        assert_eq!(None, cmd);
+        assert_eq!("rus", s.line.as_str());
+        assert_eq!(expected, s.out.written);
+        if mode == CompletionDisplayMode::Print {
+            assert_eq!("rus\nNo completions", s.out.rendered);
+            // erased by the next refresh
+            s.refresh_line().unwrap();
+            assert_eq!("rus", s.out.rendered);
+        }
+    }
+}
+
+#[test]
+fn read_key() {
+    // keys are not interpreted as commands
+    for key in [E(K::Up, M::NONE), E::ctrl('C'), E::ENTER] {
+        let mut editor = init_editor(EditMode::Emacs, &[key]);
+        assert_eq!(key, editor.read_key().unwrap());
+    }
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    assert_matches!(editor.read_key(), Err(ReadlineError::Eof));
+}
+
+#[test]
+fn process_key() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    editor.set_auto_add_history(true);
+    for c in "abc".chars() {
+        assert_eq!(None, editor.process_key(E::from(c)).unwrap());
+    }
+    assert_eq!("abc", editor.current_buffer());
+    assert_eq!(None, editor.process_key(E::ctrl('A')).unwrap());
+    // numeric argument: nothing done until the command is typed
+    assert_eq!(None, editor.process_key(E::alt('2')).unwrap());
+    assert_eq!("abc", editor.current_buffer());
+    assert_eq!(None, editor.process_key(E::ctrl('D')).unwrap());
+    assert_eq!("c", editor.current_buffer());
+    assert_eq!(None, editor.process_key(E::ctrl('Y')).unwrap());
+    assert_eq!("abc", editor.current_buffer());
+    assert_eq!(
+        Some("abc".to_owned()),
+        editor.process_key(E::ENTER).unwrap()
+    );
+    assert_eq!("", editor.current_buffer());
+    assert_eq!(Some("abc"), editor.history().get(0));
+
+    assert_matches!(editor.process_key(E::ctrl('D')), Err(ReadlineError::Eof));
+}
+
+#[test]
+fn process_key_vi() {
+    let mut editor = init_editor(EditMode::Vi, &[]);
+    for key in [E::from('a'), E::from('b'), E::ESC] {
+        assert_eq!(None, editor.process_key(key).unwrap());
+    }
+    assert_eq!("ab", editor.current_buffer());
+    // `dh` deletes the char before the cursor, once complete
+    assert_eq!(None, editor.process_key(E::from('d')).unwrap());
+    assert_eq!("ab", editor.current_buffer());
+    assert_eq!(None, editor.process_key(E::from('h')).unwrap());
+    assert_eq!("b", editor.current_buffer());
+    // still in command mode
+    assert_eq!(None, editor.process_key(E::from('i')).unwrap());
+    assert_eq!(None, editor.process_key(E::from('x')).unwrap());
+    assert_eq!(Some("xb".to_owned()), editor.process_key(E::ENTER).unwrap());
+}
+
+#[test]
+fn color_enabled() {
+    // the dummy terminal output is not a tty
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    assert!(!editor.color_enabled());
+    assert_eq!(None, editor.dimensions());
+    editor.set_color_mode(ColorMode::Forced);
+    assert!(editor.color_enabled());
+    editor.set_color_mode(ColorMode::Disabled);
+    assert!(!editor.color_enabled());
+}
+
+#[test]
+fn save_restore_state() {
+    let mut editor = init_editor(EditMode::Vi, &[]);
+    editor.set_max_history_size(50);
+    editor.set_bell_style(BellStyle::None);
+    editor.add_history_entry("ls -l");
+    editor.add_history_entry("echo héllo 日本語");
+    let state = editor.save_state();
+    assert_eq!(vec!["ls -l", "echo héllo 日本語"], state.history);
+
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    editor.add_history_entry("forgotten");
+    for c in "pwd".chars() {
+        editor.process_key(E::from(c)).unwrap();
+    }
+    editor.restore_state(state.clone());
+    assert_eq!(state, editor.save_state());
+    assert_eq!(EditMode::Vi, editor.config.edit_mode());
+    assert_eq!(BellStyle::None, editor.term.bell_style);
+    // the line being edited is kept
+    assert_eq!("pwd", editor.current_buffer());
+}
+
+#[test]
+fn restore_state_unfiltered() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    editor.set_history_ignore_dups(false);
+    editor.set_history_ignore_space(false);
+    for entry in &["ls", "ls", " secret", "pwd"] {
+        editor.add_history_entry(*entry);
+    }
+    #[allow(unused_mut)]
+    let mut state = editor.save_state();
+    assert_eq!(4, state.history.len());
+    #[cfg(feature = "with-timestamps")]
+    {
+        state.timestamps[0] = Some(std::time::UNIX_EPOCH);
+    }
+
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    editor.set_history_ignore_dups(true);
+    editor.set_history_ignore_space(true);
+    editor.restore_state(state.clone());
+    assert_eq!(state, editor.save_state());
+}
+
+#[test]
+fn clone_editor() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    editor.add_history_entry("ls -l");
+    let mut fork = editor.clone();
+    fork.add_history_entry("pwd");
+    assert_eq!(1, editor.history().len());
+    assert_eq!(2, fork.history().len());
+    // the kill ring is not shared
+    fork.term.keys = vec![E::from('a'), E::ctrl('W'), E::ctrl('Y'), E::ENTER];
+    assert_eq!("a", fork.readline("> ").unwrap());
+    editor.term.keys = vec![E::ctrl('Y'), E::ENTER];
+    assert_eq!("", editor.readline("> ").unwrap());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn editor_state_serde() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    editor.add_history_entry("echo héllo 日本語");
+    let state = editor.save_state();
+    let json = serde_json::to_string(&state).unwrap();
+    let restored: crate::EditorState = serde_json::from_str(&json).unwrap();
+    assert_eq!(state, restored);
+}
+
+#[test]
+fn readline_sensitive() {
+    let mut editor = Editor::<SimpleCompleter>::new().unwrap();
+    editor.set_auto_add_history(true);
+    editor.term.keys.extend([
+        E::from('s'),
+        E(K::Tab, M::NONE),
+        E::from('3'),
+        E(K::Left, M::NONE),
+        E::from('x'),
+        E::ENTER,
+    ]);
+    editor.set_helper(Some(SimpleCompleter));
+    // no completion: the tab is inserted
+    assert_eq!("s\tx3", editor.readline_sensitive("> ", Some('*')).unwrap());
+    assert!(editor.history().is_empty());
+    assert!(editor.mask.is_none());
+    // back to normal
+    editor.term.keys = vec![E::from('a'), E::ENTER];
+    assert_eq!("a", editor.readline("> ").unwrap());
+    assert_eq!(1, editor.history().len());
+    // no history browsing or search
+    editor.term.keys = vec![
+        E(K::Up, M::NONE),
+        E::from('b'),
+        E::ctrl('U'),
+        E::ctrl('R'),
+        E::from('c'),
+        E::ENTER,
+    ];
+    assert_eq!("c", editor.readline_sensitive("> ", None).unwrap());
+    // nothing killed while sensitive is yanked
+    editor.term.keys = vec![E::ctrl('Y'), E::from('d'), E::ENTER];
+    assert_eq!("d", editor.readline("> ").unwrap());
+}
+
+#[test]
+fn line_transform() {
+    let mut editor = init_editor(EditMode::Emacs, &[E::from('l'), E::from('s'), E::ENTER]);
+    editor.set_auto_add_history(true);
+    editor.set_line_transform(Some(Box::new(|line: &str| line.to_uppercase())));
+    assert_eq!("LS", editor.readline("> ").unwrap());
+    assert_eq!(Some("LS"), editor.history().last());
+    for c in "pwd".chars() {
+        editor.process_key(E::from(c)).unwrap();
+    }
+    assert_eq!(
+        Some("PWD".to_owned()),
+        editor.process_key(E::ENTER).unwrap()
+    );
+}
+
+#[test]
+fn readline_with_events() {
+    use crate::ReadlineEvent;
+
+    let mut editor = init_editor(
+        EditMode::Emacs,
+        &[
+            E::from('a'),
+            E(K::Tab, M::NONE),
+            E(K::Up, M::NONE),
+            E::from('b'),
+            E::ENTER,
+        ],
+    );
+    editor.set_helper(Some(()));
+    editor.add_history_entry("ls");
+    let mut events = Vec::new();
+    let line = editor
+        .readline_with_events("> ", |event| events.push(event))
+        .unwrap();
+    assert_eq!("lsb", line);
+    assert_eq!(
+        vec![
+            ReadlineEvent::Char('a'),
+            ReadlineEvent::Complete,
+            ReadlineEvent::HistoryNav,
+            ReadlineEvent::Char('b'),
+            ReadlineEvent::Finish("lsb".to_owned()),
+        ],
+        events
+    );
+
+    for (key, event) in [
+        (E::ctrl('C'), ReadlineEvent::CtrlC),
+        (E::ctrl('D'), ReadlineEvent::CtrlD),
+    ] {
+        editor.term.keys = vec![key];
+        let mut events = Vec::new();
+        assert!(editor
+            .readline_with_events("> ", |event| events.push(event))
+            .is_err());
+        assert_eq!(vec![event], events);
+    }
+}
+
+#[test]
+fn tab_without_completer() {
+    for mode in &[EditMode::Emacs, EditMode::Vi] {
+        assert_cursor(
+            *mode,
+            ("a", "b"),
+            &[E(K::Tab, M::NONE), E::ENTER],
+            ("a\t", "b"),
+        );
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn readline_with_abort() {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut editor = init_editor(EditMode::Emacs, &[E::from('a'), E::ENTER]);
+    assert_eq!("a", editor.readline_with_abort("> ", receiver).unwrap());
+    assert!(editor.term.abort_pipe.is_none());
+    // too late: ignored
+    assert!(sender.send(()).is_err());
+}
+
+#[test]
+fn readline_with_timeout() {
+    use std::time::{Duration, Instant};
+
+    // no input at all
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    let start = Instant::now();
+    assert_matches!(
+        editor.readline_with_timeout("> ", Duration::from_millis(10)),
+        Err(ReadlineError::Timeout)
+    );
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(10));
+    assert!(elapsed < Duration::from_secs(1));
+
+    // the partially typed line is kept for the next call
+    let mut editor = init_editor(
+        EditMode::Emacs,
+        &[E::from('a'), E::from('b'), E(K::Left, M::NONE)],
+    );
+    assert_matches!(
+        editor.readline_with_timeout("> ", Duration::from_millis(10)),
+        Err(ReadlineError::Timeout)
+    );
+    assert!(editor.history().is_empty());
+    editor.term.keys = vec![E::from('c'), E::ENTER];
+    assert_eq!(
+        "acb",
+        editor
+            .readline_with_timeout("> ", Duration::from_millis(10))
+            .unwrap()
+    );
+    assert!(editor.partial.is_none());
+
+    // discarded by a plain readline
+    editor.term.keys = vec![E::from('a')];
+    assert_matches!(
+        editor.readline_with_timeout("> ", Duration::from_millis(10)),
+        Err(ReadlineError::Timeout)
+    );
+    editor.term.keys = vec![E::from('x'), E::ENTER];
+    assert_eq!("x", editor.readline("> ").unwrap());
+    assert!(editor.partial.is_none());
+}
+
+#[test]
+fn readline_with_history_search() {
+    let keys = [E::from('h'), E::from('e'), E(K::Right, M::NONE), E::ENTER];
+    let mut editor = init_editor(EditMode::Emacs, &keys);
+    editor.add_history_entry("hello world");
+    editor.add_history_entry("help");
+    assert_eq!("help", editor.readline_with_history_search("> ").unwrap());
+
+    // End accepts the suggestion too
+    let keys = [
+        E::from('h'),
+        E::from('e'),
+        E::from('l'),
+        E::from('l'),
+        E(K::End, M::NONE),
+        E::ENTER,
+    ];
+    let mut editor = init_editor(EditMode::Emacs, &keys);
+    editor.add_history_entry("hello world");
+    editor.add_history_entry("help");
+    assert_eq!(
+        "hello world",
+        editor.readline_with_history_search("> ").unwrap()
+    );
+
+    // no matching entry
+    let keys = [E::from('h'), E::from('x'), E(K::Right, M::NONE), E::ENTER];
+    let mut editor = init_editor(EditMode::Emacs, &keys);
+    editor.add_history_entry("hello world");
+    assert_eq!("hx", editor.readline_with_history_search("> ").unwrap());
+
+    // not active with a plain readline
+    let keys = [E::from('h'), E::from('e'), E(K::Right, M::NONE), E::ENTER];
+    let mut editor = init_editor(EditMode::Emacs, &keys);
+    editor.add_history_entry("hello world");
+    assert_eq!("he", editor.readline("> ").unwrap());
+}
+
+fn layout_columns(candidates: &[&str], cols: usize) -> Vec<String> {
+    let columns = super::Columns::new(candidates, cols);
+    (0..columns.num_rows)
+        .map(|row| {
+            let mut ab = String::new();
+            columns.push_row(&mut ab, candidates, row, None);
+            ab
+        })
+        .collect()
+}
+
+#[test]
+fn completion_columns() {
+    let candidates = ["a", "bb", "ccc", "dddd", "e"];
+    // 6 columns wide, sorted top to bottom then left to right
+    assert_eq!(
+        vec!["a     dddd", "bb    e", "ccc"],
+        layout_columns(&candidates, 13)
+    );
+    assert_eq!(
+        vec!["a     ccc   e", "bb    dddd"],
+        layout_columns(&candidates, 18)
+    );
+    // all on one row
+    assert_eq!(
+        vec!["a     bb    ccc   dddd  e"],
+        layout_columns(&candidates, 80)
+    );
+    // candidate wider than the terminal
+    assert_eq!(
+        vec!["a", "bb", "ccc", "dddd", "e"],
+        layout_columns(&candidates, 3)
+    );
+}
+
+#[test]
+fn completion_descriptions() {
+    use crate::completion::Candidate;
+
+    struct Described(&'static str, Option<&'static str>);
+    impl Candidate for Described {
+        fn display(&self) -> &str {
+            self.0
+        }
+
+        fn replacement(&self) -> &str {
+            self.0
+        }
+
+        fn description(&self) -> Option<&str> {
+            self.1
+        }
+    }
+
+    let candidates = [
+        Described("commit", Some("Record changes to the repository")),
+        Described("st", None),
+        Described("log", Some("Show commit logs")),
+    ];
+    let columns = super::Columns::new(&candidates, 80);
+    let rows: Vec<String> = (0..columns.num_rows)
+        .map(|row| {
+            let mut ab = String::new();
+            columns.push_row(&mut ab, &candidates, row, None);
+            ab
+        })
+        .collect();
+    // one candidate per row, descriptions aligned
+    assert_eq!(
+        vec![
+            "commit  Record changes to the repository",
+            "st",
+            "log     Show commit logs"
+        ],
+        rows
+    );
+}
+
 // `keys`: keys to press
 // `expected_line`: line after enter key
 fn assert_line(mode: EditMode, keys: &[KeyEvent], expected_line: &str) {
@@ -100,7 +613,10 @@ fn assert_cursor(mode: EditMode, initial: (&str, &str), keys: &[KeyEvent], expec
     let mut editor = init_editor(mode, keys);
     let actual_line = editor.readline_with_initial("", initial).unwrap();
     assert_eq!(expected.0.to_owned() + expected.1, actual_line);
-    assert_eq!(expected.0.len(), editor.term.cursor);
+    // the dummy renderer counts one column per byte, including the
+    // continuation prompt displayed after each newline
+    let continuation = editor.config.continuation_prompt().len() * expected.0.matches('\n').count();
+    assert_eq!(expected.0.len() + continuation, editor.term.cursor);
 }
 
 // `entries`: history entries before `keys` pressed
@@ -149,6 +665,16 @@ fn test_apply_backspace_direct() {
         &apply_backspace_direct("Hel\u{0008}\u{0008}el\u{0008}llo ☹\u{0008}☺"),
         "Hello ☺"
     );
+    // DEL is sent by the backspace key of most terminals
+    assert_eq!(&apply_backspace_direct("ab\u{007f}c"), "ac");
+}
+
+#[test]
+fn is_dumb_terminal() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    assert!(!editor.is_dumb_terminal());
+    editor.term.unsupported = true;
+    assert!(editor.is_dumb_terminal());
 }
 
 #[test]
@@ -168,3 +694,310 @@ fn test_readline_direct() {
     );
     assert_eq!(&output.unwrap(), "([\n\n\r\n])");
 }
+
+#[test]
+fn test_with_io() {
+    use std::io::Cursor;
+
+    let input = Cursor::new("Hel\u{0008}llo\r\nworld\n".as_bytes());
+    let mut editor = Editor::<()>::with_io(Config::default(), input, io::sink()).unwrap();
+    assert_eq!("Hello", editor.readline(">>").unwrap());
+    assert_eq!("world", editor.readline(">>").unwrap());
+    assert_matches!(editor.readline(">>"), Err(ReadlineError::Eof));
+}
+
+#[test]
+fn test_with_io_editing() {
+    use std::io::Cursor;
+
+    // left arrow twice, insert, then recall the entry with up arrow
+    let input = Cursor::new("abc\x1b[D\x1b[DX\r\x1b[A\x1b[H>\r".as_bytes());
+    let config = Config::builder().auto_add_history(true).build();
+    let mut editor = Editor::<()>::with_io(config, input, io::sink()).unwrap();
+    assert_eq!("aXbc", editor.readline(">>").unwrap());
+    assert_eq!(">aXbc", editor.readline(">>").unwrap());
+    assert_matches!(editor.readline(">>"), Err(ReadlineError::Eof));
+}
+
+#[test]
+fn test_piped_input() {
+    use std::io::Cursor;
+
+    let input = Cursor::new("hello\n\r\n".as_bytes());
+    let mut editor = Editor::<()>::with_io(Config::default(), input, io::sink()).unwrap();
+    assert_eq!("hello", editor.readline(">>").unwrap());
+    // an empty line is not the end of input
+    assert_eq!("", editor.readline(">>").unwrap());
+    assert_matches!(editor.readline(">>"), Err(ReadlineError::Eof));
+
+    let input = Cursor::new("".as_bytes());
+    let mut editor = Editor::<()>::with_io(Config::default(), input, io::sink()).unwrap();
+    assert_matches!(editor.readline(">>"), Err(ReadlineError::Eof));
+}
+
+#[test]
+fn test_readline_direct_short_read() {
+    use std::io::{BufReader, Cursor, Read};
+
+    // one byte per read
+    struct ShortRead<'a>(&'a [u8]);
+    impl Read for ShortRead<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((b, rest)) if !buf.is_empty() => {
+                    buf[0] = *b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+    // last line without newline
+    let mut reader = BufReader::new(ShortRead(b"Hello\nworld"));
+    let line = readline_direct(&mut reader, io::sink(), &None::<()>);
+    assert_eq!("Hello", line.unwrap());
+    let line = readline_direct(&mut reader, io::sink(), &None::<()>);
+    assert_eq!("world", line.unwrap());
+    let line = readline_direct(&mut reader, io::sink(), &None::<()>);
+    assert_matches!(line, Err(ReadlineError::Eof));
+
+    // EOF in the middle of an incomplete input
+    let line = readline_direct(
+        Cursor::new("(a\n".as_bytes()),
+        io::sink(),
+        &Some(crate::validate::MatchingBracketValidator::new()),
+    );
+    assert_eq!("(a", line.unwrap());
+
+    // read error
+    struct Failing;
+    impl Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+    }
+    let line = readline_direct(BufReader::new(Failing), io::sink(), &None::<()>);
+    assert_matches!(line, Err(ReadlineError::Io(_)));
+}
+
+#[test]
+fn interrupt_completion_list() {
+    struct ManyCandidates;
+    impl Completer for ManyCandidates {
+        type Candidate = String;
+
+        fn complete(
+            &self,
+            _line: &str,
+            _pos: usize,
+            _ctx: &Context<'_>,
+        ) -> Result<(usize, Vec<String>)> {
+            Ok((0, (0..200).map(|i| format!("{:<38}", i)).collect()))
+        }
+    }
+    impl_helper!(ManyCandidates: Hinter, Highlighter, Validator);
+
+    // "Display all 200 possibilities? (y or n)"
+    let mut editor = Editor::<ManyCandidates>::new().unwrap();
+    editor.set_helper(Some(ManyCandidates));
+    editor.term.keys = vec![E(K::Tab, M::NONE), E(K::Tab, M::NONE), E::ctrl('C')];
+    assert_matches!(editor.readline(">>"), Err(ReadlineError::Interrupted));
+    // "--More--"
+    editor.term.keys = vec![
+        E(K::Tab, M::NONE),
+        E(K::Tab, M::NONE),
+        E::from('y'),
+        E::ctrl('C'),
+    ];
+    assert_matches!(editor.readline(">>"), Err(ReadlineError::Interrupted));
+}
+
+#[test]
+fn abort_completion_list() {
+    struct ManyCandidates;
+    impl Completer for ManyCandidates {
+        type Candidate = String;
+
+        fn complete(
+            &self,
+            _line: &str,
+            _pos: usize,
+            _ctx: &Context<'_>,
+        ) -> Result<(usize, Vec<String>)> {
+            Ok((0, (0..200).map(|i| format!("{:<38}", i)).collect()))
+        }
+    }
+    impl_helper!(ManyCandidates: Hinter, Highlighter, Validator);
+
+    let config = Config::builder()
+        .completion_type(CompletionType::List)
+        .build();
+    let mut editor = Editor::<ManyCandidates>::with_config(config).unwrap();
+    editor.set_helper(Some(ManyCandidates));
+    // "Display all 200 possibilities? (y or n)"
+    editor.term.keys = vec![
+        E(K::Tab, M::NONE),
+        E(K::Tab, M::NONE),
+        E::ctrl('G'),
+        E::from('x'),
+        E::ENTER,
+    ];
+    assert_eq!("x", editor.readline(">>").unwrap());
+    // "--More--"
+    editor.term.keys = vec![
+        E(K::Tab, M::NONE),
+        E(K::Tab, M::NONE),
+        E::from('y'),
+        E::ctrl('G'),
+        E::from('x'),
+        E::ENTER,
+    ];
+    assert_eq!("x", editor.readline(">>").unwrap());
+}
+
+#[cfg(feature = "custom-bindings")]
+#[test]
+fn load_inputrc() -> Result<()> {
+    use std::io::Write;
+
+    let mut tf = tempfile::NamedTempFile::new()?;
+    writeln!(tf, "set editing-mode vi")?;
+    writeln!(tf, "set completion-ignore-case on")?;
+    writeln!(tf, "set no-such-variable on")?;
+    writeln!(tf, r#""\C-xh": "hello""#)?;
+    tf.flush()?;
+
+    let mut editor = init_editor(EditMode::Emacs, &[E::ctrl('X'), E::from('h'), E::ENTER]);
+    editor.load_inputrc(tf.path())?;
+    assert_eq!(EditMode::Vi, editor.config.edit_mode());
+    assert_eq!(Some(true), editor.config.completion_ignore_case());
+    assert_eq!("hello", editor.readline(">>")?);
+    Ok(())
+}
+
+#[cfg(feature = "custom-bindings")]
+#[test]
+fn bind_key() {
+    use crate::keymap::Movement;
+
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    assert!(editor
+        .bind_key(E::ctrl('a'), Cmd::Move(Movement::EndOfLine))
+        .is_none());
+    editor.term.keys = vec![E::from('b'), E::ctrl('A'), E::from('c'), E::ENTER];
+    assert_eq!("bc", editor.readline(">>").unwrap());
+
+    assert!(editor.unbind_key(E::ctrl('A')).is_some());
+    editor.term.keys = vec![E::from('b'), E::ctrl('A'), E::from('c'), E::ENTER];
+    assert_eq!("cb", editor.readline(">>").unwrap());
+}
+
+#[cfg(feature = "custom-bindings")]
+#[test]
+fn redo() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    editor.bind_key(E(K::F(2), M::NONE), Cmd::Redo(1));
+    editor.term.keys = vec![
+        E::from('a'),
+        E::from('b'),
+        E::ctrl('_'),
+        E(K::F(2), M::NONE),
+        E::from('c'),
+        E::ENTER,
+    ];
+    assert_eq!("abc", editor.readline(">>").unwrap());
+}
+
+#[test]
+fn auto_add_history() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    assert!(!editor.config.auto_add_history());
+    editor.set_auto_add_history(true);
+    editor.term.keys = vec![E::from('a'), E::ENTER, E::from('a'), E::ENTER];
+    assert_eq!("a", editor.readline(">>").unwrap());
+    assert_eq!("a", editor.readline(">>").unwrap());
+    // consecutive duplicates are ignored
+    assert_eq!(1, editor.history().len());
+
+    editor.set_auto_add_history(false);
+    editor.term.keys = vec![E::from('b'), E::ENTER];
+    assert_eq!("b", editor.readline(">>").unwrap());
+    assert_eq!(1, editor.history().len());
+    assert!(editor.add_history_entry("b"));
+    assert_eq!(2, editor.history().len());
+}
+
+#[test]
+fn auto_add_history_filters() {
+    let config = Config::builder()
+        .history_ignore_space(true)
+        .history_ignore_dups(false)
+        .auto_add_history(true)
+        .build();
+    let mut editor = Editor::<()>::with_config(config).unwrap();
+    let reads: [&[KeyEvent]; 5] = [
+        &[E::from('a'), E::ENTER],
+        &[E::ENTER],                             // empty line
+        &[E::from(' '), E::from('b'), E::ENTER], // leading space
+        &[E::from('a'), E::ENTER],
+        &[E::from('c'), E::ENTER],
+    ];
+    for keys in &reads {
+        editor.term.keys = keys.to_vec();
+        editor.readline(">>").unwrap();
+    }
+    let entries: Vec<_> = (0..editor.history().len())
+        .filter_map(|i| editor.history().get(i))
+        .collect();
+    assert_eq!(vec!["a", "a", "c"], entries);
+}
+
+#[test]
+fn max_line_length() {
+    let config = Config::builder().max_line_length(3).build();
+    let mut editor = Editor::<()>::with_config(config).unwrap();
+    editor.term.keys = vec![
+        E::from('a'),
+        E::from('b'),
+        E::from('c'),
+        E::from('d'),
+        E::ENTER,
+    ];
+    assert_eq!("abc", editor.readline(">>").unwrap());
+
+    editor.set_max_line_length(4);
+    editor.term.keys = vec![E::from('a'); 5];
+    editor.term.keys.push(E::ENTER);
+    assert_eq!("aaaa", editor.readline(">>").unwrap());
+}
+
+#[test]
+fn complete_hint() {
+    struct SuffixHinter;
+    impl Hinter for SuffixHinter {
+        type Hint = String;
+
+        fn hint(&self, line: &str, _pos: usize, _ctx: &Context<'_>) -> Option<String> {
+            if line == "he" {
+                Some("llo".to_owned())
+            } else {
+                None
+            }
+        }
+    }
+    impl_helper!(SuffixHinter: Completer, Highlighter, Validator);
+
+    for mode in &[EditMode::Emacs, EditMode::Vi] {
+        for key in &[K::Right, K::End] {
+            let config = Config::builder().edit_mode(*mode).build();
+            let mut editor = Editor::<SuffixHinter>::with_config(config).unwrap();
+            editor.set_helper(Some(SuffixHinter));
+            editor.term.keys = vec![E::from('h'), E::from('e'), E(*key, M::NONE), E::ENTER];
+            assert_eq!("hello", editor.readline(">>").unwrap());
+            // hint dismissed by any other key
+            editor.term.keys = vec![E::from('h'), E::from('e'), E::from('y'), E::ENTER];
+            assert_eq!("hey", editor.readline(">>").unwrap());
+        }
+    }
+}
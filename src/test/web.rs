@@ -0,0 +1,114 @@
+//! `Editor::readline_web` tests
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use super::init_editor;
+use crate::config::EditMode;
+use crate::error::ReadlineError;
+use crate::{Result, WebInput};
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// Poll `future` once
+fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+    let waker = Waker::from(Arc::new(NoopWaker));
+    future.poll(&mut Context::from_waker(&waker))
+}
+
+/// Line read once each chunk of `data` is pushed, the last one excepted,
+/// has left the line incomplete
+fn readline(mode: EditMode, data: &[&str]) -> Result<String> {
+    let mut editor = init_editor(mode, &[]);
+    let input = WebInput::new();
+    let mut output = Vec::new();
+    let mut line = Box::pin(editor.readline_web(">> ", &input, &mut output));
+    for chunk in data {
+        assert!(poll(line.as_mut()).is_pending());
+        input.push(chunk);
+    }
+    match poll(line.as_mut()) {
+        Poll::Ready(line) => line,
+        Poll::Pending => panic!("line not accepted"),
+    }
+}
+
+#[test]
+fn keys() {
+    assert_eq!(
+        "ac",
+        readline(EditMode::Emacs, &["abc\x1b[D\x7f\r"]).unwrap()
+    );
+}
+
+#[test]
+fn split_escape_sequence() {
+    assert_eq!(
+        "axb",
+        readline(EditMode::Emacs, &["ab\x1b[", "Dx\r"]).unwrap()
+    );
+}
+
+#[test]
+fn prefix_replayed() {
+    // C-x Backspace
+    assert_eq!(
+        "d",
+        readline(EditMode::Emacs, &["abc\x18", "\x7f", "d\r"]).unwrap()
+    );
+    // d b
+    assert_eq!(
+        "hello d",
+        readline(EditMode::Vi, &["hello world\x1bd", "b\r"]).unwrap()
+    );
+}
+
+#[test]
+fn undo_after_replay() {
+    // C-x Backspace then C-_
+    assert_eq!(
+        "abc",
+        readline(EditMode::Emacs, &["abc\x18", "\x7f", "\x1f\r"]).unwrap()
+    );
+}
+
+#[test]
+fn typed_ahead() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    let input = WebInput::new();
+    input.push("a\rb\r");
+    let mut output = Vec::new();
+    let line = poll(Box::pin(editor.readline_web("", &input, &mut output)).as_mut());
+    assert_matches!(line, Poll::Ready(Ok(ref line)) if line == "a");
+    let line = poll(Box::pin(editor.readline_web("", &input, &mut output)).as_mut());
+    assert_matches!(line, Poll::Ready(Ok(ref line)) if line == "b");
+}
+
+#[test]
+fn closed() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    let input = WebInput::new();
+    input.push("a");
+    input.close();
+    let mut output = Vec::new();
+    let line = poll(Box::pin(editor.readline_web("", &input, &mut output)).as_mut());
+    assert_matches!(line, Poll::Ready(Err(ReadlineError::Eof)));
+}
+
+#[test]
+fn output() {
+    let mut editor = init_editor(EditMode::Emacs, &[]);
+    let input = WebInput::new();
+    input.push("a\r");
+    let mut output = Vec::new();
+    let line = poll(Box::pin(editor.readline_web(">> ", &input, &mut output)).as_mut());
+    assert_matches!(line, Poll::Ready(Ok(_)));
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(">> "), "{:?}", output);
+    assert!(output.ends_with("a\n"), "{:?}", output);
+}
@@ -1,10 +1,13 @@
 //! This module implements and describes common TTY methods & traits
 
-#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
 use unicode_width::UnicodeWidthStr;
 
 use crate::config::{Behavior, BellStyle, ColorMode, Config};
+use crate::grapheme::UnicodeSegmentation;
 use crate::highlight::Highlighter;
+use crate::keymap::{InputMode, SequenceTrie};
 use crate::keys::KeyEvent;
 use crate::layout::{Layout, Position};
 use crate::line_buffer::LineBuffer;
@@ -18,6 +21,7 @@ pub trait RawMode: Sized {
 
 /// Input event
 #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+#[derive(Debug)]
 pub enum Event {
     KeyPress(KeyEvent),
     ExternalPrint(String),
@@ -29,6 +33,9 @@ pub trait RawReader {
     fn wait_for_input(&mut self, single_esc_abort: bool) -> Result<Event>; // TODO replace calls to `next_key` by `wait_for_input` where relevant
     /// Blocking read of key pressed.
     fn next_key(&mut self, single_esc_abort: bool) -> Result<KeyEvent>;
+    /// Wait at most `timeout` for some input to be available.
+    /// Return `false` on timeout.
+    fn poll_input(&mut self, timeout: Duration) -> Result<bool>;
     /// For CTRL-V support
     #[cfg(unix)]
     fn next_char(&mut self) -> Result<char>;
@@ -36,6 +43,13 @@ pub trait RawReader {
     fn read_pasted_text(&mut self) -> Result<String>;
     /// Check if `key` is bound to a peculiar command
     fn find_binding(&self, key: &KeyEvent) -> Option<Cmd>;
+    /// Keys bound to raw byte sequences (only meaningful for readers seeing
+    /// the bytes sent by the terminal)
+    fn set_sequences(&mut self, _: &SequenceTrie) {}
+    /// Bytes read by the caller, when no input is pending (see
+    /// `Editor::readline_async`)
+    #[cfg(all(unix, feature = "async"))]
+    fn feed(&mut self, _: &[u8]) {}
 }
 
 /// Display prompt, line and cursor in terminal output
@@ -68,12 +82,12 @@ pub trait Renderer {
     ) -> Layout {
         // calculate the desired position of the cursor
         let pos = line.pos();
-        let cursor = self.calculate_position(&line[..pos], prompt_size);
+        let cursor = self.calculate_line_position(&line[..pos], prompt_size);
         // calculate the position of the end of the input line
         let mut end = if pos == line.len() {
             cursor
         } else {
-            self.calculate_position(&line[pos..], cursor)
+            self.calculate_line_position(&line[pos..], cursor)
         };
         if let Some(info) = info {
             end = self.calculate_position(info, end);
@@ -94,6 +108,29 @@ pub trait Renderer {
     /// `cols` width terminal starting at `orig`.
     fn calculate_position(&self, s: &str, orig: Position) -> Position;
 
+    /// Same as `calculate_position` for (a part of) the edited line: the
+    /// continuation prompt is displayed after each newline.
+    fn calculate_line_position(&self, s: &str, orig: Position) -> Position {
+        let continuation = self.continuation_prompt();
+        if continuation.is_empty() {
+            return self.calculate_position(s, orig);
+        }
+        let mut pos = orig;
+        for l in s.split_inclusive('\n') {
+            pos = self.calculate_position(l, pos);
+            if l.ends_with('\n') {
+                pos = self.calculate_position(continuation, pos);
+            }
+        }
+        pos
+    }
+
+    /// Prompt displayed at the beginning of each line of the edited input
+    /// after the first one.
+    fn continuation_prompt(&self) -> &str;
+    /// Set the continuation prompt.
+    fn set_continuation_prompt(&mut self, prompt: &str);
+
     fn write_and_flush(&mut self, buf: &str) -> Result<()>;
 
     /// Beep, used for completion when there is nothing to complete or when all
@@ -116,6 +153,12 @@ pub trait Renderer {
 
     /// Make sure prompt is at the leftmost edge of the screen
     fn move_cursor_at_leftmost(&mut self, rdr: &mut Self::Reader) -> Result<()>;
+
+    /// Change the cursor shape to reflect vi input `mode`
+    /// (`None` restores the default shape).
+    fn set_cursor_shape(&mut self, _mode: Option<InputMode>) -> Result<()> {
+        Ok(())
+    }
 }
 
 impl<'a, R: Renderer + ?Sized> Renderer for &'a mut R {
@@ -141,6 +184,18 @@ impl<'a, R: Renderer + ?Sized> Renderer for &'a mut R {
         (**self).calculate_position(s, orig)
     }
 
+    fn calculate_line_position(&self, s: &str, orig: Position) -> Position {
+        (**self).calculate_line_position(s, orig)
+    }
+
+    fn continuation_prompt(&self) -> &str {
+        (**self).continuation_prompt()
+    }
+
+    fn set_continuation_prompt(&mut self, prompt: &str) {
+        (**self).set_continuation_prompt(prompt);
+    }
+
     fn write_and_flush(&mut self, buf: &str) -> Result<()> {
         (**self).write_and_flush(buf)
     }
@@ -176,10 +231,28 @@ impl<'a, R: Renderer + ?Sized> Renderer for &'a mut R {
     fn move_cursor_at_leftmost(&mut self, rdr: &mut R::Reader) -> Result<()> {
         (**self).move_cursor_at_leftmost(rdr)
     }
+
+    fn set_cursor_shape(&mut self, mode: Option<InputMode>) -> Result<()> {
+        (**self).set_cursor_shape(mode)
+    }
+}
+
+/// Append the edited `line` to `buffer` with `continuation` after each
+/// newline.
+fn push_line(buffer: &mut String, line: &str, continuation: &str) {
+    if continuation.is_empty() {
+        buffer.push_str(line);
+        return;
+    }
+    for l in line.split_inclusive('\n') {
+        buffer.push_str(l);
+        if l.ends_with('\n') {
+            buffer.push_str(continuation);
+        }
+    }
 }
 
 // ignore ANSI escape sequence
-#[cfg(not(target_arch = "wasm32"))]
 fn width(s: &str, esc_seq: &mut u8) -> usize {
     if *esc_seq == 1 {
         if s == "[" {
@@ -191,12 +264,9 @@ fn width(s: &str, esc_seq: &mut u8) -> usize {
         }
         0
     } else if *esc_seq == 2 {
-        if s == ";" || (s.as_bytes()[0] >= b'0' && s.as_bytes()[0] <= b'9') {
-            /*} else if s == "m" {
-            // last
-             *esc_seq = 0;*/
-        } else {
-            // not supported
+        // parameter bytes (like `1;32` or `?25`) and intermediate bytes are
+        // skipped until the final byte (like `m` for SGR)
+        if !matches!(s.as_bytes(), [0x20..=0x3f]) {
             *esc_seq = 0;
         }
         0
@@ -210,6 +280,72 @@ fn width(s: &str, esc_seq: &mut u8) -> usize {
     }
 }
 
+/// Position of the cursor once `s` is displayed from `orig` on a `cols` width
+/// terminal with tab stops every `tab_stop` columns.
+/// Control characters are treated as having zero width.
+/// Characters with 2 column width are correctly handled (not split).
+fn ansi_position(s: &str, orig: Position, tab_stop: usize, cols: usize) -> Position {
+    let mut pos = orig;
+    let mut esc_seq = 0;
+    for c in s.graphemes(true) {
+        if c == "\n" {
+            pos.row += 1;
+            pos.col = 0;
+            continue;
+        }
+        let cw = if c == "\t" {
+            tab_stop - (pos.col % tab_stop)
+        } else {
+            width(c, &mut esc_seq)
+        };
+        pos.col += cw;
+        if pos.col > cols {
+            pos.row += 1;
+            pos.col = cw;
+        }
+    }
+    if pos.col == cols {
+        pos.col = 0;
+        pos.row += 1;
+    }
+    pos
+}
+
+/// Replace tabs written in `buffer` from `start` (at column 0) by spaces up
+/// to the next tab stop, like `ansi_position` does, instead of relying on the
+/// terminal tab stops.
+fn expand_tabs(buffer: &mut String, start: usize, tab_stop: usize, cols: usize) {
+    if !buffer[start..].contains('\t') {
+        return;
+    }
+    let mut expanded = String::with_capacity(buffer.len() - start);
+    let mut col = 0;
+    let mut esc_seq = 0;
+    for c in buffer[start..].graphemes(true) {
+        if c == "\n" {
+            expanded.push('\n');
+            col = 0;
+            continue;
+        }
+        let cw = if c == "\t" {
+            let n = tab_stop - (col % tab_stop);
+            for _ in 0..n {
+                expanded.push(' ');
+            }
+            n
+        } else {
+            expanded.push_str(c);
+            width(c, &mut esc_seq)
+        };
+        col += cw;
+        if col > cols {
+            col = cw;
+        }
+    }
+    buffer.truncate(start);
+    buffer.push_str(&expanded);
+}
+
 /// External printer
 pub trait ExternalPrinter {
     /// Print message to stdout
@@ -230,6 +366,7 @@ pub trait Term {
         tab_stop: usize,
         bell_style: BellStyle,
         enable_bracketed_paste: bool,
+        ctrlc_as_signal: bool,
     ) -> Result<Self>
     where
         Self: Sized;
@@ -240,6 +377,9 @@ pub trait Term {
     fn is_input_tty(&self) -> bool;
     /// check if output stream is connected to a terminal.
     fn is_output_tty(&self) -> bool;
+    /// check if colors are enabled, given the configured `ColorMode` and
+    /// whether the output stream is a terminal.
+    fn colors_enabled(&self) -> bool;
     /// Enable RAW mode for the terminal.
     fn enable_raw_mode(&mut self) -> Result<(Self::Mode, Self::KeyMap)>;
     /// Create a RAW reader
@@ -265,6 +405,18 @@ mod unix;
 #[cfg(all(unix, not(target_arch = "wasm32")))]
 pub use self::unix::*;
 
+// If on wasm32 with web-sys, read lines with `window.prompt`
+#[cfg(all(target_arch = "wasm32", feature = "web-sys"))]
+mod wasm;
+#[cfg(all(target_arch = "wasm32", feature = "web-sys"))]
+pub use self::wasm::prompt;
+
+mod offscreen;
+pub use self::offscreen::{Offscreen, PendingKeys};
+
+mod stream;
+pub use self::stream::{StreamReader, StreamRenderer};
+
 #[cfg(any(test, target_arch = "wasm32"))]
 mod test;
 #[cfg(any(test, target_arch = "wasm32"))]
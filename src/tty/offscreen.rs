@@ -0,0 +1,175 @@
+//! Renderer drawing nothing, used to edit a line without a terminal (like with
+//! `Editor::process_key`)
+use std::io;
+use std::time::Duration;
+use std::vec::IntoIter;
+
+use super::{Event, RawReader, Renderer};
+use crate::error::ReadlineError;
+use crate::highlight::Highlighter;
+use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
+use crate::layout::{Layout, Position};
+use crate::line_buffer::LineBuffer;
+use crate::{Cmd, Result};
+
+/// Keys given in advance
+pub struct PendingKeys(IntoIter<E>);
+
+impl From<Vec<E>> for PendingKeys {
+    fn from(keys: Vec<E>) -> Self {
+        PendingKeys(keys.into_iter())
+    }
+}
+
+impl RawReader for PendingKeys {
+    fn wait_for_input(&mut self, single_esc_abort: bool) -> Result<Event> {
+        self.next_key(single_esc_abort).map(Event::KeyPress)
+    }
+
+    fn next_key(&mut self, _: bool) -> Result<E> {
+        self.0.next().ok_or(ReadlineError::Eof)
+    }
+
+    fn poll_input(&mut self, _: Duration) -> Result<bool> {
+        // no more key will come until the next call
+        Ok(self.0.len() > 0)
+    }
+
+    #[cfg(unix)]
+    fn next_char(&mut self) -> Result<char> {
+        match self.0.next() {
+            Some(E(K::Char(c), M::NONE)) => Ok(c),
+            Some(key) => Err(ReadlineError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} is not a character", key),
+            ))),
+            None => Err(ReadlineError::Eof),
+        }
+    }
+
+    fn read_pasted_text(&mut self) -> Result<String> {
+        let mut buffer = String::new();
+        loop {
+            match self.0.next() {
+                Some(E(K::BracketedPasteEnd, M::NONE)) => break,
+                Some(E(K::Char(c), M::NONE)) => buffer.push(c),
+                Some(E(K::Enter, M::NONE)) => buffer.push('\n'),
+                Some(E(K::Tab, M::NONE)) => buffer.push('\t'),
+                Some(_) => continue,
+                None => return Err(ReadlineError::Eof),
+            }
+        }
+        Ok(buffer)
+    }
+
+    fn find_binding(&self, _: &E) -> Option<Cmd> {
+        None
+    }
+}
+
+/// Renderer keeping track of the layout only: nothing is written to the
+/// terminal.
+#[derive(Default)]
+pub struct Offscreen {
+    continuation_prompt: String,
+}
+
+impl Offscreen {
+    const COLUMNS: usize = 80;
+    const ROWS: usize = 24;
+}
+
+impl Renderer for Offscreen {
+    type Reader = PendingKeys;
+
+    fn move_cursor(&mut self, _: Position, _: Position) -> Result<()> {
+        Ok(())
+    }
+
+    fn refresh_line(
+        &mut self,
+        _prompt: &str,
+        _line: &LineBuffer,
+        _hint: Option<&str>,
+        _old_layout: &Layout,
+        _new_layout: &Layout,
+        _highlighter: Option<&dyn Highlighter>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn calculate_position(&self, s: &str, orig: Position) -> Position {
+        // one column per char, wrapped at `get_columns`
+        let mut pos = orig;
+        pos.col += s.chars().count();
+        while pos.col > Self::COLUMNS {
+            pos.row += 1;
+            pos.col -= Self::COLUMNS;
+        }
+        pos
+    }
+
+    fn continuation_prompt(&self) -> &str {
+        &self.continuation_prompt
+    }
+
+    fn set_continuation_prompt(&mut self, prompt: &str) {
+        self.continuation_prompt = prompt.to_owned();
+    }
+
+    fn write_and_flush(&mut self, _: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn beep(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear_screen(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear_rows(&mut self, _: &Layout) -> Result<()> {
+        Ok(())
+    }
+
+    fn update_size(&mut self) {}
+
+    fn get_columns(&self) -> usize {
+        Self::COLUMNS
+    }
+
+    fn get_rows(&self) -> usize {
+        Self::ROWS
+    }
+
+    fn colors_enabled(&self) -> bool {
+        false
+    }
+
+    fn move_cursor_at_leftmost(&mut self, _: &mut PendingKeys) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PendingKeys;
+    use crate::error::ReadlineError;
+    use crate::keys::{KeyCode as K, KeyEvent as E};
+    use crate::tty::RawReader;
+    use std::time::Duration;
+
+    #[test]
+    fn pending_keys() {
+        let mut rdr = PendingKeys::from(vec![E::from('a'), E(K::Up, crate::keys::Modifiers::NONE)]);
+        assert!(rdr.poll_input(Duration::from_secs(60)).unwrap());
+        assert_eq!(E::from('a'), rdr.next_key(false).unwrap());
+        #[cfg(unix)]
+        assert!(matches!(rdr.next_char(), Err(ReadlineError::Io(_))));
+        #[cfg(not(unix))]
+        rdr.next_key(false).unwrap();
+        assert!(!rdr.poll_input(Duration::from_secs(60)).unwrap());
+        assert!(matches!(rdr.next_key(false), Err(ReadlineError::Eof)));
+    }
+}
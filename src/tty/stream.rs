@@ -0,0 +1,428 @@
+//! ANSI terminal driven through any reader/writer (like a socket or a PTY
+//! master), see `Editor::with_io`
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+use log::debug;
+
+use super::{ansi_position, expand_tabs, push_line, Event, RawReader, Renderer};
+use crate::config::{BellStyle, ColorMode, Config};
+use crate::error::ReadlineError;
+use crate::highlight::Highlighter;
+use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
+use crate::layout::{Layout, Position};
+use crate::line_buffer::LineBuffer;
+use crate::{Cmd, Result};
+
+/// Decode keys from the bytes sent by a terminal.
+///
+/// The input cannot be polled: a lone `Esc` is only known once the next key
+/// is received.
+pub struct StreamReader<'a> {
+    input: &'a mut dyn BufRead,
+    // skip the LF of a CRLF pair
+    after_cr: &'a mut bool,
+}
+
+impl<'a> StreamReader<'a> {
+    /// `after_cr` tells whether the last key read from `input` was a CR.
+    pub fn new(input: &'a mut dyn BufRead, after_cr: &'a mut bool) -> Self {
+        Self { input, after_cr }
+    }
+
+    /// Next byte, without consuming it
+    fn peek(&mut self) -> Result<Option<u8>> {
+        Ok(self.input.fill_buf()?.first().copied())
+    }
+
+    fn next_byte(&mut self) -> Result<u8> {
+        match self.peek()? {
+            Some(b) => {
+                self.input.consume(1);
+                Ok(b)
+            }
+            None => Err(ReadlineError::Eof),
+        }
+    }
+
+    fn read_char(&mut self) -> Result<char> {
+        let b = self.next_byte()?;
+        let len = match b {
+            0x00..=0x7f => return Ok(char::from(b)),
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return Ok(char::REPLACEMENT_CHARACTER),
+        };
+        let mut buf = [b, 0, 0, 0];
+        for byte in buf.iter_mut().take(len).skip(1) {
+            *byte = self.next_byte()?;
+        }
+        Ok(std::str::from_utf8(&buf[..len])
+            .ok()
+            .and_then(|s| s.chars().next())
+            .unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    /// Handle \E[ sequences
+    fn escape_csi(&mut self) -> Result<E> {
+        let mut params = String::new();
+        let last = loop {
+            let c = self.read_char()?;
+            if ('\x30'..='\x3f').contains(&c) {
+                params.push(c);
+            } else {
+                break c;
+            }
+        };
+        let mut args = params.split(';');
+        let first = args.next().unwrap_or("");
+        let mods = args.next().map_or(M::NONE, modifiers);
+        let code = match (last, first) {
+            ('A', _) => K::Up,
+            ('B', _) => K::Down,
+            ('C', _) => K::Right,
+            ('D', _) => K::Left,
+            ('F', _) => K::End,
+            ('H', _) => K::Home,
+            ('Z', _) => return Ok(E(K::BackTab, M::NONE)),
+            ('~', "1") | ('~', "7") => K::Home,
+            ('~', "2") => K::Insert,
+            ('~', "3") => K::Delete,
+            ('~', "4") | ('~', "8") => K::End,
+            ('~', "5") => K::PageUp,
+            ('~', "6") => K::PageDown,
+            ('~', "200") => K::BracketedPasteStart,
+            ('~', "201") => K::BracketedPasteEnd,
+            _ => {
+                debug!(target: "rustyline", "unsupported esc sequence: \\E[{}{}", params, last);
+                K::UnknownEscSeq
+            }
+        };
+        Ok(E(code, mods))
+    }
+
+    /// Handle \EO sequences
+    fn escape_o(&mut self) -> Result<E> {
+        let c = self.read_char()?;
+        Ok(match c {
+            'A' => E(K::Up, M::NONE),
+            'B' => E(K::Down, M::NONE),
+            'C' => E(K::Right, M::NONE),
+            'D' => E(K::Left, M::NONE),
+            'F' => E(K::End, M::NONE),
+            'H' => E(K::Home, M::NONE),
+            'P' => E(K::F(1), M::NONE),
+            'Q' => E(K::F(2), M::NONE),
+            'R' => E(K::F(3), M::NONE),
+            'S' => E(K::F(4), M::NONE),
+            _ => {
+                debug!(target: "rustyline", "unsupported esc sequence: \\EO{:?}", c);
+                E(K::UnknownEscSeq, M::NONE)
+            }
+        })
+    }
+}
+
+/// xterm modifier parameter (like `5` in `\E[1;5C`)
+fn modifiers(param: &str) -> M {
+    let n = param.parse::<u8>().unwrap_or(1).saturating_sub(1);
+    let mut mods = M::NONE;
+    if n & 1 != 0 {
+        mods |= M::SHIFT;
+    }
+    if n & 2 != 0 {
+        mods |= M::ALT;
+    }
+    if n & 4 != 0 {
+        mods |= M::CTRL;
+    }
+    mods
+}
+
+impl RawReader for StreamReader<'_> {
+    fn wait_for_input(&mut self, single_esc_abort: bool) -> Result<Event> {
+        self.next_key(single_esc_abort).map(Event::KeyPress)
+    }
+
+    fn next_key(&mut self, _: bool) -> Result<E> {
+        let mut c = self.read_char()?;
+        if *self.after_cr {
+            *self.after_cr = false;
+            if c == '\n' {
+                c = self.read_char()?;
+            }
+        }
+        let key = match c {
+            '\r' => {
+                *self.after_cr = true;
+                E::ENTER
+            }
+            '\n' => E::ENTER,
+            '\t' => E(K::Tab, M::NONE),
+            '\x08' | '\x7f' => E::BACKSPACE,
+            '\x1b' => match self.peek()? {
+                Some(b'[') => {
+                    self.input.consume(1);
+                    self.escape_csi()?
+                }
+                Some(b'O') => {
+                    self.input.consume(1);
+                    self.escape_o()?
+                }
+                _ => E::ESC,
+            },
+            c => E::new(c, M::NONE),
+        };
+        debug!(target: "rustyline", "key: {:?}", key);
+        Ok(key)
+    }
+
+    fn poll_input(&mut self, _: Duration) -> Result<bool> {
+        // the input cannot be polled: wait for the next key
+        Ok(true)
+    }
+
+    #[cfg(unix)]
+    fn next_char(&mut self) -> Result<char> {
+        self.read_char()
+    }
+
+    fn read_pasted_text(&mut self) -> Result<String> {
+        let mut buffer = String::new();
+        loop {
+            match self.next_key(false)? {
+                E(K::BracketedPasteEnd, M::NONE) => break,
+                E(K::Char(c), M::NONE) => buffer.push(c),
+                E(K::Enter, M::NONE) => buffer.push('\n'),
+                E(K::Tab, M::NONE) => buffer.push('\t'),
+                _ => continue,
+            }
+        }
+        Ok(buffer)
+    }
+
+    fn find_binding(&self, _: &E) -> Option<Cmd> {
+        None
+    }
+}
+
+/// Write ANSI escape sequences to any writer.
+///
+/// The terminal size cannot be queried: 80 columns and 24 rows are assumed.
+pub struct StreamRenderer<'a> {
+    out: &'a mut dyn Write,
+    buffer: String,
+    tab_stop: usize,
+    colors_enabled: bool,
+    bell_style: BellStyle,
+    continuation_prompt: String,
+}
+
+impl<'a> StreamRenderer<'a> {
+    const COLUMNS: usize = 80;
+    const ROWS: usize = 24;
+
+    pub fn new(out: &'a mut dyn Write, config: &Config) -> Self {
+        Self {
+            out,
+            buffer: String::with_capacity(1024),
+            tab_stop: config.tab_stop(),
+            // the output is not known to be a terminal
+            colors_enabled: config.color_mode() == ColorMode::Forced,
+            bell_style: config.bell_style(),
+            continuation_prompt: String::new(),
+        }
+    }
+
+    fn clear_old_rows(&mut self, layout: &Layout) {
+        let current_row = layout.cursor.row;
+        let old_rows = layout.end.row;
+        let cursor_row_movement = old_rows.saturating_sub(current_row);
+        // move the cursor down as required
+        if cursor_row_movement > 0 {
+            write!(self.buffer, "\x1b[{}B", cursor_row_movement).unwrap();
+        }
+        // clear old rows
+        for _ in 0..old_rows {
+            self.buffer.push_str("\r\x1b[K\x1b[A");
+        }
+        // clear the line
+        self.buffer.push_str("\r\x1b[K");
+    }
+
+    fn flush_buffer(&mut self) -> Result<()> {
+        self.out.write_all(self.buffer.as_bytes())?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+impl<'a> Renderer for StreamRenderer<'a> {
+    type Reader = StreamReader<'a>;
+
+    fn move_cursor(&mut self, old: Position, new: Position) -> Result<()> {
+        self.buffer.clear();
+        if new.row > old.row {
+            write!(self.buffer, "\x1b[{}B", new.row - old.row)?;
+        } else if new.row < old.row {
+            write!(self.buffer, "\x1b[{}A", old.row - new.row)?;
+        }
+        if new.col > old.col {
+            write!(self.buffer, "\x1b[{}C", new.col - old.col)?;
+        } else if new.col < old.col {
+            write!(self.buffer, "\x1b[{}D", old.col - new.col)?;
+        }
+        self.flush_buffer()
+    }
+
+    fn refresh_line(
+        &mut self,
+        prompt: &str,
+        line: &LineBuffer,
+        hint: Option<&str>,
+        old_layout: &Layout,
+        new_layout: &Layout,
+        highlighter: Option<&dyn Highlighter>,
+    ) -> Result<()> {
+        self.buffer.clear();
+        let cursor = new_layout.cursor;
+        let end_pos = new_layout.end;
+
+        self.clear_old_rows(old_layout);
+        let start = self.buffer.len();
+        if let Some(highlighter) = highlighter {
+            self.buffer
+                .push_str(&highlighter.highlight_prompt(prompt, new_layout.default_prompt));
+            push_line(
+                &mut self.buffer,
+                &highlighter.highlight(line, line.pos()),
+                &highlighter.highlight_continuation_prompt(&self.continuation_prompt),
+            );
+            if let Some(hint) = hint {
+                self.buffer.push_str(&highlighter.highlight_hint(hint));
+            }
+        } else {
+            self.buffer.push_str(prompt);
+            push_line(&mut self.buffer, line, &self.continuation_prompt);
+            if let Some(hint) = hint {
+                self.buffer.push_str(hint);
+            }
+        }
+        expand_tabs(&mut self.buffer, start, self.tab_stop, Self::COLUMNS);
+        // we have to generate our own newline on line wrap
+        if end_pos.col == 0
+            && end_pos.row > 0
+            && !hint
+                .map(|h| h.ends_with('\n'))
+                .unwrap_or_else(|| line.ends_with('\n'))
+        {
+            self.buffer.push('\n');
+        }
+        // position the cursor
+        if end_pos.row > cursor.row {
+            write!(self.buffer, "\x1b[{}A", end_pos.row - cursor.row)?;
+        }
+        if cursor.col > 0 {
+            write!(self.buffer, "\r\x1b[{}C", cursor.col)?;
+        } else {
+            self.buffer.push('\r');
+        }
+        self.flush_buffer()
+    }
+
+    fn calculate_position(&self, s: &str, orig: Position) -> Position {
+        ansi_position(s, orig, self.tab_stop, Self::COLUMNS)
+    }
+
+    fn continuation_prompt(&self) -> &str {
+        &self.continuation_prompt
+    }
+
+    fn set_continuation_prompt(&mut self, prompt: &str) {
+        self.continuation_prompt = prompt.to_owned();
+    }
+
+    fn write_and_flush(&mut self, buf: &str) -> Result<()> {
+        self.out.write_all(buf.as_bytes())?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn beep(&mut self) -> Result<()> {
+        match self.bell_style {
+            BellStyle::Audible => self.write_and_flush("\x07"),
+            BellStyle::Visible => self.write_and_flush("\x1b[?5h\x1b[?5l"),
+            BellStyle::None => Ok(()),
+        }
+    }
+
+    fn clear_screen(&mut self) -> Result<()> {
+        self.write_and_flush("\x1b[H\x1b[J")
+    }
+
+    fn clear_rows(&mut self, layout: &Layout) -> Result<()> {
+        self.buffer.clear();
+        self.clear_old_rows(layout);
+        self.flush_buffer()
+    }
+
+    fn update_size(&mut self) {}
+
+    fn get_columns(&self) -> usize {
+        Self::COLUMNS
+    }
+
+    fn get_rows(&self) -> usize {
+        Self::ROWS
+    }
+
+    fn colors_enabled(&self) -> bool {
+        self.colors_enabled
+    }
+
+    fn move_cursor_at_leftmost(&mut self, _: &mut StreamReader<'a>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::StreamReader;
+    use crate::error::ReadlineError;
+    use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
+    use crate::tty::RawReader;
+
+    #[test]
+    fn next_key() {
+        let mut input = Cursor::new("a\u{e9}\r\n\x1b[A\x1b[1;5C\x1b[3~\x1bOP\x01\x1bb".as_bytes());
+        let mut after_cr = false;
+        let mut rdr = StreamReader::new(&mut input, &mut after_cr);
+        let mut keys = Vec::new();
+        loop {
+            match rdr.next_key(false) {
+                Ok(key) => keys.push(key),
+                Err(ReadlineError::Eof) => break,
+                Err(err) => panic!("{:?}", err),
+            }
+        }
+        assert_eq!(
+            vec![
+                E::from('a'),
+                E::from('\u{e9}'),
+                E::ENTER,
+                E(K::Up, M::NONE),
+                E(K::Right, M::CTRL),
+                E(K::Delete, M::NONE),
+                E(K::F(1), M::NONE),
+                E::ctrl('A'),
+                E::ESC,
+                E::from('b'),
+            ],
+            keys
+        );
+    }
+}
@@ -1,9 +1,10 @@
-//! Tests specific definitions
+//! Tests specific definitions, also used as the no-termios fallback on wasm32
 use std::iter::IntoIterator;
 use std::slice::Iter;
+use std::time::Duration;
 use std::vec::IntoIter;
 
-use super::{Event, ExternalPrinter, RawMode, RawReader, Renderer, Term};
+use super::{push_line, Event, ExternalPrinter, RawMode, RawReader, Renderer, Term};
 use crate::config::{Behavior, BellStyle, ColorMode, Config};
 use crate::error::ReadlineError;
 use crate::highlight::Highlighter;
@@ -33,6 +34,10 @@ impl<'a> RawReader for Iter<'a, KeyEvent> {
         }
     }
 
+    fn poll_input(&mut self, _: Duration) -> Result<bool> {
+        Ok(self.len() > 0)
+    }
+
     #[cfg(unix)]
     fn next_char(&mut self) -> Result<char> {
         unimplemented!();
@@ -59,6 +64,15 @@ impl RawReader for IntoIter<KeyEvent> {
         }
     }
 
+    fn poll_input(&mut self, timeout: Duration) -> Result<bool> {
+        if self.len() > 0 {
+            return Ok(true);
+        }
+        // no more key will come: behave like an idle terminal
+        std::thread::sleep(timeout);
+        Ok(false)
+    }
+
     #[cfg(unix)]
     fn next_char(&mut self) -> Result<char> {
         use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
@@ -70,7 +84,19 @@ impl RawReader for IntoIter<KeyEvent> {
     }
 
     fn read_pasted_text(&mut self) -> Result<String> {
-        unimplemented!()
+        use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
+        let mut buffer = String::new();
+        loop {
+            match self.next() {
+                Some(E(K::BracketedPasteEnd, M::NONE)) => break,
+                Some(E(K::Char(c), M::NONE)) => buffer.push(c),
+                Some(E(K::Enter, M::NONE)) => buffer.push('\n'),
+                Some(E(K::Tab, M::NONE)) => buffer.push('\t'),
+                Some(_) => continue,
+                None => return Err(ReadlineError::Eof),
+            }
+        }
+        Ok(buffer)
     }
 
     fn find_binding(&self, _: &KeyEvent) -> Option<Cmd> {
@@ -79,7 +105,13 @@ impl RawReader for IntoIter<KeyEvent> {
 }
 
 #[derive(Default)]
-pub struct Sink {}
+pub struct Sink {
+    pub written: String,      // text written by `write_and_flush` and bells
+    pub rendered: String,     // prompt, line and hint of the last `refresh_line`
+    pub colors_enabled: bool, // whether a highlighter is used
+    pub continuation_prompt: String,
+    pub bell_style: BellStyle,
+}
 
 impl Renderer for Sink {
     type Reader = IntoIter<KeyEvent>;
@@ -90,27 +122,65 @@ impl Renderer for Sink {
 
     fn refresh_line(
         &mut self,
-        _prompt: &str,
-        _line: &LineBuffer,
-        _hint: Option<&str>,
+        prompt: &str,
+        line: &LineBuffer,
+        hint: Option<&str>,
         _old_layout: &Layout,
-        _new_layout: &Layout,
-        _highlighter: Option<&dyn Highlighter>,
+        new_layout: &Layout,
+        highlighter: Option<&dyn Highlighter>,
     ) -> Result<()> {
+        self.rendered.clear();
+        if let Some(highlighter) = highlighter {
+            self.rendered
+                .push_str(&highlighter.highlight_prompt(prompt, new_layout.default_prompt));
+            push_line(
+                &mut self.rendered,
+                &highlighter.highlight(line, line.pos()),
+                &highlighter.highlight_continuation_prompt(&self.continuation_prompt),
+            );
+            if let Some(hint) = hint {
+                self.rendered.push_str(&highlighter.highlight_hint(hint));
+            }
+        } else {
+            self.rendered.push_str(prompt);
+            push_line(&mut self.rendered, line, &self.continuation_prompt);
+            if let Some(hint) = hint {
+                self.rendered.push_str(hint);
+            }
+        }
         Ok(())
     }
 
     fn calculate_position(&self, s: &str, orig: Position) -> Position {
+        // one column per byte, wrapped at `get_columns`
         let mut pos = orig;
         pos.col += s.len();
+        while pos.col > self.get_columns() {
+            pos.row += 1;
+            pos.col -= self.get_columns();
+        }
         pos
     }
 
-    fn write_and_flush(&mut self, _: &str) -> Result<()> {
+    fn continuation_prompt(&self) -> &str {
+        &self.continuation_prompt
+    }
+
+    fn set_continuation_prompt(&mut self, prompt: &str) {
+        self.continuation_prompt = prompt.to_owned();
+    }
+
+    fn write_and_flush(&mut self, buf: &str) -> Result<()> {
+        self.written.push_str(buf);
         Ok(())
     }
 
     fn beep(&mut self) -> Result<()> {
+        match self.bell_style {
+            BellStyle::Audible => self.written.push('\x07'),
+            BellStyle::Visible => self.written.push_str("\x1b[?5h\x1b[?5l"),
+            BellStyle::None => {}
+        }
         Ok(())
     }
 
@@ -133,7 +203,7 @@ impl Renderer for Sink {
     }
 
     fn colors_enabled(&self) -> bool {
-        false
+        self.colors_enabled
     }
 
     fn move_cursor_at_leftmost(&mut self, _: &mut IntoIter<KeyEvent>) -> Result<()> {
@@ -157,6 +227,9 @@ pub struct DummyTerminal {
     pub cursor: usize, // cursor position before last command
     pub color_mode: ColorMode,
     pub bell_style: BellStyle,
+    pub unsupported: bool,
+    #[cfg(unix)]
+    pub abort_pipe: Option<std::os::unix::io::RawFd>,
 }
 
 impl Term for DummyTerminal {
@@ -172,25 +245,23 @@ impl Term for DummyTerminal {
         _tab_stop: usize,
         bell_style: BellStyle,
         _enable_bracketed_paste: bool,
+        _ctrlc_as_signal: bool,
     ) -> Result<DummyTerminal> {
         Ok(DummyTerminal {
             keys: Vec::new(),
             cursor: 0,
             color_mode,
             bell_style,
+            unsupported: cfg!(target_arch = "wasm32"),
+            #[cfg(unix)]
+            abort_pipe: None,
         })
     }
 
     // Init checks:
 
-    #[cfg(not(target_arch = "wasm32"))]
-    fn is_unsupported(&self) -> bool {
-        false
-    }
-
-    #[cfg(target_arch = "wasm32")]
     fn is_unsupported(&self) -> bool {
-        true
+        self.unsupported
     }
 
     fn is_input_tty(&self) -> bool {
@@ -201,6 +272,10 @@ impl Term for DummyTerminal {
         false
     }
 
+    fn colors_enabled(&self) -> bool {
+        self.color_mode.colors_enabled(false)
+    }
+
     // Interactive loop:
 
     fn enable_raw_mode(&mut self) -> Result<(Mode, KeyMap)> {
@@ -212,7 +287,12 @@ impl Term for DummyTerminal {
     }
 
     fn create_writer(&self) -> Sink {
-        Sink::default()
+        Sink {
+            // like a terminal whose output is not a tty
+            colors_enabled: self.colors_enabled(),
+            bell_style: self.bell_style,
+            ..Sink::default()
+        }
     }
 
     fn create_external_printer(&mut self) -> Result<DummyExternalPrinter> {
@@ -228,3 +308,38 @@ impl Term for DummyTerminal {
 pub fn suspend() -> Result<()> {
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{DummyTerminal, Event, RawReader, Renderer, Term};
+    use crate::config::{Behavior, BellStyle, ColorMode, Config};
+    use crate::keys::KeyEvent;
+
+    #[test]
+    fn fallback_term() {
+        let mut term = DummyTerminal::new(
+            ColorMode::Enabled,
+            Behavior::default(),
+            8,
+            BellStyle::Audible,
+            true,
+            false,
+        )
+        .unwrap();
+        assert!(term.is_input_tty());
+        assert!(!term.is_output_tty());
+        term.keys.push(KeyEvent::from('a'));
+        let (_, key_map) = term.enable_raw_mode().unwrap();
+
+        let mut rdr = term.create_reader(&Config::default(), key_map);
+        match rdr.wait_for_input(false).unwrap() {
+            Event::KeyPress(key) => assert_eq!(KeyEvent::from('a'), key),
+            _ => panic!("unexpected event"),
+        }
+        let mut out = term.create_writer();
+        out.write_and_flush("a").unwrap();
+        out.beep().unwrap();
+        assert_eq!("a\x07", out.written);
+        term.writeln().unwrap();
+    }
+}
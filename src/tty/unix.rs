@@ -1,6 +1,6 @@
 //! Unix specific definitions
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fs::{File, OpenOptions};
 use std::io::{self, BufReader, ErrorKind, Read, Write};
 use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
@@ -8,19 +8,21 @@ use std::os::unix::net::UnixStream;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, SyncSender};
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use log::{debug, warn};
 use nix::errno::Errno;
 use nix::poll::{self, PollFlags};
 use nix::sys::select::{self, FdSet};
-use nix::sys::termios::{self, SetArg, SpecialCharacterIndices as SCI, Termios};
-use nix::unistd::{close, isatty, read, write};
-use unicode_segmentation::UnicodeSegmentation;
+use nix::sys::termios::{self, LocalFlags, SetArg, SpecialCharacterIndices as SCI, Termios};
+use nix::unistd::{close, dup, isatty, read, write};
 use utf8parse::{Parser, Receiver};
 
-use super::{width, Event, RawMode, RawReader, Renderer, Term};
+use super::{ansi_position, expand_tabs, push_line, Event, RawMode, RawReader, Renderer, Term};
 use crate::config::{Behavior, BellStyle, ColorMode, Config};
 use crate::highlight::Highlighter;
+use crate::keymap::{InputMode, SequenceTrie};
 use crate::keys::{KeyCode as K, KeyEvent, KeyEvent as E, Modifiers as M};
 use crate::layout::{Layout, Position};
 use crate::line_buffer::LineBuffer;
@@ -178,9 +180,16 @@ pub struct PosixRawReader {
     tty_in: BufReader<TtyIn>,
     timeout_ms: i32,
     parser: Parser,
+    replace_invalid_utf8: bool,
     key_map: PosixKeyMap,
+    // keys sent as byte sequences
+    sequences: SequenceTrie,
+    // bytes read while matching a sequence, not decoded yet
+    pending: VecDeque<u8>,
     // external print reader
     pipe_reader: Option<PipeReader>,
+    // readable when `Editor::readline_with_abort` is aborted
+    abort_pipe: Option<RawFd>,
     fds: FdSet,
 }
 
@@ -195,32 +204,6 @@ struct Utf8 {
     valid: bool,
 }
 
-const UP: char = 'A'; // kcuu1, kUP*
-const DOWN: char = 'B'; // kcud1, kDN*
-const RIGHT: char = 'C'; // kcuf1, kRIT*
-const LEFT: char = 'D'; // kcub1, kLFT*
-const END: char = 'F'; // kend*
-const HOME: char = 'H'; // khom*
-const INSERT: char = '2'; // kic*
-const DELETE: char = '3'; // kdch1, kDC*
-const PAGE_UP: char = '5'; // kpp, kPRV*
-const PAGE_DOWN: char = '6'; // knp, kNXT*
-
-const RXVT_HOME: char = '7';
-const RXVT_END: char = '8';
-
-const SHIFT: char = '2';
-const ALT: char = '3';
-const ALT_SHIFT: char = '4';
-const CTRL: char = '5';
-const CTRL_SHIFT: char = '6';
-const CTRL_ALT: char = '7';
-const CTRL_ALT_SHIFT: char = '8';
-
-const RXVT_SHIFT: char = '$';
-const RXVT_CTRL: char = '\x1e';
-const RXVT_CTRL_SHIFT: char = '@';
-
 impl PosixRawReader {
     fn new(
         fd: RawFd,
@@ -233,14 +216,91 @@ impl PosixRawReader {
             tty_in: BufReader::with_capacity(1024, TtyIn { fd, sigwinch_pipe }),
             timeout_ms: config.keyseq_timeout(),
             parser: Parser::new(),
+            replace_invalid_utf8: config.replace_invalid_utf8(),
             key_map,
+            sequences: SequenceTrie::new(),
+            pending: VecDeque::new(),
             pipe_reader,
+            abort_pipe: None,
             fds: FdSet::new(),
         }
     }
 
-    /// Handle \E <seq1> sequences
-    // https://invisible-island.net/xterm/xterm-function-keys.html
+    /// Time (in milliseconds, negative for no limit) to wait for the next
+    /// byte of a partially received sequence
+    fn keyseq_timeout(&self, single_esc_abort: bool) -> i32 {
+        if single_esc_abort && self.timeout_ms == -1 {
+            0
+        } else {
+            self.timeout_ms
+        }
+    }
+
+    /// Read the next byte, the ones put back by `unread` first
+    fn next_byte(&mut self) -> Result<u8> {
+        if let Some(b) = self.pending.pop_front() {
+            return Ok(b);
+        }
+        let mut buf = [0; 1];
+        let n = self.tty_in.read(&mut buf)?;
+        if n == 0 {
+            return Err(error::ReadlineError::Eof);
+        }
+        Ok(buf[0])
+    }
+
+    /// Put back `bytes`, to be read again before any other input
+    fn unread(&mut self, bytes: &[u8]) {
+        for b in bytes.iter().rev() {
+            self.pending.push_front(*b);
+        }
+    }
+
+    /// Consume the longest sequence of `sequences` at the start of the
+    /// input. Bytes are matched one at a time: the next one of a partial
+    /// sequence may come from another read, if received within
+    /// `timeout_ms`. Otherwise (or if there is no match), the bytes are put
+    /// back to be processed individually.
+    fn sequence(&mut self, timeout_ms: i32) -> Result<Option<KeyEvent>> {
+        let mut seq = Vec::new();
+        let mut found = None;
+        loop {
+            if !seq.is_empty() {
+                match self.poll(timeout_ms) {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        self.unread(&seq);
+                        return Err(e);
+                    }
+                }
+            }
+            match self.next_byte() {
+                Ok(b) => seq.push(b),
+                Err(e) if seq.is_empty() => return Err(e),
+                Err(_) => break, // reported by the next read
+            }
+            match self.sequences.get(&seq) {
+                Some(node) => {
+                    if let Some(key) = node.key() {
+                        found = Some((seq.len(), key));
+                    }
+                    if node.is_leaf() {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+        let n = found.map_or(0, |(n, _)| n);
+        self.unread(&seq[n..]);
+        if let Some((_, key)) = found {
+            debug!(target: "rustyline", "sequence: {:?} => {:?}", &seq[..n], key);
+        }
+        Ok(found.map(|(_, key)| key))
+    }
+
+    /// Handle \E <seq1> sequences which are not in `sequences`
     fn escape_sequence(&mut self) -> Result<KeyEvent> {
         self._do_escape_sequence(true)
     }
@@ -251,11 +311,13 @@ impl PosixRawReader {
         let seq1 = self.next_char()?;
         if seq1 == '[' {
             // \E[ sequences. (CSI)
-            self.escape_csi()
+            self.unsupported_csi()
         } else if seq1 == 'O' {
             // xterm
             // \EO sequences. (SS3)
-            self.escape_o()
+            let seq2 = self.next_char()?;
+            debug!(target: "rustyline", "unsupported esc sequence: \\EO{:?}", seq2);
+            Ok(E(K::UnknownEscSeq, M::NONE))
         } else if seq1 == '\x1b' {
             // \E\E — used by rxvt, iTerm (under default config), etc.
             // ```
@@ -287,7 +349,14 @@ impl PosixRawReader {
                 Ok(n) => {
                     debug_assert!(n > 0, "{}", n);
                     // recurse, and add the alt modifier.
-                    let E(k, m) = self._do_escape_sequence(false)?;
+                    self.unread(b"\x1b");
+                    let E(k, m) = match self.sequence(timeout)? {
+                        Some(key) => key,
+                        None => {
+                            self.next_byte()?;
+                            self._do_escape_sequence(false)?
+                        }
+                    };
                     Ok(E(k, m | M::ALT))
                 }
             }
@@ -296,391 +365,23 @@ impl PosixRawReader {
         }
     }
 
-    /// Handle \E[ <seq2> escape sequences
-    fn escape_csi(&mut self) -> Result<KeyEvent> {
-        let seq2 = self.next_char()?;
-        if seq2.is_ascii_digit() {
-            match seq2 {
-                '0' | '9' => {
-                    debug!(target: "rustyline", "unsupported esc sequence: \\E[{:?}", seq2);
-                    Ok(E(K::UnknownEscSeq, M::NONE))
-                }
-                _ => {
-                    // Extended escape, read additional byte.
-                    self.extended_escape(seq2)
-                }
-            }
-        } else if seq2 == '[' {
-            let seq3 = self.next_char()?;
-            // Linux console
-            Ok(match seq3 {
-                'A' => E(K::F(1), M::NONE),
-                'B' => E(K::F(2), M::NONE),
-                'C' => E(K::F(3), M::NONE),
-                'D' => E(K::F(4), M::NONE),
-                'E' => E(K::F(5), M::NONE),
-                _ => {
-                    debug!(target: "rustyline", "unsupported esc sequence: \\E[[{:?}", seq3);
-                    E(K::UnknownEscSeq, M::NONE)
-                }
-            })
-        } else {
-            // ANSI
-            Ok(match seq2 {
-                UP => E(K::Up, M::NONE),
-                DOWN => E(K::Down, M::NONE),
-                RIGHT => E(K::Right, M::NONE),
-                LEFT => E(K::Left, M::NONE),
-                //'E' => E(K::, M::), // Ignore
-                END => E(K::End, M::NONE),
-                //'G' => E(K::, M::), // Ignore
-                HOME => E(K::Home, M::NONE), // khome
-                //'J' => E(K::, M::), // clr_eos
-                //'K' => E(K::, M::), // clr_eol
-                //'L' => E(K::, M::), // il1
-                //'M' => E(K::, M::), // kmous
-                //'P' => E(K::Delete, M::NONE), // dch1
-                'Z' => E(K::BackTab, M::NONE),
-                'a' => E(K::Up, M::SHIFT),    // rxvt: kind or kUP
-                'b' => E(K::Down, M::SHIFT),  // rxvt: kri or kDN
-                'c' => E(K::Right, M::SHIFT), // rxvt
-                'd' => E(K::Left, M::SHIFT),  // rxvt
-                _ => {
-                    debug!(target: "rustyline", "unsupported esc sequence: \\E[{:?}", seq2);
-                    E(K::UnknownEscSeq, M::NONE)
-                }
-            })
-        }
-    }
-
-    /// Handle \E[ <seq2:digit> escape sequences
-    #[allow(clippy::cognitive_complexity)]
-    fn extended_escape(&mut self, seq2: char) -> Result<KeyEvent> {
-        let seq3 = self.next_char()?;
-        if seq3 == '~' {
-            Ok(match seq2 {
-                '1' | RXVT_HOME => E(K::Home, M::NONE), // tmux, xrvt
-                INSERT => E(K::Insert, M::NONE),
-                DELETE => E(K::Delete, M::NONE),
-                '4' | RXVT_END => E(K::End, M::NONE), // tmux, xrvt
-                PAGE_UP => E(K::PageUp, M::NONE),
-                PAGE_DOWN => E(K::PageDown, M::NONE),
-                _ => {
-                    debug!(target: "rustyline",
-                           "unsupported esc sequence: \\E[{}~", seq2);
-                    E(K::UnknownEscSeq, M::NONE)
-                }
-            })
-        } else if seq3.is_ascii_digit() {
-            let seq4 = self.next_char()?;
-            if seq4 == '~' {
-                Ok(match (seq2, seq3) {
-                    ('1', '1') => E(K::F(1), M::NONE),  // rxvt-unicode
-                    ('1', '2') => E(K::F(2), M::NONE),  // rxvt-unicode
-                    ('1', '3') => E(K::F(3), M::NONE),  // rxvt-unicode
-                    ('1', '4') => E(K::F(4), M::NONE),  // rxvt-unicode
-                    ('1', '5') => E(K::F(5), M::NONE),  // kf5
-                    ('1', '7') => E(K::F(6), M::NONE),  // kf6
-                    ('1', '8') => E(K::F(7), M::NONE),  // kf7
-                    ('1', '9') => E(K::F(8), M::NONE),  // kf8
-                    ('2', '0') => E(K::F(9), M::NONE),  // kf9
-                    ('2', '1') => E(K::F(10), M::NONE), // kf10
-                    ('2', '3') => E(K::F(11), M::NONE), // kf11
-                    ('2', '4') => E(K::F(12), M::NONE), // kf12
-                    //('6', '2') => KeyCode::ScrollUp,
-                    //('6', '3') => KeyCode::ScrollDown,
-                    _ => {
-                        debug!(target: "rustyline",
-                               "unsupported esc sequence: \\E[{}{}~", seq2, seq3);
-                        E(K::UnknownEscSeq, M::NONE)
-                    }
-                })
-            } else if seq4 == ';' {
-                let seq5 = self.next_char()?;
-                if seq5.is_ascii_digit() {
-                    let seq6 = self.next_char()?;
-                    if seq6.is_ascii_digit() {
-                        self.next_char()?; // 'R' expected
-                        Ok(E(K::UnknownEscSeq, M::NONE))
-                    } else if seq6 == 'R' {
-                        Ok(E(K::UnknownEscSeq, M::NONE))
-                    } else if seq6 == '~' {
-                        Ok(match (seq2, seq3, seq5) {
-                            ('1', '5', CTRL) => E(K::F(5), M::CTRL),
-                            //('1', '5', '6') => E(K::F(17), M::CTRL),
-                            ('1', '7', CTRL) => E(K::F(6), M::CTRL),
-                            //('1', '7', '6') => E(K::F(18), M::CTRL),
-                            ('1', '8', CTRL) => E(K::F(7), M::CTRL),
-                            ('1', '9', CTRL) => E(K::F(8), M::CTRL),
-                            //('1', '9', '6') => E(K::F(19), M::CTRL),
-                            ('2', '0', CTRL) => E(K::F(9), M::CTRL),
-                            //('2', '0', '6') => E(K::F(21), M::CTRL),
-                            ('2', '1', CTRL) => E(K::F(10), M::CTRL),
-                            //('2', '1', '6') => E(K::F(22), M::CTRL),
-                            ('2', '3', CTRL) => E(K::F(11), M::CTRL),
-                            //('2', '3', '6') => E(K::F(23), M::CTRL),
-                            ('2', '4', CTRL) => E(K::F(12), M::CTRL),
-                            //('2', '4', '6') => E(K::F(24), M::CTRL),
-                            _ => {
-                                debug!(target: "rustyline",
-                                       "unsupported esc sequence: \\E[{}{};{}~", seq2, seq3, seq5);
-                                E(K::UnknownEscSeq, M::NONE)
-                            }
-                        })
-                    } else {
-                        debug!(target: "rustyline",
-                               "unsupported esc sequence: \\E[{}{};{}{}", seq2, seq3, seq5, seq6);
-                        Ok(E(K::UnknownEscSeq, M::NONE))
-                    }
-                } else {
-                    debug!(target: "rustyline",
-                           "unsupported esc sequence: \\E[{}{};{:?}", seq2, seq3, seq5);
-                    Ok(E(K::UnknownEscSeq, M::NONE))
-                }
-            } else if seq4.is_ascii_digit() {
-                let seq5 = self.next_char()?;
-                if seq5 == '~' {
-                    Ok(match (seq2, seq3, seq4) {
-                        ('2', '0', '0') => E(K::BracketedPasteStart, M::NONE),
-                        ('2', '0', '1') => E(K::BracketedPasteEnd, M::NONE),
-                        _ => {
-                            debug!(target: "rustyline",
-                                   "unsupported esc sequence: \\E[{}{}{}~", seq2, seq3, seq4);
-                            E(K::UnknownEscSeq, M::NONE)
-                        }
-                    })
-                } else {
-                    debug!(target: "rustyline",
-                           "unsupported esc sequence: \\E[{}{}{}{}", seq2, seq3, seq4, seq5);
-                    Ok(E(K::UnknownEscSeq, M::NONE))
-                }
-            } else {
-                debug!(target: "rustyline",
-                       "unsupported esc sequence: \\E[{}{}{:?}", seq2, seq3, seq4);
-                Ok(E(K::UnknownEscSeq, M::NONE))
-            }
-        } else if seq3 == ';' {
-            let seq4 = self.next_char()?;
-            if seq4.is_ascii_digit() {
-                let seq5 = self.next_char()?;
-                if seq5.is_ascii_digit() {
-                    self.next_char()?; // 'R' expected
-                                       //('1', '0', UP) => E(K::, M::), // Alt + Shift + Up
-                    Ok(E(K::UnknownEscSeq, M::NONE))
-                } else if seq2 == '1' {
-                    Ok(match (seq4, seq5) {
-                        (SHIFT, UP) => E(K::Up, M::SHIFT),     // ~ key_sr
-                        (SHIFT, DOWN) => E(K::Down, M::SHIFT), // ~ key_sf
-                        (SHIFT, RIGHT) => E(K::Right, M::SHIFT),
-                        (SHIFT, LEFT) => E(K::Left, M::SHIFT),
-                        (SHIFT, END) => E(K::End, M::SHIFT), // kEND
-                        (SHIFT, HOME) => E(K::Home, M::SHIFT), // kHOM
-                        //('2', 'P') => E(K::F(13), M::NONE),
-                        //('2', 'Q') => E(K::F(14), M::NONE),
-                        //('2', 'S') => E(K::F(16), M::NONE),
-                        (ALT, UP) => E(K::Up, M::ALT),
-                        (ALT, DOWN) => E(K::Down, M::ALT),
-                        (ALT, RIGHT) => E(K::Right, M::ALT),
-                        (ALT, LEFT) => E(K::Left, M::ALT),
-                        (ALT, END) => E(K::End, M::ALT),
-                        (ALT, HOME) => E(K::Home, M::ALT),
-                        (ALT_SHIFT, UP) => E(K::Up, M::ALT_SHIFT),
-                        (ALT_SHIFT, DOWN) => E(K::Down, M::ALT_SHIFT),
-                        (ALT_SHIFT, RIGHT) => E(K::Right, M::ALT_SHIFT),
-                        (ALT_SHIFT, LEFT) => E(K::Left, M::ALT_SHIFT),
-                        (ALT_SHIFT, END) => E(K::End, M::ALT_SHIFT),
-                        (ALT_SHIFT, HOME) => E(K::Home, M::ALT_SHIFT),
-                        (CTRL, UP) => E(K::Up, M::CTRL),
-                        (CTRL, DOWN) => E(K::Down, M::CTRL),
-                        (CTRL, RIGHT) => E(K::Right, M::CTRL),
-                        (CTRL, LEFT) => E(K::Left, M::CTRL),
-                        (CTRL, END) => E(K::End, M::CTRL),
-                        (CTRL, HOME) => E(K::Home, M::CTRL),
-                        (CTRL, 'P') => E(K::F(1), M::CTRL),
-                        (CTRL, 'Q') => E(K::F(2), M::CTRL),
-                        (CTRL, 'S') => E(K::F(4), M::CTRL),
-                        (CTRL, 'p') => E(K::Char('0'), M::CTRL),
-                        (CTRL, 'q') => E(K::Char('1'), M::CTRL),
-                        (CTRL, 'r') => E(K::Char('2'), M::CTRL),
-                        (CTRL, 's') => E(K::Char('3'), M::CTRL),
-                        (CTRL, 't') => E(K::Char('4'), M::CTRL),
-                        (CTRL, 'u') => E(K::Char('5'), M::CTRL),
-                        (CTRL, 'v') => E(K::Char('6'), M::CTRL),
-                        (CTRL, 'w') => E(K::Char('7'), M::CTRL),
-                        (CTRL, 'x') => E(K::Char('8'), M::CTRL),
-                        (CTRL, 'y') => E(K::Char('9'), M::CTRL),
-                        (CTRL_SHIFT, UP) => E(K::Up, M::CTRL_SHIFT),
-                        (CTRL_SHIFT, DOWN) => E(K::Down, M::CTRL_SHIFT),
-                        (CTRL_SHIFT, RIGHT) => E(K::Right, M::CTRL_SHIFT),
-                        (CTRL_SHIFT, LEFT) => E(K::Left, M::CTRL_SHIFT),
-                        (CTRL_SHIFT, END) => E(K::End, M::CTRL_SHIFT),
-                        (CTRL_SHIFT, HOME) => E(K::Home, M::CTRL_SHIFT),
-                        //('6', 'P') => E(K::F(13), M::CTRL),
-                        //('6', 'Q') => E(K::F(14), M::CTRL),
-                        //('6', 'S') => E(K::F(16), M::CTRL),
-                        (CTRL_SHIFT, 'p') => E(K::Char('0'), M::CTRL_SHIFT),
-                        (CTRL_SHIFT, 'q') => E(K::Char('1'), M::CTRL_SHIFT),
-                        (CTRL_SHIFT, 'r') => E(K::Char('2'), M::CTRL_SHIFT),
-                        (CTRL_SHIFT, 's') => E(K::Char('3'), M::CTRL_SHIFT),
-                        (CTRL_SHIFT, 't') => E(K::Char('4'), M::CTRL_SHIFT),
-                        (CTRL_SHIFT, 'u') => E(K::Char('5'), M::CTRL_SHIFT),
-                        (CTRL_SHIFT, 'v') => E(K::Char('6'), M::CTRL_SHIFT),
-                        (CTRL_SHIFT, 'w') => E(K::Char('7'), M::CTRL_SHIFT),
-                        (CTRL_SHIFT, 'x') => E(K::Char('8'), M::CTRL_SHIFT),
-                        (CTRL_SHIFT, 'y') => E(K::Char('9'), M::CTRL_SHIFT),
-                        (CTRL_ALT, UP) => E(K::Up, M::CTRL_ALT),
-                        (CTRL_ALT, DOWN) => E(K::Down, M::CTRL_ALT),
-                        (CTRL_ALT, RIGHT) => E(K::Right, M::CTRL_ALT),
-                        (CTRL_ALT, LEFT) => E(K::Left, M::CTRL_ALT),
-                        (CTRL_ALT, END) => E(K::End, M::CTRL_ALT),
-                        (CTRL_ALT, HOME) => E(K::Home, M::CTRL_ALT),
-                        (CTRL_ALT, 'p') => E(K::Char('0'), M::CTRL_ALT),
-                        (CTRL_ALT, 'q') => E(K::Char('1'), M::CTRL_ALT),
-                        (CTRL_ALT, 'r') => E(K::Char('2'), M::CTRL_ALT),
-                        (CTRL_ALT, 's') => E(K::Char('3'), M::CTRL_ALT),
-                        (CTRL_ALT, 't') => E(K::Char('4'), M::CTRL_ALT),
-                        (CTRL_ALT, 'u') => E(K::Char('5'), M::CTRL_ALT),
-                        (CTRL_ALT, 'v') => E(K::Char('6'), M::CTRL_ALT),
-                        (CTRL_ALT, 'w') => E(K::Char('7'), M::CTRL_ALT),
-                        (CTRL_ALT, 'x') => E(K::Char('8'), M::CTRL_ALT),
-                        (CTRL_ALT, 'y') => E(K::Char('9'), M::CTRL_ALT),
-                        (CTRL_ALT_SHIFT, UP) => E(K::Up, M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, DOWN) => E(K::Down, M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, RIGHT) => E(K::Right, M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, LEFT) => E(K::Left, M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, END) => E(K::End, M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, HOME) => E(K::Home, M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, 'p') => E(K::Char('0'), M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, 'q') => E(K::Char('1'), M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, 'r') => E(K::Char('2'), M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, 's') => E(K::Char('3'), M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, 't') => E(K::Char('4'), M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, 'u') => E(K::Char('5'), M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, 'v') => E(K::Char('6'), M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, 'w') => E(K::Char('7'), M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, 'x') => E(K::Char('8'), M::CTRL_ALT_SHIFT),
-                        (CTRL_ALT_SHIFT, 'y') => E(K::Char('9'), M::CTRL_ALT_SHIFT),
-                        // Meta + arrow on (some?) Macs when using iTerm defaults
-                        ('9', UP) => E(K::Up, M::ALT),
-                        ('9', DOWN) => E(K::Down, M::ALT),
-                        ('9', RIGHT) => E(K::Right, M::ALT),
-                        ('9', LEFT) => E(K::Left, M::ALT),
-                        _ => {
-                            debug!(target: "rustyline",
-                                   "unsupported esc sequence: \\E[1;{}{:?}", seq4, seq5);
-                            E(K::UnknownEscSeq, M::NONE)
-                        }
-                    })
-                } else if seq5 == '~' {
-                    Ok(match (seq2, seq4) {
-                        (INSERT, SHIFT) => E(K::Insert, M::SHIFT),
-                        (INSERT, ALT) => E(K::Insert, M::ALT),
-                        (INSERT, ALT_SHIFT) => E(K::Insert, M::ALT_SHIFT),
-                        (INSERT, CTRL) => E(K::Insert, M::CTRL),
-                        (INSERT, CTRL_SHIFT) => E(K::Insert, M::CTRL_SHIFT),
-                        (INSERT, CTRL_ALT) => E(K::Insert, M::CTRL_ALT),
-                        (INSERT, CTRL_ALT_SHIFT) => E(K::Insert, M::CTRL_ALT_SHIFT),
-                        (DELETE, SHIFT) => E(K::Delete, M::SHIFT),
-                        (DELETE, ALT) => E(K::Delete, M::ALT),
-                        (DELETE, ALT_SHIFT) => E(K::Delete, M::ALT_SHIFT),
-                        (DELETE, CTRL) => E(K::Delete, M::CTRL),
-                        (DELETE, CTRL_SHIFT) => E(K::Delete, M::CTRL_SHIFT),
-                        (DELETE, CTRL_ALT) => E(K::Delete, M::CTRL_ALT),
-                        (DELETE, CTRL_ALT_SHIFT) => E(K::Delete, M::CTRL_ALT_SHIFT),
-                        (PAGE_UP, SHIFT) => E(K::PageUp, M::SHIFT),
-                        (PAGE_UP, ALT) => E(K::PageUp, M::ALT),
-                        (PAGE_UP, ALT_SHIFT) => E(K::PageUp, M::ALT_SHIFT),
-                        (PAGE_UP, CTRL) => E(K::PageUp, M::CTRL),
-                        (PAGE_UP, CTRL_SHIFT) => E(K::PageUp, M::CTRL_SHIFT),
-                        (PAGE_UP, CTRL_ALT) => E(K::PageUp, M::CTRL_ALT),
-                        (PAGE_UP, CTRL_ALT_SHIFT) => E(K::PageUp, M::CTRL_ALT_SHIFT),
-                        (PAGE_DOWN, SHIFT) => E(K::PageDown, M::SHIFT),
-                        (PAGE_DOWN, ALT) => E(K::PageDown, M::ALT),
-                        (PAGE_DOWN, ALT_SHIFT) => E(K::PageDown, M::ALT_SHIFT),
-                        (PAGE_DOWN, CTRL) => E(K::PageDown, M::CTRL),
-                        (PAGE_DOWN, CTRL_SHIFT) => E(K::PageDown, M::CTRL_SHIFT),
-                        (PAGE_DOWN, CTRL_ALT) => E(K::PageDown, M::CTRL_ALT),
-                        (PAGE_DOWN, CTRL_ALT_SHIFT) => E(K::PageDown, M::CTRL_ALT_SHIFT),
-                        _ => {
-                            debug!(target: "rustyline",
-                                   "unsupported esc sequence: \\E[{};{:?}~", seq2, seq4);
-                            E(K::UnknownEscSeq, M::NONE)
-                        }
-                    })
-                } else {
-                    debug!(target: "rustyline",
-                           "unsupported esc sequence: \\E[{};{}{:?}", seq2, seq4, seq5);
-                    Ok(E(K::UnknownEscSeq, M::NONE))
-                }
-            } else {
-                debug!(target: "rustyline",
-                       "unsupported esc sequence: \\E[{};{:?}", seq2, seq4);
-                Ok(E(K::UnknownEscSeq, M::NONE))
+    /// Skip an unsupported \E[ sequence: parameter and intermediate bytes
+    /// up to the final byte
+    fn unsupported_csi(&mut self) -> Result<KeyEvent> {
+        let mut seq = String::new();
+        loop {
+            let c = self.next_char()?;
+            seq.push(c);
+            if !('\x20'..='\x3f').contains(&c) {
+                break;
             }
-        } else {
-            Ok(match (seq2, seq3) {
-                (DELETE, RXVT_CTRL) => E(K::Delete, M::CTRL),
-                (DELETE, RXVT_CTRL_SHIFT) => E(K::Delete, M::CTRL_SHIFT),
-                (CTRL, UP) => E(K::Up, M::CTRL),
-                (CTRL, DOWN) => E(K::Down, M::CTRL),
-                (CTRL, RIGHT) => E(K::Right, M::CTRL),
-                (CTRL, LEFT) => E(K::Left, M::CTRL),
-                (PAGE_UP, RXVT_CTRL) => E(K::PageUp, M::CTRL),
-                (PAGE_UP, RXVT_SHIFT) => E(K::PageUp, M::SHIFT),
-                (PAGE_UP, RXVT_CTRL_SHIFT) => E(K::PageUp, M::CTRL_SHIFT),
-                (PAGE_DOWN, RXVT_CTRL) => E(K::PageDown, M::CTRL),
-                (PAGE_DOWN, RXVT_SHIFT) => E(K::PageDown, M::SHIFT),
-                (PAGE_DOWN, RXVT_CTRL_SHIFT) => E(K::PageDown, M::CTRL_SHIFT),
-                (RXVT_HOME, RXVT_CTRL) => E(K::Home, M::CTRL),
-                (RXVT_HOME, RXVT_SHIFT) => E(K::Home, M::SHIFT),
-                (RXVT_HOME, RXVT_CTRL_SHIFT) => E(K::Home, M::CTRL_SHIFT),
-                (RXVT_END, RXVT_CTRL) => E(K::End, M::CTRL), // kEND5 or kel
-                (RXVT_END, RXVT_SHIFT) => E(K::End, M::SHIFT),
-                (RXVT_END, RXVT_CTRL_SHIFT) => E(K::End, M::CTRL_SHIFT),
-                _ => {
-                    debug!(target: "rustyline",
-                           "unsupported esc sequence: \\E[{}{:?}", seq2, seq3);
-                    E(K::UnknownEscSeq, M::NONE)
-                }
-            })
         }
-    }
-
-    /// Handle \EO <seq2> escape sequences
-    fn escape_o(&mut self) -> Result<KeyEvent> {
-        let seq2 = self.next_char()?;
-        Ok(match seq2 {
-            UP => E(K::Up, M::NONE),
-            DOWN => E(K::Down, M::NONE),
-            RIGHT => E(K::Right, M::NONE),
-            LEFT => E(K::Left, M::NONE),
-            //'E' => E(K::, M::),// key_b2, kb2
-            END => E(K::End, M::NONE),   // kend
-            HOME => E(K::Home, M::NONE), // khome
-            'M' => E::ENTER,             // kent
-            'P' => E(K::F(1), M::NONE),  // kf1
-            'Q' => E(K::F(2), M::NONE),  // kf2
-            'R' => E(K::F(3), M::NONE),  // kf3
-            'S' => E(K::F(4), M::NONE),  // kf4
-            'a' => E(K::Up, M::CTRL),
-            'b' => E(K::Down, M::CTRL),
-            'c' => E(K::Right, M::CTRL), // rxvt
-            'd' => E(K::Left, M::CTRL),  // rxvt
-            'l' => E(K::F(8), M::NONE),
-            't' => E(K::F(5), M::NONE),  // kf5 or kb1
-            'u' => E(K::F(6), M::NONE),  // kf6 or kb2
-            'v' => E(K::F(7), M::NONE),  // kf7 or kb3
-            'w' => E(K::F(9), M::NONE),  // kf9 or ka1
-            'x' => E(K::F(10), M::NONE), // kf10 or ka2
-            _ => {
-                debug!(target: "rustyline", "unsupported esc sequence: \\EO{:?}", seq2);
-                E(K::UnknownEscSeq, M::NONE)
-            }
-        })
+        debug!(target: "rustyline", "unsupported esc sequence: \\E[{:?}", seq);
+        Ok(E(K::UnknownEscSeq, M::NONE))
     }
 
     fn poll(&mut self, timeout_ms: i32) -> Result<i32> {
-        let n = self.tty_in.buffer().len();
+        let n = self.tty_in.buffer().len() + self.pending.len();
         if n > 0 {
             return Ok(n as i32);
         }
@@ -706,6 +407,11 @@ impl PosixRawReader {
             .pipe_reader
             .as_ref()
             .map(|pr| pr.lock().unwrap().0.as_raw_fd());
+        let abort_pipe = self.abort_pipe;
+        if !self.tty_in.buffer().is_empty() || !self.pending.is_empty() {
+            // already read from `tty_in`: `select` would not see it
+            return self.next_key(single_esc_abort).map(Event::KeyPress);
+        }
         loop {
             let mut readfds = self.fds;
             readfds.clear();
@@ -716,6 +422,9 @@ impl PosixRawReader {
             if let Some(pipe_reader) = pipe_reader {
                 readfds.insert(pipe_reader);
             }
+            if let Some(abort_pipe) = abort_pipe {
+                readfds.insert(abort_pipe);
+            }
             if let Err(err) = select::select(
                 readfds.highest().map(|h| h + 1),
                 Some(&mut readfds),
@@ -731,7 +440,9 @@ impl PosixRawReader {
                     continue;
                 }
             };
-            if sigwinch_pipe.map_or(false, |fd| readfds.contains(fd)) {
+            if matches!(abort_pipe, Some(fd) if readfds.contains(fd)) {
+                return Err(ReadlineError::Interrupted);
+            } else if sigwinch_pipe.map_or(false, |fd| readfds.contains(fd)) {
                 self.tty_in.get_ref().sigwinch()?;
                 return Err(ReadlineError::WindowResized);
             } else if readfds.contains(tty_in) {
@@ -752,9 +463,10 @@ impl PosixRawReader {
 impl RawReader for PosixRawReader {
     #[cfg(not(feature = "signal-hook"))]
     fn wait_for_input(&mut self, single_esc_abort: bool) -> Result<Event> {
-        match self.pipe_reader {
-            Some(_) => self.select(single_esc_abort),
-            None => self.next_key(single_esc_abort).map(Event::KeyPress),
+        if self.pipe_reader.is_some() || self.abort_pipe.is_some() {
+            self.select(single_esc_abort)
+        } else {
+            self.next_key(single_esc_abort).map(Event::KeyPress)
         }
     }
 
@@ -764,48 +476,55 @@ impl RawReader for PosixRawReader {
     }
 
     fn next_key(&mut self, single_esc_abort: bool) -> Result<KeyEvent> {
+        let timeout_ms = self.keyseq_timeout(single_esc_abort);
+        if let Some(key) = self.sequence(timeout_ms)? {
+            return Ok(key);
+        }
         let c = self.next_char()?;
 
         let mut key = KeyEvent::new(c, M::NONE);
-        if key == E::ESC {
-            if !self.tty_in.buffer().is_empty() {
-                debug!(target: "rustyline", "read buffer {:?}", self.tty_in.buffer());
-            }
-            let timeout_ms = if single_esc_abort && self.timeout_ms == -1 {
-                0
-            } else {
-                self.timeout_ms
-            };
-            match self.poll(timeout_ms) {
-                Ok(n) if n == 0 => {
-                    // single escape
-                }
-                Ok(_) => {
-                    // escape sequence
-                    key = self.escape_sequence()?
-                }
-                // Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
-                Err(e) => return Err(e),
-            }
+        // `sequence` has already waited for the byte following ESC: a single
+        // escape otherwise
+        if key == E::ESC && !self.pending.is_empty() {
+            debug!(target: "rustyline", "unmatched sequence {:?}", self.pending);
+            key = self.escape_sequence()?;
         }
         debug!(target: "rustyline", "c: {:?} => key: {:?}", c, key);
         Ok(key)
     }
 
+    fn poll_input(&mut self, timeout: Duration) -> Result<bool> {
+        use std::convert::TryFrom;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            // rounded up so that the deadline is reached
+            let timeout_ms = (remaining + Duration::from_micros(999)).as_millis();
+            let timeout_ms = i32::try_from(timeout_ms).unwrap_or(i32::MAX);
+            // `poll` returns 0 when interrupted by a signal (EINTR): wait for
+            // the remaining time
+            let n = self.poll(timeout_ms)?;
+            if n > 0 || Instant::now() >= deadline {
+                return Ok(n > 0);
+            }
+        }
+    }
+
     fn next_char(&mut self) -> Result<char> {
-        let mut buf = [0; 1];
         let mut receiver = Utf8 {
             c: None,
             valid: true,
         };
         loop {
-            let n = self.tty_in.read(&mut buf)?;
-            if n == 0 {
-                return Err(error::ReadlineError::Eof);
-            }
-            let b = buf[0];
+            // a multibyte char may be split across reads: the parser keeps
+            // the leading bytes until the char is complete
+            let b = self.next_byte()?;
             self.parser.advance(&mut receiver, b);
             if !receiver.valid {
+                if self.replace_invalid_utf8 {
+                    return Ok(char::REPLACEMENT_CHARACTER);
+                }
                 return Err(error::ReadlineError::from(ErrorKind::InvalidData));
             } else if let Some(c) = receiver.c.take() {
                 return Ok(c);
@@ -818,7 +537,14 @@ impl RawReader for PosixRawReader {
         loop {
             match self.next_char()? {
                 '\x1b' => {
-                    let key = self.escape_sequence()?;
+                    self.unread(b"\x1b");
+                    let key = match self.sequence(self.timeout_ms)? {
+                        Some(key) => key,
+                        None => {
+                            self.next_byte()?;
+                            self.escape_sequence()?
+                        }
+                    };
                     if key == E(K::BracketedPasteEnd, M::NONE) {
                         break;
                     } else {
@@ -840,6 +566,15 @@ impl RawReader for PosixRawReader {
         }
         cmd
     }
+
+    fn set_sequences(&mut self, sequences: &SequenceTrie) {
+        self.sequences = sequences.clone();
+    }
+
+    #[cfg(feature = "async")]
+    fn feed(&mut self, bytes: &[u8]) {
+        self.pending.extend(bytes);
+    }
 }
 
 impl Receiver for Utf8 {
@@ -856,6 +591,9 @@ impl Receiver for Utf8 {
     }
 }
 
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+
 /// Console output writer
 pub struct PosixRenderer {
     out: RawFd,
@@ -864,6 +602,7 @@ pub struct PosixRenderer {
     tab_stop: usize,
     colors_enabled: bool,
     bell_style: BellStyle,
+    continuation_prompt: String,
 }
 
 impl PosixRenderer {
@@ -876,6 +615,7 @@ impl PosixRenderer {
             tab_stop,
             colors_enabled,
             bell_style,
+            continuation_prompt: String::new(),
         }
     }
 
@@ -956,25 +696,31 @@ impl Renderer for PosixRenderer {
     ) -> Result<()> {
         use std::fmt::Write;
         self.buffer.clear();
+        // hide the cursor while the whole redraw is written at once
+        self.buffer.push_str(HIDE_CURSOR);
 
         let default_prompt = new_layout.default_prompt;
         let cursor = new_layout.cursor;
         let end_pos = new_layout.end;
 
         self.clear_old_rows(old_layout);
+        let start = self.buffer.len();
 
         if let Some(highlighter) = highlighter {
             // display the prompt
             self.buffer
                 .push_str(&highlighter.highlight_prompt(prompt, default_prompt));
             // display the input line
-            self.buffer
-                .push_str(&highlighter.highlight(line, line.pos()));
+            push_line(
+                &mut self.buffer,
+                &highlighter.highlight(line, line.pos()),
+                &highlighter.highlight_continuation_prompt(&self.continuation_prompt),
+            );
         } else {
             // display the prompt
             self.buffer.push_str(prompt);
             // display the input line
-            self.buffer.push_str(line);
+            push_line(&mut self.buffer, line, &self.continuation_prompt);
         }
         // display hint
         if let Some(hint) = hint {
@@ -984,6 +730,7 @@ impl Renderer for PosixRenderer {
                 self.buffer.push_str(hint);
             }
         }
+        expand_tabs(&mut self.buffer, start, self.tab_stop, self.cols);
         // we have to generate our own newline on line wrap
         if end_pos.col == 0
             && end_pos.row > 0
@@ -1005,6 +752,7 @@ impl Renderer for PosixRenderer {
         } else {
             self.buffer.push('\r');
         }
+        self.buffer.push_str(SHOW_CURSOR);
 
         write_all(self.out, self.buffer.as_str())?;
         Ok(())
@@ -1015,39 +763,28 @@ impl Renderer for PosixRenderer {
         Ok(())
     }
 
-    /// Control characters are treated as having zero width.
-    /// Characters with 2 column width are correctly handled (not split).
     fn calculate_position(&self, s: &str, orig: Position) -> Position {
-        let mut pos = orig;
-        let mut esc_seq = 0;
-        for c in s.graphemes(true) {
-            if c == "\n" {
-                pos.row += 1;
-                pos.col = 0;
-                continue;
-            }
-            let cw = if c == "\t" {
-                self.tab_stop - (pos.col % self.tab_stop)
-            } else {
-                width(c, &mut esc_seq)
-            };
-            pos.col += cw;
-            if pos.col > self.cols {
-                pos.row += 1;
-                pos.col = cw;
-            }
-        }
-        if pos.col == self.cols {
-            pos.col = 0;
-            pos.row += 1;
-        }
-        pos
+        ansi_position(s, orig, self.tab_stop, self.cols)
+    }
+
+    fn continuation_prompt(&self) -> &str {
+        &self.continuation_prompt
+    }
+
+    fn set_continuation_prompt(&mut self, prompt: &str) {
+        self.continuation_prompt = prompt.to_owned();
     }
 
     fn beep(&mut self) -> Result<()> {
         match self.bell_style {
             BellStyle::Audible => self.write_and_flush("\x07"),
-            _ => Ok(()),
+            BellStyle::Visible => {
+                // flash the screen with reverse video
+                self.write_and_flush("\x1b[?5h")?;
+                thread::sleep(Duration::from_millis(100));
+                self.write_and_flush("\x1b[?5l")
+            }
+            BellStyle::None => Ok(()),
         }
     }
 
@@ -1107,6 +844,20 @@ impl Renderer for PosixRenderer {
         }
         Ok(())
     }
+
+    fn set_cursor_shape(&mut self, mode: Option<InputMode>) -> Result<()> {
+        self.write_and_flush(cursor_shape(mode))
+    }
+}
+
+/// DECSCUSR sequence matching vi input `mode`
+fn cursor_shape(mode: Option<InputMode>) -> &'static str {
+    match mode {
+        Some(InputMode::Command) => "\x1b[1 q", // blinking block
+        Some(InputMode::Insert) => "\x1b[5 q",  // blinking bar
+        Some(InputMode::Replace) => "\x1b[3 q", // blinking underline
+        None => "\x1b[0 q",                     // default
+    }
 }
 
 fn read_digits_until(rdr: &mut PosixRawReader, sep: char) -> Result<Option<u32>> {
@@ -1202,6 +953,21 @@ impl SigWinCh {
     }
 }
 
+/// Local flags for raw mode: disable echoing, canonical mode, extended input
+/// processing and, unless `ctrlc_as_signal`, signals.
+fn raw_local_flags(flags: LocalFlags, ctrlc_as_signal: bool) -> LocalFlags {
+    let mut cleared = LocalFlags::ECHO
+        | LocalFlags::ECHOE
+        | LocalFlags::ECHOK
+        | LocalFlags::ECHONL
+        | LocalFlags::ICANON
+        | LocalFlags::IEXTEN;
+    if !ctrlc_as_signal {
+        cleared |= LocalFlags::ISIG;
+    }
+    flags & !cleared
+}
+
 fn map_key(key_map: &mut HashMap<KeyEvent, Cmd>, raw: &Termios, index: SCI, name: &str, cmd: Cmd) {
     let cc = char::from(raw.control_chars[index as usize]);
     let key = KeyEvent::new(cc, M::NONE);
@@ -1212,7 +978,7 @@ fn map_key(key_map: &mut HashMap<KeyEvent, Cmd>, raw: &Termios, index: SCI, name
 #[cfg(not(test))]
 pub type Terminal = PosixTerminal;
 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct PosixTerminal {
     unsupported: bool,
     tty_in: RawFd,
@@ -1222,22 +988,61 @@ pub struct PosixTerminal {
     close_on_drop: bool,
     pub(crate) color_mode: ColorMode,
     tab_stop: usize,
-    bell_style: BellStyle,
+    pub(crate) bell_style: BellStyle,
     enable_bracketed_paste: bool,
+    ctrlc_as_signal: bool,
     raw_mode: Arc<AtomicBool>,
     // external print reader
     pipe_reader: Option<PipeReader>,
     // external print writer
     pipe_writer: Option<PipeWriter>,
+    // see `Editor::readline_with_abort`
+    pub(crate) abort_pipe: Option<RawFd>,
     sigwinch: Option<SigWinCh>,
 }
 
 impl PosixTerminal {
-    fn colors_enabled(&self) -> bool {
-        match self.color_mode {
-            ColorMode::Enabled => self.is_out_a_tty,
-            ColorMode::Forced => true,
-            ColorMode::Disabled => false,
+    fn bell_style(&self) -> BellStyle {
+        if self.is_out_a_tty {
+            self.bell_style
+        } else {
+            BellStyle::None
+        }
+    }
+}
+
+/// The clone does not share the raw mode state, the external printer nor the
+/// `SIGWINCH` handler of the original: the window size is only read when a
+/// line is edited. A `/dev/tty` descriptor is duplicated so that each instance
+/// closes its own.
+impl Clone for PosixTerminal {
+    fn clone(&self) -> Self {
+        let tty = if self.close_on_drop {
+            dup(self.tty_in).expect("cannot duplicate tty descriptor")
+        } else {
+            self.tty_in
+        };
+        Self {
+            unsupported: self.unsupported,
+            tty_in: tty,
+            is_in_a_tty: self.is_in_a_tty,
+            tty_out: if self.close_on_drop {
+                tty
+            } else {
+                self.tty_out
+            },
+            is_out_a_tty: self.is_out_a_tty,
+            close_on_drop: self.close_on_drop,
+            color_mode: self.color_mode,
+            tab_stop: self.tab_stop,
+            bell_style: self.bell_style,
+            enable_bracketed_paste: self.enable_bracketed_paste,
+            ctrlc_as_signal: self.ctrlc_as_signal,
+            raw_mode: Arc::new(AtomicBool::new(false)),
+            pipe_reader: None,
+            pipe_writer: None,
+            abort_pipe: None,
+            sigwinch: None,
         }
     }
 }
@@ -1255,6 +1060,7 @@ impl Term for PosixTerminal {
         tab_stop: usize,
         bell_style: BellStyle,
         enable_bracketed_paste: bool,
+        ctrlc_as_signal: bool,
     ) -> Result<Self> {
         let (tty_in, is_in_a_tty, tty_out, is_out_a_tty, close_on_drop) =
             if behavior == Behavior::PreferTerm {
@@ -1299,9 +1105,11 @@ impl Term for PosixTerminal {
             tab_stop,
             bell_style,
             enable_bracketed_paste,
+            ctrlc_as_signal,
             raw_mode: Arc::new(AtomicBool::new(false)),
             pipe_reader: None,
             pipe_writer: None,
+            abort_pipe: None,
             sigwinch,
         })
     }
@@ -1322,11 +1130,15 @@ impl Term for PosixTerminal {
         self.is_out_a_tty
     }
 
+    fn colors_enabled(&self) -> bool {
+        self.color_mode.colors_enabled(self.is_out_a_tty)
+    }
+
     // Interactive loop:
 
     fn enable_raw_mode(&mut self) -> Result<(Self::Mode, PosixKeyMap)> {
         use nix::errno::Errno::ENOTTY;
-        use nix::sys::termios::{ControlFlags, InputFlags, LocalFlags};
+        use nix::sys::termios::{ControlFlags, InputFlags};
         if !self.is_in_a_tty {
             return Err(ENOTTY.into());
         }
@@ -1334,6 +1146,8 @@ impl Term for PosixTerminal {
         let mut raw = original_mode.clone();
         // disable BREAK interrupt, CR to NL conversion on input,
         // input parity check, strip high bit (bit 8), output flow control
+        // (without IXON, Ctrl-S and Ctrl-Q are read as keys: Ctrl-S is
+        // forward-search-history)
         raw.input_flags &= !(InputFlags::BRKINT
             | InputFlags::ICRNL
             | InputFlags::INPCK
@@ -1345,9 +1159,7 @@ impl Term for PosixTerminal {
 
         // character-size mark (8 bits)
         raw.control_flags |= ControlFlags::CS8;
-        // disable echoing, canonical mode, extended input processing and signals
-        raw.local_flags &=
-            !(LocalFlags::ECHO | LocalFlags::ICANON | LocalFlags::IEXTEN | LocalFlags::ISIG);
+        raw.local_flags = raw_local_flags(raw.local_flags, self.ctrlc_as_signal);
         raw.control_chars[SCI::VMIN as usize] = 1; // One character-at-a-time input
         raw.control_chars[SCI::VTIME as usize] = 0; // with blocking read
 
@@ -1389,13 +1201,15 @@ impl Term for PosixTerminal {
 
     /// Create a RAW reader
     fn create_reader(&self, config: &Config, key_map: PosixKeyMap) -> PosixRawReader {
-        PosixRawReader::new(
+        let mut rdr = PosixRawReader::new(
             self.tty_in,
             self.sigwinch.as_ref().map(|s| s.pipe),
             config,
             key_map,
             self.pipe_reader.clone(),
-        )
+        );
+        rdr.abort_pipe = self.abort_pipe;
+        rdr
     }
 
     fn create_writer(&self) -> PosixRenderer {
@@ -1403,7 +1217,7 @@ impl Term for PosixTerminal {
             self.tty_out,
             self.tab_stop,
             self.colors_enabled(),
-            self.bell_style,
+            self.bell_style(),
         )
     }
 
@@ -1468,14 +1282,17 @@ impl super::ExternalPrinter for ExternalPrinter {
         if !self.raw_mode.load(Ordering::SeqCst) {
             write_all(self.tty_out, msg.as_str())?;
         } else if let Ok(mut writer) = self.writer.0.lock() {
+            // the receiver is dropped only when the terminal is: the editor is
+            // gone, so report a broken pipe
             self.writer
                 .1
                 .send(msg)
-                .map_err(|_| io::Error::from(ErrorKind::Other))?; // FIXME
+                .map_err(|_| io::Error::from(ErrorKind::BrokenPipe))?;
             writer.write_all(&[b'm'])?;
             writer.flush()?;
         } else {
-            return Err(io::Error::from(ErrorKind::Other).into()); // FIXME
+            // another printer panicked while holding the lock
+            return Err(io::Error::from(ErrorKind::Other).into());
         }
         Ok(())
     }
@@ -1492,10 +1309,49 @@ pub fn suspend() -> Result<()> {
 
 #[cfg(test)]
 mod test {
-    use super::{Position, PosixRenderer, PosixTerminal, Renderer};
+    use super::{raw_local_flags, Position, PosixRenderer, PosixTerminal, Renderer};
     use crate::config::BellStyle;
     use crate::line_buffer::LineBuffer;
 
+    fn visible_width(s: &str) -> usize {
+        use crate::grapheme::UnicodeSegmentation;
+
+        let mut esc_seq = 0;
+        s.graphemes(true)
+            .map(|g| super::super::width(g, &mut esc_seq))
+            .sum()
+    }
+
+    #[test]
+    fn visible_width_with_escape_codes() {
+        assert_eq!(3, visible_width(">> "));
+        assert_eq!(3, visible_width("\x1b[1;32m>>\x1b[0m "));
+        assert_eq!(3, visible_width("\x1b[38;5;208m>>\x1b[m "));
+        assert_eq!(3, visible_width("\x1b[?25h>> "));
+    }
+
+    #[test]
+    fn visible_width_with_wide_chars() {
+        assert_eq!(4, visible_width("中文"));
+        assert_eq!(8, visible_width("ab中文cd"));
+        // combining acute accent
+        assert_eq!(1, visible_width("e\u{301}"));
+        assert_eq!(5, visible_width("a\u{301}b中e\u{301}"));
+        assert_eq!(2, visible_width("\u{1f600}"));
+    }
+
+    #[test]
+    fn wide_char_is_not_split() {
+        let mut out = PosixRenderer::new(libc::STDOUT_FILENO, 4, true, BellStyle::default());
+        out.cols = 10;
+        let prompt_size = out.calculate_position("> ", Position::default());
+        let pos = out.calculate_position("中中中中", prompt_size);
+        assert_eq!(Position { col: 0, row: 1 }, pos);
+        // only one column left: the last wide char goes to the next row
+        let pos = out.calculate_position("a中中中中", prompt_size);
+        assert_eq!(Position { col: 2, row: 1 }, pos);
+    }
+
     #[test]
     #[ignore]
     fn prompt_with_ansi_escape_codes() {
@@ -1505,13 +1361,33 @@ mod test {
         assert_eq!(0, pos.row);
     }
 
+    #[test]
+    fn test_cursor_shape() {
+        use super::cursor_shape;
+        use crate::keymap::InputMode;
+        assert_eq!("\x1b[1 q", cursor_shape(Some(InputMode::Command)));
+        assert_eq!("\x1b[5 q", cursor_shape(Some(InputMode::Insert)));
+        assert_eq!("\x1b[0 q", cursor_shape(None));
+    }
+
+    #[test]
+    fn test_send_sync_external_printer() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<super::ExternalPrinter>();
+    }
+
     #[test]
     fn test_unsupported_term() {
+        let term = std::env::var_os("TERM");
         std::env::set_var("TERM", "xterm");
         assert!(!super::is_unsupported_term());
 
         std::env::set_var("TERM", "dumb");
         assert!(super::is_unsupported_term());
+        match term {
+            Some(term) => std::env::set_var("TERM", term),
+            None => std::env::remove_var("TERM"),
+        }
     }
 
     #[test]
@@ -1526,6 +1402,273 @@ mod test {
         assert_sync::<PosixTerminal>();
     }
 
+    #[test]
+    fn tab_expansion() {
+        let mut out = PosixRenderer::new(libc::STDOUT_FILENO, 8, true, BellStyle::default());
+        let prompt = "abc";
+        let prompt_size = out.calculate_position(prompt, Position::default());
+        let old_layout =
+            out.compute_layout(prompt_size, true, &LineBuffer::init("", 0, None), None);
+
+        let line = LineBuffer::init("\tx", 1, None);
+        let new_layout = out.compute_layout(prompt_size, true, &line, None);
+        assert_eq!(Position { col: 8, row: 0 }, new_layout.cursor);
+        assert_eq!(Position { col: 9, row: 0 }, new_layout.end);
+        out.refresh_line(prompt, &line, None, &old_layout, &new_layout, None)
+            .unwrap();
+        // 5 spaces up to the tab stop, the buffer keeps the tab
+        assert!(out.buffer.starts_with("\x1b[?25l\r\u{1b}[Kabc     x"));
+        assert_eq!("\tx", line.as_str());
+    }
+
+    #[test]
+    fn tab_stop_columns() {
+        let out = PosixRenderer::new(libc::STDOUT_FILENO, 4, true, BellStyle::default());
+        let line = LineBuffer::init("ab\tc\t", 3, None);
+        let layout = out.compute_layout(Position::default(), true, &line, None);
+        // the tab after "ab" reaches the next tab stop
+        assert_eq!(Position { col: 4, row: 0 }, layout.cursor);
+        assert_eq!(Position { col: 8, row: 0 }, layout.end);
+    }
+
+    #[test]
+    fn external_print_between_keys() {
+        use std::fs::File;
+        use std::os::unix::io::FromRawFd;
+        use std::sync::atomic::AtomicBool;
+        use std::sync::{mpsc, Arc, Mutex};
+
+        use super::{ExternalPrinter, PosixRawReader};
+        use crate::config::Config;
+        use crate::keys::KeyEvent;
+        use crate::tty::{Event, ExternalPrinter as _, RawReader};
+
+        let (key_rd, key_wr) = nix::unistd::pipe().unwrap();
+        let (msg_rd, msg_wr) = nix::unistd::pipe().unwrap();
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let pipe_reader = Arc::new(Mutex::new((unsafe { File::from_raw_fd(msg_rd) }, receiver)));
+        let mut printer = ExternalPrinter {
+            writer: (
+                Arc::new(Mutex::new(unsafe { File::from_raw_fd(msg_wr) })),
+                sender,
+            ),
+            raw_mode: Arc::new(AtomicBool::new(true)),
+            tty_out: libc::STDOUT_FILENO,
+        };
+        let config = Config::default();
+        let mut rdr =
+            PosixRawReader::new(key_rd, None, &config, Default::default(), Some(pipe_reader));
+
+        nix::unistd::write(key_wr, b"a").unwrap();
+        assert_matches!(
+            rdr.wait_for_input(false),
+            Ok(Event::KeyPress(k)) if k == KeyEvent::from('a')
+        );
+        // message sent by another thread while the user is typing
+        std::thread::spawn(move || printer.print("msg".to_owned()).unwrap())
+            .join()
+            .unwrap();
+        assert_matches!(
+            rdr.wait_for_input(false),
+            Ok(Event::ExternalPrint(msg)) if msg == "msg"
+        );
+        nix::unistd::write(key_wr, b"b").unwrap();
+        assert_matches!(
+            rdr.wait_for_input(false),
+            Ok(Event::KeyPress(k)) if k == KeyEvent::from('b')
+        );
+        nix::unistd::close(key_wr).unwrap();
+        nix::unistd::close(key_rd).unwrap();
+    }
+
+    #[test]
+    fn bound_sequences() {
+        use super::PosixRawReader;
+        use crate::config::Config;
+        use crate::keymap::SequenceTrie;
+        use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
+        use crate::tty::RawReader;
+
+        let (rd, wr) = nix::unistd::pipe().unwrap();
+        let config = Config::default();
+        let mut rdr = PosixRawReader::new(rd, None, &config, Default::default(), None);
+        let mut sequences = SequenceTrie::new();
+        sequences.insert(b"\x1bOc", E(K::Right, M::CTRL));
+        sequences.insert(b"jk", E::ESC);
+        rdr.set_sequences(&sequences);
+
+        nix::unistd::write(wr, b"\x1bOc").unwrap();
+        assert_eq!(E(K::Right, M::CTRL), rdr.next_key(false).unwrap());
+        // built-in
+        nix::unistd::write(wr, b"\x1b[1;5D").unwrap();
+        assert_eq!(E(K::Left, M::CTRL), rdr.next_key(false).unwrap());
+        nix::unistd::write(wr, b"jk").unwrap();
+        assert_eq!(E::ESC, rdr.next_key(false).unwrap());
+        // prefix only: bytes processed individually
+        nix::unistd::write(wr, b"jx").unwrap();
+        assert_eq!(E::from('j'), rdr.next_key(false).unwrap());
+        assert_eq!(E::from('x'), rdr.next_key(false).unwrap());
+        nix::unistd::close(wr).unwrap();
+        nix::unistd::close(rd).unwrap();
+    }
+
+    #[test]
+    fn sequence_split_across_reads() {
+        use std::time::Duration;
+
+        use super::PosixRawReader;
+        use crate::config::Config;
+        use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
+        use crate::tty::RawReader;
+
+        let (rd, wr) = nix::unistd::pipe().unwrap();
+        let config = Config::builder().keyseq_timeout(500).build();
+        let mut rdr = PosixRawReader::new(rd, None, &config, Default::default(), None);
+        let writer = std::thread::spawn(move || {
+            for b in b"\x1b[1;5C" {
+                nix::unistd::write(wr, &[*b]).unwrap();
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            wr
+        });
+        assert_eq!(E(K::Right, M::CTRL), rdr.next_key(false).unwrap());
+        let wr = writer.join().unwrap();
+
+        // nothing received within the timeout: single escape
+        let config = Config::builder().keyseq_timeout(10).build();
+        let mut rdr = PosixRawReader::new(rd, None, &config, Default::default(), None);
+        nix::unistd::write(wr, b"\x1b").unwrap();
+        assert_eq!(E::ESC, rdr.next_key(false).unwrap());
+        // unknown sequence: skipped
+        nix::unistd::write(wr, b"\x1b[12;40Rq").unwrap();
+        assert_eq!(E(K::UnknownEscSeq, M::NONE), rdr.next_key(false).unwrap());
+        assert_eq!(E::from('q'), rdr.next_key(false).unwrap());
+        // Alt + built-in sequence
+        nix::unistd::write(wr, b"\x1b\x1b[A").unwrap();
+        assert_eq!(E(K::Up, M::ALT), rdr.next_key(false).unwrap());
+        nix::unistd::close(wr).unwrap();
+        nix::unistd::close(rd).unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn fed_input() {
+        use std::time::Duration;
+
+        use super::PosixRawReader;
+        use crate::config::Config;
+        use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
+        use crate::tty::RawReader;
+
+        let (rd, wr) = nix::unistd::pipe().unwrap();
+        let mut rdr = PosixRawReader::new(rd, None, &Config::default(), Default::default(), None);
+        rdr.feed(b"\x1b[Ax");
+        assert!(rdr.poll_input(Duration::ZERO).unwrap());
+        assert_eq!(E(K::Up, M::NONE), rdr.next_key(false).unwrap());
+        assert_eq!(E::from('x'), rdr.next_key(false).unwrap());
+        assert!(!rdr.poll_input(Duration::ZERO).unwrap());
+        nix::unistd::close(wr).unwrap();
+        nix::unistd::close(rd).unwrap();
+    }
+
+    #[test]
+    fn utf8_split_across_reads() {
+        use std::time::Duration;
+
+        use super::PosixRawReader;
+        use crate::config::Config;
+        use crate::tty::RawReader;
+
+        let (rd, wr) = nix::unistd::pipe().unwrap();
+        let config = Config::default();
+        let mut rdr = PosixRawReader::new(rd, None, &config, Default::default(), None);
+        let writer = std::thread::spawn(move || {
+            for b in "\u{4e2d}".bytes() {
+                nix::unistd::write(wr, &[b]).unwrap();
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            wr
+        });
+        assert_eq!('\u{4e2d}', rdr.next_char().unwrap());
+        let wr = writer.join().unwrap();
+
+        nix::unistd::write(wr, b"\xe4\xb8a").unwrap();
+        assert!(rdr.next_char().is_err());
+        let config = Config::builder().replace_invalid_utf8(true).build();
+        let mut rdr = PosixRawReader::new(rd, None, &config, Default::default(), None);
+        nix::unistd::write(wr, b"\xff").unwrap();
+        assert_eq!(char::REPLACEMENT_CHARACTER, rdr.next_char().unwrap());
+        nix::unistd::close(wr).unwrap();
+        nix::unistd::close(rd).unwrap();
+    }
+
+    #[test]
+    fn abort_pipe() {
+        use std::time::Duration;
+
+        use super::PosixRawReader;
+        use crate::config::Config;
+        use crate::error::ReadlineError;
+        use crate::keys::KeyEvent;
+        use crate::tty::{Event, RawReader};
+
+        let (key_rd, key_wr) = nix::unistd::pipe().unwrap();
+        let (abort_rd, abort_wr) = nix::unistd::pipe().unwrap();
+        let config = Config::default();
+        let mut rdr = PosixRawReader::new(key_rd, None, &config, Default::default(), None);
+        rdr.abort_pipe = Some(abort_rd);
+        let aborter = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            nix::unistd::write(abort_wr, b"a").unwrap();
+            abort_wr
+        });
+        // keys read at once: the second one is buffered
+        nix::unistd::write(key_wr, b"ab").unwrap();
+        assert_matches!(
+            rdr.wait_for_input(false),
+            Ok(Event::KeyPress(k)) if k == KeyEvent::from('a')
+        );
+        assert_matches!(
+            rdr.wait_for_input(false),
+            Ok(Event::KeyPress(k)) if k == KeyEvent::from('b')
+        );
+        // no more key typed
+        assert_matches!(rdr.wait_for_input(false), Err(ReadlineError::Interrupted));
+        let abort_wr = aborter.join().unwrap();
+        for fd in [key_rd, key_wr, abort_rd, abort_wr] {
+            nix::unistd::close(fd).unwrap();
+        }
+    }
+
+    #[test]
+    fn single_write_per_refresh() {
+        let (rd, wr) = nix::unistd::pipe().unwrap();
+        let mut out = PosixRenderer::new(wr, 8, true, BellStyle::default());
+        let prompt = "> ";
+        let prompt_size = out.calculate_position(prompt, Position::default());
+        let old_layout =
+            out.compute_layout(prompt_size, true, &LineBuffer::init("", 0, None), None);
+        let line = LineBuffer::init("a", 1, None);
+        let new_layout = out.compute_layout(prompt_size, true, &line, None);
+        out.refresh_line(prompt, &line, None, &old_layout, &new_layout, None)
+            .unwrap();
+        nix::unistd::close(wr).unwrap();
+
+        let mut written = Vec::new();
+        let mut buf = [0; 256];
+        loop {
+            let n = nix::unistd::read(rd, &mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            written.extend_from_slice(&buf[..n]);
+        }
+        nix::unistd::close(rd).unwrap();
+        // the whole redraw has been written from one buffer, cursor hidden
+        assert_eq!(out.buffer.as_bytes(), &written[..]);
+        assert_eq!("\x1b[?25l\r\x1b[K> a\r\x1b[3C\x1b[?25h", out.buffer);
+    }
+
     #[test]
     fn test_line_wrap() {
         let mut out = PosixRenderer::new(libc::STDOUT_FILENO, 4, true, BellStyle::default());
@@ -1546,8 +1689,49 @@ mod test {
             .unwrap();
         #[rustfmt::skip]
         assert_eq!(
-            "\r\u{1b}[K> aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\r\u{1b}[1C",
+            "\u{1b}[?25l\r\u{1b}[K> aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\r\u{1b}[1C\u{1b}[?25h",
             out.buffer
         );
     }
+
+    #[test]
+    fn raw_mode_isig() {
+        use nix::sys::termios::LocalFlags;
+
+        let cooked = LocalFlags::ECHO | LocalFlags::ICANON | LocalFlags::IEXTEN | LocalFlags::ISIG;
+        // by default, Ctrl-C is read as a key
+        let raw = raw_local_flags(cooked, false);
+        assert!(!raw.contains(LocalFlags::ISIG));
+        assert!(!raw.intersects(LocalFlags::ECHO | LocalFlags::ICANON | LocalFlags::IEXTEN));
+        // otherwise, the terminal driver raises SIGINT
+        let raw = raw_local_flags(cooked, true);
+        assert_eq!(LocalFlags::ISIG, raw);
+    }
+
+    #[test]
+    fn next_key_escape_sequence() {
+        use super::PosixRawReader;
+        use crate::config::Config;
+        use crate::keys::{KeyCode as K, KeyEvent as E, Modifiers as M};
+        use crate::tty::RawReader;
+
+        let (key_rd, key_wr) = nix::unistd::pipe().unwrap();
+        let config = Config::default();
+        let mut rdr = PosixRawReader::new(key_rd, None, &config, Default::default(), None);
+
+        nix::unistd::write(key_wr, b"\x1b[A\x1b[1;5Cq").unwrap();
+        assert_eq!(E(K::Up, M::NONE), rdr.next_key(false).unwrap());
+        assert_eq!(E(K::Right, M::CTRL), rdr.next_key(false).unwrap());
+        assert_eq!(E::from('q'), rdr.next_key(false).unwrap());
+
+        // ESC followed by a digit is a numeric argument prefix
+        nix::unistd::write(key_wr, b"\x1b3").unwrap();
+        assert_eq!(E::alt('3'), rdr.next_key(false).unwrap());
+
+        // Delete, Ctrl-Delete, Ctrl-Left
+        nix::unistd::write(key_wr, b"\x1b[3~\x1b[3;5~\x1b[1;5D").unwrap();
+        assert_eq!(E(K::Delete, M::NONE), rdr.next_key(false).unwrap());
+        assert_eq!(E(K::Delete, M::CTRL), rdr.next_key(false).unwrap());
+        assert_eq!(E(K::Left, M::CTRL), rdr.next_key(false).unwrap());
+    }
 }
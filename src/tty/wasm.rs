@@ -0,0 +1,19 @@
+//! Browser input (wasm32 with the `web-sys` feature)
+use std::io;
+
+use crate::error::ReadlineError;
+use crate::Result;
+
+/// Read a line with `window.prompt(prompt, "")`.
+///
+/// There is no line edition: the browser dialog is used instead of the
+/// terminal, and `Eof` is returned when the dialog is cancelled.
+pub fn prompt(prompt: &str) -> Result<String> {
+    let window = web_sys::window()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "no global `window`"))?;
+    match window.prompt_with_message_and_default(prompt, "") {
+        Ok(Some(line)) => Ok(line),
+        Ok(None) => Err(ReadlineError::Eof),
+        Err(err) => Err(io::Error::new(io::ErrorKind::Other, format!("{:?}", err)).into()),
+    }
+}
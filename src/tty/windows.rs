@@ -9,10 +9,9 @@ use std::ptr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use log::{debug, warn};
-use unicode_segmentation::UnicodeSegmentation;
-use unicode_width::UnicodeWidthStr;
 use winapi::shared::minwindef::{BOOL, DWORD, FALSE, TRUE, WORD};
 use winapi::shared::winerror;
 use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
@@ -21,8 +20,11 @@ use winapi::um::wincon::{self, CONSOLE_SCREEN_BUFFER_INFO, COORD};
 use winapi::um::winnt::{CHAR, HANDLE};
 use winapi::um::{consoleapi, processenv, winbase, winuser};
 
-use super::{width, Event, RawMode, RawReader, Renderer, Term};
+use super::{
+    ansi_position, expand_tabs, push_line, width, Event, RawMode, RawReader, Renderer, Term,
+};
 use crate::config::{Behavior, BellStyle, ColorMode, Config};
+use crate::grapheme::UnicodeSegmentation;
 use crate::highlight::Highlighter;
 use crate::keys::{KeyCode as K, KeyEvent, Modifiers as M};
 use crate::layout::{Layout, Position};
@@ -157,6 +159,43 @@ impl RawReader for ConsoleRawReader {
         read_input(self.conin, u32::MAX)
     }
 
+    fn poll_input(&mut self, timeout: Duration) -> Result<bool> {
+        use std::convert::TryFrom;
+        use winapi::um::synchapi::WaitForSingleObject;
+        use winapi::um::winbase::{WAIT_FAILED, WAIT_OBJECT_0};
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let ms = DWORD::try_from(remaining.as_millis()).unwrap_or(winbase::INFINITE - 1);
+            match unsafe { WaitForSingleObject(self.conin, ms) } {
+                WAIT_OBJECT_0 => {}
+                WAIT_FAILED => return Err(io::Error::last_os_error().into()),
+                _ => return Ok(false),
+            }
+            // mouse, focus, menu and key up events also signal the handle but
+            // are skipped by `read_input`: discard them and keep waiting
+            let mut rec: wincon::INPUT_RECORD = unsafe { mem::zeroed() };
+            let mut count = 0;
+            check(unsafe { wincon::PeekConsoleInputW(self.conin, &mut rec, 1, &mut count) })?;
+            if count == 0 {
+                continue;
+            }
+            let is_input = match rec.EventType {
+                wincon::WINDOW_BUFFER_SIZE_EVENT => true,
+                wincon::KEY_EVENT => {
+                    let key_event = unsafe { rec.Event.KeyEvent() };
+                    key_event.bKeyDown != 0 || key_event.wVirtualKeyCode == winuser::VK_MENU as WORD
+                }
+                _ => false,
+            };
+            if is_input {
+                return Ok(true);
+            }
+            check(unsafe { consoleapi::ReadConsoleInputW(self.conin, &mut rec, 1, &mut count) })?;
+        }
+    }
+
     fn read_pasted_text(&mut self) -> Result<String> {
         Ok(clipboard_win::get_clipboard_string()?)
     }
@@ -286,12 +325,19 @@ pub struct ConsoleRenderer {
     cols: usize, // Number of columns in terminal
     buffer: String,
     utf16: Vec<u16>,
+    tab_stop: usize,
     colors_enabled: bool,
     bell_style: BellStyle,
+    continuation_prompt: String,
 }
 
 impl ConsoleRenderer {
-    fn new(conout: HANDLE, colors_enabled: bool, bell_style: BellStyle) -> ConsoleRenderer {
+    fn new(
+        conout: HANDLE,
+        tab_stop: usize,
+        colors_enabled: bool,
+        bell_style: BellStyle,
+    ) -> ConsoleRenderer {
         // Multi line editing is enabled by ENABLE_WRAP_AT_EOL_OUTPUT mode
         let (cols, _) = get_win_size(conout);
         ConsoleRenderer {
@@ -299,8 +345,10 @@ impl ConsoleRenderer {
             cols,
             buffer: String::with_capacity(1024),
             utf16: Vec::with_capacity(1024),
+            tab_stop,
             colors_enabled,
             bell_style,
+            continuation_prompt: String::new(),
         }
     }
 
@@ -341,6 +389,18 @@ impl ConsoleRenderer {
         for c in s.graphemes(true) {
             if c == "\n" {
                 col = 0;
+            } else if c == "\t" {
+                // expanded like `expand_tabs` does
+                let n = self.tab_stop - (col % self.tab_stop);
+                col += n;
+                if col > self.cols {
+                    self.buffer.push('\n');
+                    col = n;
+                }
+                for _ in 0..n {
+                    self.buffer.push(' ');
+                }
+                continue;
             } else {
                 let cw = width(c, &mut esc_seq);
                 col += cw;
@@ -426,12 +486,18 @@ impl Renderer for ConsoleRenderer {
             // append the prompt
             col = self.wrap_at_eol(&highlighter.highlight_prompt(prompt, default_prompt), col);
             // append the input line
-            col = self.wrap_at_eol(&highlighter.highlight(line, line.pos()), col);
+            let mut highlighted = String::new();
+            push_line(
+                &mut highlighted,
+                &highlighter.highlight(line, line.pos()),
+                &highlighter.highlight_continuation_prompt(&self.continuation_prompt),
+            );
+            col = self.wrap_at_eol(&highlighted, col);
         } else {
             // append the prompt
             self.buffer.push_str(prompt);
             // append the input line
-            self.buffer.push_str(line);
+            push_line(&mut self.buffer, line, &self.continuation_prompt);
         }
         // append hint
         if let Some(hint) = hint {
@@ -441,6 +507,7 @@ impl Renderer for ConsoleRenderer {
                 self.buffer.push_str(hint);
             }
         }
+        expand_tabs(&mut self.buffer, 0, self.tab_stop, self.cols);
         let info = self.get_console_screen_buffer_info()?;
         self.set_cursor_visible(FALSE)?; // just to avoid flickering
         let handle = self.conout;
@@ -466,27 +533,16 @@ impl Renderer for ConsoleRenderer {
         write_to_console(self.conout, buf, &mut self.utf16)
     }
 
-    /// Characters with 2 column width are correctly handled (not split).
     fn calculate_position(&self, s: &str, orig: Position) -> Position {
-        let mut pos = orig;
-        for c in s.graphemes(true) {
-            if c == "\n" {
-                pos.col = 0;
-                pos.row += 1;
-            } else {
-                let cw = c.width();
-                pos.col += cw;
-                if pos.col > self.cols {
-                    pos.row += 1;
-                    pos.col = cw;
-                }
-            }
-        }
-        if pos.col == self.cols {
-            pos.col = 0;
-            pos.row += 1;
-        }
-        pos
+        ansi_position(s, orig, self.tab_stop, self.cols)
+    }
+
+    fn continuation_prompt(&self) -> &str {
+        &self.continuation_prompt
+    }
+
+    fn set_continuation_prompt(&mut self, prompt: &str) {
+        self.continuation_prompt = prompt.to_owned();
     }
 
     fn beep(&mut self) -> Result<()> {
@@ -592,16 +648,17 @@ fn write_all(handle: HANDLE, mut data: &[u16]) -> Result<()> {
 #[cfg(not(test))]
 pub type Terminal = Console;
 
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct Console {
     conin_isatty: bool,
     conin: HANDLE,
     conout_isatty: bool,
     conout: HANDLE,
     close_on_drop: bool,
+    tab_stop: usize,
     pub(crate) color_mode: ColorMode,
     ansi_colors_supported: bool,
-    bell_style: BellStyle,
+    pub(crate) bell_style: BellStyle,
     raw_mode: Arc<AtomicBool>,
     // external print reader
     pipe_reader: Option<Arc<AsyncPipe>>,
@@ -609,17 +666,55 @@ pub struct Console {
     pipe_writer: Option<SyncSender<String>>,
 }
 
-impl Console {
-    fn colors_enabled(&self) -> bool {
-        // TODO ANSI Colors & Windows <10
-        match self.color_mode {
-            ColorMode::Enabled => self.conout_isatty && self.ansi_colors_supported,
-            ColorMode::Forced => true,
-            ColorMode::Disabled => false,
+/// The clone does not share the raw mode state nor the external printer of
+/// the original. Console handles opened by the terminal are duplicated so that
+/// each instance closes its own.
+impl Clone for Console {
+    fn clone(&self) -> Self {
+        let (conin, conout) = if self.close_on_drop {
+            (duplicate_handle(self.conin), duplicate_handle(self.conout))
+        } else {
+            (self.conin, self.conout)
+        };
+        Self {
+            conin_isatty: self.conin_isatty,
+            conin,
+            conout_isatty: self.conout_isatty,
+            conout,
+            close_on_drop: self.close_on_drop,
+            tab_stop: self.tab_stop,
+            color_mode: self.color_mode,
+            ansi_colors_supported: self.ansi_colors_supported,
+            bell_style: self.bell_style,
+            raw_mode: Arc::new(AtomicBool::new(false)),
+            pipe_reader: None,
+            pipe_writer: None,
         }
     }
 }
 
+fn duplicate_handle(handle: HANDLE) -> HANDLE {
+    use winapi::um::handleapi::DuplicateHandle;
+    use winapi::um::processthreadsapi::GetCurrentProcess;
+    use winapi::um::winnt::DUPLICATE_SAME_ACCESS;
+
+    let process = unsafe { GetCurrentProcess() };
+    let mut dup = INVALID_HANDLE_VALUE;
+    check(unsafe {
+        DuplicateHandle(
+            process,
+            handle,
+            process,
+            &mut dup,
+            0,
+            FALSE,
+            DUPLICATE_SAME_ACCESS,
+        )
+    })
+    .expect("cannot duplicate console handle");
+    dup
+}
+
 impl Term for Console {
     type ExternalPrinter = ExternalPrinter;
     type KeyMap = ConsoleKeyMap;
@@ -630,9 +725,10 @@ impl Term for Console {
     fn new(
         color_mode: ColorMode,
         behavior: Behavior,
-        _tab_stop: usize,
+        tab_stop: usize,
         bell_style: BellStyle,
         _enable_bracketed_paste: bool,
+        _ctrlc_as_signal: bool,
     ) -> Result<Console> {
         let (conin, conout, close_on_drop) = if behavior == Behavior::PreferTerm {
             if let (Ok(conin), Ok(conout)) = (
@@ -680,6 +776,7 @@ impl Term for Console {
             conout_isatty,
             conout: conout.unwrap_or(ptr::null_mut()),
             close_on_drop,
+            tab_stop,
             color_mode,
             ansi_colors_supported: false,
             bell_style,
@@ -702,6 +799,12 @@ impl Term for Console {
         self.conout_isatty
     }
 
+    fn colors_enabled(&self) -> bool {
+        // TODO ANSI Colors & Windows <10
+        self.color_mode
+            .colors_enabled(self.conout_isatty && self.ansi_colors_supported)
+    }
+
     // pub fn install_sigwinch_handler(&mut self) {
     // See ReadConsoleInputW && WINDOW_BUFFER_SIZE_EVENT
     // }
@@ -786,7 +889,12 @@ impl Term for Console {
     }
 
     fn create_writer(&self) -> ConsoleRenderer {
-        ConsoleRenderer::new(self.conout, self.colors_enabled(), self.bell_style)
+        ConsoleRenderer::new(
+            self.conout,
+            self.tab_stop,
+            self.colors_enabled(),
+            self.bell_style,
+        )
     }
 
     fn writeln(&self) -> Result<()> {
@@ -803,7 +911,10 @@ impl Term for Console {
             });
         }
         if !self.is_input_tty() || !self.is_output_tty() {
-            Err(io::Error::from(io::ErrorKind::Other))?; // FIXME
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "no console available for this process",
+            ))?;
         }
         let event = unsafe { CreateEventW(ptr::null_mut(), TRUE, FALSE, ptr::null()) };
         if event.is_null() {
@@ -862,9 +973,11 @@ impl super::ExternalPrinter for ExternalPrinter {
             let mut utf16 = Vec::new();
             write_to_console(self.conout, msg.as_str(), &mut utf16)
         } else {
+            // the receiver is dropped only when the console is: the editor is
+            // gone, so report a broken pipe
             self.sender
                 .send(msg)
-                .map_err(|_| io::Error::from(io::ErrorKind::Other))?; // FIXME
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
             Ok(check(unsafe { SetEvent(self.event) })?)
         }
     }
@@ -884,7 +997,47 @@ impl Drop for Handle {
 
 #[cfg(test)]
 mod test {
-    use super::Console;
+    use super::{Console, ConsoleRenderer};
+    use crate::config::{Behavior, BellStyle, ColorMode};
+    use crate::layout::Position;
+    use crate::tty::{Renderer, Term};
+
+    #[test]
+    fn test_new() {
+        // works with or without an attached console
+        let mut console = Console::new(
+            ColorMode::Enabled,
+            Behavior::Stdio,
+            4,
+            BellStyle::default(),
+            false,
+            false,
+        )
+        .unwrap();
+        assert!(!console.is_unsupported());
+        if !console.is_input_tty() {
+            assert!(console.enable_raw_mode().is_err());
+        }
+    }
+
+    #[test]
+    fn prompt_with_ansi_escape_codes() {
+        let out = ConsoleRenderer::new(std::ptr::null_mut(), 8, true, BellStyle::default());
+        let pos = out.calculate_position("\x1b[1;32m>>\x1b[0m ", Position::default());
+        assert_eq!(Position { col: 3, row: 0 }, pos);
+    }
+
+    #[test]
+    fn tab_stop_columns() {
+        use crate::line_buffer::LineBuffer;
+
+        let out = ConsoleRenderer::new(std::ptr::null_mut(), 4, true, BellStyle::default());
+        let line = LineBuffer::init("ab\tc\t", 3, None);
+        let layout = out.compute_layout(Position::default(), true, &line, None);
+        // the tab after "ab" reaches the next tab stop
+        assert_eq!(Position { col: 4, row: 0 }, layout.cursor);
+        assert_eq!(Position { col: 8, row: 0 }, layout.end);
+    }
 
     #[test]
     fn test_send() {
@@ -1,11 +1,12 @@
 //! Undo API
 use std::fmt::Debug;
 
+use crate::grapheme::UnicodeSegmentation;
 use crate::keymap::RepeatCount;
 use crate::line_buffer::{ChangeListener, DeleteListener, Direction, LineBuffer};
 use log::debug;
-use unicode_segmentation::UnicodeSegmentation;
 
+#[derive(Clone)]
 enum Change {
     Begin,
     End,
@@ -50,7 +51,6 @@ impl Change {
         }
     }
 
-    #[cfg(test)]
     fn redo(&self, line: &mut LineBuffer) {
         match *self {
             Change::Begin | Change::End => {
@@ -58,6 +58,7 @@ impl Change {
             }
             Change::Insert { idx, ref text } => {
                 line.insert_str(idx, text);
+                line.set_pos(idx + text.len());
             }
             Change::Delete { idx, ref text } => {
                 line.delete_range(idx..idx + text.len());
@@ -98,6 +99,7 @@ impl Change {
     }
 }
 
+#[derive(Clone)]
 pub struct Changeset {
     undo_group_level: u32,
     undos: Vec<Change>, // undoable changes
@@ -284,7 +286,6 @@ impl Changeset {
         self.undos.truncate(len);
     }
 
-    #[cfg(test)]
     pub fn redo(&mut self, line: &mut LineBuffer) -> bool {
         let mut waiting_for_end = 0;
         let mut redone = false;
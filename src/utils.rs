@@ -0,0 +1,34 @@
+//! Miscellaneous helpers
+
+/// Levenshtein (edit) distance between `a` and `b`, counted in chars.
+pub(crate) fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // distances from the current prefix of `a` to each prefix of `b`
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::levenshtein;
+
+    #[test]
+    fn distance() {
+        assert_eq!(0, levenshtein("", ""));
+        assert_eq!(3, levenshtein("", "abc"));
+        assert_eq!(3, levenshtein("abc", ""));
+        assert_eq!(0, levenshtein("word", "word"));
+        assert_eq!(2, levenshtein("wrod", "word"));
+        assert_eq!(3, levenshtein("kitten", "sitting"));
+        assert_eq!(1, levenshtein("héllo", "hello"));
+    }
+}
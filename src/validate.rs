@@ -77,10 +77,11 @@ pub trait Validator {
     /// Configure whether validation is performed while typing or only
     /// when user presses the Enter key.
     ///
-    /// Default is `false`.
+    /// When `true`, the message of an [`Invalid`](ValidationResult::Invalid)
+    /// result is displayed in place of the hint after each edit. The input is
+    /// still only accepted when the Enter key is pressed.
     ///
-    /// This feature is not yet implemented, so this function is currently a
-    /// no-op
+    /// Default is `false`.
     fn validate_while_typing(&self) -> bool {
         false
     }
@@ -119,31 +120,80 @@ impl Validator for MatchingBracketValidator {
 }
 
 fn validate_brackets(input: &str) -> ValidationResult {
+    match unclosed_brackets(input, false) {
+        Ok(stack) if stack.is_empty() => ValidationResult::Valid(None),
+        Ok(_) => ValidationResult::Incomplete,
+        Err(msg) => ValidationResult::Invalid(Some(msg)),
+    }
+}
+
+/// Brackets of `input`, except the ones inside double-quoted string literals
+/// with `skip_strings`.
+fn brackets(input: &str, skip_strings: bool) -> impl Iterator<Item = char> + '_ {
+    let mut in_string = false;
+    let mut escaped = false;
+    input.chars().filter(move |&c| {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            return false;
+        }
+        if c == '"' && skip_strings {
+            in_string = true;
+            return false;
+        }
+        matches!(c, '(' | '[' | '{' | ')' | ']' | '}')
+    })
+}
+
+/// Returns the brackets opened but not closed in `input`, innermost last,
+/// or an error message when a closing bracket is mismatched or unpaired.
+///
+/// With `skip_strings`, brackets inside double-quoted string literals are
+/// ignored.
+pub(crate) fn unclosed_brackets(
+    input: &str,
+    skip_strings: bool,
+) -> std::result::Result<Vec<char>, String> {
     let mut stack = vec![];
-    for c in input.chars() {
+    for c in brackets(input, skip_strings) {
         match c {
             '(' | '[' | '{' => stack.push(c),
             ')' | ']' | '}' => match (stack.pop(), c) {
                 (Some('('), ')') | (Some('['), ']') | (Some('{'), '}') => {}
                 (Some(wanted), _) => {
-                    return ValidationResult::Invalid(Some(format!(
+                    return Err(format!(
                         "Mismatched brackets: {:?} is not properly closed",
                         wanted
-                    )))
-                }
-                (None, c) => {
-                    return ValidationResult::Invalid(Some(format!(
-                        "Mismatched brackets: {:?} is unpaired",
-                        c
-                    )))
+                    ))
                 }
+                (None, c) => return Err(format!("Mismatched brackets: {:?} is unpaired", c)),
             },
             _ => {}
         }
     }
-    if stack.is_empty() {
-        ValidationResult::Valid(None)
-    } else {
-        ValidationResult::Incomplete
+    Ok(stack)
+}
+
+/// Returns the closing brackets of `input` without an opening one in
+/// `input` (i.e. closing brackets opened before), in order.
+///
+/// With `skip_strings`, brackets inside double-quoted string literals are
+/// ignored.
+pub(crate) fn unpaired_closing_brackets(input: &str, skip_strings: bool) -> Vec<char> {
+    let mut depth = 0usize;
+    let mut unpaired = vec![];
+    for c in brackets(input, skip_strings) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            _ if depth > 0 => depth -= 1,
+            _ => unpaired.push(c),
+        }
     }
+    unpaired
 }
@@ -0,0 +1,314 @@
+//! Line edition in a web terminal (like xterm.js): the keys are pushed by the
+//! page and awaited by the editor
+use std::cell::RefCell;
+use std::future::Future;
+use std::io::{self, BufRead, Read, Write};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use crate::error::ReadlineError;
+use crate::tty::{Renderer, StreamReader, StreamRenderer};
+use crate::{Edition, Editor, Helper, History, Result};
+
+/// Keys sent by a web terminal, awaited by [`Editor::readline_web`].
+///
+/// Clones share the same input.
+#[derive(Clone, Default)]
+pub struct WebInput(Rc<RefCell<Feed>>);
+
+#[derive(Default)]
+struct Feed {
+    /// Bytes pushed and not yet seen by the reader
+    data: Vec<u8>,
+    closed: bool,
+    waker: Option<Waker>,
+    /// Whether the last key read was a CR (see `StreamReader`)
+    after_cr: bool,
+    /// Set by the edit loop, applied (and reset) by the reader
+    command: Option<Command>,
+    /// Whether the reader has run out of bytes during the current command
+    starved: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Command {
+    /// The previous command is done: forget its bytes
+    Next,
+    /// The current command needs more keys: read it again from its first byte
+    Replay,
+}
+
+impl WebInput {
+    /// Empty input
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue the `data` sent by the terminal (e.g. from xterm.js `onData`)
+    /// and wake up the pending `readline_web`.
+    pub fn push(&self, data: &str) {
+        let mut feed = self.0.borrow_mut();
+        feed.data.extend_from_slice(data.as_bytes());
+        if let Some(waker) = feed.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// No more input: once the keys already pushed are read,
+    /// `readline_web` returns [`ReadlineError::Eof`].
+    pub fn close(&self) {
+        let mut feed = self.0.borrow_mut();
+        feed.closed = true;
+        if let Some(waker) = feed.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn set_command(&self, command: Command) {
+        self.0.borrow_mut().command = Some(command);
+    }
+
+    /// Whether the current command has read all the bytes received
+    fn starved(&self) -> bool {
+        self.0.borrow().starved
+    }
+
+    /// Wait for new bytes: `false` when the input is closed
+    fn received(&self) -> Received<'_> {
+        Received(self)
+    }
+}
+
+struct Received<'i>(&'i WebInput);
+
+impl Future for Received<'_> {
+    type Output = bool;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<bool> {
+        let mut feed = self.0 .0.borrow_mut();
+        if !feed.data.is_empty() {
+            Poll::Ready(true)
+        } else if feed.closed {
+            Poll::Ready(false)
+        } else {
+            feed.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// Bytes of the command being read, kept until it is complete so that it can
+/// be replayed
+struct ReplayReader {
+    feed: Rc<RefCell<Feed>>,
+    bytes: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for ReplayReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.read(buf)?;
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for ReplayReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let mut feed = self.feed.borrow_mut();
+        match feed.command.take() {
+            None => {}
+            Some(Command::Next) => {
+                self.bytes.drain(..self.pos);
+                self.pos = 0;
+            }
+            Some(Command::Replay) => self.pos = 0,
+        }
+        self.bytes.append(&mut feed.data);
+        feed.starved = self.pos == self.bytes.len();
+        Ok(&self.bytes[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+}
+
+impl Drop for ReplayReader {
+    // keys typed ahead are read by the next `readline_web`
+    fn drop(&mut self) {
+        let mut feed = self.feed.borrow_mut();
+        let unread = self.bytes.split_off(self.pos);
+        feed.data.splice(0..0, unread);
+        feed.command = None;
+    }
+}
+
+impl<H: Helper> Editor<H> {
+    /// Same as [`Editor::readline`] but the keys sent by a web terminal are
+    /// awaited from `input` and the line is displayed by writing ANSI escape
+    /// sequences to `output` (the terminal is assumed to have 80 columns, and
+    /// must translate `\n` to `\r\n`).
+    ///
+    /// A command is executed once all its keys are received: when it needs
+    /// more (like a prefix, or an incremental search), it is undone and
+    /// executed again with the next keys. On wasm32, [`WebEditor`] exposes
+    /// this method to JavaScript.
+    #[cfg_attr(docsrs, doc(cfg(feature = "web")))]
+    pub async fn readline_web(
+        &mut self,
+        prompt: &str,
+        input: &WebInput,
+        output: &mut dyn Write,
+    ) -> Result<String> {
+        self.partial = None;
+        let mut after_cr = input.0.borrow().after_cr;
+        let mut reader = ReplayReader {
+            feed: input.0.clone(),
+            bytes: Vec::new(),
+            pos: 0,
+        };
+        let user_input = {
+            let mut out = StreamRenderer::new(output, &self.config);
+            let mut rdr = StreamReader::new(&mut reader, &mut after_cr);
+            let user_input = self.edit_web(&mut out, &mut rdr, prompt, input).await;
+            out.write_and_flush("\n")?;
+            user_input
+        };
+        drop(reader);
+        input.0.borrow_mut().after_cr = after_cr;
+        self.accept_input(user_input)
+    }
+
+    async fn edit_web<'a>(
+        &mut self,
+        out: &mut StreamRenderer<'a>,
+        rdr: &mut StreamReader<'a>,
+        prompt: &str,
+        input: &WebInput,
+    ) -> Result<String> {
+        let no_history = History::new();
+        let mut edition = Edition::new(self, out, rdr, prompt, None, None, &no_history)?;
+        loop {
+            let checkpoint = edition.checkpoint();
+            match edition.step(rdr, &mut None) {
+                Ok(true) => return edition.finish(),
+                Ok(false) => input.set_command(Command::Next),
+                Err(ReadlineError::Eof) if input.starved() => {
+                    edition.restore(checkpoint);
+                    input.set_command(Command::Replay);
+                    if !input.received().await {
+                        return Err(ReadlineError::Eof);
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub use self::bindings::WebEditor;
+
+#[cfg(target_arch = "wasm32")]
+mod bindings {
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    use js_sys::{Function, Promise};
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen_futures::future_to_promise;
+
+    use super::WebInput;
+    use crate::{Config, DefaultEditor};
+
+    /// Line editor of a web terminal, for JavaScript.
+    ///
+    /// ```js
+    /// const editor = new WebEditor((data) => term.write(data));
+    /// term.onData((data) => editor.push(data));
+    /// const line = await editor.readline(">> ");
+    /// ```
+    #[wasm_bindgen]
+    pub struct WebEditor {
+        /// `None` while a `readline` is pending
+        editor: Rc<RefCell<Option<DefaultEditor>>>,
+        input: WebInput,
+        write: Function,
+    }
+
+    #[wasm_bindgen]
+    impl WebEditor {
+        /// Editor displaying the line with `write(data: string)`, lines
+        /// accepted being added to its history
+        #[wasm_bindgen(constructor)]
+        pub fn new(write: Function) -> Result<WebEditor, JsValue> {
+            let config = Config::builder().auto_add_history(true).build();
+            let editor = DefaultEditor::with_config(config).map_err(to_js)?;
+            Ok(Self {
+                editor: Rc::new(RefCell::new(Some(editor))),
+                input: WebInput::new(),
+                write,
+            })
+        }
+
+        /// Keys sent by the terminal (xterm.js `onData`)
+        pub fn push(&self, data: &str) {
+            self.input.push(data);
+        }
+
+        /// End of input: the pending `readline` is rejected
+        pub fn close(&self) {
+            self.input.close();
+        }
+
+        /// Promise of the next line, rejected with `Interrupted` on Ctrl-C
+        /// and `Eof` on Ctrl-D
+        pub fn readline(&self, prompt: String) -> Promise {
+            let editor = self.editor.clone();
+            let input = self.input.clone();
+            let mut output = JsWriter {
+                write: self.write.clone(),
+                buf: Vec::new(),
+            };
+            future_to_promise(async move {
+                let mut rl = editor
+                    .borrow_mut()
+                    .take()
+                    .ok_or_else(|| JsValue::from_str("readline already pending"))?;
+                let line = rl.readline_web(&prompt, &input, &mut output).await;
+                *editor.borrow_mut() = Some(rl);
+                line.map(JsValue::from).map_err(to_js)
+            })
+        }
+    }
+
+    fn to_js(err: crate::error::ReadlineError) -> JsValue {
+        JsValue::from_str(&format!("{:?}", err))
+    }
+
+    /// Call `write` with the text flushed
+    struct JsWriter {
+        write: Function,
+        buf: Vec<u8>,
+    }
+
+    impl Write for JsWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.buf.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            let data = String::from_utf8_lossy(&self.buf).into_owned();
+            self.buf.clear();
+            self.write
+                .call1(&JsValue::NULL, &JsValue::from(data))
+                .map(drop)
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))
+        }
+    }
+}